@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tracing::warn;
+
+/// Webhook body. `text` is what Slack renders, `content` what Discord renders;
+/// `kind` and `timestamp` are for anything parsing the payload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AlertPayload {
+    pub kind: String,
+    pub text: String,
+    pub content: String,
+    pub timestamp: String,
+}
+
+impl AlertPayload {
+    pub fn new(kind: &str, message: &str, timestamp: String) -> Self {
+        let text = format!("[{}] {}", kind, message);
+        Self { kind: kind.to_string(), content: text.clone(), text, timestamp }
+    }
+}
+
+/// Fire-and-forget webhook alerts for events an operator must see without tailing logs.
+///
+/// Alerts of the same kind within `cooldown` of the last one sent are dropped, so a condition
+/// that persists for many cycles (e.g. a stale WebSocket) produces one message, not hundreds.
+/// Without a URL every call is a no-op.
+#[derive(Debug)]
+pub struct AlertSink {
+    url: Option<String>,
+    cooldown: Duration,
+    client: reqwest::Client,
+    last_sent: Mutex<HashMap<String, Instant>>,
+}
+
+impl AlertSink {
+    pub fn new(url: Option<String>, cooldown: Duration) -> Self {
+        Self {
+            url: url.filter(|u| !u.is_empty()),
+            cooldown,
+            client: reqwest::Client::new(),
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sink that never sends anything
+    pub fn disabled() -> Self {
+        Self::new(None, Duration::ZERO)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.url.is_some()
+    }
+
+    /// Record an alert of `kind` at `now`; false when one went out within the cooldown.
+    fn claim(&self, kind: &str, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock();
+        if last_sent.get(kind).is_some_and(|t| now.duration_since(*t) < self.cooldown) {
+            return false;
+        }
+        last_sent.insert(kind.to_string(), now);
+        true
+    }
+
+    /// Build the payload for `kind`, or None when disabled or coalesced into a recent alert.
+    fn prepare(&self, kind: &str, message: &str, now: Instant) -> Option<AlertPayload> {
+        if !self.is_enabled() || !self.claim(kind, now) {
+            return None;
+        }
+        Some(AlertPayload::new(kind, message, chrono::Utc::now().to_rfc3339()))
+    }
+
+    /// POST the alert in the background. Never blocks the caller and never fails it:
+    /// webhook errors are only logged.
+    pub fn send(&self, kind: &str, message: &str) {
+        let (Some(url), Some(payload)) = (self.url.clone(), self.prepare(kind, message, Instant::now())) else {
+            return;
+        };
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            let result = client
+                .post(&url)
+                .timeout(Duration::from_secs(10))
+                .json(&payload)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            if let Err(e) = result {
+                warn!("[ALERT] webhook POST failed for {}: {:?}", payload.kind, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sink(cooldown_secs: u64) -> AlertSink {
+        AlertSink::new(Some("http://127.0.0.1:9/hook".to_string()), Duration::from_secs(cooldown_secs))
+    }
+
+    #[test]
+    fn test_payload_json_shape() {
+        let payload = AlertPayload::new("STOP_LOSS", "side=SELL size=0.001", "2026-01-01T00:00:00+00:00".to_string());
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "STOP_LOSS",
+                "text": "[STOP_LOSS] side=SELL size=0.001",
+                "content": "[STOP_LOSS] side=SELL size=0.001",
+                "timestamp": "2026-01-01T00:00:00+00:00",
+            })
+        );
+    }
+
+    #[test]
+    fn test_duplicates_within_cooldown_are_coalesced() {
+        let sink = sink(60);
+        let t0 = Instant::now();
+        assert!(sink.prepare("WS_STALE", "no message for 6000ms", t0).is_some());
+        assert!(sink.prepare("WS_STALE", "no message for 7000ms", t0 + Duration::from_secs(1)).is_none());
+        assert!(sink.prepare("WS_STALE", "no message for 9000ms", t0 + Duration::from_secs(59)).is_none());
+        // Other kinds have their own cooldown
+        assert!(sink.prepare("GHOST_POSITION", "reset", t0 + Duration::from_secs(1)).is_some());
+        // And the same kind goes out again once the cooldown has passed
+        assert!(sink.prepare("WS_STALE", "no message for 6000ms", t0 + Duration::from_secs(60)).is_some());
+    }
+
+    #[test]
+    fn test_no_url_is_noop() {
+        let disabled = AlertSink::disabled();
+        assert!(!disabled.is_enabled());
+        assert!(disabled.prepare("STOP_LOSS", "x", Instant::now()).is_none());
+        assert!(!AlertSink::new(Some(String::new()), Duration::ZERO).is_enabled());
+        // Safe outside a runtime: nothing is spawned
+        disabled.send("STOP_LOSS", "x");
+    }
+}
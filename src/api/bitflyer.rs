@@ -1,9 +1,12 @@
 pub mod api;
+pub mod cancel_all_child_orders;
 pub mod cancel_child_order;
 pub mod get_collateral;
 pub mod get_position;
 pub mod ws;
+pub mod ws_private;
 pub mod auth;
 pub mod get_balance;
 pub mod get_health;
+pub mod get_spot_price;
 pub mod send_order;
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
+use tracing::{debug, error};
 
 pub const ENDPOINT: &str = "https://api.bitflyer.com";
 
@@ -70,12 +71,61 @@ impl FromStr for ChildOrderType {
     }
 }
 
+/// bitFlyer error body: `{"status": -xxx, "error_message": "...", "data": null}`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct BitflyerErrorBody {
+    pub status: i32,
+    pub error_message: String,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl fmt::Display for BitflyerErrorBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}", self.status, self.error_message)
+    }
+}
+
+impl BitflyerErrorBody {
+    /// The order no longer exists on the exchange (already filled, expired or cancelled).
+    pub fn is_order_not_found(&self) -> bool {
+        self.error_message.to_ascii_lowercase().contains("not found")
+    }
+}
+
 #[derive(Debug)]
 pub enum ApiResponseError {
     Credential(CredentialError),
     Reqwest(reqwest::Error),
     StatusCode(StatusCode),
     UrlParse(url::ParseError),
+    Deserialize(serde_json::Error),
+    ApiError(BitflyerErrorBody),
+}
+
+impl fmt::Display for ApiResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ApiResponseError::Credential(e) => write!(f, "Credential error: {:?}", e),
+            ApiResponseError::Reqwest(e) => write!(f, "Request error: {}", e),
+            ApiResponseError::StatusCode(s) => write!(f, "HTTP status: {}", s),
+            ApiResponseError::UrlParse(e) => write!(f, "URL parse error: {}", e),
+            ApiResponseError::Deserialize(e) => write!(f, "Deserialize error: {}", e),
+            ApiResponseError::ApiError(body) => write!(f, "API error: {}", body),
+        }
+    }
+}
+
+impl ApiResponseError {
+    pub fn is_order_not_found(&self) -> bool {
+        matches!(self, ApiResponseError::ApiError(body) if body.is_order_not_found())
+    }
+}
+
+impl From<serde_json::Error> for ApiResponseError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiResponseError::Deserialize(error)
+    }
 }
 
 impl From<StatusCode> for ApiResponseError {
@@ -96,6 +146,44 @@ impl From<url::ParseError> for ApiResponseError {
     }
 }
 
+/// Stage 1: HTTP status; on failure parse the bitFlyer error body when present.
+/// Stage 2: parse the success body (an empty body is treated as JSON `null`, e.g. for `()`).
+fn parse_response<T: serde::de::DeserializeOwned>(
+    status: StatusCode,
+    response_text: &str,
+) -> Result<T, ApiResponseError> {
+    if !status.is_success() {
+        return match serde_json::from_str::<BitflyerErrorBody>(response_text) {
+            Ok(body) => {
+                error!("API error: {} (HTTP {})", body, status);
+                Err(ApiResponseError::ApiError(body))
+            }
+            Err(_) => {
+                error!("HTTP error: {}", status);
+                Err(ApiResponseError::from(status))
+            }
+        };
+    }
+
+    let text = if response_text.trim().is_empty() { "null" } else { response_text };
+    serde_json::from_str(text).map_err(|e| {
+        error!("Failed to parse response data: {}", e);
+        ApiResponseError::Deserialize(e)
+    })
+}
+
+async fn handle_response<T: serde::de::DeserializeOwned>(
+    response: Result<reqwest::Response, reqwest::Error>,
+) -> Result<(StatusCode, T), ApiResponseError> {
+    let response = response?;
+    let status = response.status();
+    let response_text = response.text().await?;
+
+    debug!("API response: {}", response_text);
+
+    parse_response(status, &response_text).map(|parsed| (status, parsed))
+}
+
 pub async fn get<T: serde::de::DeserializeOwned>(
     client: &reqwest::Client,
     path: &str,
@@ -117,17 +205,7 @@ pub async fn get<T: serde::de::DeserializeOwned>(
     };
 
     let get = client.get(url).headers(header.unwrap()).send().await;
-
-    match get {
-        Ok(t) => {
-            if t.status().is_success() {
-                Ok(t.json().await?)
-            } else {
-                Err(ApiResponseError::from(t.status()))
-            }
-        }
-        Err(e) => Err(ApiResponseError::from(e)),
-    }
+    handle_response(get).await.map(|(_, parsed)| parsed)
 }
 
 pub async fn post<T: serde::Serialize, U: serde::de::DeserializeOwned>(
@@ -142,17 +220,7 @@ pub async fn post<T: serde::Serialize, U: serde::de::DeserializeOwned>(
     let header = make_http_header(Method::POST.as_ref(), path, &body_json)
         .map_err(ApiResponseError::Credential)?;
     let post = client.post(url).headers(header).json(body).send().await;
-
-    match post {
-        Ok(t) => {
-            if t.status().is_success() {
-                Ok((t.status(), t.json().await?))
-            } else {
-                Err(ApiResponseError::from(t.status()))
-            }
-        }
-        Err(e) => Err(ApiResponseError::from(e)),
-    }
+    handle_response(post).await
 }
 
 fn make_http_header(method: &str, path: &str, body: &str) -> Result<HeaderMap, CredentialError> {
@@ -173,3 +241,42 @@ fn make_http_header(method: &str, path: &str, body: &str) -> Result<HeaderMap, C
 
     Ok(header)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error_body_into_api_error() {
+        let body = r#"{"status":-205,"error_message":"Margin amount is insufficient for this order.","data":null}"#;
+        let result = parse_response::<serde_json::Value>(StatusCode::BAD_REQUEST, body);
+        match result {
+            Err(ApiResponseError::ApiError(e)) => {
+                assert_eq!(e.status, -205);
+                assert_eq!(e.error_message, "Margin amount is insufficient for this order.");
+                assert!(!e.is_order_not_found());
+            }
+            other => panic!("expected ApiError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_order_not_found_error_detected() {
+        let body = r#"{"status":-111,"error_message":"Order not found","data":null}"#;
+        let err = parse_response::<()>(StatusCode::BAD_REQUEST, body).unwrap_err();
+        assert!(err.is_order_not_found());
+        assert_eq!(err.to_string(), "API error: [-111] Order not found");
+    }
+
+    #[test]
+    fn test_non_json_error_body_falls_back_to_status() {
+        let err = parse_response::<()>(StatusCode::BAD_GATEWAY, "<html>Bad Gateway</html>").unwrap_err();
+        assert!(matches!(err, ApiResponseError::StatusCode(StatusCode::BAD_GATEWAY)));
+        assert!(!err.is_order_not_found());
+    }
+
+    #[test]
+    fn test_empty_success_body_parses_as_unit() {
+        assert!(parse_response::<()>(StatusCode::OK, "").is_ok());
+    }
+}
@@ -15,20 +15,19 @@ pub enum CredentialError {
     EnvVar(env::VarError),
 }
 
+/// (API key, API secret) from the environment
+pub fn get_api_keys() -> Result<(String, String), CredentialError> {
+    let api_key = env::var(API_KEY).map_err(CredentialError::EnvVar)?;
+    let api_secret = env::var(API_SECRET).map_err(CredentialError::EnvVar)?;
+    Ok((api_key, api_secret))
+}
+
 pub fn get_credential(
     method: &str,
     path: &str,
     body: &str,
 ) -> Result<HashMap<String, String>, CredentialError> {
-    let api_key = match env::var(API_KEY) {
-        Ok(val) => val,
-        Err(e) => return Err(CredentialError::EnvVar(e)),
-    };
-
-    let api_secret = match env::var(API_SECRET) {
-        Ok(val) => val,
-        Err(e) => return Err(CredentialError::EnvVar(e)),
-    };
+    let (api_key, api_secret) = get_api_keys()?;
 
     let timestamp = Utc::now().timestamp().to_string();
     let sign = get_access_sign(method, path, body, &timestamp, &api_secret);
@@ -48,7 +47,11 @@ fn get_access_sign(
     timestamp: &str,
     secret: &str,
 ) -> String {
-    let data = format!("{}{}{}{}", timestamp, method, path, body);
+    sign(secret, &format!("{}{}{}{}", timestamp, method, path, body))
+}
+
+/// Hex-encoded HMAC-SHA256 of `data` keyed by `secret`
+pub fn sign(secret: &str, data: &str) -> String {
     let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
     let signature = hmac::sign(&key, data.as_bytes());
     hex::encode(signature.as_ref())
@@ -0,0 +1,19 @@
+use crate::api::bitflyer::api;
+use reqwest::StatusCode;
+use serde::Serialize;
+
+const PATH: &str = "/v1/me/cancelallchildorders";
+
+#[derive(Serialize, Debug)]
+pub struct CancelAllChildOrdersParameter {
+    pub product_code: api::ProductCode,
+}
+
+/// Cancel every open order for the product. The response body is empty, so no count comes back.
+pub async fn cancel_all_child_orders(
+    client: &reqwest::Client,
+    parameter: &CancelAllChildOrdersParameter,
+) -> Result<(StatusCode, ()), api::ApiResponseError> {
+    api::post::<CancelAllChildOrdersParameter, ()>(client, PATH, parameter).await
+}
+
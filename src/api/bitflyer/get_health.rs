@@ -1,6 +1,6 @@
 use crate::api::bitflyer::api;
 use reqwest;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
 use std::str::FromStr;
 
 const PATH: &str = "/v1/gethealth";
@@ -10,7 +10,7 @@ pub struct HealthStatus {
     pub status: HealthStatusEnum,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum HealthStatusEnum {
     Normal,
     Busy,
@@ -37,11 +37,42 @@ impl FromStr for HealthStatusEnum {
     }
 }
 
-pub async fn get_health(client: &reqwest::Client) -> Result<std::string::String, reqwest::Error> {
-    let client = client.clone();
+// bitFlyer sends "VERY BUSY" etc., so go through FromStr rather than the variant names
+impl<'de> Deserialize<'de> for HealthStatusEnum {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Ok(HealthStatusEnum::from_str(&s).unwrap_or(HealthStatusEnum::Unknown))
+    }
+}
 
-    match client.get(api::ENDPOINT.to_owned() + PATH).send().await {
-        Ok(res) => res.text().await,
-        Err(e) => Err(e),
+pub async fn get_health(client: &reqwest::Client) -> Result<HealthStatus, reqwest::Error> {
+    client
+        .get(api::ENDPOINT.to_owned() + PATH)
+        .send()
+        .await?
+        .json()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_status_deserialize() {
+        let cases = [
+            ("NORMAL", HealthStatusEnum::Normal),
+            ("BUSY", HealthStatusEnum::Busy),
+            ("VERY BUSY", HealthStatusEnum::VeryBusy),
+            ("SUPER BUSY", HealthStatusEnum::SuperBusy),
+            ("NO ORDER", HealthStatusEnum::NoOrder),
+            ("STOP", HealthStatusEnum::Stop),
+            ("MAINTENANCE", HealthStatusEnum::Unknown),
+        ];
+        for (raw, expected) in cases {
+            let json = format!(r#"{{"status":"{}"}}"#, raw);
+            let health: HealthStatus = serde_json::from_str(&json).unwrap();
+            assert_eq!(health.status, expected, "{}", raw);
+        }
     }
 }
@@ -0,0 +1,49 @@
+use crate::api::bitflyer::api;
+use reqwest;
+use serde::Deserialize;
+
+const PATH: &str = "/v1/ticker";
+
+#[derive(Deserialize, Debug)]
+pub struct Ticker {
+    pub product_code: String,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    pub ltp: f64,
+}
+
+impl Ticker {
+    pub fn mid_price(&self) -> f64 {
+        (self.best_bid + self.best_ask) / 2.0
+    }
+}
+
+/// Public ticker for `product_code` (unsigned request).
+pub async fn get_ticker(client: &reqwest::Client, product_code: api::ProductCode) -> Result<Ticker, reqwest::Error> {
+    client
+        .get(api::ENDPOINT.to_owned() + PATH)
+        .query(&[("product_code", product_code.to_string())])
+        .send()
+        .await?
+        .json()
+        .await
+}
+
+/// Spot BTC_JPY mid price, the reference SFD deviation is measured against.
+pub async fn get_spot_price(client: &reqwest::Client) -> Result<f64, reqwest::Error> {
+    Ok(get_ticker(client, api::ProductCode::BTC_JPY).await?.mid_price())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ticker_deserialize() {
+        let json = r#"{"product_code":"BTC_JPY","state":"RUNNING","timestamp":"2024-01-15T10:30:00.000","tick_id":1,"best_bid":9999000.0,"best_ask":10001000.0,"best_bid_size":0.1,"best_ask_size":0.2,"total_bid_depth":10.0,"total_ask_depth":12.0,"market_bid_size":0.0,"market_ask_size":0.0,"ltp":10000500.0,"volume":1000.0,"volume_by_product":500.0}"#;
+        let ticker: Ticker = serde_json::from_str(json).unwrap();
+        assert_eq!(ticker.product_code, "BTC_JPY");
+        assert_eq!(ticker.ltp, 10_000_500.0);
+        assert_eq!(ticker.mid_price(), 10_000_000.0);
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::Deserializer;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
@@ -36,9 +36,13 @@ impl<'de> Deserialize<'de> for Timestamp {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let datetime = DateTime::parse_from_rfc3339(&s)
-            .map_err(serde::de::Error::custom)?
-            .with_timezone(&Utc);
+        // expire_date comes without an offset; bitFlyer times are UTC
+        let datetime = match DateTime::parse_from_rfc3339(&s) {
+            Ok(datetime) => datetime.with_timezone(&Utc),
+            Err(e) => NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.f")
+                .map_err(|_| serde::de::Error::custom(e))?
+                .and_utc(),
+        };
         Ok(Timestamp(datetime))
     }
 }
@@ -88,6 +92,7 @@ pub enum Side {
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct UpdateChildOrderItem {
+    pub product_code: String,
     pub child_order_id: String,
     pub child_order_acceptance_id: String,
     pub event_type: String,
@@ -0,0 +1,130 @@
+use serde::Deserialize;
+
+use crate::api::bitflyer::auth;
+use crate::api::bitflyer::ws::UpdateChildOrderItem;
+
+pub const CHILD_ORDER_EVENTS: &str = "child_order_events";
+
+/// JSON-RPC id of the auth request, so its response can be told apart from channel messages
+pub const AUTH_REQUEST_ID: u64 = 1;
+
+/// `auth` request for the Realtime API: HMAC-SHA256 of `timestamp + nonce` under the API secret
+pub fn auth_message(api_key: &str, api_secret: &str, timestamp_ms: i64, nonce: &str) -> String {
+    let signature = auth::sign(api_secret, &format!("{}{}", timestamp_ms, nonce));
+    serde_json::json!({
+        "method": "auth",
+        "params": {
+            "api_key": api_key,
+            "timestamp": timestamp_ms,
+            "nonce": nonce,
+            "signature": signature,
+        },
+        "id": AUTH_REQUEST_ID,
+    }).to_string()
+}
+
+pub fn subscribe_message() -> String {
+    serde_json::json!({
+        "method": "subscribe",
+        "params": { "channel": CHILD_ORDER_EVENTS },
+    }).to_string()
+}
+
+/// Response to a JSON-RPC request (as opposed to a `channelMessage` notification)
+#[derive(Deserialize, Debug)]
+pub struct RpcResponse {
+    pub id: u64,
+    #[serde(default)]
+    pub result: Option<bool>,
+    #[serde(default)]
+    pub error: Option<serde_json::Value>,
+}
+
+impl RpcResponse {
+    pub fn is_auth_ok(&self) -> bool {
+        self.id == AUTH_REQUEST_ID && self.result == Some(true)
+    }
+}
+
+/// Kind of a child_order_events item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildOrderEventType {
+    Order,
+    OrderFailed,
+    Cancel,
+    CancelFailed,
+    Execution,
+    Expire,
+}
+
+impl ChildOrderEventType {
+    pub fn parse(event_type: &str) -> Option<Self> {
+        match event_type {
+            "ORDER" => Some(Self::Order),
+            "ORDER_FAILED" => Some(Self::OrderFailed),
+            "CANCEL" => Some(Self::Cancel),
+            "CANCEL_FAILED" => Some(Self::CancelFailed),
+            "EXECUTION" => Some(Self::Execution),
+            "EXPIRE" => Some(Self::Expire),
+            _ => None,
+        }
+    }
+}
+
+impl UpdateChildOrderItem {
+    /// None for event types this client does not know
+    pub fn kind(&self) -> Option<ChildOrderEventType> {
+        ChildOrderEventType::parse(&self.event_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::bitflyer::ws::{Message, Side};
+
+    const CHILD_ORDER_EVENTS_MESSAGE: &str = r#"{
+        "jsonrpc":"2.0",
+        "method":"channelMessage",
+        "params":{
+            "channel":"child_order_events",
+            "message":[
+                {"product_code":"FX_BTC_JPY","child_order_id":"JFX20240115-000001-000001F","child_order_acceptance_id":"JRF20240115-000001-000001","event_date":"2024-01-15T10:30:00.000Z","event_type":"ORDER","child_order_type":"LIMIT","side":"BUY","price":10000000,"size":0.01,"expire_date":"2024-01-15T10:31:00"},
+                {"product_code":"FX_BTC_JPY","child_order_id":"JFX20240115-000001-000001F","child_order_acceptance_id":"JRF20240115-000001-000001","event_date":"2024-01-15T10:30:01.000Z","event_type":"EXECUTION","exec_id":123,"side":"BUY","price":10000000,"size":0.004,"commission":0,"sfd":0,"outstanding_size":0.006},
+                {"product_code":"FX_BTC_JPY","child_order_id":"JFX20240115-000001-000001F","child_order_acceptance_id":"JRF20240115-000001-000001","event_date":"2024-01-15T10:30:02.000Z","event_type":"CANCEL"}
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_deserialize_child_order_events() {
+        let message: Message = serde_json::from_str(CHILD_ORDER_EVENTS_MESSAGE).unwrap();
+        assert_eq!(message.params.channel, CHILD_ORDER_EVENTS);
+        let events: Vec<UpdateChildOrderItem> = serde_json::from_value(message.params.message).unwrap();
+
+        let kinds: Vec<_> = events.iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, [Some(ChildOrderEventType::Order), Some(ChildOrderEventType::Execution), Some(ChildOrderEventType::Cancel)]);
+        let execution = &events[1];
+        assert_eq!(execution.child_order_acceptance_id, "JRF20240115-000001-000001");
+        assert_eq!((execution.side, execution.price, execution.size), (Some(Side::BUY), Some(10_000_000), Some(0.004)));
+        assert_eq!(execution.outstanding_size, Some(0.006));
+        // expire_date has no offset and is read as UTC
+        assert_eq!(events[0].expire_date.unwrap().get_timestamp(), 1_705_314_660_000);
+        assert_eq!(ChildOrderEventType::parse("SOMETHING_NEW"), None);
+    }
+
+    #[test]
+    fn test_auth_message_and_response() {
+        let message: serde_json::Value = serde_json::from_str(&auth_message("key", "secret", 1_705_314_600_000, "abc")).unwrap();
+        assert_eq!(message["method"], "auth");
+        assert_eq!(message["id"], AUTH_REQUEST_ID);
+        assert_eq!(message["params"]["timestamp"], 1_705_314_600_000i64);
+        assert_eq!(message["params"]["signature"], auth::sign("secret", "1705314600000abc"));
+
+        let ok: RpcResponse = serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":true}"#).unwrap();
+        assert!(ok.is_auth_ok());
+        let denied: RpcResponse = serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"Invalid signature"}}"#).unwrap();
+        assert!(!denied.is_auth_ok());
+        assert!(denied.error.is_some());
+    }
+}
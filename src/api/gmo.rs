@@ -3,7 +3,13 @@ pub mod auth;
 pub mod get_position;
 pub mod get_balance;
 pub mod get_collateral;
+pub mod get_server_status;
+pub mod get_symbols;
+pub mod get_trades;
 pub mod send_order;
 pub mod cancel_child_order;
+pub mod cancel_bulk_order;
 pub mod close_bulk_order;
+pub mod rate_limit;
 pub mod ws;
+pub mod ws_private;
@@ -1,6 +1,7 @@
 extern crate hyper;
 
 use crate::api::gmo::auth::{get_credential, CredentialError};
+use crate::api::gmo::rate_limit::RateLimiter;
 use hyper::header::{HeaderMap, HeaderName, CONTENT_TYPE};
 use hyper::http::HeaderValue;
 use reqwest::{Method, StatusCode, Url};
@@ -9,9 +10,13 @@ use std::collections::HashMap;
 use std::fmt;
 use std::str::FromStr;
 use serde::Deserializer;
-use tracing::{debug, error};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{debug, error, warn};
 
 pub const ENDPOINT: &str = "https://api.coin.z.com/private";
+pub const PUBLIC_ENDPOINT: &str = "https://api.coin.z.com/public";
 
 pub fn deserialize_number_from_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
@@ -24,16 +29,22 @@ where
 }
 
 #[allow(non_camel_case_types)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Symbol {
     Unknown,
     BTC_JPY,
+    ETH_JPY,
+    XRP_JPY,
+    BCH_JPY,
 }
 
 impl fmt::Display for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Symbol::BTC_JPY => write!(f, "BTC_JPY"),
+            Symbol::ETH_JPY => write!(f, "ETH_JPY"),
+            Symbol::XRP_JPY => write!(f, "XRP_JPY"),
+            Symbol::BCH_JPY => write!(f, "BCH_JPY"),
             _ => write!(f, "Unknown"),
         }
     }
@@ -45,6 +56,9 @@ impl FromStr for Symbol {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "BTC_JPY" => Ok(Symbol::BTC_JPY),
+            "ETH_JPY" => Ok(Symbol::ETH_JPY),
+            "XRP_JPY" => Ok(Symbol::XRP_JPY),
+            "BCH_JPY" => Ok(Symbol::BCH_JPY),
             _ => Err(()),
         }
     }
@@ -67,7 +81,7 @@ impl fmt::Display for ChildOrderType {
     }
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub enum TimeInForce {
     SOK,
     FAK,
@@ -75,6 +89,27 @@ pub enum TimeInForce {
     FOK,
 }
 
+impl FromStr for TimeInForce {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "SOK" => Ok(TimeInForce::SOK),
+            "FAK" => Ok(TimeInForce::FAK),
+            "FAS" => Ok(TimeInForce::FAS),
+            "FOK" => Ok(TimeInForce::FOK),
+            _ => Err(()),
+        }
+    }
+}
+
+impl TimeInForce {
+    /// SOK (post-only) is meaningless for MARKET orders and GMO rejects it.
+    pub fn is_valid_for(&self, execution_type: &ChildOrderType) -> bool {
+        !matches!((self, execution_type), (TimeInForce::SOK, ChildOrderType::MARKET))
+    }
+}
+
 impl FromStr for ChildOrderType {
     type Err = ();
 
@@ -100,6 +135,38 @@ impl fmt::Display for ApiErrorMessage {
     }
 }
 
+/// GMO `message_code`s the bot reacts to; anything else is kept as `Other`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GmoErrorCode {
+    /// ERR-201: not enough trading margin for the order
+    MarginInsufficient,
+    /// ERR-422: a close found no open position to settle
+    NoOpenPosition,
+    /// ERR-5003: a SOK (post-only) order would have taken liquidity
+    SokTaker,
+    /// ERR-5122: the order is already filled, cancelled or expired
+    OrderNotFound,
+    /// ERR-5201: scheduled maintenance
+    Maintenance,
+    /// ERR-5202: emergency maintenance
+    EmergencyMaintenance,
+    Other(String),
+}
+
+impl GmoErrorCode {
+    pub fn from_code(code: &str) -> Self {
+        match code {
+            "ERR-201" => GmoErrorCode::MarginInsufficient,
+            "ERR-422" => GmoErrorCode::NoOpenPosition,
+            "ERR-5003" => GmoErrorCode::SokTaker,
+            "ERR-5122" => GmoErrorCode::OrderNotFound,
+            "ERR-5201" => GmoErrorCode::Maintenance,
+            "ERR-5202" => GmoErrorCode::EmergencyMaintenance,
+            other => GmoErrorCode::Other(other.to_string()),
+        }
+    }
+}
+
 /// Common API response envelope for status checking (two-stage parsing)
 #[derive(Deserialize, Debug)]
 struct ApiRawResponse {
@@ -135,6 +202,31 @@ impl fmt::Display for ApiResponseError {
     }
 }
 
+impl ApiResponseError {
+    /// Codes of every message in a GMO error response; empty for transport / HTTP errors
+    pub fn error_codes(&self) -> Vec<GmoErrorCode> {
+        match self {
+            ApiResponseError::ApiError(msgs) => msgs.iter().map(|m| GmoErrorCode::from_code(&m.message_code)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn has_error_code(&self, code: &GmoErrorCode) -> bool {
+        self.error_codes().contains(code)
+    }
+
+    /// GMO is under maintenance: the private API refuses everything until it is over
+    pub fn is_maintenance(&self) -> bool {
+        match self {
+            ApiResponseError::StatusCode(status) => *status == StatusCode::SERVICE_UNAVAILABLE,
+            _ => self
+                .error_codes()
+                .iter()
+                .any(|code| matches!(code, GmoErrorCode::Maintenance | GmoErrorCode::EmergencyMaintenance)),
+        }
+    }
+}
+
 impl From<CredentialError> for ApiResponseError {
     fn from(error: CredentialError) -> Self {
         ApiResponseError::Credential(error)
@@ -211,6 +303,7 @@ async fn handle_response<T: serde::de::DeserializeOwned + std::fmt::Debug>(
 
 pub async fn get<T: serde::de::DeserializeOwned + std::fmt::Debug>(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     path: &str,
     query: Option<&HashMap<String, String>>,
 ) -> Result<T, ApiResponseError> {
@@ -219,14 +312,33 @@ pub async fn get<T: serde::de::DeserializeOwned + std::fmt::Debug>(
         Some(q) => Url::parse_with_params(&url_str, q)?,
         None => Url::parse(&url_str)?,
     };
+    // Acquire before signing so the API-TIMESTAMP is not stale after waiting
+    limiter.acquire().await;
     let header = make_http_header(Method::GET.as_ref(), path, "")?;
 
     let get = client.get(url).headers(header).send().await;
     handle_response(get).await
 }
 
+/// Unauthenticated GET against the public API.
+pub async fn get_public<T: serde::de::DeserializeOwned + std::fmt::Debug>(
+    client: &reqwest::Client,
+    path: &str,
+    query: Option<&HashMap<String, String>>,
+) -> Result<T, ApiResponseError> {
+    let url_str = format!("{}{}", PUBLIC_ENDPOINT, path);
+    let url = match query {
+        Some(q) => Url::parse_with_params(&url_str, q)?,
+        None => Url::parse(&url_str)?,
+    };
+
+    let get = client.get(url).send().await;
+    handle_response(get).await
+}
+
 pub async fn post<T: serde::Serialize, U: serde::de::DeserializeOwned + std::fmt::Debug>(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     path: &str,
     body: &T,
 ) -> Result<(StatusCode, U), ApiResponseError> {
@@ -234,12 +346,115 @@ pub async fn post<T: serde::Serialize, U: serde::de::DeserializeOwned + std::fmt
     let url = Url::parse(&url_str)?;
     let body_json = serde_json::to_string(body)
         .map_err(ApiResponseError::Deserialize)?;
+    // Orders / cancels / closes: hold an in-flight slot until the response is read
+    let _in_flight = limiter.acquire_in_flight().await;
+    limiter.acquire().await;
     let header = make_http_header(Method::POST.as_ref(), path, &body_json)?;
     let post = client.post(url).headers(header).json(body).send().await;
     let response = handle_response(post).await?;
     Ok((StatusCode::OK, response))
 }
 
+pub async fn put<T: serde::Serialize, U: serde::de::DeserializeOwned + std::fmt::Debug>(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    path: &str,
+    body: &T,
+) -> Result<U, ApiResponseError> {
+    let url_str = format!("{}{}", ENDPOINT, path);
+    let url = Url::parse(&url_str)?;
+    let body_json = serde_json::to_string(body)
+        .map_err(ApiResponseError::Deserialize)?;
+    limiter.acquire().await;
+    let header = make_http_header(Method::PUT.as_ref(), path, &body_json)?;
+    let put = client.put(url).headers(header).json(body).send().await;
+    handle_response(put).await
+}
+
+const RETRY_BASE_DELAY_MS: u64 = 100;
+
+/// Transient failures worth retrying: timeouts / connection errors and gateway 5xx.
+/// Business errors (ApiError, e.g. ERR-201) are never retried.
+pub fn is_retryable(error: &ApiResponseError) -> bool {
+    match error {
+        ApiResponseError::Reqwest(e) => e.is_timeout() || e.is_connect(),
+        ApiResponseError::StatusCode(status) => matches!(
+            *status,
+            StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+        ),
+        _ => false,
+    }
+}
+
+/// Order/close POSTs are not idempotent: a timeout or 5xx may come back after GMO already
+/// accepted the order, and resending would double it. Only retry when the connection
+/// never opened; anything else goes to the caller's lost-response handling.
+pub fn is_retryable_post(error: &ApiResponseError) -> bool {
+    matches!(error, ApiResponseError::Reqwest(e) if e.is_connect())
+}
+
+/// Exponential backoff (100ms, 200ms, 400ms, ...) plus up to 50% random jitter.
+fn retry_delay(attempt: u32, rng: &mut impl Rng) -> Duration {
+    let base = RETRY_BASE_DELAY_MS.saturating_mul(1u64 << attempt.min(10));
+    let jitter = rng.gen_range(0..=base / 2);
+    Duration::from_millis(base + jitter)
+}
+
+/// Run `op`, retrying up to `max_retries` times while the error is retryable.
+/// Backoff jitter comes from `limiter`'s RNG.
+pub async fn with_retry<T, F, Fut>(max_retries: u32, limiter: &RateLimiter, op: F) -> Result<T, ApiResponseError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ApiResponseError>>,
+{
+    with_retry_if(max_retries, is_retryable, limiter, op).await
+}
+
+/// Run `op`, retrying up to `max_retries` times while `retryable` accepts the error.
+pub async fn with_retry_if<T, F, Fut>(
+    max_retries: u32,
+    retryable: fn(&ApiResponseError) -> bool,
+    limiter: &RateLimiter,
+    mut op: F,
+) -> Result<T, ApiResponseError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, ApiResponseError>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if attempt < max_retries && retryable(&e) => {
+                let delay = retry_delay(attempt, &mut *limiter.jitter_rng());
+                attempt += 1;
+                warn!("[API_RETRY] {} (attempt {}/{}), retrying in {}ms", e, attempt, max_retries, delay.as_millis());
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub async fn get_with_retry<T: serde::de::DeserializeOwned + std::fmt::Debug>(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    path: &str,
+    query: Option<&HashMap<String, String>>,
+    max_retries: u32,
+) -> Result<T, ApiResponseError> {
+    with_retry(max_retries, limiter, || get(client, limiter, path, query)).await
+}
+
+pub async fn post_with_retry<T: serde::Serialize, U: serde::de::DeserializeOwned + std::fmt::Debug>(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    path: &str,
+    body: &T,
+    max_retries: u32,
+) -> Result<(StatusCode, U), ApiResponseError> {
+    with_retry_if(max_retries, is_retryable_post, limiter, || post(client, limiter, path, body)).await
+}
+
 fn make_http_header(method: &str, path: &str, body: &str) -> Result<HeaderMap, CredentialError> {
     let mut header = HeaderMap::new();
     let credential = get_credential(method, path, body)?;
@@ -258,3 +473,154 @@ fn make_http_header(method: &str, path: &str, body: &str) -> Result<HeaderMap, C
 
     Ok(header)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn test_limiter() -> RateLimiter {
+        RateLimiter::new(100.0, 100.0)
+    }
+
+    fn err_201() -> ApiResponseError {
+        ApiResponseError::ApiError(vec![ApiErrorMessage {
+            message_code: "ERR-201".to_string(),
+            message_string: "Trading margin is insufficient".to_string(),
+        }])
+    }
+
+    #[test]
+    fn test_error_codes_map_to_variants() {
+        for (code, expected) in [
+            ("ERR-201", GmoErrorCode::MarginInsufficient),
+            ("ERR-422", GmoErrorCode::NoOpenPosition),
+            ("ERR-5003", GmoErrorCode::SokTaker),
+            ("ERR-5122", GmoErrorCode::OrderNotFound),
+            ("ERR-5201", GmoErrorCode::Maintenance),
+            ("ERR-5202", GmoErrorCode::EmergencyMaintenance),
+        ] {
+            assert_eq!(GmoErrorCode::from_code(code), expected);
+        }
+        assert_eq!(GmoErrorCode::from_code("ERR-9999"), GmoErrorCode::Other("ERR-9999".to_string()));
+
+        let both = ApiResponseError::ApiError(vec![
+            ApiErrorMessage { message_code: "ERR-201".to_string(), message_string: String::new() },
+            ApiErrorMessage { message_code: "ERR-123".to_string(), message_string: String::new() },
+        ]);
+        assert_eq!(both.error_codes(), [GmoErrorCode::MarginInsufficient, GmoErrorCode::Other("ERR-123".to_string())]);
+        assert!(both.has_error_code(&GmoErrorCode::MarginInsufficient));
+        assert!(!both.has_error_code(&GmoErrorCode::NoOpenPosition));
+        assert!(ApiResponseError::StatusCode(StatusCode::TOO_MANY_REQUESTS).error_codes().is_empty());
+    }
+
+    #[test]
+    fn test_maintenance_errors() {
+        let api_error = |code: &str| ApiResponseError::ApiError(vec![ApiErrorMessage {
+            message_code: code.to_string(),
+            message_string: "MAINTENANCE. Please wait for a while".to_string(),
+        }]);
+        assert!(api_error("ERR-5201").is_maintenance());
+        assert!(api_error("ERR-5202").is_maintenance());
+        assert!(ApiResponseError::StatusCode(StatusCode::SERVICE_UNAVAILABLE).is_maintenance());
+        assert!(!err_201().is_maintenance());
+        assert!(!ApiResponseError::StatusCode(StatusCode::BAD_GATEWAY).is_maintenance());
+    }
+
+    #[tokio::test]
+    async fn test_retry_fails_twice_then_succeeds() {
+        let calls = AtomicU32::new(0);
+        let result = with_retry(3, &test_limiter(), || async {
+            let n = calls.fetch_add(1, Ordering::SeqCst);
+            if n < 2 {
+                Err(ApiResponseError::StatusCode(StatusCode::SERVICE_UNAVAILABLE))
+            } else {
+                Ok("ok")
+            }
+        }).await;
+
+        assert_eq!(result.unwrap(), "ok");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_retries() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_retry(1, &test_limiter(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ApiResponseError::StatusCode(StatusCode::BAD_GATEWAY))
+        }).await;
+
+        assert!(matches!(result, Err(ApiResponseError::StatusCode(StatusCode::BAD_GATEWAY))));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_business_error_not_retried() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_retry(3, &test_limiter(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(err_201())
+        }).await;
+
+        assert!(matches!(result, Err(ApiResponseError::ApiError(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_is_retryable_status_codes() {
+        assert!(is_retryable(&ApiResponseError::StatusCode(StatusCode::BAD_GATEWAY)));
+        assert!(is_retryable(&ApiResponseError::StatusCode(StatusCode::SERVICE_UNAVAILABLE)));
+        assert!(is_retryable(&ApiResponseError::StatusCode(StatusCode::GATEWAY_TIMEOUT)));
+        assert!(!is_retryable(&ApiResponseError::StatusCode(StatusCode::INTERNAL_SERVER_ERROR)));
+        assert!(!is_retryable(&ApiResponseError::StatusCode(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!is_retryable(&err_201()));
+    }
+
+    #[tokio::test]
+    async fn test_post_retried_only_when_connection_never_opened() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), _> = with_retry_if(3, is_retryable_post, &test_limiter(), || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ApiResponseError::StatusCode(StatusCode::SERVICE_UNAVAILABLE))
+        }).await;
+        assert!(matches!(result, Err(ApiResponseError::StatusCode(StatusCode::SERVICE_UNAVAILABLE))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "a 503 may hide an accepted order");
+
+        // Nothing listens on port 1, so the request never leaves
+        let refused = reqwest::Client::new().get("http://127.0.0.1:1/").send().await.unwrap_err();
+        assert!(refused.is_connect());
+        assert!(is_retryable_post(&ApiResponseError::Reqwest(refused)));
+        assert!(!is_retryable_post(&ApiResponseError::StatusCode(StatusCode::GATEWAY_TIMEOUT)));
+    }
+
+    #[test]
+    fn test_retry_delay_grows_with_jitter_bound() {
+        let mut rng = rand::thread_rng();
+        for attempt in 0..4 {
+            let base = RETRY_BASE_DELAY_MS << attempt;
+            let d = retry_delay(attempt, &mut rng).as_millis() as u64;
+            assert!((base..=base + base / 2).contains(&d), "attempt {} delay {}", attempt, d);
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_repeats_under_a_seed() {
+        let delays = |seed| {
+            let limiter = RateLimiter::new(1.0, 1.0).with_jitter_rng(crate::util::seeded_rng(Some(seed)));
+            (0..6).map(|attempt| retry_delay(attempt, &mut *limiter.jitter_rng())).collect::<Vec<_>>()
+        };
+        assert_eq!(delays(7), delays(7));
+        assert_ne!(delays(7), delays(8));
+    }
+
+    #[test]
+    fn test_symbol_display_from_str_round_trip() {
+        for symbol in [Symbol::BTC_JPY, Symbol::ETH_JPY, Symbol::XRP_JPY, Symbol::BCH_JPY] {
+            assert_eq!(Symbol::from_str(&symbol.to_string()), Ok(symbol.clone()));
+            assert_eq!(serde_json::to_string(&symbol).unwrap(), format!("\"{}\"", symbol));
+        }
+        assert_eq!(Symbol::from_str("DOGE_JPY"), Err(()));
+        assert_eq!(Symbol::from_str("Unknown"), Err(()));
+    }
+}
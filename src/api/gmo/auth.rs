@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::env;
 use std::string::String;
+use std::sync::atomic::{AtomicI64, Ordering};
 use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -13,6 +14,17 @@ const API_SECRET: &str = "GMO_API_SECRET";
 static CACHED_API_KEY: OnceLock<String> = OnceLock::new();
 /// Cached HMAC signing key (derived from API secret)
 static CACHED_HMAC_KEY: OnceLock<hmac::Key> = OnceLock::new();
+/// Server time minus local time (ms), applied to API-TIMESTAMP
+static TIME_OFFSET_MS: AtomicI64 = AtomicI64::new(0);
+
+/// Set the server-local clock offset used when signing requests.
+pub fn set_time_offset(ms: i64) {
+    TIME_OFFSET_MS.store(ms, Ordering::Relaxed);
+}
+
+pub fn time_offset() -> i64 {
+    TIME_OFFSET_MS.load(Ordering::Relaxed)
+}
 
 fn get_cached_api_key() -> Result<&'static String, CredentialError> {
     if let Some(key) = CACHED_API_KEY.get() {
@@ -43,25 +55,40 @@ pub fn get_credential(
     let api_key = get_cached_api_key()?;
     let hmac_key = get_cached_hmac_key()?;
 
-    let timestamp = get_timestamp();
+    Ok(build_credential(api_key, hmac_key, method, path, body, get_timestamp()))
+}
+
+fn build_credential(
+    api_key: &str,
+    hmac_key: &hmac::Key,
+    method: &str,
+    path: &str,
+    body: &str,
+    timestamp: u64,
+) -> HashMap<String, String> {
     let sign = get_access_sign(method, path, body, &timestamp, hmac_key);
 
     let mut map = HashMap::new();
 
-    map.insert("API-KEY".to_string(), api_key.clone());
+    map.insert("API-KEY".to_string(), api_key.to_string());
     map.insert("API-TIMESTAMP".to_string(), timestamp.to_string());
     map.insert("API-SIGN".to_string(), sign);
 
-    Ok(map)
+    map
 }
 
-fn get_timestamp() -> u64 {
+fn local_timestamp() -> u64 {
     let start = SystemTime::now();
     let since_epoch = start.duration_since(UNIX_EPOCH).expect("Time went backwards");
 
     since_epoch.as_secs() * 1000 + since_epoch.subsec_nanos() as u64 / 1_000_000
 }
 
+/// Local time corrected by the server offset (ms since epoch)
+fn get_timestamp() -> u64 {
+    local_timestamp().saturating_add_signed(time_offset())
+}
+
 fn get_access_sign(
     method: &str,
     path: &str,
@@ -78,7 +105,10 @@ fn get_access_sign(
 #[cfg(test)]
 mod tests {
     use ring::hmac;
-    use crate::api::gmo::auth::{get_credential, get_access_sign};
+    use crate::api::gmo::auth::{
+        build_credential, get_access_sign, get_credential, get_timestamp, local_timestamp,
+        set_time_offset, time_offset,
+    };
 
     fn test_key(secret: &str) -> hmac::Key {
         hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())
@@ -125,4 +155,25 @@ mod tests {
 
         assert_ne!(sign1, sign2);
     }
+
+    #[test]
+    fn test_signed_timestamp_reflects_offset() {
+        // Only this test touches the global offset
+        set_time_offset(-90_000);
+        assert_eq!(time_offset(), -90_000);
+
+        let local = local_timestamp();
+        let ts = get_timestamp();
+        let drift = ts as i64 - local as i64;
+        assert!((-90_000..=-89_900).contains(&drift), "drift {}", drift);
+
+        let key = test_key("secret");
+        let cred = build_credential("key", &key, "POST", "/v1/order", "{}", ts);
+        assert_eq!(cred["API-TIMESTAMP"], ts.to_string());
+        assert_eq!(cred["API-SIGN"], get_access_sign("POST", "/v1/order", "{}", &ts, &key));
+
+        set_time_offset(0);
+        let drift = get_timestamp() as i64 - local_timestamp() as i64;
+        assert!(drift.abs() <= 100, "drift after reset {}", drift);
+    }
 }
@@ -0,0 +1,47 @@
+use crate::api::gmo::api;
+use crate::api::gmo::api::Symbol;
+use crate::api::gmo::rate_limit::RateLimiter;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+const PATH: &str = "/v1/cancelBulkOrder";
+
+#[derive(Deserialize, Debug)]
+pub struct CancelBulkOrderResponse {
+    /// IDs of the orders that were cancelled
+    pub data: Vec<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CancelBulkOrderParameter {
+    pub symbols: Vec<Symbol>,
+}
+
+/// Cancel every open order for the given symbols.
+pub async fn cancel_bulk_order(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    symbols: &[Symbol],
+) -> Result<(StatusCode, CancelBulkOrderResponse), api::ApiResponseError> {
+    let parameter = CancelBulkOrderParameter { symbols: symbols.to_vec() };
+    api::post::<CancelBulkOrderParameter, CancelBulkOrderResponse>(client, limiter, PATH, &parameter).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_bulk_order_parameter_serialization() {
+        let parameter = CancelBulkOrderParameter { symbols: vec![Symbol::BTC_JPY] };
+        let json = serde_json::to_string(&parameter).unwrap();
+        assert_eq!(json, r#"{"symbols":["BTC_JPY"]}"#);
+    }
+
+    #[test]
+    fn test_cancel_bulk_order_response() {
+        let json = r#"{"status":0,"data":[637000,637002],"responsetime":"2019-03-19T01:07:24.557Z"}"#;
+        let response: CancelBulkOrderResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.data, vec![637000, 637002]);
+    }
+}
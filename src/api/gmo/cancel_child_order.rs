@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 
@@ -15,7 +16,8 @@ pub struct CancelOrderParameter {
 
 pub async fn cancel_order(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     parameter: &CancelOrderParameter,
 ) -> Result<(StatusCode, CancelOrderResponse), api::ApiResponseError> {
-    api::post::<CancelOrderParameter, CancelOrderResponse>(client, PATH, parameter).await
+    api::post::<CancelOrderParameter, CancelOrderResponse>(client, limiter, PATH, parameter).await
 }
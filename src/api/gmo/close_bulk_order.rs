@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use crate::model::OrderSide;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -25,7 +26,9 @@ pub struct CloseBulkOrderParameter {
 
 pub async fn close_bulk_order(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     parameter: &CloseBulkOrderParameter,
+    max_retries: u32,
 ) -> Result<(StatusCode, CloseBulkOrderResponse), api::ApiResponseError> {
-    api::post::<CloseBulkOrderParameter, CloseBulkOrderResponse>(client, PATH, parameter).await
+    api::post_with_retry::<CloseBulkOrderParameter, CloseBulkOrderResponse>(client, limiter, PATH, parameter, max_retries).await
 }
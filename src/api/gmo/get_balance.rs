@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use crate::api::gmo::api::ApiResponseError;
 use serde::Deserialize;
 
@@ -16,8 +17,19 @@ pub struct BalanceResponse {
     pub data: Vec<BalanceDetail>,
 }
 
+impl BalanceResponse {
+    /// Available amount of `currency` (e.g. "JPY", "BTC"); 0 when the wallet has none
+    pub fn available(&self, currency: &str) -> f64 {
+        self.data
+            .iter()
+            .find(|d| d.currency == currency)
+            .map_or(0.0, |d| d.available)
+    }
+}
+
 pub async fn get_balance(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
 ) -> Result<BalanceResponse, ApiResponseError> {
-    api::get::<BalanceResponse>(client, PATH, None).await
+    api::get::<BalanceResponse>(client, limiter, PATH, None).await
 }
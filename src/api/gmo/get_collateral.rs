@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use crate::api::gmo::api::deserialize_number_from_string;
 use serde::{Deserialize};
 
@@ -27,6 +28,6 @@ pub struct CollateralDetail {
     pub margin_call_status: String,
 }
 
-pub async fn get_collateral(client: &reqwest::Client) -> Result<Collateral, api::ApiResponseError> {
-    api::get::<Collateral>(client, PATH, None).await
+pub async fn get_collateral(client: &reqwest::Client, limiter: &RateLimiter) -> Result<Collateral, api::ApiResponseError> {
+    api::get::<Collateral>(client, limiter, PATH, None).await
 }
@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use crate::api::gmo::api::deserialize_number_from_string;
 use std::collections::HashMap;
 use serde::{Deserialize};
@@ -44,9 +45,11 @@ pub struct PositionResponse {
 
 pub async fn get_position(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     symbol: api::Symbol,
+    max_retries: u32,
 ) -> Result<PositionResponse, api::ApiResponseError> {
     let mut params = HashMap::new();
     params.insert("symbol".to_string(), symbol.to_string());
-    api::get::<PositionResponse>(client, PATH, Some(&params)).await
+    api::get_with_retry::<PositionResponse>(client, limiter, PATH, Some(&params), max_retries).await
 }
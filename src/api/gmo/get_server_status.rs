@@ -0,0 +1,108 @@
+use crate::api::gmo::api;
+use crate::api::gmo::auth;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize};
+use std::str::FromStr;
+
+const PATH: &str = "/v1/status";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServerStatus {
+    pub status: String,
+}
+
+impl ServerStatus {
+    pub fn exchange_status(&self) -> ExchangeStatus {
+        ExchangeStatus::from_str(&self.status).unwrap_or(ExchangeStatus::Unknown)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeStatus {
+    Open,
+    Preopen,
+    Maintenance,
+    Unknown,
+}
+
+impl FromStr for ExchangeStatus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "OPEN" => Ok(ExchangeStatus::Open),
+            "PREOPEN" => Ok(ExchangeStatus::Preopen),
+            "MAINTENANCE" => Ok(ExchangeStatus::Maintenance),
+            _ => Ok(ExchangeStatus::Unknown),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServerStatusResponse {
+    pub data: ServerStatus,
+    pub responsetime: String,
+}
+
+pub async fn get_server_status(client: &reqwest::Client) -> Result<ServerStatusResponse, api::ApiResponseError> {
+    api::get_public::<ServerStatusResponse>(client, PATH, None).await
+}
+
+/// Offset (server - local, ms) from GMO's `responsetime`, using the midpoint of the
+/// local request/response times to cancel out half the round trip.
+pub fn compute_time_offset(responsetime: &str, local_sent_ms: i64, local_received_ms: i64) -> Option<i64> {
+    let server_ms = DateTime::parse_from_rfc3339(responsetime).ok()?.timestamp_millis();
+    let local_mid_ms = local_sent_ms + (local_received_ms - local_sent_ms) / 2;
+    Some(server_ms - local_mid_ms)
+}
+
+/// Fetch server time and apply the offset to request signing.
+/// Returns the applied offset, or None if `responsetime` could not be parsed.
+pub async fn sync_server_time(client: &reqwest::Client) -> Result<Option<i64>, api::ApiResponseError> {
+    let sent = Utc::now().timestamp_millis();
+    let response = get_server_status(client).await?;
+    let received = Utc::now().timestamp_millis();
+
+    let offset = compute_time_offset(&response.responsetime, sent, received);
+    if let Some(ms) = offset {
+        auth::set_time_offset(ms);
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compute_time_offset_uses_round_trip_midpoint() {
+        // server 2024-01-15T10:30:00.000Z = 1705314600000
+        let offset = compute_time_offset("2024-01-15T10:30:00.000Z", 1705314601000, 1705314601200);
+        assert_eq!(offset, Some(-1100));
+
+        let offset = compute_time_offset("2024-01-15T10:30:00.500Z", 1705314600000, 1705314600000);
+        assert_eq!(offset, Some(500));
+    }
+
+    #[test]
+    fn test_compute_time_offset_rejects_garbage() {
+        assert_eq!(compute_time_offset("not-a-time", 0, 0), None);
+    }
+
+    #[test]
+    fn test_parse_status_response() {
+        let json = r#"{"status":0,"data":{"status":"OPEN"},"responsetime":"2019-03-19T02:15:06.001Z"}"#;
+        let response: ServerStatusResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(response.data.status, "OPEN");
+        assert_eq!(response.responsetime, "2019-03-19T02:15:06.001Z");
+    }
+
+    #[test]
+    fn test_exchange_status_from_status_string() {
+        let status = |s: &str| ServerStatus { status: s.to_string() }.exchange_status();
+        assert_eq!(status("OPEN"), ExchangeStatus::Open);
+        assert_eq!(status("PREOPEN"), ExchangeStatus::Preopen);
+        assert_eq!(status("MAINTENANCE"), ExchangeStatus::Maintenance);
+        assert_eq!(status("CLOSE"), ExchangeStatus::Unknown);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::api::gmo::api;
+use crate::api::gmo::api::deserialize_number_from_string;
+use crate::model::SymbolRule;
+use serde::{Deserialize};
+
+const PATH: &str = "/v1/symbols";
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SymbolsResponse {
+    pub data: Vec<SymbolDetail>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SymbolDetail {
+    pub symbol: String,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "minOrderSize")]
+    pub min_order_size: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "maxOrderSize")]
+    pub max_order_size: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "sizeStep")]
+    pub size_step: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "tickSize")]
+    pub tick_size: f64,
+}
+
+impl From<SymbolDetail> for SymbolRule {
+    fn from(detail: SymbolDetail) -> Self {
+        SymbolRule {
+            symbol: detail.symbol,
+            tick_size: detail.tick_size,
+            size_step: detail.size_step,
+            min_lot: detail.min_order_size,
+            max_lot: detail.max_order_size,
+        }
+    }
+}
+
+/// Fetch tick size / size step / order size bounds for every symbol.
+pub async fn get_symbol_rules(client: &reqwest::Client) -> Result<Vec<SymbolRule>, api::ApiResponseError> {
+    let response = api::get_public::<SymbolsResponse>(client, PATH, None).await?;
+    Ok(response.data.into_iter().map(SymbolRule::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_symbols_response() {
+        let json = r#"{
+            "status": 0,
+            "data": [
+                {"symbol": "BTC_JPY", "minOrderSize": "0.01", "maxOrderSize": "5", "sizeStep": "0.01", "tickSize": "1", "takerFee": "0", "makerFee": "0"},
+                {"symbol": "ETH_JPY", "minOrderSize": "0.1", "maxOrderSize": "50", "sizeStep": "0.1", "tickSize": "1", "takerFee": "0", "makerFee": "0"}
+            ],
+            "responsetime": "2024-01-15T10:30:00.000Z"
+        }"#;
+        let response: SymbolsResponse = serde_json::from_str(json).unwrap();
+        let rules: Vec<SymbolRule> = response.data.into_iter().map(SymbolRule::from).collect();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].symbol, "BTC_JPY");
+        assert_eq!(rules[0].min_lot, 0.01);
+        assert_eq!(rules[0].max_lot, 5.0);
+        assert_eq!(rules[1].symbol, "ETH_JPY");
+        assert_eq!(rules[1].size_step, 0.1);
+        assert_eq!(rules[1].tick_size, 1.0);
+    }
+}
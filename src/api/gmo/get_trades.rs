@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+
+use crate::api::gmo::api::{self, deserialize_number_from_string, Symbol};
+use crate::api::gmo::ws::{Side, Timestamp};
+use serde::Deserialize;
+
+const PATH: &str = "/v1/trades";
+/// Largest page the endpoint serves
+pub const MAX_COUNT: u32 = 100;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TradesResponse {
+    pub data: TradesData,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TradesData {
+    /// Newest first
+    pub list: Vec<TradeItem>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TradeItem {
+    pub side: Side,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub price: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub size: f64,
+    pub timestamp: Timestamp,
+}
+
+/// Most recent public trades for `symbol` (up to `MAX_COUNT`), newest first.
+pub async fn get_trades(
+    client: &reqwest::Client,
+    symbol: &Symbol,
+    count: u32,
+) -> Result<Vec<TradeItem>, api::ApiResponseError> {
+    let query = HashMap::from([
+        ("symbol".to_string(), symbol.to_string()),
+        ("page".to_string(), "1".to_string()),
+        ("count".to_string(), count.clamp(1, MAX_COUNT).to_string()),
+    ]);
+    let response = api::get_public::<TradesResponse>(client, PATH, Some(&query)).await?;
+    Ok(response.data.list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trades_response() {
+        let json = r#"{
+            "status": 0,
+            "data": {
+                "pagination": {"currentPage": 1, "count": 2},
+                "list": [
+                    {"price": "10000500", "side": "BUY", "size": "0.01", "timestamp": "2024-01-15T10:30:01.250Z"},
+                    {"price": "10000000", "side": "SELL", "size": "0.2", "timestamp": "2024-01-15T10:30:00.000Z"}
+                ]
+            },
+            "responsetime": "2024-01-15T10:30:02.000Z"
+        }"#;
+        let response: TradesResponse = serde_json::from_str(json).unwrap();
+        let list = response.data.list;
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].side, Side::BUY);
+        assert_eq!((list[0].price, list[0].size), (10_000_500.0, 0.01));
+        assert_eq!(list[0].timestamp.get_timestamp(), 1_705_314_601_250);
+        assert_eq!(list[1].side, Side::SELL);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+
+use parking_lot::{Mutex, MutexGuard};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{sleep, Duration, Instant};
+
+/// Async token bucket shared by every private API call.
+///
+/// Holds up to `capacity` tokens and refills `refill_per_sec` tokens per second.
+/// `acquire()` takes one token, sleeping until one is available.
+///
+/// Optionally also caps how many order requests may be in flight at once across tasks
+/// (`with_max_in_flight`); the bucket alone only limits how fast they start.
+///
+/// `with_max_orders_per_sec` adds a hard sliding-window cap on order / cancel requests. Unlike
+/// the bucket it never waits: `try_order_slot()` says no and the caller defers to its next cycle.
+///
+/// It also carries the RNG the API retries draw their backoff jitter from (`with_jitter_rng`).
+#[derive(Debug)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<Bucket>,
+    in_flight: Option<Semaphore>,
+    order_window: Option<OrderWindow>,
+    jitter: Mutex<StdRng>,
+}
+
+#[derive(Debug)]
+struct OrderWindow {
+    max_per_sec: usize,
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        assert!(capacity >= 1.0, "rate limiter capacity must be >= 1");
+        assert!(refill_per_sec > 0.0, "rate limiter refill_per_sec must be > 0");
+        Self {
+            capacity,
+            refill_per_sec,
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+            in_flight: None,
+            order_window: None,
+            jitter: Mutex::new(StdRng::from_entropy()),
+        }
+    }
+
+    /// Draw retry jitter from `rng` instead of entropy (seeded from `rng_seed` for reproducible runs).
+    pub fn with_jitter_rng(mut self, rng: StdRng) -> Self {
+        self.jitter = Mutex::new(rng);
+        self
+    }
+
+    /// The retry-jitter RNG. Never hold the guard across an await.
+    pub fn jitter_rng(&self) -> MutexGuard<'_, StdRng> {
+        self.jitter.lock()
+    }
+
+    /// Allow at most `max` concurrent holders of `acquire_in_flight()` (0 = unlimited).
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.in_flight = (max > 0).then(|| Semaphore::new(max));
+        self
+    }
+
+    /// Allow at most `max` order / cancel requests in any 1s window (0 = unlimited).
+    pub fn with_max_orders_per_sec(mut self, max: usize) -> Self {
+        self.order_window = (max > 0).then(|| OrderWindow { max_per_sec: max, sent: Mutex::new(VecDeque::new()) });
+        self
+    }
+
+    /// Claim a slot in the per-second order window. Returns false when the window is full;
+    /// the request must then be skipped (not queued). Always true when no cap is configured.
+    pub fn try_order_slot(&self) -> bool {
+        let Some(window) = &self.order_window else {
+            return true;
+        };
+        let now = Instant::now();
+        let mut sent = window.sent.lock();
+        while sent.front().is_some_and(|t| now.duration_since(*t) >= Duration::from_secs(1)) {
+            sent.pop_front();
+        }
+        if sent.len() >= window.max_per_sec {
+            return false;
+        }
+        sent.push_back(now);
+        true
+    }
+
+    /// Wait for an in-flight slot. Hold the permit until the response has been read.
+    /// Returns None when no cap is configured.
+    pub async fn acquire_in_flight(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.in_flight {
+            Some(semaphore) => Some(semaphore.acquire().await.expect("in-flight semaphore is never closed")),
+            None => None,
+        }
+    }
+
+    /// Wait for and consume one token.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.state.lock();
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+            sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_burst_within_capacity_is_immediate() {
+        let limiter = RateLimiter::new(3.0, 1.0);
+        for _ in 0..3 {
+            assert!(tokio::time::timeout(Duration::from_millis(5), limiter.acquire()).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exceeding_burst_waits_for_refill() {
+        let limiter = RateLimiter::new(2.0, 10.0);
+        limiter.acquire().await;
+        limiter.acquire().await;
+
+        // Bucket empty: the third permit must not be granted immediately
+        assert!(tokio::time::timeout(Duration::from_millis(20), limiter.acquire()).await.is_err());
+
+        // ...but arrives once a token has refilled (~100ms at 10/s)
+        let start = Instant::now();
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight_bounds_concurrency_in_burst() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let limiter = Arc::new(RateLimiter::new(100.0, 100.0).with_max_in_flight(2));
+        let current = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        // Burst of 10 simulated order requests, each holding its slot for 10ms
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let (limiter, current, peak) = (limiter.clone(), current.clone(), peak.clone());
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire_in_flight().await;
+                    limiter.acquire().await;
+                    let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    sleep(Duration::from_millis(10)).await;
+                    current.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_in_flight_zero_is_unlimited() {
+        let limiter = RateLimiter::new(1.0, 1.0).with_max_in_flight(0);
+        assert!(limiter.acquire_in_flight().await.is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_order_window_never_exceeds_limit_per_second() {
+        let limiter = RateLimiter::new(100.0, 100.0).with_max_orders_per_sec(3);
+        let mut placed: Vec<Instant> = Vec::new();
+
+        // An attempt every 50ms for 3s
+        for _ in 0..60 {
+            if limiter.try_order_slot() {
+                placed.push(Instant::now());
+            }
+            tokio::time::advance(Duration::from_millis(50)).await;
+        }
+
+        assert_eq!(placed.len(), 9, "3 per second over 3s");
+        for (i, t) in placed.iter().enumerate() {
+            let in_window = placed[i..].iter().filter(|u| u.duration_since(*t) < Duration::from_secs(1)).count();
+            assert!(in_window <= 3, "{} orders within 1s of {:?}", in_window, t);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_order_window_unlimited_by_default() {
+        let limiter = RateLimiter::new(1.0, 1.0);
+        assert!((0..1000).all(|_| limiter.try_order_slot()));
+    }
+}
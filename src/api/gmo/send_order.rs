@@ -1,4 +1,5 @@
 use crate::api::gmo::api;
+use crate::api::gmo::rate_limit::RateLimiter;
 use crate::model::OrderSide;
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
@@ -28,7 +29,9 @@ pub struct ChildOrderParameter {
 
 pub async fn post_child_order(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     parameter: &ChildOrderParameter,
+    max_retries: u32,
 ) -> Result<(StatusCode, PostSendOrderResponse), api::ApiResponseError> {
-    api::post::<ChildOrderParameter, PostSendOrderResponse>(client, PATH, parameter).await
+    api::post_with_retry::<ChildOrderParameter, PostSendOrderResponse>(client, limiter, PATH, parameter, max_retries).await
 }
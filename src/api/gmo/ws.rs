@@ -77,6 +77,8 @@ pub struct BoardItem {
 #[derive(Deserialize, Debug)]
 pub struct Message {
     pub channel: Channel,
+    #[serde(default)]
+    pub symbol: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
@@ -0,0 +1,266 @@
+use crate::api::gmo::api;
+use crate::api::gmo::api::deserialize_number_from_string;
+use crate::api::gmo::rate_limit::RateLimiter;
+use crate::api::gmo::ws::Timestamp;
+use crate::model::OrderSide;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+const PATH: &str = "/v1/ws-auth";
+pub const PRIVATE_WS_ENDPOINT: &str = "wss://api.coin.z.com/ws/private/v1";
+
+#[derive(Deserialize, Debug)]
+pub struct WsAuthResponse {
+    /// Access token (valid for 60 minutes unless extended)
+    pub data: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WsAuthExtendResponse {}
+
+#[derive(Serialize, Debug)]
+pub struct WsAuthParameter {}
+
+#[derive(Serialize, Debug)]
+pub struct WsAuthExtendParameter {
+    pub token: String,
+}
+
+/// Obtain a private WebSocket access token.
+pub async fn get_ws_token(client: &reqwest::Client, limiter: &RateLimiter) -> Result<String, api::ApiResponseError> {
+    let (_, response) = api::post::<WsAuthParameter, WsAuthResponse>(client, limiter, PATH, &WsAuthParameter {}).await?;
+    Ok(response.data)
+}
+
+/// Extend a token's validity by another 60 minutes.
+pub async fn extend_ws_token(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    token: &str,
+) -> Result<(), api::ApiResponseError> {
+    let parameter = WsAuthExtendParameter { token: token.to_string() };
+    api::put::<WsAuthExtendParameter, WsAuthExtendResponse>(client, limiter, PATH, &parameter).await?;
+    Ok(())
+}
+
+pub fn private_ws_url(token: &str) -> String {
+    format!("{}/{}", PRIVATE_WS_ENDPOINT, token)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum PrivateChannel {
+    ExecutionEvents,
+    OrderEvents,
+}
+
+impl PrivateChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrivateChannel::ExecutionEvents => "executionEvents",
+            PrivateChannel::OrderEvents => "orderEvents",
+        }
+    }
+}
+
+pub fn subscribe_message(channel: &PrivateChannel) -> String {
+    serde_json::json!({
+        "command": "subscribe",
+        "channel": channel.as_str(),
+    }).to_string()
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PrivateMessage {
+    pub channel: PrivateChannel,
+    #[serde(default)]
+    pub symbol: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SettleType {
+    Open,
+    Close,
+}
+
+fn deserialize_order_side<'de, D>(deserializer: D) -> Result<OrderSide, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    OrderSide::from_str(&s).map_err(|_| serde::de::Error::custom(format!("unknown side {}", s)))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExecutionEvent {
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
+    #[serde(rename = "executionId")]
+    pub execution_id: u64,
+    pub symbol: String,
+    #[serde(rename = "settleType")]
+    pub settle_type: SettleType,
+    #[serde(deserialize_with = "deserialize_order_side")]
+    pub side: OrderSide,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "executionPrice")]
+    pub execution_price: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "executionSize")]
+    pub execution_size: f64,
+
+    #[serde(rename = "positionId")]
+    pub position_id: Option<u64>,
+
+    #[serde(rename = "executionTimestamp")]
+    pub execution_timestamp: Timestamp,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "orderSize")]
+    pub order_size: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "orderExecutedSize")]
+    pub order_executed_size: f64,
+}
+
+impl ExecutionEvent {
+    /// The whole order has been executed (no remaining size on the book)
+    pub fn is_fully_filled(&self) -> bool {
+        self.order_executed_size >= self.order_size
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum OrderStatus {
+    Waiting,
+    Ordered,
+    Modifying,
+    Cancelling,
+    Canceled,
+    Executed,
+    Expired,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OrderEvent {
+    #[serde(rename = "orderId")]
+    pub order_id: u64,
+    pub symbol: String,
+    #[serde(rename = "settleType")]
+    pub settle_type: SettleType,
+    #[serde(deserialize_with = "deserialize_order_side")]
+    pub side: OrderSide,
+    #[serde(rename = "orderStatus")]
+    pub order_status: OrderStatus,
+
+    /// 0 for MARKET orders
+    #[serde(default, deserialize_with = "deserialize_number_from_string", rename = "orderPrice")]
+    pub order_price: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "orderSize")]
+    pub order_size: f64,
+
+    #[serde(deserialize_with = "deserialize_number_from_string", rename = "orderExecutedSize")]
+    pub order_executed_size: f64,
+}
+
+impl OrderEvent {
+    /// Order left the book without (fully) executing
+    pub fn is_terminated(&self) -> bool {
+        matches!(self.order_status, OrderStatus::Canceled | OrderStatus::Expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXECUTION_EVENT: &str = r#"{
+        "channel":"executionEvents",
+        "orderId":123456789,
+        "executionId":72123911,
+        "symbol":"BTC_JPY",
+        "settleType":"OPEN",
+        "executionType":"LIMIT",
+        "side":"BUY",
+        "executionPrice":"10000000",
+        "executionSize":"0.001",
+        "positionId":123456789,
+        "orderTimestamp":"2019-03-19T02:15:06.081Z",
+        "executionTimestamp":"2019-03-19T02:15:06.081Z",
+        "lossGain":"0",
+        "fee":"0",
+        "orderPrice":"10000000",
+        "orderSize":"0.001",
+        "orderExecutedSize":"0.001",
+        "timeInForce":"SOK",
+        "msgType":"ER"
+    }"#;
+
+    #[test]
+    fn test_deserialize_execution_event() {
+        let msg: PrivateMessage = serde_json::from_str(EXECUTION_EVENT).unwrap();
+        assert_eq!(msg.channel, PrivateChannel::ExecutionEvents);
+
+        let event: ExecutionEvent = serde_json::from_str(EXECUTION_EVENT).unwrap();
+        assert_eq!(event.order_id, 123456789);
+        assert_eq!(event.execution_id, 72123911);
+        assert_eq!(event.settle_type, SettleType::Open);
+        assert_eq!(event.side, OrderSide::BUY);
+        assert_eq!(event.execution_price, 10_000_000.0);
+        assert_eq!(event.execution_size, 0.001);
+        assert_eq!(event.execution_timestamp.get_timestamp(), 1552961706081);
+        assert!(event.is_fully_filled());
+    }
+
+    #[test]
+    fn test_deserialize_partial_close_execution() {
+        let json = EXECUTION_EVENT
+            .replace(r#""settleType":"OPEN""#, r#""settleType":"CLOSE""#)
+            .replace(r#""side":"BUY""#, r#""side":"SELL""#)
+            .replace(r#""orderSize":"0.001""#, r#""orderSize":"0.002""#);
+        let event: ExecutionEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(event.settle_type, SettleType::Close);
+        assert_eq!(event.side, OrderSide::SELL);
+        assert!(!event.is_fully_filled());
+    }
+
+    #[test]
+    fn test_deserialize_order_event() {
+        let json = r#"{
+            "channel":"orderEvents",
+            "orderId":123456789,
+            "symbol":"BTC_JPY",
+            "settleType":"OPEN",
+            "executionType":"LIMIT",
+            "side":"SELL",
+            "orderStatus":"CANCELED",
+            "cancelType":"USER",
+            "orderTimestamp":"2019-03-19T02:15:06.081Z",
+            "orderPrice":"10000100",
+            "orderSize":"0.001",
+            "orderExecutedSize":"0",
+            "losscutPrice":"0",
+            "timeInForce":"SOK",
+            "msgType":"COR"
+        }"#;
+        let msg: PrivateMessage = serde_json::from_str(json).unwrap();
+        assert_eq!(msg.channel, PrivateChannel::OrderEvents);
+
+        let event: OrderEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event.order_status, OrderStatus::Canceled);
+        assert!(event.is_terminated());
+        assert_eq!(event.side, OrderSide::SELL);
+        assert_eq!(event.order_price, 10_000_100.0);
+    }
+
+    #[test]
+    fn test_subscribe_message_and_url() {
+        assert_eq!(
+            subscribe_message(&PrivateChannel::ExecutionEvents),
+            r#"{"channel":"executionEvents","command":"subscribe"}"#
+        );
+        assert_eq!(private_ws_url("abc"), "wss://api.coin.z.com/ws/private/v1/abc");
+    }
+}
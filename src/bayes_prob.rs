@@ -1,51 +1,111 @@
 use crate::time_queue::TimeQueue;
-use std::time::Duration;
+use rand::Rng;
+use rand_distr::{Beta, Distribution};
+use std::time::{Duration, Instant};
 
 // ベータ分布を用いたベイズ確率
 // データは直近duration間を保持するTimeQueueを用いる
+// half_lifeを指定した場合は、各データを 0.5^(経過時間/half_life) で重み付けして集計する
+// 事後分布は読み出し時点で集計するので、更新がなくても古いデータの重みは下がっていく
 #[derive(Debug, Clone)]
 pub struct BayesProb {
-    pub distribution: BetaDistribution,
     prior: BetaDistribution,
     time_data: TimeQueue<(u64, u64)>,
+    half_life: Option<Duration>,
 }
 
 impl BayesProb {
     pub fn new(prior_distribution: BetaDistribution, retain_duration: Duration) -> BayesProb {
         BayesProb {
-            distribution: prior_distribution.clone(),
             prior: prior_distribution,
             time_data: TimeQueue::new(retain_duration),
+            half_life: None,
         }
     }
 
+    // 直近のデータほど重くする (None = 窓内のデータを均等に扱う)
+    pub fn with_half_life(mut self, half_life: Option<Duration>) -> BayesProb {
+        self.half_life = half_life.filter(|h| !h.is_zero());
+        self
+    }
+
     // ベイズ更新
     // n: 試行回数, r: 成功回数
     // 1回試行して成功したかを更新する場合はupdate(1, 1 or 0)とする
     pub fn update(&mut self, n: u64, r: u64) {
         self.time_data.retain();
         self.time_data.push((n, r));
+    }
 
-        // Accumulate: total_n = sum of trials, total_r = sum of successes
-        let (total_n, total_r) = self.time_data.get_data().iter()
-            .fold((0u64, 0u64), |(acc_n, acc_r), &(n, r)| (acc_n + n, acc_r + r));
+    // 事後分布Be(a, b)の擬似カウント: Prior + 窓内の(重み付き)成功数 / 失敗数
+    // 重みは呼び出し時点の経過時間で計算し、丸めずに浮動小数のまま持つ
+    pub fn posterior(&self) -> (f64, f64) {
+        let now = Instant::now();
+        let window = self.time_data.duration();
+        let (total_n, total_r) = self.time_data.data().iter()
+            .filter(|(at, _)| now.duration_since(*at) <= window)
+            .fold((0.0, 0.0), |(acc_n, acc_r), (at, (n, r))| {
+                let weight = match self.half_life {
+                    Some(half_life) => 0.5f64.powf(now.duration_since(*at).as_secs_f64() / half_life.as_secs_f64()),
+                    None => 1.0,
+                };
+                (acc_n + weight * *n as f64, acc_r + weight * *r as f64)
+            });
+        // r <= n per entry keeps the failures non-negative
+        (self.prior.a as f64 + total_r, self.prior.b as f64 + (total_n - total_r))
+    }
 
-        // Posterior = Prior + Likelihood: Be(prior_a + successes, prior_b + failures)
-        self.distribution = BetaDistribution::new(
-            self.prior.a + total_r,
-            self.prior.b + (total_n - total_r),
-        );
+    // 観測を捨てて事前分布に戻す (レジーム変化で過去の約定率が当てにならない時)
+    pub fn reset_to_prior(&mut self) {
+        self.time_data.clear();
     }
-    
+
     // ベータ分布の平均確率
     pub fn calc_average(&self) -> f64 {
-        let denominator = self.distribution.a + self.distribution.b;
-        if denominator == 0 {
+        let (a, b) = self.posterior();
+        if a + b <= 0.0 {
             return 0.5; // Return uninformative prior expectation
         }
-        let e = self.distribution.a as f64 / denominator as f64;
-        e.clamp(0.0, 1.0)
-    }    
+        (a / (a + b)).clamp(0.0, 1.0)
+    }
+
+    // 事後分布Be(a, b)の分散: ab / ((a+b)^2 (a+b+1))
+    pub fn variance(&self) -> f64 {
+        let (a, b) = self.posterior();
+        let n = a + b;
+        if n <= 0.0 {
+            return 0.0;
+        }
+        a * b / (n * n * (n + 1.0))
+    }
+
+    // 中心信用区間: p=0.9なら5%点と95%点
+    // a or b が0の退化ケースは点質量として扱う
+    pub fn credible_interval(&self, p: f64) -> (f64, f64) {
+        match self.posterior() {
+            (a, b) if a <= 0.0 && b <= 0.0 => (0.0, 1.0),
+            (a, _) if a <= 0.0 => (0.0, 0.0),
+            (_, b) if b <= 0.0 => (1.0, 1.0),
+            (a, b) => {
+                let tail = (1.0 - p.clamp(0.0, 1.0)) / 2.0;
+                (beta_quantile(a, b, tail), beta_quantile(a, b, 1.0 - tail))
+            }
+        }
+    }
+
+    // Thompson sampling: 事後分布Be(a, b)から1サンプル
+    // a or b が0の退化ケースは点質量として扱う
+    pub fn sample(&self, rng: &mut impl Rng) -> f64 {
+        match self.posterior() {
+            (a, b) if a <= 0.0 && b <= 0.0 => 0.5,
+            (a, _) if a <= 0.0 => 0.0,
+            (_, b) if b <= 0.0 => 1.0,
+            (a, b) => match Beta::new(a, b) {
+                Ok(beta) => beta.sample(rng).clamp(0.0, 1.0),
+                Err(_) => self.calc_average(),
+            },
+        }
+    }
 }
 
 // ベータ分布
@@ -60,3 +120,108 @@ impl BetaDistribution {
         BetaDistribution { a, b }
     }
 }
+
+// ln Γ(x) (Lanczos近似, g=7)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // 反転公式
+        return (std::f64::consts::PI / (std::f64::consts::PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let series = COEFFS[1..]
+        .iter()
+        .enumerate()
+        .fold(COEFFS[0], |acc, (i, c)| acc + c / (x + i as f64 + 1.0));
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+// 不完全ベータ関数の連分数展開 (Lentz法)
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const MAX_ITER: usize = 300;
+    const EPS: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    let (qab, qap, qam) = (a + b, a + 1.0, a - 1.0);
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..=MAX_ITER {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let del = d * c;
+        h *= del;
+        if (del - 1.0).abs() < EPS {
+            break;
+        }
+    }
+    h
+}
+
+// 正則化不完全ベータ関数 I_x(a, b) = Be(a, b)の累積分布関数
+fn beta_cdf(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let ln_front = ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln();
+    let front = ln_front.exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Be(a, b)のq分位点 (CDFは単調なので二分法)
+fn beta_quantile(a: f64, b: f64, q: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0, 1.0);
+    for _ in 0..100 {
+        let mid = (lo + hi) / 2.0;
+        if beta_cdf(a, b, mid) < q {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
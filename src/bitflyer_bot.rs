@@ -1,29 +1,34 @@
 pub mod api;
 pub mod bayes_prob;
+pub mod decimal;
+pub mod exchange;
 pub mod model;
+pub mod reconnect;
 pub mod time_queue;
 pub mod util;
 
 use crate::api::bitflyer;
+use crate::api::bitflyer::api::ProductCode;
 use crate::bitflyer::ws::Side;
 use crate::model::BotConfig;
 use crate::bayes_prob::{BayesProb, BetaDistribution};
-use crate::api::bitflyer::api::ProductCode;
-use crate::api::bitflyer::api::ChildOrderType;
+use crate::api::bitflyer::get_health::HealthStatusEnum;
+use crate::api::bitflyer::ws_private::ChildOrderEventType;
+use crate::exchange::{Exchange, FeedEvent, NewOrder};
+use crate::exchange::bitflyer::BitflyerExchange;
 
 use std::{
     collections::BTreeMap,
     collections::HashMap,
     ops::{Add, Sub},
-    str::FromStr,
     sync::Arc,
-    time::Duration,
-    fs,
+    time::{Duration, Instant},
 };
 
 use chrono::Utc;
 use futures::{SinkExt, StreamExt};
 use parking_lot::{Mutex, RwLock};
+use rand::{rngs::StdRng, Rng};
 use tokio::{runtime::Builder, time::sleep};
 use tokio_tungstenite::{connect_async, tungstenite::{Message, Result}};
 use rayon::prelude::*;
@@ -39,6 +44,49 @@ type OrderBook = RwLock<BTreeMap<u64, f64>>;
 // (price, size, timestamp, delay)
 type Executions = RwLock<Vec<(u64, f64, i64, i64, Side)>>;
 
+type Health = RwLock<HealthStatusEnum>;
+
+const HEALTH_POLL_INTERVAL_SECS: u64 = 10;
+
+/// SFD is charged once |FX - spot| / spot reaches 5%, stepping up every further 0.5%.
+const SFD_THRESHOLD: f64 = 0.05;
+const SFD_STEP: f64 = 0.005;
+const SFD_BASE_RATE: f64 = 0.0025;
+const SFD_RATE_STEP: f64 = 0.0025;
+const SFD_MAX_RATE: f64 = 0.02;
+
+/// SFD rate (fraction of notional) charged on trades that widen the FX-vs-spot deviation.
+/// `deviation` = (fx - spot) / spot; sign is ignored.
+fn sfd_rate(deviation: f64) -> f64 {
+    let d = deviation.abs();
+    if !d.is_finite() || d < SFD_THRESHOLD {
+        return 0.0;
+    }
+    // Small epsilon so exact step boundaries (5.5%, 6.0%...) land on the upper step
+    let steps = ((d - SFD_THRESHOLD) / SFD_STEP + 1e-9).floor();
+    (SFD_BASE_RATE + steps * SFD_RATE_STEP).min(SFD_MAX_RATE)
+}
+
+/// Side whose fills widen the deviation (and pay SFD): buying when FX trades above spot,
+/// selling when below.
+fn sfd_penalized_side(deviation: f64) -> Option<model::OrderSide> {
+    if deviation > 0.0 {
+        Some(model::OrderSide::BUY)
+    } else if deviation < 0.0 {
+        Some(model::OrderSide::SELL)
+    } else {
+        None
+    }
+}
+
+/// Exchange states where fills are unreliable or orders are rejected: do not quote.
+fn health_allows_orders(status: &HealthStatusEnum) -> bool {
+    !matches!(
+        status,
+        HealthStatusEnum::VeryBusy | HealthStatusEnum::SuperBusy | HealthStatusEnum::NoOrder | HealthStatusEnum::Stop
+    )
+}
+
 /// 注文パラメータのバリデーション
 fn validate_order_params(
     price: u64,
@@ -60,44 +108,23 @@ fn validate_order_params(
     Ok(())
 }
 
-async fn cancel_child_order(client: &reqwest::Client, config: &BotConfig, order_list: &Orders) -> Result<()> {
+async fn cancel_child_order<E: Exchange>(exchange: &E, config: &BotConfig, order_list: &Orders) -> Result<()> {
     loop {
-        sleep(Duration::from_millis(500)).await;
+        sleep(Duration::from_millis(config.cancel_interval_ms)).await;
 
-        let list = order_list.lock().clone();
-
-        for order in list.iter() {
-            let now = Utc::now().timestamp_millis() as u64;
-
-            if now - order.1.timestamp < config.order_cancel_ms {
-                continue;
-            }
-
-            let child_order_acceptance_id = order.0.to_string();
-
-            let parameter = bitflyer::cancel_child_order::CancelChildOrderParameter {
-                product_code: ProductCode::FX_BTC_JPY,
-                child_order_acceptance_id: child_order_acceptance_id.clone(),
-            };
-
-            if let Err(e) = bitflyer::cancel_child_order::cancel_child_order(client, &parameter).await {
-                warn!("Failed to cancel order {}: {:?}", child_order_acceptance_id, e);
-            }
-
-            if order_list.lock().contains_key(&child_order_acceptance_id) {
-                order_list.lock().remove(&child_order_acceptance_id);
-            }
-        }
+        let now = Utc::now().timestamp_millis() as u64;
+        exchange::cancel_stale_orders(exchange, order_list, now, config.order_cancel_ms).await;
     }
 }
 
-async fn send_order(
-    client: &reqwest::Client,
+async fn send_order<E: Exchange>(
+    exchange: &E,
     config: &BotConfig,
     order_list: &Orders,
     side: model::OrderSide,
     price: u64,
     size: f64,
+    ev: &OrderEv,
 ) -> Result<()> {
     // 注文パラメータのバリデーション
     if let Err(e) = validate_order_params(price, size, config) {
@@ -105,55 +132,98 @@ async fn send_order(
         return Ok(());
     }
 
-    let parameter = bitflyer::send_order::ChildOrderParameter {
-        product_code: ProductCode::FX_BTC_JPY,
-        child_order_type: ChildOrderType::LIMIT,
-        side: side.clone(),
-        price: Some(price),
+    let order = NewOrder { side: side.clone(), price: Some(price), size };
+    let order_info = model::OrderInfo {
+        price,
         size,
-        minute_to_expire: 1,
+        side,
+        timestamp: Utc::now().timestamp_millis() as u64,
+        is_close: false,
+        mid_price: 0.0,
+        t_optimal_ms: 0,
+        sigma_1s: 0.0,
+        spread_pct: 0.0,
+        level: ev.level,
+        // Fill outcomes aren't fed back into the posteriors here
+        level_key: None,
+        p_fill: ev.p_fill,
+        best_ev: ev.best_ev,
+        single_leg_ev: ev.single_leg_ev,
+        client_id: util::new_client_order_id(),
     };
 
-    let response = bitflyer::send_order::post_child_order(client, &parameter).await;
-
-    match response {
-        Ok(response) => {
-            let order_info = model::OrderInfo {
-                price,
-                size,
-                side,
-                timestamp: Utc::now().timestamp_millis() as u64,
-                is_close: false,
-                mid_price: 0,
-                t_optimal_ms: 0,
-                sigma_1s: 0.0,
-                spread_pct: 0.0,
-                level: 0,
-                p_fill: 0.0,
-                best_ev: 0.0,
-                single_leg_ev: 0.0,
-            };
-
-            info!("Send Order: {:?}", parameter);
-
-            order_list
-                .lock()
-                .insert(response.1.child_order_acceptance_id, order_info);
-        }
-        Err(e) => {
-            error!("Send Order Failed: {:?}", e);
-        }
+    if let Err(e) = exchange::place_order(exchange, order_list, &order, order_info).await {
+        error!("Send Order Failed: {}", e);
     }
     Ok(())
 }
 
+/// Level and EV recorded with an order in `OrderInfo`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct OrderEv {
+    level: u32,
+    p_fill: f64,
+    /// EV of the chosen (buy, sell) pair
+    best_ev: f64,
+    /// This leg alone: P(fill) times its distance from mid, net of the expected fee
+    single_leg_ev: f64,
+}
+
+/// `OrderEv` for the `side` leg quoted at `level` of `probabilities`
+#[allow(clippy::too_many_arguments)]
+fn order_ev(
+    side: &model::OrderSide,
+    level: &model::FloatingExp,
+    probabilities: &BTreeMap<model::FloatingExp, (f64, BayesProb)>,
+    best_ev: f64,
+    mid_price: f64,
+    best_bid: f64,
+    best_ask: f64,
+    fees: &FeeModel,
+) -> OrderEv {
+    let p_fill = probabilities.get(level).map_or(0.0, |(_, b)| b.calc_average());
+    let distance = mid_price * level.calc();
+    let price = if *side == model::OrderSide::BUY { mid_price - distance } else { mid_price + distance };
+    OrderEv {
+        level: level.rate as u32,
+        p_fill,
+        best_ev,
+        single_leg_ev: p_fill * (distance - fees.leg_fee(side, price, best_bid, best_ask)),
+    }
+}
+
+/// Maker/taker fees (bps of notional) charged on each filled leg
+#[derive(Debug, Clone, Copy, Default)]
+struct FeeModel {
+    maker_bps: f64,
+    taker_bps: f64,
+}
+
+impl FeeModel {
+    fn from_config(config: &BotConfig) -> Self {
+        Self { maker_bps: config.maker_fee_bps, taker_bps: config.taker_fee_bps }
+    }
+
+    /// Fee (JPY per unit size) for a limit at `price`: taker when it crosses the touch, else maker.
+    /// An empty side of the book (0.0) never counts as crossed.
+    fn leg_fee(&self, side: &model::OrderSide, price: f64, best_bid: f64, best_ask: f64) -> f64 {
+        let crosses = match side {
+            model::OrderSide::BUY => best_ask > 0.0 && price >= best_ask,
+            _ => best_bid > 0.0 && price <= best_bid,
+        };
+        let bps = if crosses { self.taker_bps } else { self.maker_bps };
+        price * bps / 10_000.0
+    }
+}
+
 fn maximize_expected_value(
-    _best_bid: f64,
-    _best_ask: f64,
+    best_bid: f64,
+    best_ask: f64,
     mid_price: f64,
     buy: &BTreeMap<model::FloatingExp, (f64, BayesProb)>,
     sell: &BTreeMap<model::FloatingExp, (f64, BayesProb)>,
-) -> Option<(model::FloatingExp, model::FloatingExp)> {
+    fees: &FeeModel,
+) -> Option<(model::FloatingExp, model::FloatingExp, f64)> {
     let mut best_pair = None;
     let mut best_expected_value = f64::NEG_INFINITY;
 
@@ -165,8 +235,12 @@ fn maximize_expected_value(
             let sell_probability: f64 = s.1.1.calc_average();
             let sell_price: f64 = mid_price + (mid_price * s.0.calc());
 
-            // 期待収益
-            let expected_profit = buy_probability * sell_probability * (sell_price - buy_price);
+            // 期待手数料: each leg pays its fee only if it fills
+            let expected_fee = buy_probability * fees.leg_fee(&model::OrderSide::BUY, buy_price, best_bid, best_ask)
+                + sell_probability * fees.leg_fee(&model::OrderSide::SELL, sell_price, best_bid, best_ask);
+
+            // 期待収益 (net of fees)
+            let expected_profit = buy_probability * sell_probability * (sell_price - buy_price) - expected_fee;
 
             let volatility = sell_price - buy_price;
             let alpha = 0.5;
@@ -181,7 +255,7 @@ fn maximize_expected_value(
             let ev = expected_profit - expected_loss;
 
             if ev > best_expected_value {
-                best_pair = Some((b.0.clone(), s.0.clone()));
+                best_pair = Some((b.0.clone(), s.0.clone(), ev));
                 best_expected_value = ev;
             }
         }
@@ -189,7 +263,18 @@ fn maximize_expected_value(
     best_pair
 }
 
-async fn trade(
+/// (buy, sell) allowed under the minimum-edge gate. At or above `min_ev` both sides quote;
+/// below it only the side that reduces an existing position does (buy when short, sell when long).
+fn ev_allowed_sides(best_ev: f64, min_ev: f64, position: &model::Position) -> (bool, bool) {
+    if best_ev >= min_ev {
+        return (true, true);
+    }
+    (position.short_size > 0.0, position.long_size > 0.0)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn trade<E: Exchange>(
+    exchange: &E,
     client: &reqwest::Client,
     config: &BotConfig,
     order_list: &Orders,
@@ -197,6 +282,7 @@ async fn trade(
     board_asks: &OrderBook,
     board_bids: &OrderBook,
     executions: &Executions,
+    health: &Health,
 ) -> Result<()> {
     const MAX_KEEP_BOARD_PRICE: u64 = 100_000;
 
@@ -205,16 +291,15 @@ async fn trade(
     let max_lot: f64 = config.max_lot;
     let position_ratio: f64 = config.position_ratio;
 
-    let collateral = match bitflyer::get_collateral::get_collateral(client).await {
-        Ok(response) => response.collateral,
-        Err(_) => 0.0,
-    };
+    let collateral = exchange.get_collateral().await.unwrap_or(0.0);
     info!("Collateral: {:?}", collateral);
 
     sleep(Duration::from_millis(config.order_interval_ms)).await;
 
     let mut ltp = 0;
 
+    let fees = FeeModel::from_config(config);
+
     // 事前分布をBe(0, 1)とする
     let initial_bayes_prob = BayesProb::new(
         BetaDistribution::new(0, 1),
@@ -229,18 +314,18 @@ async fn trade(
     // 1 step = price * base^exp * rate yen
     let price_step_count = 15;
 
-    for i in 0..price_step_count {
-        let key = model::FloatingExp {
-            base: 10.0,
-            exp: -5.0,
-            rate: (i + 1) as f64,
-        };
+    let keys = config.levels.clone().unwrap_or_else(|| {
+        (0..price_step_count)
+            .map(|i| model::FloatingExp { base: 10.0, exp: -5.0, rate: (i + 1) as f64 })
+            .collect()
+    });
+    for key in keys {
         buy_probabilities.insert(key.clone(), (0.0, initial_bayes_prob.clone()));
         sell_probabilities.insert(key.clone(), (0.0, initial_bayes_prob.clone()));
     }
 
     loop {
-        sleep(Duration::from_secs(5)).await;
+        sleep(Duration::from_millis(config.trade_interval_ms.unwrap_or(5000))).await;
 
         let now = Utc::now().timestamp_millis();
 
@@ -307,33 +392,78 @@ async fn trade(
             .iter_mut()
             .for_each(|p| p.1.0 = mid_price + (mid_price * p.0.calc()));
 
-        let best_pair = match maximize_expected_value(
+        let (buy_level, sell_level, best_ev) = match maximize_expected_value(
             best_bid,
             best_ask,
             mid_price,
             &buy_probabilities,
             &sell_probabilities,
+            &fees,
         ) {
             Some(p) => p,
             None => continue,
         };
+        let best_pair = (buy_level, sell_level);
+
+        let health_status = health.read().clone();
+        if !health_allows_orders(&health_status) {
+            debug!("[HEALTH] Skipping order placement: exchange status {:?}", health_status);
+            continue;
+        }
 
         let position = *position.read();
 
+        // SFD: suppress the side that would pay SFD; within one step of the threshold,
+        // widen it by the rate it would pay if the deviation tips over
+        let sfd_deviation = match bitflyer::get_spot_price::get_spot_price(client).await {
+            Ok(spot) if spot > 0.0 => (mid_price - spot) / spot,
+            Ok(_) => 0.0,
+            Err(e) => {
+                warn!("Failed to get spot price for SFD check: {:?}", e);
+                0.0
+            }
+        };
+        let sfd_side = sfd_penalized_side(sfd_deviation);
+        let current_sfd_rate = sfd_rate(sfd_deviation);
+        let sfd_widen = if current_sfd_rate > 0.0 {
+            0.0
+        } else {
+            mid_price * sfd_rate(sfd_deviation.abs() + SFD_STEP)
+        };
+        if current_sfd_rate > 0.0 || sfd_widen > 0.0 {
+            info!(
+                "[SFD] deviation={:.4}% rate={:.4}% side={:?} widen={:.0}",
+                sfd_deviation * 100.0, current_sfd_rate * 100.0, sfd_side, sfd_widen,
+            );
+        }
+        let buy_sfd_blocked = current_sfd_rate > 0.0 && sfd_side == Some(model::OrderSide::BUY);
+        let sell_sfd_blocked = current_sfd_rate > 0.0 && sfd_side == Some(model::OrderSide::SELL);
+        let buy_sfd_widen = if sfd_side == Some(model::OrderSide::BUY) { sfd_widen } else { 0.0 };
+        let sell_sfd_widen = if sfd_side == Some(model::OrderSide::SELL) { sfd_widen } else { 0.0 };
+
         // // 期待収益が最大となる指値価格を計算
-        let bid = mid_price - (mid_price * best_pair.0.calc());
-        let ask = mid_price + (mid_price * best_pair.1.calc());
+        let bid = mid_price - (mid_price * best_pair.0.calc()) - buy_sfd_widen;
+        let ask = mid_price + (mid_price * best_pair.1.calc()) + sell_sfd_widen;
 
         // ポジションがある場合はポジションサイズに応じてペナルティを課すことでΔ0に近づける
         let position_penalty = ((ask - bid) * 0.25).min(500.0);
 
-        if position.long_size < max_position_size {
+        // Minimum edge: below min_ev only orders that reduce the position go out
+        let (buy_allowed, sell_allowed) = ev_allowed_sides(best_ev, config.min_ev, &position);
+        if !buy_allowed || !sell_allowed {
+            debug!("[MIN_EV] best_ev={:.4} < min_ev={}, only reducing orders", best_ev, config.min_ev);
+        }
+
+        if buy_allowed && !buy_sfd_blocked && position.long_size < max_position_size {
             let size = util::round_size(
                 max_lot * (1.0 - position.long_size.powf(position_ratio) / max_position_size),
             )
             .max(min_lot);
+            let ev = order_ev(
+                &model::OrderSide::BUY, &best_pair.0, &buy_probabilities, best_ev, mid_price, best_bid, best_ask, &fees,
+            );
             if let Err(e) = send_order(
-                client,
+                exchange,
                 config,
                 order_list,
                 model::OrderSide::BUY,
@@ -342,19 +472,23 @@ async fn trade(
                     .add(position_penalty * position.short_size / min_lot)
                     .min(best_bid) as u64,
                 size,
+                &ev,
             )
             .await {
                 error!("Failed to send buy order: {:?}", e);
             }
         }
 
-        if position.short_size < max_position_size {
+        if sell_allowed && !sell_sfd_blocked && position.short_size < max_position_size {
             let size = util::round_size(
                 max_lot * (1.0 - position.short_size.powf(position_ratio) / max_position_size),
             )
             .max(min_lot);
+            let ev = order_ev(
+                &model::OrderSide::SELL, &best_pair.1, &sell_probabilities, best_ev, mid_price, best_bid, best_ask, &fees,
+            );
             if let Err(e) = send_order(
-                client,
+                exchange,
                 config,
                 order_list,
                 model::OrderSide::SELL,
@@ -363,6 +497,7 @@ async fn trade(
                     .sub(position_penalty * position.long_size / min_lot)
                     .max(best_ask) as u64,
                 size,
+                &ev,
             )
             .await {
                 error!("Failed to send sell order: {:?}", e);
@@ -371,53 +506,43 @@ async fn trade(
     }
 }
 
-async fn get_position(client: &reqwest::Client, position: &Positions) -> Result<()> {
+/// Poll /v1/gethealth and publish the latest exchange status for the trade loop.
+/// A failed poll keeps the previous status.
+async fn poll_health(client: &reqwest::Client, health: &Health) -> Result<()> {
     loop {
-        sleep(Duration::from_secs(5)).await;
-
-        let response =
-            match bitflyer::get_position::get_position(client, ProductCode::FX_BTC_JPY).await {
-                Ok(response) => response,
-                Err(e) => {
-                    error!("Failed to get position: {:?}", e);
-                    continue;
+        match bitflyer::get_health::get_health(client).await {
+            Ok(response) => {
+                let previous = std::mem::replace(&mut *health.write(), response.status.clone());
+                if previous != response.status {
+                    info!("[HEALTH] Exchange status {:?} -> {:?}", previous, response.status);
                 }
-            };
+            }
+            Err(e) => warn!("Failed to get health: {:?}", e),
+        }
 
-        let total_position = response.iter().fold(0.0, |acc, x| {
-            acc + if x.side == "BUY" { x.size } else { -x.size }
-        });
+        sleep(Duration::from_secs(HEALTH_POLL_INTERVAL_SECS)).await;
+    }
+}
 
-        // Single atomic update for position
-        let new_position = model::Position {
-            short_size: if total_position < 0.0 {
-                -util::round_size(total_position)
-            } else {
-                0.0
-            },
-            long_size: if total_position > 0.0 {
-                util::round_size(total_position)
-            } else {
-                0.0
-            },
-            long_open_price: 0.0,
-            short_open_price: 0.0,
-            long_open_time: None,
-            short_open_time: None,
-        };
-        *position.write() = new_position;
+async fn get_position<E: Exchange>(exchange: &E, position: &Positions, poll_interval: Duration) -> Result<()> {
+    loop {
+        sleep(poll_interval).await;
 
-        debug!("Position: {:?}", position.read());
+        match exchange::sync_position(exchange, position).await {
+            Ok(position) => debug!("Position: {:?}", position),
+            Err(e) => error!("Failed to get position: {}", e),
+        }
     }
 }
 
 /// WebSocket接続とメッセージ処理（内部関数）
-async fn connect_and_process_websocket(
+async fn connect_and_process_websocket<E: Exchange>(
+    exchange: &E,
     board_asks: &OrderBook,
     board_bids: &OrderBook,
     executions: &Executions,
 ) -> Result<()> {
-    let url = Url::parse("wss://ws.lightstream.bitflyer.com/json-rpc")
+    let url = Url::parse(exchange.ws_url())
         .expect("Invalid WebSocket URL");
     let (socket, _) = connect_async(url).await?;
 
@@ -425,18 +550,8 @@ async fn connect_and_process_websocket(
 
     let (mut write, mut read) = socket.split();
 
-    let channels = vec![
-        "lightning_board_FX_BTC_JPY",
-        "lightning_executions_FX_BTC_JPY",
-    ];
-
-    for channel in channels {
-        let data = serde_json::json!({
-            "method": "subscribe",
-            "params":  {"channel": channel}
-        });
-
-        write.send(Message::Text(data.to_string())).await?;
+    for subscription in exchange.ws_subscriptions() {
+        write.send(Message::Text(subscription)).await?;
     }
 
     while let Some(msg) = read.next().await {
@@ -447,69 +562,25 @@ async fn connect_and_process_websocket(
             _ => continue,
         };
 
-        let parsed: bitflyer::ws::Message = match serde_json::from_str(&msg) {
-            Ok(parsed) => parsed,
-            _ => continue,
-        };
-
-        if &parsed.method != "channelMessage" {
-            continue;
-        }
-
-        let channel = bitflyer::ws::Channel::from_str(&parsed.params.channel);
-
-        match channel {
-            Ok(bitflyer::ws::Channel::lightning_board_FX_BTC_JPY) => {
-                let board: bitflyer::ws::Board = match serde_json::from_value(parsed.params.message) {
-                    Ok(board) => board,
-                    _ => continue,
-                };
-
-                let ask_pairs = board
-                    .asks
-                    .par_iter()
-                    .map(|x| (x.price as u64, x.size))
-                    .collect::<Vec<(u64, f64)>>();
-
-                board_asks.write().extend(ask_pairs);
-
-                let bid_pairs = board
-                    .bids
-                    .par_iter()
-                    .map(|x| (x.price as u64, x.size))
-                    .collect::<Vec<(u64, f64)>>();
-
-                board_bids.write().extend(bid_pairs);
+        match exchange.parse_ws_message(&msg) {
+            Some(FeedEvent::Board { asks, bids }) => {
+                board_asks.write().extend(asks);
+                board_bids.write().extend(bids);
             }
-            Ok(bitflyer::ws::Channel::lightning_executions_FX_BTC_JPY) => {
-                let all: Vec<bitflyer::ws::ExecutionItem> =
-                    match serde_json::from_value(parsed.params.message) {
-                        Ok(executions) => executions,
-                        _ => continue,
-                    };
-
+            Some(FeedEvent::Trades(trades)) => {
                 let now = Utc::now().timestamp_millis();
 
-                let items = all
+                let items = trades
                     .par_iter()
-                    .map(|e| {
-                        (
-                            e.price as u64,
-                            if e.side == bitflyer::ws::Side::BUY {
-                                e.size
-                            } else {
-                                -e.size
-                            },
-                            e.exec_date.get_timestamp(),
-                            now - e.exec_date.get_timestamp(),
-                            e.side,
-                        )
+                    .map(|&(price, size, timestamp)| {
+                        let side = if size >= 0.0 { Side::BUY } else { Side::SELL };
+                        (price, size, timestamp, now - timestamp, side)
                     })
-                    .collect::<Vec<(u64, f64, i64, i64, bitflyer::ws::Side)>>();
+                    .collect::<Vec<(u64, f64, i64, i64, Side)>>();
 
                 executions.write().extend(items);
             }
-            _ => continue,
+            None => continue,
         }
     }
 
@@ -517,30 +588,163 @@ async fn connect_and_process_websocket(
 }
 
 /// WebSocket接続（指数バックオフによる自動再接続付き）
-async fn subscribe_websocket(
+async fn subscribe_websocket<E: Exchange>(
+    exchange: &E,
     board_asks: &OrderBook,
     board_bids: &OrderBook,
     executions: &Executions,
+    rng: &mut StdRng,
 ) -> Result<()> {
-    const MAX_RECONNECT_DELAY_SECS: u64 = 60;
-    let mut reconnect_delay = Duration::from_secs(1);
+    let mut backoff = reconnect::ReconnectBackoff::new();
 
     loop {
-        match connect_and_process_websocket(board_asks, board_bids, executions).await {
-            Ok(_) => {
-                warn!("WebSocket connection closed normally, reconnecting...");
-                reconnect_delay = Duration::from_secs(1);
+        let result = connect_and_process_websocket(exchange, board_asks, board_bids, executions).await;
+        let reconnect_delay = backoff.next_delay(result.is_ok(), Instant::now(), rng);
+        match result {
+            Ok(_) => warn!("WebSocket connection closed normally, reconnecting in {:?}...", reconnect_delay),
+            Err(e) => error!("WebSocket error: {:?}, reconnecting in {:?}...", e, reconnect_delay),
+        }
+        if backoff.in_storm() {
+            warn!("[RECONNECT_STORM] Too many WebSocket reconnects, cooling down for {:?}", reconnect_delay);
+        }
+
+        sleep(reconnect_delay).await;
+    }
+}
+
+/// Apply one child_order_events item: executions move the net position and shrink (or, once
+/// nothing is outstanding, remove) the order; cancels and expiries remove it.
+/// get_position polling still overwrites the position as the source of truth.
+/// The channel carries every product on the account; only FX_BTC_JPY events are ours.
+fn apply_child_order_event(
+    orders: &mut HashMap<String, model::OrderInfo>,
+    position: &mut model::Position,
+    event: &bitflyer::ws::UpdateChildOrderItem,
+) {
+    if event.product_code != ProductCode::FX_BTC_JPY.to_string() {
+        return;
+    }
+    let id = &event.child_order_acceptance_id;
+    match event.kind() {
+        Some(ChildOrderEventType::Execution) => {
+            let size = event.size.unwrap_or(0.0);
+            let net = position.long_size - position.short_size
+                + if event.side == Some(Side::BUY) { size } else { -size };
+            position.long_size = util::round_size(net.max(0.0));
+            position.short_size = util::round_size((-net).max(0.0));
+
+            match event.outstanding_size {
+                Some(outstanding) if outstanding > 0.0 => {
+                    if let Some(order) = orders.get_mut(id) {
+                        order.size = util::round_size(outstanding);
+                    }
+                }
+                _ => {
+                    if orders.remove(id).is_some() {
+                        info!("[PRIVATE_WS] Order {} filled: {:?} {} @ {:?}", id, event.side, size, event.price);
+                    }
+                }
+            }
+        }
+        Some(ChildOrderEventType::Cancel) | Some(ChildOrderEventType::Expire) => {
+            let removed = orders.remove(id);
+            if removed.is_some() {
+                debug!("[PRIVATE_WS] Order {} {}", id, event.event_type);
+            }
+        }
+        Some(ChildOrderEventType::OrderFailed) => {
+            warn!("[PRIVATE_WS] Order {} failed: {:?}", id, event.reason);
+            orders.remove(id);
+        }
+        _ => {}
+    }
+}
+
+async fn connect_and_process_private_websocket(
+    api_key: &str,
+    api_secret: &str,
+    order_list: &Orders,
+    position: &Positions,
+) -> Result<()> {
+    let url = Url::parse("wss://ws.lightstream.bitflyer.com/json-rpc")
+        .expect("Invalid WebSocket URL");
+    let (socket, _) = connect_async(url).await?;
+
+    let (mut write, mut read) = socket.split();
+
+    let nonce: String = rand::thread_rng()
+        .sample_iter(&rand::distributions::Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    let auth = bitflyer::ws_private::auth_message(api_key, api_secret, Utc::now().timestamp_millis(), &nonce);
+    write.send(Message::Text(auth)).await?;
+
+    while let Some(msg) = read.next().await {
+        let msg = match msg? {
+            Message::Text(s) => s,
+            _ => continue,
+        };
+
+        // Subscribing only makes sense once the auth request is accepted
+        if let Ok(response) = serde_json::from_str::<bitflyer::ws_private::RpcResponse>(&msg) {
+            if response.is_auth_ok() {
+                info!("[PRIVATE_WS] Authenticated");
+                write.send(Message::Text(bitflyer::ws_private::subscribe_message())).await?;
+            } else if response.id == bitflyer::ws_private::AUTH_REQUEST_ID {
+                error!("[PRIVATE_WS] Authentication rejected: {:?}", response.error);
+                return Ok(());
             }
+            continue;
+        }
+
+        let parsed: bitflyer::ws::Message = match serde_json::from_str(&msg) {
+            Ok(parsed) => parsed,
+            _ => continue,
+        };
+        if parsed.method != "channelMessage" || parsed.params.channel != bitflyer::ws_private::CHILD_ORDER_EVENTS {
+            continue;
+        }
+        let events: Vec<bitflyer::ws::UpdateChildOrderItem> = match serde_json::from_value(parsed.params.message) {
+            Ok(events) => events,
             Err(e) => {
-                error!("WebSocket error: {:?}, reconnecting in {:?}...", e, reconnect_delay);
+                warn!("[PRIVATE_WS] Failed to parse child_order_events: {}", e);
+                continue;
             }
+        };
+
+        let mut orders = order_list.lock();
+        let mut position = position.write();
+        for event in &events {
+            apply_child_order_event(&mut orders, &mut position, event);
         }
+    }
+    Ok(())
+}
+
+/// child_order_events購読（自動再接続機能付き）
+async fn subscribe_private_websocket(order_list: &Orders, position: &Positions, rng: &mut StdRng) -> Result<()> {
+    let mut backoff = reconnect::ReconnectBackoff::new();
+
+    loop {
+        let reconnect_delay = match bitflyer::auth::get_api_keys() {
+            Ok((api_key, api_secret)) => {
+                let result = connect_and_process_private_websocket(&api_key, &api_secret, order_list, position).await;
+                let delay = backoff.next_delay(result.is_ok(), Instant::now(), rng);
+                match result {
+                    Ok(_) => warn!("[PRIVATE_WS] Connection closed, reconnecting in {:?}...", delay),
+                    Err(e) => error!("[PRIVATE_WS] WebSocket error: {:?}, reconnecting in {:?}...", e, delay),
+                }
+                delay
+            }
+            Err(e) => {
+                let delay = backoff.next_delay(false, Instant::now(), rng);
+                error!("[PRIVATE_WS] Missing API credentials: {:?}, retrying in {:?}...", e, delay);
+                delay
+            }
+        };
 
         sleep(reconnect_delay).await;
-        reconnect_delay = std::cmp::min(
-            reconnect_delay * 2,
-            Duration::from_secs(MAX_RECONNECT_DELAY_SECS),
-        );
     }
 }
 
@@ -551,6 +755,11 @@ async fn run(config: &BotConfig) {
     let position = Arc::new(RwLock::new(model::Position::new()));
     let position_ref = position.clone();
 
+    let orders_private = orders.clone();
+    let position_private = position.clone();
+    let private_ws_enabled = config.bitflyer_private_ws_enabled;
+    let rng_seed = config.rng_seed;
+
     let board_asks = Arc::new(RwLock::new(BTreeMap::new()));
     let board_asks_ref = board_asks.clone();
 
@@ -560,6 +769,11 @@ async fn run(config: &BotConfig) {
     let executions = Arc::new(RwLock::new(Vec::<(u64, f64, i64, i64, bitflyer::ws::Side)>::new()));
     let executions_ref = executions.clone();
 
+    let position_poll_interval = Duration::from_millis(config.position_poll_ms);
+
+    let health = Arc::new(RwLock::new(HealthStatusEnum::Normal));
+    let health_ref = health.clone();
+
     let config_ref = config.clone();
     let config_ref2 = config.clone();
 
@@ -571,36 +785,57 @@ async fn run(config: &BotConfig) {
         .expect("Failed to build HTTP client");
     let client2 = client.clone();
     let client3 = client.clone();
+    let client4 = client.clone();
+    let client5 = client.clone();
 
     tokio::select! {
-        result = tokio::spawn(async move { cancel_child_order(&client, &config_ref, &orders).await }) => {
+        result = tokio::spawn(async move { cancel_child_order(&BitflyerExchange::new(&client), &config_ref, &orders).await }) => {
             match result {
                 Ok(Ok(_)) => info!("cancel_child_order completed"),
                 Ok(Err(e)) => error!("cancel_child_order error: {:?}", e),
                 Err(e) => error!("cancel_child_order task panicked: {:?}", e),
             }
         }
-        result = tokio::spawn(async move { trade(&client2, &config_ref2, &orders_ref, &position, &board_asks, &board_bids, &executions).await }) => {
+        result = tokio::spawn(async move { trade(&BitflyerExchange::new(&client2), &client2, &config_ref2, &orders_ref, &position, &board_asks, &board_bids, &executions, &health).await }) => {
             match result {
                 Ok(Ok(_)) => info!("trade completed"),
                 Ok(Err(e)) => error!("trade error: {:?}", e),
                 Err(e) => error!("trade task panicked: {:?}", e),
             }
         }
-        result = tokio::spawn(async move { get_position(&client3, &position_ref).await }) => {
+        result = tokio::spawn(async move { poll_health(&client4, &health_ref).await }) => {
+            match result {
+                Ok(Ok(_)) => info!("poll_health completed"),
+                Ok(Err(e)) => error!("poll_health error: {:?}", e),
+                Err(e) => error!("poll_health task panicked: {:?}", e),
+            }
+        }
+        result = tokio::spawn(async move { get_position(&BitflyerExchange::new(&client3), &position_ref, position_poll_interval).await }) => {
             match result {
                 Ok(Ok(_)) => info!("get_position completed"),
                 Ok(Err(e)) => error!("get_position error: {:?}", e),
                 Err(e) => error!("get_position task panicked: {:?}", e),
             }
         }
-        result = tokio::spawn(async move { subscribe_websocket(&board_asks_ref, &board_bids_ref, &executions_ref).await }) => {
+        result = tokio::spawn(async move { subscribe_websocket(&BitflyerExchange::new(&client5), &board_asks_ref, &board_bids_ref, &executions_ref, &mut util::task_rng(rng_seed, "public_ws")).await }) => {
             match result {
                 Ok(Ok(_)) => info!("subscribe_websocket completed"),
                 Ok(Err(e)) => error!("subscribe_websocket error: {:?}", e),
                 Err(e) => error!("subscribe_websocket task panicked: {:?}", e),
             }
         }
+        result = tokio::spawn(async move {
+            if !private_ws_enabled {
+                return std::future::pending().await;
+            }
+            subscribe_private_websocket(&orders_private, &position_private, &mut util::task_rng(rng_seed, "private_ws")).await
+        }) => {
+            match result {
+                Ok(Ok(_)) => info!("subscribe_private_websocket completed"),
+                Ok(Err(e)) => error!("subscribe_private_websocket error: {:?}", e),
+                Err(e) => error!("subscribe_private_websocket task panicked: {:?}", e),
+            }
+        }
     }
 }
 
@@ -622,11 +857,16 @@ fn main() {
     let config_path = std::env::var("BOT_CONFIG_PATH")
         .unwrap_or_else(|_| "src/trade-config.yaml".to_string());
 
-    let yaml_str = fs::read_to_string(&config_path)
-        .unwrap_or_else(|_| panic!("Failed to read config file: {}", config_path));
-
-    let config: BotConfig = serde_yaml::from_str(&yaml_str)
-        .expect("Failed to parse config file");
+    let config = match BotConfig::load(&config_path) {
+        Ok(config) => config,
+        Err(errors) => {
+            for e in &errors {
+                error!("[CONFIG] {}", e);
+            }
+            error!("[CONFIG] {} invalid setting(s) in {}, refusing to start", errors.len(), config_path);
+            std::process::exit(1);
+        }
+    };
 
     info!("Config loaded: {:?}", config);
     runtime.block_on(run(&config));
@@ -658,4 +898,215 @@ mod tests {
     fn rust_default_decimal_check5() {
         assert_eq!(0.015 * 2.0, 0.03);
     }
+
+    #[test]
+    fn test_health_allows_orders_per_status() {
+        use super::{health_allows_orders, HealthStatusEnum};
+
+        let cases = [
+            (HealthStatusEnum::Normal, true),
+            (HealthStatusEnum::Busy, true),
+            (HealthStatusEnum::VeryBusy, false),
+            (HealthStatusEnum::SuperBusy, false),
+            (HealthStatusEnum::NoOrder, false),
+            (HealthStatusEnum::Stop, false),
+            (HealthStatusEnum::Unknown, true),
+        ];
+        for (status, expected) in cases {
+            assert_eq!(health_allows_orders(&status), expected, "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn test_sfd_rate_steps_at_boundaries() {
+        use super::sfd_rate;
+
+        assert_eq!(sfd_rate(0.0), 0.0);
+        assert_eq!(sfd_rate(0.0499), 0.0);
+        assert_eq!(sfd_rate(0.05), 0.0025);
+        assert_eq!(sfd_rate(0.0549), 0.0025);
+        assert_eq!(sfd_rate(0.055), 0.005);
+        assert_eq!(sfd_rate(0.06), 0.0075);
+        // Sign does not matter
+        assert_eq!(sfd_rate(-0.055), 0.005);
+        // Capped
+        assert_eq!(sfd_rate(0.5), 0.02);
+        assert_eq!(sfd_rate(f64::NAN), 0.0);
+    }
+
+    #[test]
+    fn test_sfd_penalized_side() {
+        use super::{sfd_penalized_side, model::OrderSide};
+
+        assert_eq!(sfd_penalized_side(0.051), Some(OrderSide::BUY));
+        assert_eq!(sfd_penalized_side(-0.051), Some(OrderSide::SELL));
+        assert_eq!(sfd_penalized_side(0.0), None);
+    }
+
+    fn fee_levels(probabilities: &[(f64, u64, u64)]) -> std::collections::BTreeMap<crate::model::FloatingExp, (f64, crate::bayes_prob::BayesProb)> {
+        use crate::bayes_prob::{BayesProb, BetaDistribution};
+        probabilities
+            .iter()
+            .map(|&(rate, a, b)| {
+                let prob = BayesProb::new(BetaDistribution::new(a, b), std::time::Duration::from_secs(300));
+                (crate::model::FloatingExp::new(10.0, -5.0, rate), (0.0, prob))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fees_change_best_pair() {
+        use super::{maximize_expected_value, FeeModel};
+
+        // L1 fills 90% of the time, L5 60%
+        let levels = fee_levels(&[(1.0, 9, 1), (5.0, 6, 4)]);
+        let mid = 10_000_000.0;
+        let (bid, ask) = (mid - 50.0, mid + 50.0);
+
+        // Fee-free: the mixed pair has the best EV (198 JPY/unit vs 144 for L1/L1)
+        let free = maximize_expected_value(bid, ask, mid, &levels, &levels, &FeeModel::default()).unwrap();
+        assert_eq!((free.0.rate, free.1.rate), (1.0, 5.0));
+
+        // 1bp maker: ~1000 JPY per filled leg swamps every pair; the least-filled (cheapest) wins
+        let fees = FeeModel { maker_bps: 1.0, taker_bps: 2.0 };
+        let paid = maximize_expected_value(bid, ask, mid, &levels, &levels, &fees).unwrap();
+        assert_eq!((paid.0.rate, paid.1.rate), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_negative_ev_only_reduces_position() {
+        use super::{ev_allowed_sides, maximize_expected_value, FeeModel};
+        use crate::model::Position;
+
+        // 1bp maker fee makes every pair negative
+        let levels = fee_levels(&[(1.0, 9, 1), (5.0, 6, 4)]);
+        let mid = 10_000_000.0;
+        let fees = FeeModel { maker_bps: 1.0, taker_bps: 2.0 };
+        let (_, _, best_ev) = maximize_expected_value(mid - 50.0, mid + 50.0, mid, &levels, &levels, &fees).unwrap();
+        assert!(best_ev < 0.0);
+
+        // Flat: nothing to reduce, no orders
+        assert_eq!(ev_allowed_sides(best_ev, 0.0, &Position::new()), (false, false));
+        // Long: the closing sell still goes out; short: the closing buy
+        let long = Position { long_size: 0.01, ..Position::new() };
+        assert_eq!(ev_allowed_sides(best_ev, 0.0, &long), (false, true));
+        let short = Position { short_size: 0.01, ..Position::new() };
+        assert_eq!(ev_allowed_sides(best_ev, 0.0, &short), (true, false));
+        // A threshold below the best EV lets both sides quote
+        assert_eq!(ev_allowed_sides(best_ev, best_ev - 1.0, &Position::new()), (true, true));
+    }
+
+    #[test]
+    fn test_order_ev_matches_selected_levels() {
+        use super::{maximize_expected_value, order_ev, FeeModel};
+        use crate::model::OrderSide;
+
+        let levels = fee_levels(&[(1.0, 9, 1), (5.0, 6, 4)]);
+        let mid = 10_000_000.0;
+        let (bid, ask) = (mid - 50.0, mid + 50.0);
+        let fees = FeeModel::default();
+        let (buy_level, sell_level, best_ev) = maximize_expected_value(bid, ask, mid, &levels, &levels, &fees).unwrap();
+
+        let buy = order_ev(&OrderSide::BUY, &buy_level, &levels, best_ev, mid, bid, ask, &fees);
+        assert_eq!(buy.level, 1);
+        assert_eq!(buy.p_fill, levels[&buy_level].1.calc_average());
+        assert_eq!(buy.best_ev, best_ev);
+        // Fee-free: P(fill) times the 100 JPY (1e-5 of mid) quoted from mid
+        assert!((buy.single_leg_ev - buy.p_fill * 100.0).abs() < 1e-6, "{:?}", buy);
+
+        let sell = order_ev(&OrderSide::SELL, &sell_level, &levels, best_ev, mid, bid, ask, &fees);
+        assert_eq!(sell.level, 5);
+        assert_eq!(sell.p_fill, levels[&sell_level].1.calc_average());
+        assert!((sell.single_leg_ev - sell.p_fill * 500.0).abs() < 1e-6, "{:?}", sell);
+
+        // Fees come off the leg EV
+        let paid = order_ev(&OrderSide::SELL, &sell_level, &levels, best_ev, mid, bid, ask, &FeeModel { maker_bps: 1.0, taker_bps: 2.0 });
+        assert!(paid.single_leg_ev < sell.single_leg_ev);
+    }
+
+    #[test]
+    fn test_child_order_events_update_orders_and_position() {
+        use super::apply_child_order_event;
+        use crate::api::bitflyer::ws::{Message, UpdateChildOrderItem};
+        use crate::model::{OrderInfo, OrderSide, Position};
+        use std::collections::HashMap;
+
+        let event_for = |product: &str, id: &str, event_type: &str, extra: &str| {
+            format!(
+                r#"{{"product_code":"{product}","child_order_id":"C{id}","child_order_acceptance_id":"{id}","event_date":"2024-01-15T10:30:00.000Z","event_type":"{event_type}"{extra}}}"#,
+            )
+        };
+        let event = |id: &str, event_type: &str, extra: &str| event_for("FX_BTC_JPY", id, event_type, extra);
+        let events = [
+            event("A", "ORDER", r#","child_order_type":"LIMIT","side":"BUY","price":10000000,"size":0.01"#),
+            event("A", "EXECUTION", r#","side":"BUY","price":10000000,"size":0.004,"outstanding_size":0.006"#),
+            event("A", "EXECUTION", r#","side":"BUY","price":10000000,"size":0.006,"outstanding_size":0"#),
+            event("B", "CANCEL", ""),
+            event("C", "EXPIRE", ""),
+            event("D", "EXECUTION", r#","side":"SELL","price":10001000,"size":0.015,"outstanding_size":0"#),
+            // A spot BTC_JPY fill on the same account: not this bot's product
+            event_for("BTC_JPY", "E", "EXECUTION", r#","side":"BUY","price":10000000,"size":0.5,"outstanding_size":0"#),
+        ];
+        let raw = format!(
+            r#"{{"jsonrpc":"2.0","method":"channelMessage","params":{{"channel":"child_order_events","message":[{}]}}}}"#,
+            events.join(","),
+        );
+        let message: Message = serde_json::from_str(&raw).unwrap();
+        let events: Vec<UpdateChildOrderItem> = serde_json::from_value(message.params.message).unwrap();
+
+        let order = |side: OrderSide, size: f64| OrderInfo {
+            price: 10_000_000, size, side, timestamp: 0, is_close: false,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        };
+        let mut orders: HashMap<String, OrderInfo> = ["A", "B", "C", "D", "E"]
+            .iter()
+            .map(|id| (id.to_string(), order(OrderSide::BUY, 0.01)))
+            .collect();
+        orders.get_mut("D").unwrap().side = OrderSide::SELL;
+        orders.get_mut("D").unwrap().size = 0.015;
+        let mut position = Position::new();
+
+        // Another product's fill touches neither the orders nor the position
+        apply_child_order_event(&mut orders, &mut position, &events[6]);
+        assert_eq!((orders.len(), position.long_size), (5, 0.0));
+        orders.remove("E");
+        // ORDER: acknowledgement only
+        apply_child_order_event(&mut orders, &mut position, &events[0]);
+        assert_eq!((orders.len(), position.long_size), (4, 0.0));
+        // Partial EXECUTION: position grows, order shrinks to the outstanding size
+        apply_child_order_event(&mut orders, &mut position, &events[1]);
+        assert_eq!(position.long_size, 0.004);
+        assert_eq!(orders["A"].size, 0.006);
+        // Final EXECUTION: nothing outstanding, order gone
+        apply_child_order_event(&mut orders, &mut position, &events[2]);
+        assert_eq!(position.long_size, 0.01);
+        assert!(!orders.contains_key("A"));
+        // CANCEL and EXPIRE drop the order without touching the position
+        apply_child_order_event(&mut orders, &mut position, &events[3]);
+        apply_child_order_event(&mut orders, &mut position, &events[4]);
+        assert!(!orders.contains_key("B") && !orders.contains_key("C"));
+        assert_eq!(position.long_size, 0.01);
+        // A SELL larger than the long nets through to a short
+        apply_child_order_event(&mut orders, &mut position, &events[5]);
+        assert_eq!((position.long_size, position.short_size), (0.0, 0.005));
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn test_leg_fee_maker_vs_taker() {
+        use super::FeeModel;
+        use crate::model::OrderSide;
+
+        let fees = FeeModel { maker_bps: 1.0, taker_bps: 5.0 };
+        let (bid, ask) = (9_999_950.0, 10_000_050.0);
+        // Resting quotes pay maker
+        assert!((fees.leg_fee(&OrderSide::BUY, 9_999_000.0, bid, ask) - 999.9).abs() < 1e-9);
+        assert!((fees.leg_fee(&OrderSide::SELL, 10_001_000.0, bid, ask) - 1_000.1).abs() < 1e-9);
+        // Crossing the touch pays taker
+        assert!((fees.leg_fee(&OrderSide::BUY, 10_000_050.0, bid, ask) - 5_000.025).abs() < 1e-9);
+        assert!((fees.leg_fee(&OrderSide::SELL, 9_999_950.0, bid, ask) - 4_999.975).abs() < 1e-9);
+        // Zero fees cost nothing
+        assert_eq!(FeeModel::default().leg_fee(&OrderSide::BUY, 10_000_050.0, bid, ask), 0.0);
+    }
 }
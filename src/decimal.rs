@@ -0,0 +1,156 @@
+//! Fixed-point prices and sizes for order math.
+//!
+//! Values are held as integer counts of 1e-8 (the precision `util::round_size` already uses),
+//! so tick/step checks are exact integer arithmetic and exchange strings come out without
+//! float artefacts ("0.3", never "0.30000000000000004").
+
+use std::fmt;
+
+/// Decimal places kept
+const DECIMALS: u32 = 8;
+const SCALE: i64 = 10i64.pow(DECIMALS);
+
+/// Nearest multiple of 1e-8; float noise below that (e.g. 9_999_999.9999999 for 10_000_000) is gone
+fn to_units(value: f64) -> i64 {
+    (value * SCALE as f64).round() as i64
+}
+
+/// Largest multiple of `step` not above `units` (`units` as is for a non-positive step)
+fn floor_to_step(units: i64, step: f64) -> i64 {
+    let step = to_units(step);
+    if step <= 0 {
+        return units;
+    }
+    units.div_euclid(step) * step
+}
+
+/// Smallest multiple of `step` not below `units` (`units` as is for a non-positive step)
+fn ceil_to_step(units: i64, step: f64) -> i64 {
+    let floor = floor_to_step(units, step);
+    if floor == units { units } else { floor + to_units(step) }
+}
+
+fn is_multiple_of_step(units: i64, step: f64) -> bool {
+    let step = to_units(step);
+    step > 0 && units.rem_euclid(step) == 0
+}
+
+fn write_units(f: &mut fmt::Formatter, units: i64) -> fmt::Result {
+    let sign = if units < 0 { "-" } else { "" };
+    let units = units.unsigned_abs();
+    let whole = units / SCALE as u64;
+    let frac = units % SCALE as u64;
+    if frac == 0 {
+        return write!(f, "{}{}", sign, whole);
+    }
+    let frac = format!("{:0width$}", frac, width = DECIMALS as usize);
+    write!(f, "{}{}.{}", sign, whole, frac.trim_end_matches('0'))
+}
+
+/// Order price in JPY
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Price(i64);
+
+impl Price {
+    pub fn from_f64(price: f64) -> Self {
+        Self(to_units(price))
+    }
+
+    /// Truncated down to a multiple of `tick`
+    pub fn floor_to_tick(self, tick: f64) -> Self {
+        Self(floor_to_step(self.0, tick))
+    }
+
+    /// Raised up to a multiple of `tick`
+    pub fn ceil_to_tick(self, tick: f64) -> Self {
+        Self(ceil_to_step(self.0, tick))
+    }
+
+    pub fn is_multiple_of(self, tick: f64) -> bool {
+        is_multiple_of_step(self.0, tick)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+
+    /// Whole JPY, for the integer prices orders are tracked at (negative clamps to 0)
+    pub fn to_u64(self) -> u64 {
+        (self.0.max(0) / SCALE) as u64
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_units(f, self.0)
+    }
+}
+
+/// Order size in base currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Size(i64);
+
+impl Size {
+    pub fn from_f64(size: f64) -> Self {
+        Self(to_units(size))
+    }
+
+    /// Truncated down to a multiple of `step`
+    pub fn floor_to_step(self, step: f64) -> Self {
+        Self(floor_to_step(self.0, step))
+    }
+
+    pub fn is_multiple_of(self, step: f64) -> bool {
+        is_multiple_of_step(self.0, step)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / SCALE as f64
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_units(f, self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_just_below_a_tick_rounds_onto_it() {
+        // Penalty math can land a hair under a whole yen; truncating the float loses 1 JPY
+        let price: f64 = 10_000_000.0 - 2e-9;
+        assert_eq!(price as u64, 9_999_999);
+
+        let fixed = Price::from_f64(price).floor_to_tick(1.0);
+        assert_eq!(fixed.to_u64(), 10_000_000);
+        assert!(fixed.is_multiple_of(1.0));
+        // A genuinely fractional price still truncates down to the tick
+        assert_eq!(Price::from_f64(10_000_123.9).floor_to_tick(1.0).to_u64(), 10_000_123);
+        assert_eq!(Price::from_f64(10_000_123.0).floor_to_tick(10.0).to_u64(), 10_000_120);
+    }
+
+    #[test]
+    fn test_sub_yen_tick() {
+        let price = Price::from_f64(85.1237).floor_to_tick(0.001);
+        assert_eq!(price.to_string(), "85.123");
+        assert!(price.is_multiple_of(0.001));
+        assert!(!Price::from_f64(85.1237).is_multiple_of(0.001));
+    }
+
+    #[test]
+    fn test_size_steps_and_strings() {
+        let size = Size::from_f64(0.1 + 0.2);
+        assert_eq!((0.1f64 + 0.2).to_string(), "0.30000000000000004");
+        assert_eq!(size.to_string(), "0.3");
+        assert!(size.is_multiple_of(0.1));
+        assert!(Size::from_f64(0.0037).is_multiple_of(0.0001));
+        assert!(!Size::from_f64(0.00015).is_multiple_of(0.0001));
+        assert_eq!(Size::from_f64(0.00379).floor_to_step(0.0001).to_f64(), 0.0037);
+        assert_eq!(Size::from_f64(30.0).to_string(), "30");
+        assert_eq!(Size::from_f64(0.0001).to_string(), "0.0001");
+    }
+}
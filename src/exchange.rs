@@ -0,0 +1,403 @@
+//! Exchange abstraction shared by the GMO and bitFlyer bots.
+//!
+//! `Exchange` covers the REST calls every trade loop needs (orders, cancels, closes, position,
+//! collateral) plus a public WebSocket adapter, so the helpers below are written once and
+//! driven by either venue (or a mock in tests). The bitFlyer bot runs its order sweep, position
+//! sync and quoting through them. The GMO bot uses them for position polling and flattening;
+//! its order and cancel loops stay on the GMO API, which they need for lost-response
+//! reconciliation, dry-run simulation and P(fill) outcome feedback.
+
+#[cfg(feature = "bitflyer")]
+pub mod bitflyer;
+#[cfg(feature = "gmo")]
+pub mod gmo;
+#[cfg(test)]
+pub(crate) mod mock;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use tracing::{debug, error, info, warn};
+
+use crate::model::{OrderInfo, OrderSide, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExchangeError {
+    /// The exchange no longer knows the order: already filled, expired or cancelled
+    OrderNotFound,
+    /// A close found nothing to settle (ghost position)
+    NoOpenPosition,
+    /// The exchange is under maintenance and refuses private calls
+    Maintenance,
+    Other(String),
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExchangeError::OrderNotFound => write!(f, "order not found"),
+            ExchangeError::NoOpenPosition => write!(f, "no open position"),
+            ExchangeError::Maintenance => write!(f, "exchange under maintenance"),
+            ExchangeError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Order to submit; `price` None = MARKET
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOrder {
+    pub side: OrderSide,
+    pub price: Option<u64>,
+    pub size: f64,
+}
+
+/// Public feed update decoded by `Exchange::parse_ws_message`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedEvent {
+    /// Book levels as (price, size); size 0 removes the level
+    Board { asks: Vec<(u64, f64)>, bids: Vec<(u64, f64)> },
+    /// (price, signed size: + buy / - sell, exchange timestamp ms)
+    Trades(Vec<(u64, f64, i64)>),
+}
+
+pub trait Exchange: Send + Sync {
+    /// Place an open order; returns the exchange order id
+    fn send_order(&self, order: &NewOrder) -> impl Future<Output = Result<String, ExchangeError>> + Send;
+    fn cancel_order(&self, order_id: &str) -> impl Future<Output = Result<(), ExchangeError>> + Send;
+    /// Cancel every open order; returns how many were cancelled when the exchange reports it
+    fn cancel_all_orders(&self) -> impl Future<Output = Result<Option<usize>, ExchangeError>> + Send;
+    /// Reduce the position held opposite to `order.side`; returns the exchange order id
+    fn close(&self, order: &NewOrder) -> impl Future<Output = Result<String, ExchangeError>> + Send;
+    /// Current position (sizes and open prices; open times are tracked by the caller)
+    fn get_position(&self) -> impl Future<Output = Result<Position, ExchangeError>> + Send;
+    /// Collateral in JPY
+    fn get_collateral(&self) -> impl Future<Output = Result<f64, ExchangeError>> + Send;
+
+    /// Public WebSocket endpoint
+    fn ws_url(&self) -> &str;
+    /// Messages to send after connecting, in order
+    fn ws_subscriptions(&self) -> Vec<String>;
+    /// None for messages that are not board / trade updates
+    fn parse_ws_message(&self, msg: &str) -> Option<FeedEvent>;
+}
+
+/// Cancel every order at least `max_age_ms` old. An order the exchange no longer knows was
+/// filled before we got to it and is dropped too; any other failure keeps it for the next sweep.
+/// Returns the ids removed from `orders`.
+pub async fn cancel_stale_orders<E: Exchange>(
+    exchange: &E,
+    orders: &Mutex<HashMap<String, OrderInfo>>,
+    now_ms: u64,
+    max_age_ms: u64,
+) -> Vec<String> {
+    let stale: Vec<String> = orders
+        .lock()
+        .iter()
+        .filter(|(_, info)| now_ms.saturating_sub(info.timestamp) >= max_age_ms)
+        .map(|(id, _)| id.clone())
+        .collect();
+
+    let mut removed = Vec::new();
+    for order_id in stale {
+        match exchange.cancel_order(&order_id).await {
+            Ok(()) => debug!("Cancel Order {:?}", order_id),
+            Err(ExchangeError::OrderNotFound) => {
+                info!("[FILLED] Order {} already gone at cancel time, treating as filled", order_id);
+            }
+            Err(e) => {
+                warn!("Failed to cancel order {} (will retry): {}", order_id, e);
+                continue;
+            }
+        }
+        if orders.lock().remove(&order_id).is_some() {
+            removed.push(order_id);
+        }
+    }
+    removed
+}
+
+/// Fetch the exchange position and apply it with `apply_remote_position`
+pub async fn sync_position<E: Exchange>(exchange: &E, position: &RwLock<Position>) -> Result<Position, ExchangeError> {
+    let remote = exchange.get_position().await?;
+    Ok(apply_remote_position(position, remote))
+}
+
+/// Overwrite the local position with the exchange's, keeping open times: set on a leg going
+/// from flat to held, cleared when it goes flat.
+pub fn apply_remote_position(position: &RwLock<Position>, remote: Position) -> Position {
+    let mut pos = position.write();
+    let long_open_time = match (pos.long_size > 0.0, remote.long_size > 0.0) {
+        (_, false) => None,
+        (false, true) => pos.long_open_time.or_else(|| Some(std::time::Instant::now())),
+        (true, true) => pos.long_open_time,
+    };
+    let short_open_time = match (pos.short_size > 0.0, remote.short_size > 0.0) {
+        (_, false) => None,
+        (false, true) => pos.short_open_time.or_else(|| Some(std::time::Instant::now())),
+        (true, true) => pos.short_open_time,
+    };
+    *pos = Position { long_open_time, short_open_time, ..remote };
+    *pos
+}
+
+/// Submit `order` and track it under the returned id with `info`
+pub async fn place_order<E: Exchange>(
+    exchange: &E,
+    orders: &Mutex<HashMap<String, OrderInfo>>,
+    order: &NewOrder,
+    info: OrderInfo,
+) -> Result<String, ExchangeError> {
+    let order_id = exchange.send_order(order).await?;
+    info!("Send Order: {} {:?} client_id={}", order_id, order, info.client_id);
+    orders.lock().insert(order_id.clone(), info);
+    Ok(order_id)
+}
+
+/// MARKET-close rounds `flatten` sends before giving up on reaching flat
+pub const FLATTEN_ROUNDS: u32 = 3;
+/// Wait after a close round before re-reading the position, so the fills have settled
+pub const FLATTEN_SETTLE: Duration = Duration::from_millis(1_000);
+
+/// Outcome of `flatten`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FlattenReport {
+    /// None when the exchange does not report a count
+    pub cancelled: Option<usize>,
+    /// The cancel-all went through; only then may the caller forget its tracked orders
+    pub orders_cancelled: bool,
+    /// Ids of the MARKET closes that went out
+    pub close_order_ids: Vec<String>,
+    pub errors: Vec<String>,
+    /// The exchange reported no leg of `min_lot` or more on the last check
+    pub flat: bool,
+}
+
+/// Cancel every open order, then MARKET-close each leg the exchange reports holding at least
+/// `min_lot`, re-reading the position after each round until it is flat or `rounds` closes
+/// have gone out. The local position is synced on every read. A failed step is recorded and
+/// the rest still run, so one rejection never leaves a leg open.
+pub async fn flatten<E: Exchange>(
+    exchange: &E,
+    position: &RwLock<Position>,
+    min_lot: f64,
+    rounds: u32,
+    settle: Duration,
+) -> FlattenReport {
+    let mut report = FlattenReport::default();
+    match exchange.cancel_all_orders().await {
+        Ok(cancelled) => {
+            info!("[FLATTEN] Cancelled open orders: {:?}", cancelled);
+            report.cancelled = cancelled;
+            report.orders_cancelled = true;
+        }
+        Err(e) => {
+            error!("[FLATTEN] Cancel-all failed: {}", e);
+            report.errors.push(format!("cancel-all: {}", e));
+        }
+    }
+
+    for round in 0..=rounds {
+        let current = match sync_position(exchange, position).await {
+            Ok(current) => current,
+            Err(e) => {
+                error!("[FLATTEN] Position fetch failed: {}", e);
+                report.errors.push(format!("get-position: {}", e));
+                if round < rounds {
+                    tokio::time::sleep(settle).await;
+                }
+                continue;
+            }
+        };
+        let legs: Vec<(OrderSide, f64)> = [(OrderSide::BUY, current.long_size), (OrderSide::SELL, current.short_size)]
+            .into_iter()
+            .filter(|&(_, size)| size >= min_lot)
+            .collect();
+        if legs.is_empty() {
+            report.flat = true;
+            return report;
+        }
+        if round == rounds {
+            break;
+        }
+        for (held, size) in legs {
+            let order = NewOrder { side: held.opposite(), price: None, size };
+            match exchange.close(&order).await {
+                Ok(order_id) => {
+                    info!("[FLATTEN] MARKET close sent: order_id={} {:?}", order_id, order);
+                    report.close_order_ids.push(order_id);
+                }
+                Err(e) => {
+                    error!("[FLATTEN] MARKET close failed: {:?} {}", order, e);
+                    report.errors.push(format!("close {:?} {}: {}", order.side, size, e));
+                }
+            }
+        }
+        tokio::time::sleep(settle).await;
+    }
+    error!("[FLATTEN] Still not flat after {} close rounds", rounds);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{Call, MockExchange};
+
+    fn order_info(side: OrderSide, price: u64, timestamp: u64) -> OrderInfo {
+        OrderInfo {
+            price, size: 0.01, side, timestamp, is_close: false,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_sweep_and_place_with_mock_exchange() {
+        let exchange = MockExchange {
+            position: Mutex::new(Position { long_size: 0.02, long_open_price: 10_000_000.0, ..Position::new() }),
+            gone: vec!["filled".to_string()],
+            ..Default::default()
+        };
+        let orders = Mutex::new(HashMap::from([
+            ("stale".to_string(), order_info(OrderSide::BUY, 9_990_000, 1_000)),
+            ("filled".to_string(), order_info(OrderSide::SELL, 10_010_000, 2_000)),
+            ("fresh".to_string(), order_info(OrderSide::BUY, 9_995_000, 9_000)),
+        ]));
+        let position = RwLock::new(Position::new());
+
+        let buy = NewOrder { side: OrderSide::BUY, price: Some(9_998_000), size: 0.01 };
+        let sell = NewOrder { side: OrderSide::SELL, price: Some(10_002_000), size: 0.01 };
+        let quotes = vec![
+            (buy.clone(), order_info(OrderSide::BUY, 9_998_000, 10_000)),
+            (sell.clone(), order_info(OrderSide::SELL, 10_002_000, 10_000)),
+        ];
+        sync_position(&exchange, &position).await.unwrap();
+        cancel_stale_orders(&exchange, &orders, 10_000, 5_000).await;
+        let mut placed = Vec::new();
+        for (order, info) in quotes {
+            placed.push(place_order(&exchange, &orders, &order, info).await.unwrap());
+        }
+
+        // Position first, then the two orders older than 5s, then both quotes
+        let mut calls = exchange.calls();
+        calls[1..3].sort_by_key(|c| format!("{:?}", c));
+        assert_eq!(calls, vec![
+            Call::GetPosition,
+            Call::Cancel("filled".to_string()),
+            Call::Cancel("stale".to_string()),
+            Call::SendOrder(buy),
+            Call::SendOrder(sell),
+        ]);
+        assert_eq!(placed, vec!["mock-1".to_string(), "mock-2".to_string()]);
+
+        let orders = orders.lock();
+        let mut ids: Vec<&String> = orders.keys().collect();
+        ids.sort();
+        assert_eq!(ids, ["fresh", "mock-1", "mock-2"]);
+
+        let position = position.read();
+        assert_eq!(position.long_size, 0.02);
+        assert_eq!(position.long_open_price, 10_000_000.0);
+        assert!(position.long_open_time.is_some());
+        assert!(position.short_open_time.is_none());
+    }
+
+    fn held(long_size: f64, short_size: f64) -> MockExchange {
+        MockExchange { position: Mutex::new(Position { long_size, short_size, ..Position::new() }), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_flatten_cancels_everything_then_closes_each_held_leg() {
+        // The cached position is stale: the exchange's is what gets closed
+        let exchange = held(0.02, 0.01);
+        let position = RwLock::new(Position { long_size: 0.05, ..Position::new() });
+
+        let report = flatten(&exchange, &position, 0.01, FLATTEN_ROUNDS, Duration::ZERO).await;
+        assert_eq!(exchange.calls(), vec![
+            Call::CancelAll,
+            Call::GetPosition,
+            Call::Close(NewOrder { side: OrderSide::SELL, price: None, size: 0.02 }),
+            Call::Close(NewOrder { side: OrderSide::BUY, price: None, size: 0.01 }),
+            Call::GetPosition,
+        ]);
+        assert_eq!(report, FlattenReport {
+            cancelled: Some(2),
+            orders_cancelled: true,
+            close_order_ids: vec!["mock-close".to_string(), "mock-close".to_string()],
+            errors: Vec::new(),
+            flat: true,
+        });
+        assert_eq!((position.read().long_size, position.read().short_size), (0.0, 0.0));
+
+        // Dust below min_lot is not closed
+        let exchange = held(0.0, 0.0005);
+        let report = flatten(&exchange, &RwLock::new(Position::new()), 0.001, FLATTEN_ROUNDS, Duration::ZERO).await;
+        assert_eq!(exchange.calls(), vec![Call::CancelAll, Call::GetPosition]);
+        assert!(report.flat);
+    }
+
+    #[tokio::test]
+    async fn test_flatten_retries_failed_closes_and_reports_failure() {
+        // One failed close: the next round closes the leg
+        let exchange = MockExchange { failing_closes: Mutex::new(1), ..held(0.02, 0.0) };
+        let report = flatten(&exchange, &RwLock::new(Position::new()), 0.01, FLATTEN_ROUNDS, Duration::ZERO).await;
+        assert!(report.flat);
+        assert_eq!(report.close_order_ids.len(), 1);
+        assert_eq!(report.errors.len(), 1);
+
+        // Every close fails and the cancel-all too: not flat, and orders must be kept
+        let exchange = MockExchange { failing_closes: Mutex::new(u32::MAX), cancel_all_fails: true, ..held(0.02, 0.0) };
+        let report = flatten(&exchange, &RwLock::new(Position::new()), 0.01, 2, Duration::ZERO).await;
+        assert!(!report.flat);
+        assert!(!report.orders_cancelled);
+        let closes = exchange.calls().iter().filter(|c| matches!(c, Call::Close(_))).count();
+        assert_eq!(closes, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_cancel_keeps_order_for_next_sweep() {
+        struct FailingCancel(MockExchange);
+        impl Exchange for FailingCancel {
+            async fn send_order(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+                self.0.send_order(order).await
+            }
+            async fn cancel_order(&self, order_id: &str) -> Result<(), ExchangeError> {
+                self.0.cancel_order(order_id).await?;
+                Err(ExchangeError::Other("timeout".to_string()))
+            }
+            async fn cancel_all_orders(&self) -> Result<Option<usize>, ExchangeError> {
+                self.0.cancel_all_orders().await
+            }
+            async fn close(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+                self.0.close(order).await
+            }
+            async fn get_position(&self) -> Result<Position, ExchangeError> {
+                self.0.get_position().await
+            }
+            async fn get_collateral(&self) -> Result<f64, ExchangeError> {
+                self.0.get_collateral().await
+            }
+            fn ws_url(&self) -> &str {
+                self.0.ws_url()
+            }
+            fn ws_subscriptions(&self) -> Vec<String> {
+                self.0.ws_subscriptions()
+            }
+            fn parse_ws_message(&self, msg: &str) -> Option<FeedEvent> {
+                self.0.parse_ws_message(msg)
+            }
+        }
+
+        let exchange = FailingCancel(MockExchange::default());
+        let orders = Mutex::new(HashMap::from([("stale".to_string(), order_info(OrderSide::BUY, 9_990_000, 0))]));
+
+        let removed = cancel_stale_orders(&exchange, &orders, 10_000, 5_000).await;
+        assert!(removed.is_empty());
+        assert!(orders.lock().contains_key("stale"));
+        assert_eq!(exchange.0.calls(), vec![Call::Cancel("stale".to_string())]);
+    }
+}
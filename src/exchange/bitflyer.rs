@@ -0,0 +1,134 @@
+use std::str::FromStr;
+
+use crate::api::bitflyer;
+use crate::api::bitflyer::api::{ApiResponseError, ChildOrderType, ProductCode};
+use crate::exchange::{Exchange, ExchangeError, FeedEvent, NewOrder};
+use crate::model::Position;
+use crate::util;
+
+const WS_URL: &str = "wss://ws.lightstream.bitflyer.com/json-rpc";
+
+/// Minutes a bitFlyer LIMIT order rests before the exchange expires it
+const MINUTE_TO_EXPIRE: u32 = 1;
+
+/// bitFlyer Lightning FX_BTC_JPY over the bot's shared client
+#[derive(Debug, Clone, Copy)]
+pub struct BitflyerExchange<'a> {
+    client: &'a reqwest::Client,
+}
+
+impl<'a> BitflyerExchange<'a> {
+    pub fn new(client: &'a reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl From<ApiResponseError> for ExchangeError {
+    fn from(error: ApiResponseError) -> Self {
+        if error.is_order_not_found() {
+            ExchangeError::OrderNotFound
+        } else {
+            ExchangeError::Other(error.to_string())
+        }
+    }
+}
+
+/// Net position: FX positions of both sides offset each other
+pub fn net_position(list: &[bitflyer::get_position::PositionDetail]) -> Position {
+    let total = list.iter().fold(0.0, |acc, x| acc + if x.side == "BUY" { x.size } else { -x.size });
+    Position {
+        long_size: if total > 0.0 { util::round_size(total) } else { 0.0 },
+        short_size: if total < 0.0 { -util::round_size(total) } else { 0.0 },
+        ..Position::default()
+    }
+}
+
+impl Exchange for BitflyerExchange<'_> {
+    async fn send_order(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        let parameter = bitflyer::send_order::ChildOrderParameter {
+            product_code: ProductCode::FX_BTC_JPY,
+            child_order_type: if order.price.is_some() { ChildOrderType::LIMIT } else { ChildOrderType::MARKET },
+            side: order.side.clone(),
+            price: order.price,
+            size: order.size,
+            minute_to_expire: MINUTE_TO_EXPIRE,
+        };
+        let response = bitflyer::send_order::post_child_order(self.client, &parameter).await?;
+        Ok(response.1.child_order_acceptance_id)
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<(), ExchangeError> {
+        let parameter = bitflyer::cancel_child_order::CancelChildOrderParameter {
+            product_code: ProductCode::FX_BTC_JPY,
+            child_order_acceptance_id: order_id.to_string(),
+        };
+        bitflyer::cancel_child_order::cancel_child_order(self.client, &parameter).await?;
+        Ok(())
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Option<usize>, ExchangeError> {
+        let parameter =
+            bitflyer::cancel_all_child_orders::CancelAllChildOrdersParameter { product_code: ProductCode::FX_BTC_JPY };
+        bitflyer::cancel_all_child_orders::cancel_all_child_orders(self.client, &parameter).await?;
+        Ok(None)
+    }
+
+    /// FX positions are netted, so a close is just an order on the reducing side
+    async fn close(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        self.send_order(order).await
+    }
+
+    async fn get_position(&self) -> Result<Position, ExchangeError> {
+        let response = bitflyer::get_position::get_position(self.client, ProductCode::FX_BTC_JPY).await?;
+        Ok(net_position(&response))
+    }
+
+    async fn get_collateral(&self) -> Result<f64, ExchangeError> {
+        let response = bitflyer::get_collateral::get_collateral(self.client).await?;
+        Ok(response.collateral)
+    }
+
+    fn ws_url(&self) -> &str {
+        WS_URL
+    }
+
+    fn ws_subscriptions(&self) -> Vec<String> {
+        ["lightning_board_FX_BTC_JPY", "lightning_executions_FX_BTC_JPY"]
+            .iter()
+            .map(|channel| {
+                serde_json::json!({
+                    "method": "subscribe",
+                    "params": {"channel": channel}
+                })
+                .to_string()
+            })
+            .collect()
+    }
+
+    fn parse_ws_message(&self, msg: &str) -> Option<FeedEvent> {
+        let parsed: bitflyer::ws::Message = serde_json::from_str(msg).ok()?;
+        if parsed.method != "channelMessage" {
+            return None;
+        }
+
+        match bitflyer::ws::Channel::from_str(&parsed.params.channel) {
+            Ok(bitflyer::ws::Channel::lightning_board_FX_BTC_JPY) => {
+                let board: bitflyer::ws::Board = serde_json::from_value(parsed.params.message).ok()?;
+                let levels = |items: &[bitflyer::ws::BoardItem]| items.iter().map(|x| (x.price as u64, x.size)).collect();
+                Some(FeedEvent::Board { asks: levels(&board.asks), bids: levels(&board.bids) })
+            }
+            Ok(bitflyer::ws::Channel::lightning_executions_FX_BTC_JPY) => {
+                let all: Vec<bitflyer::ws::ExecutionItem> = serde_json::from_value(parsed.params.message).ok()?;
+                Some(FeedEvent::Trades(
+                    all.iter()
+                        .map(|e| {
+                            let size = if e.side == bitflyer::ws::Side::BUY { e.size } else { -e.size };
+                            (e.price as u64, size, e.exec_date.get_timestamp())
+                        })
+                        .collect(),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use crate::api::gmo;
+use crate::api::gmo::api::{ApiResponseError, ChildOrderType, GmoErrorCode, Symbol};
+use crate::api::gmo::rate_limit::RateLimiter;
+use crate::api::gmo::ws;
+use crate::decimal::Size;
+use crate::exchange::{Exchange, ExchangeError, FeedEvent, NewOrder};
+use crate::model::Position;
+use crate::util;
+
+const WS_URL: &str = "wss://api.coin.z.com/ws/public/v1";
+
+/// GMO Coin leveraged trading for one symbol, over the bot's shared client and rate limiter
+#[derive(Debug, Clone)]
+pub struct GmoExchange {
+    client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
+    symbol: Symbol,
+    max_retries: u32,
+}
+
+impl GmoExchange {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, symbol: Symbol, max_retries: u32) -> Self {
+        Self { client, limiter, symbol, max_retries }
+    }
+
+    fn order_fields(order: &NewOrder) -> (ChildOrderType, Option<String>) {
+        match order.price {
+            Some(price) => (ChildOrderType::LIMIT, Some(price.to_string())),
+            None => (ChildOrderType::MARKET, None),
+        }
+    }
+}
+
+impl From<ApiResponseError> for ExchangeError {
+    fn from(error: ApiResponseError) -> Self {
+        if error.has_error_code(&GmoErrorCode::OrderNotFound) {
+            ExchangeError::OrderNotFound
+        } else if error.has_error_code(&GmoErrorCode::NoOpenPosition) {
+            ExchangeError::NoOpenPosition
+        } else if error.is_maintenance() {
+            ExchangeError::Maintenance
+        } else {
+            ExchangeError::Other(error.to_string())
+        }
+    }
+}
+
+/// Gross position: both legs tracked independently, each at its size-weighted open price
+pub fn gross_position(list: &[gmo::get_position::Position]) -> Position {
+    let mut long_total = 0.0;
+    let mut short_total = 0.0;
+    let mut long_price_sum = 0.0;
+    let mut short_price_sum = 0.0;
+    for x in list {
+        if x.side == "BUY" {
+            long_total += x.size;
+            long_price_sum += x.price * x.size;
+        } else {
+            short_total += x.size;
+            short_price_sum += x.price * x.size;
+        }
+    }
+
+    Position {
+        long_size: util::round_size(long_total),
+        short_size: util::round_size(short_total),
+        long_open_price: if long_total > 0.0 { long_price_sum / long_total } else { 0.0 },
+        short_open_price: if short_total > 0.0 { short_price_sum / short_total } else { 0.0 },
+        ..Position::default()
+    }
+}
+
+impl Exchange for GmoExchange {
+    async fn send_order(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        let (execution_type, price) = Self::order_fields(order);
+        let parameter = gmo::send_order::ChildOrderParameter {
+            symbol: self.symbol.clone(),
+            side: order.side.clone(),
+            execution_type,
+            price,
+            size: Size::from_f64(order.size).to_string(),
+            time_in_force: None,
+        };
+        let response = gmo::send_order::post_child_order(&self.client, &self.limiter, &parameter, self.max_retries).await?;
+        Ok(response.1.data)
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<(), ExchangeError> {
+        let parameter = gmo::cancel_child_order::CancelOrderParameter { order_id: order_id.to_string() };
+        gmo::cancel_child_order::cancel_order(&self.client, &self.limiter, &parameter).await?;
+        Ok(())
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Option<usize>, ExchangeError> {
+        let symbols = std::slice::from_ref(&self.symbol);
+        let response = gmo::cancel_bulk_order::cancel_bulk_order(&self.client, &self.limiter, symbols).await?;
+        Ok(Some(response.1.data.len()))
+    }
+
+    async fn close(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        let (execution_type, price) = Self::order_fields(order);
+        let parameter = gmo::close_bulk_order::CloseBulkOrderParameter {
+            symbol: self.symbol.clone(),
+            side: order.side.clone(),
+            execution_type,
+            price,
+            size: Size::from_f64(order.size).to_string(),
+            time_in_force: None,
+        };
+        let response = gmo::close_bulk_order::close_bulk_order(&self.client, &self.limiter, &parameter, self.max_retries).await?;
+        Ok(response.1.data)
+    }
+
+    async fn get_position(&self) -> Result<Position, ExchangeError> {
+        let response = gmo::get_position::get_position(&self.client, &self.limiter, self.symbol.clone(), self.max_retries).await?;
+        Ok(gross_position(&response.data.unwrap_or_default().list.unwrap_or_default()))
+    }
+
+    async fn get_collateral(&self) -> Result<f64, ExchangeError> {
+        let response = gmo::get_collateral::get_collateral(&self.client, &self.limiter).await?;
+        Ok(response.data.actual_profit_loss)
+    }
+
+    fn ws_url(&self) -> &str {
+        WS_URL
+    }
+
+    fn ws_subscriptions(&self) -> Vec<String> {
+        ["orderbooks", "trades"]
+            .iter()
+            .map(|channel| {
+                serde_json::json!({
+                    "command": "subscribe",
+                    "channel": channel,
+                    "symbol": self.symbol.to_string()
+                })
+                .to_string()
+            })
+            .collect()
+    }
+
+    fn parse_ws_message(&self, msg: &str) -> Option<FeedEvent> {
+        let parsed: ws::Message = serde_json::from_str(msg).ok()?;
+        match parsed.channel {
+            ws::Channel::Orderbooks => {
+                let board: ws::Board = serde_json::from_str(msg).ok()?;
+                let levels = |items: &[ws::BoardItem]| items.iter().map(|x| (x.price as u64, x.size)).collect();
+                Some(FeedEvent::Board { asks: levels(&board.asks), bids: levels(&board.bids) })
+            }
+            ws::Channel::Trades => {
+                let item: ws::ExecutionItem = serde_json::from_str(msg).ok()?;
+                let size = if item.side == ws::Side::BUY { item.size } else { -item.size };
+                Some(FeedEvent::Trades(vec![(item.price as u64, size, item.timestamp.get_timestamp())]))
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Recording `Exchange` for tests of code driven through the trait
+
+use parking_lot::Mutex;
+
+use crate::exchange::{Exchange, ExchangeError, FeedEvent, NewOrder};
+use crate::model::{OrderSide, Position};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Call {
+    SendOrder(NewOrder),
+    Cancel(String),
+    CancelAll,
+    Close(NewOrder),
+    GetPosition,
+    GetCollateral,
+}
+
+#[derive(Default)]
+pub struct MockExchange {
+    pub calls: Mutex<Vec<Call>>,
+    /// Reported by `get_position`; a successful close fills against it immediately
+    pub position: Mutex<Position>,
+    /// Ids whose cancel answers OrderNotFound
+    pub gone: Vec<String>,
+    pub next_id: Mutex<u64>,
+    pub cancel_all_fails: bool,
+    /// This many closes fail before they start going through
+    pub failing_closes: Mutex<u32>,
+}
+
+impl MockExchange {
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().clone()
+    }
+}
+
+impl Exchange for MockExchange {
+    async fn send_order(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        self.calls.lock().push(Call::SendOrder(order.clone()));
+        let mut next_id = self.next_id.lock();
+        *next_id += 1;
+        Ok(format!("mock-{}", *next_id))
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> Result<(), ExchangeError> {
+        self.calls.lock().push(Call::Cancel(order_id.to_string()));
+        if self.gone.iter().any(|id| id == order_id) {
+            return Err(ExchangeError::OrderNotFound);
+        }
+        Ok(())
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Option<usize>, ExchangeError> {
+        self.calls.lock().push(Call::CancelAll);
+        if self.cancel_all_fails {
+            return Err(ExchangeError::Other("timeout".to_string()));
+        }
+        Ok(Some(2))
+    }
+
+    async fn close(&self, order: &NewOrder) -> Result<String, ExchangeError> {
+        self.calls.lock().push(Call::Close(order.clone()));
+        let mut failing = self.failing_closes.lock();
+        if *failing > 0 {
+            *failing -= 1;
+            return Err(ExchangeError::Other("timeout".to_string()));
+        }
+        let mut position = self.position.lock();
+        let held = if order.side == OrderSide::SELL { &mut position.long_size } else { &mut position.short_size };
+        *held = (*held - order.size).max(0.0);
+        Ok("mock-close".to_string())
+    }
+
+    async fn get_position(&self) -> Result<Position, ExchangeError> {
+        self.calls.lock().push(Call::GetPosition);
+        Ok(*self.position.lock())
+    }
+
+    async fn get_collateral(&self) -> Result<f64, ExchangeError> {
+        self.calls.lock().push(Call::GetCollateral);
+        Ok(1_000_000.0)
+    }
+
+    fn ws_url(&self) -> &str {
+        "wss://mock"
+    }
+
+    fn ws_subscriptions(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn parse_ws_message(&self, _msg: &str) -> Option<FeedEvent> {
+        None
+    }
+}
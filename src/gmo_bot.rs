@@ -1,110 +1,174 @@
+pub mod alerting;
 pub mod api;
 pub mod bayes_prob;
+pub mod decimal;
+pub mod exchange;
+pub mod health;
 pub mod logging;
+pub mod metrics_exporter;
 pub mod model;
+pub mod reconnect;
+pub mod sim_exchange;
+pub mod strategy;
 pub mod time_queue;
 pub mod util;
 
 use std::{
-    collections::BTreeMap,
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::Arc,
     time::Duration,
-    fs,
 };
 
 use tokio::time::Instant;
 
 use crate::api::gmo;
-use crate::api::gmo::api::ApiResponseError;
+use crate::api::gmo::api::{ApiResponseError, GmoErrorCode};
 use crate::api::gmo::ws;
+use crate::api::gmo::ws_private;
 use crate::bayes_prob::{BayesProb, BetaDistribution};
+use crate::decimal::{Price, Size};
+use crate::exchange::{Exchange, ExchangeError, FLATTEN_ROUNDS, FLATTEN_SETTLE};
+use crate::exchange::gmo::GmoExchange;
 use crate::logging::trade_logger::{TradeEvent, TradeLogger};
 use crate::logging::metrics_logger::{MetricsLogger, MetricsSnapshot};
+use crate::logging::level_stats::LevelStats;
+use crate::logging::order_age_hist::OrderAgeHistogram;
 use crate::model::Position;
 use crate::model::OrderSide;
 use crate::model::OrderOutcome;
+use crate::model::ReportedOrder;
 use crate::model::BotConfig;
+use crate::model::parse_time_in_force;
+use crate::model::{SymbolConfig, SymbolRegistry, SymbolRule};
+use crate::model::ExplorationMode;
+use crate::reconnect::ReconnectBackoff;
+use crate::sim_exchange::{SimExchange, SimFill};
+use crate::alerting::AlertSink;
+use crate::health::{AdminState, HealthState, HealthStates, TradeStatus, TradingHalt};
+use crate::model::LimitBasis;
+use crate::model::SizingSource;
+use crate::strategy::{
+    balance_capped_sizes, calculate_order_prices, collateral_max_position, calculate_volatility, calculate_volatility_multi, classify_regime,
+    jpy_offset_levels, maximize_single_leg_ev_within, order_book_imbalance, order_sizes, passive_quotes, regime_params,
+    round_to_tick, single_leg_ev, step_levels, trade_flow_imbalance, trade_flow_widening, vol_expansion_widening, VolRegime,
+};
 use crate::api::gmo::api::Symbol;
+use crate::api::gmo::get_server_status::ExchangeStatus;
 use crate::api::gmo::api::ChildOrderType;
-// TimeInForce removed: SOK disabled (leverage trading has zero fees)
+use crate::api::gmo::api::TimeInForce;
+use crate::api::gmo::rate_limit::RateLimiter;
 
 use chrono::{Timelike, Utc};
 use futures::{SinkExt, StreamExt};
 use parking_lot::{Mutex, RwLock};
-use tokio::{runtime::Builder, time::sleep};
+use tokio::{runtime::Builder, sync::Notify, time::sleep};
 use tokio_tungstenite::{connect_async, tungstenite::{Message, Result}};
-use rayon::prelude::*;
-use tracing::{info, warn, error, debug};
+use rand::rngs::StdRng;
+use tracing::{info, warn, error, debug, Instrument};
 use url::Url;
 
-type Orders = Arc<Mutex<HashMap<String, model::OrderInfo>>>;
+type Orders = Arc<Mutex<model::OrderMap>>;
 type Positions = RwLock<model::Position>;
 use crate::model::FloatingExp;
 
 type OrderBook = RwLock<BTreeMap<u64, f64>>;
-type Executions = RwLock<Vec<(u64, f64, i64)>>;
+// (price, signed size, exchange timestamp ms, feed delay ms)
+type Executions = RwLock<Vec<(u64, f64, i64, i64)>>;
 type LastWsMessage = Arc<RwLock<i64>>;
 type SharedU64 = Arc<RwLock<u64>>;
 type GhostSuppression = Arc<RwLock<Option<Instant>>>;
+type ParseFailures = Arc<RwLock<WsParseFailures>>;
+type SharedExchangeStatus = Arc<RwLock<ExchangeStatus>>;
+type SharedFillGuard = Arc<FillGuard>;
+type SharedPnl = Arc<RwLock<model::PnlTracker>>;
+type SharedTradeStatus = Arc<RwLock<TradeStatus>>;
+type SharedMaintenance = Arc<Mutex<MaintenancePause>>;
+/// Live config: swapped wholesale on SIGHUP, cloned by the trade loop once per cycle
+type SharedConfig = Arc<RwLock<BotConfig>>;
+
+/// Reconnect hook: a public WS reconnect wakes the position poll and the order sweep at once
+/// instead of leaving them on their regular interval, since fills and cancels may have been
+/// missed while the feed was down.
+#[derive(Debug, Default)]
+struct ResyncSignal {
+    position: Notify,
+    orders: Notify,
+}
 
-/// Single-leg EV: P(fill) * (spread_capture - expected_adverse)
-fn single_leg_ev(
-    mid_price: f64,
-    volatility: f64,
-    alpha: f64,
-    level: &FloatingExp,
-    p_fill: f64,
-) -> f64 {
-    let spread_capture = mid_price * level.calc();
-    let expected_adverse = volatility * alpha;
-    p_fill * (spread_capture - expected_adverse)
+impl ResyncSignal {
+    /// Stores a permit per task, so a task busy mid-cycle still resyncs on its next wait
+    fn fire(&self) {
+        self.position.notify_one();
+        self.orders.notify_one();
+    }
 }
 
-/// Each side independently selects optimal level (old: 22x22 pair -> new: 22+22 independent)
-/// Returns (best_buy_key, buy_p_fill, best_sell_key, sell_p_fill, combined_ev)
-fn maximize_single_leg_ev(
-    mid_price: f64,
-    volatility: f64,
-    alpha: f64,
-    buy: &BTreeMap<FloatingExp, (f64, BayesProb)>,
-    sell: &BTreeMap<FloatingExp, (f64, BayesProb)>,
-) -> Option<(FloatingExp, f64, FloatingExp, f64, f64)> {
-    let best_buy = buy.iter()
-        .map(|(k, (_, b))| {
-            let p = b.calc_average();
-            (k.clone(), p, single_leg_ev(mid_price, volatility, alpha, k, p))
-        })
-        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+/// Public-feed state of one symbol, written by the process-wide public WS connection
+#[derive(Debug, Clone, Default)]
+struct SymbolFeed {
+    board_asks: Arc<OrderBook>,
+    board_bids: Arc<OrderBook>,
+    executions: Arc<Executions>,
+    /// Timestamp (ms) of the latest message for this symbol; 0 while (re)connecting
+    last_ws_message: LastWsMessage,
+    /// When the current (re)connect began (ms), so a reconnect that never delivers still goes stale
+    ws_waiting_since: LastWsMessage,
+    /// Timestamp (ms) of the latest trades-channel message, for the REST executions fallback
+    last_ws_trade: LastWsMessage,
+    /// This symbol's position poll and order sweep, woken when the connection comes back
+    resync: Arc<ResyncSignal>,
+}
 
-    let best_sell = sell.iter()
-        .map(|(k, (_, b))| {
-            let p = b.calc_average();
-            (k.clone(), p, single_leg_ev(mid_price, volatility, alpha, k, p))
-        })
-        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+type SymbolFeeds = HashMap<Symbol, SymbolFeed>;
+
+/// What one symbol's private-WS events update
+#[derive(Clone)]
+struct PrivateRoute {
+    orders: Orders,
+    position: Arc<Positions>,
+    trade_logger: Option<TradeLogger>,
+    outcome_tx: tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    fill_guard: SharedFillGuard,
+    pnl: SharedPnl,
+}
 
-    match (best_buy, best_sell) {
-        (Some((bk, bp, bev)), Some((sk, sp, sev))) => {
-            debug!("Best single-leg EV: buy={:.6} sell={:.6} combined={:.6}", bev, sev, bev + sev);
-            Some((bk, bp, sk, sp, bev + sev))
-        }
-        _ => None,
+type PrivateRoutes = HashMap<Symbol, PrivateRoute>;
+
+/// Sleep for `interval`, or less if `wake` is notified. Returns true when woken early.
+async fn sleep_or_notified(interval: Duration, wake: &Notify) -> bool {
+    tokio::select! {
+        _ = sleep(interval) => false,
+        _ = wake.notified() => true,
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cancel_child_order(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     config: &BotConfig,
     order_list: &Orders,
     trade_logger: &Option<TradeLogger>,
     _current_t_optimal_ms: &SharedU64, // kept for API compat; per-order t_optimal used now
     outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    resync: &ResyncSignal,
+    sim: Option<&SimExchange>,
 ) -> Result<()> {
     loop {
-        sleep(Duration::from_millis(500)).await;
+        // After a WS reconnect every resting order was quoted off pre-outage prices:
+        // cancel them all now (ERR-5122 replies reveal fills missed during the outage)
+        let resync_now = sleep_or_notified(Duration::from_millis(config.cancel_interval_ms), &resync.orders).await;
+
+        // Lost-response orders nobody claimed in time (always the case without the private WS)
+        let reaped = order_list.lock().reap_unconfirmed(Utc::now().timestamp_millis() as u64, UNCONFIRMED_MATCH_MS);
+        if reaped > 0 {
+            warn!("[UNCONFIRMED] Dropped {} lost-response orders unclaimed after {}ms", reaped, UNCONFIRMED_MATCH_MS);
+        }
 
         let list = order_list.lock().clone();
+        if resync_now {
+            info!("[RESYNC] WS reconnected, sweeping {} open orders", list.len());
+        }
 
         for order in list.iter() {
             let now = Utc::now().timestamp_millis() as u64;
@@ -114,7 +178,7 @@ async fn cancel_child_order(
             let order_t_optimal = order.1.t_optimal_ms;
             let cancel_threshold = if order_t_optimal > 0 { order_t_optimal } else { config.order_cancel_ms };
 
-            if order_age < cancel_threshold {
+            if order_age < cancel_threshold && !resync_now {
                 continue;
             }
 
@@ -124,63 +188,47 @@ async fn cancel_child_order(
                 order_id: child_order_acceptance_id.clone(),
             };
 
+            // Order-rate cap: leave the rest for the next cancel cycle
+            if !limiter.try_order_slot() {
+                info!("[THROTTLE] max_orders_per_sec reached, deferring cancels to next cycle");
+                break;
+            }
+
             let timestamp = Utc::now().to_rfc3339();
 
-            match gmo::cancel_child_order::cancel_order(client, &parameter).await {
-                Ok(_) => {
+            let result = match sim {
+                // Unknown to the simulator = already filled; simulate_fills records it
+                Some(sim) if !sim.cancel(&child_order_acceptance_id) => continue,
+                Some(_) => Ok(()),
+                None => gmo::cancel_child_order::cancel_order(client, limiter, &parameter).await.map(|_| ()),
+            };
+
+            match result {
+                Ok(()) => {
                     info!("Cancel Order {:?} (age={}ms, threshold={}ms)",
                         child_order_acceptance_id, order_age, cancel_threshold);
-                    let info = order.1;
-                    let _ = outcome_tx.send(OrderOutcome {
-                        side: info.side.clone(),
-                        filled: false,
-                        is_close: info.is_close,
+                    // Private WS may have already recorded this order's outcome
+                    let Some(info) = order_list.lock().remove(&child_order_acceptance_id) else {
+                        continue;
+                    };
+                    let _ = outcome_tx.send(info.outcome(false, order_age));
+                    log_order_event(&config.symbol.to_string(), trade_logger, TradeEvent::OrderCancelled {
+                        timestamp,
+                        order_id: child_order_acceptance_id.clone(),
+                        order_age_ms: order_age,
                         level: info.level,
+                        side: info.side.to_string(),
+                        is_close: info.is_close,
                     });
-                    if let Some(logger) = trade_logger {
-                        logger.log(TradeEvent::OrderCancelled {
-                            timestamp,
-                            order_id: child_order_acceptance_id.clone(),
-                            order_age_ms: order_age,
-                            level: info.level,
-                            side: info.side.to_string(),
-                            is_close: info.is_close,
-                        });
-                    }
-                    order_list.lock().remove(&child_order_acceptance_id);
                 }
-                Err(ApiResponseError::ApiError(ref msgs))
-                    if msgs.iter().any(|m| m.message_code == "ERR-5122") =>
-                {
+                Err(ref e) if e.has_error_code(&GmoErrorCode::OrderNotFound) => {
                     info!("Order already filled (ERR-5122): {:?} (age={}ms)",
                         child_order_acceptance_id, order_age);
-                    let info = order.1;
-                    let _ = outcome_tx.send(OrderOutcome {
-                        side: info.side.clone(),
-                        filled: true,
-                        is_close: info.is_close,
-                        level: info.level,
-                    });
-                    if let Some(logger) = trade_logger {
-                        logger.log(TradeEvent::OrderFilled {
-                            timestamp,
-                            order_id: child_order_acceptance_id.clone(),
-                            side: info.side.to_string(),
-                            price: info.price,
-                            size: info.size,
-                            order_age_ms: order_age,
-                            is_close: info.is_close,
-                            mid_price: info.mid_price,
-                            t_optimal_ms: info.t_optimal_ms,
-                            sigma_1s: info.sigma_1s,
-                            spread_pct: info.spread_pct,
-                            level: info.level,
-                            p_fill: info.p_fill,
-                            best_ev: info.best_ev,
-                            single_leg_ev: info.single_leg_ev,
-                        });
-                    }
-                    order_list.lock().remove(&child_order_acceptance_id);
+                    let Some(info) = order_list.lock().remove(&child_order_acceptance_id) else {
+                        continue;
+                    };
+                    let _ = outcome_tx.send(info.outcome(true, order_age));
+                    log_order_event(&config.symbol.to_string(), trade_logger, order_filled_event(timestamp, &child_order_acceptance_id, &info, order_age));
                 }
                 Err(e) => {
                     error!("Cancel failed (will retry): {:?}", e);
@@ -191,46 +239,128 @@ async fn cancel_child_order(
     }
 }
 
+/// Count an order lifecycle event for `/metrics`, then trade-log it when logging is enabled
+fn log_order_event(symbol: &str, trade_logger: &Option<TradeLogger>, event: TradeEvent) {
+    metrics_exporter::order_counters(symbol).record(&event);
+    if let Some(logger) = trade_logger {
+        logger.log(event);
+    }
+}
+
+fn order_filled_event(timestamp: String, order_id: &str, info: &model::OrderInfo, order_age_ms: u64) -> TradeEvent {
+    TradeEvent::OrderFilled {
+        timestamp,
+        order_id: order_id.to_string(),
+        side: info.side.to_string(),
+        price: info.price,
+        size: info.size,
+        order_age_ms,
+        is_close: info.is_close,
+        mid_price: info.mid_price as u64,
+        t_optimal_ms: info.t_optimal_ms,
+        sigma_1s: info.sigma_1s,
+        spread_pct: info.spread_pct,
+        level: info.level,
+        p_fill: info.p_fill,
+        best_ev: info.best_ev,
+        single_leg_ev: info.single_leg_ev,
+    }
+}
+
 /// 注文パラメータを検証する
 fn validate_order_params(
-    price: u64,
+    price: Price,
     size: f64,
     config: &BotConfig,
+    rule: &SymbolRule,
 ) -> std::result::Result<(), &'static str> {
     // 価格の検証
-    if price == 0 {
+    if price == Price::default() {
         return Err("Price cannot be zero");
     }
+    if !rule.is_valid_price(price.to_f64()) {
+        return Err("Price is not a multiple of the tick size");
+    }
 
     // サイズの検証
-    if size < config.min_lot {
+    if size < config.min_lot || size < rule.min_lot {
         return Err("Size below minimum lot");
     }
-    if size > config.max_lot * 10.0 {
+    if size > config.max_lot * 10.0 || size > rule.max_lot {
         return Err("Size exceeds maximum allowed");
     }
 
-    // 小数点精度の検証 (GMO BTC minimum unit: 0.0001)
-    if (size * 10000.0).fract() != 0.0 {
-        return Err("Size precision too high (max 4 decimal places)");
+    // 小数点精度の検証 (銘柄ごとの size step)
+    if !rule.is_valid_size(size) {
+        return Err("Size is not a multiple of the size step");
     }
 
     Ok(())
 }
 
+/// Build the symbol registry: built-in defaults, then rules fetched from the exchange,
+/// then config overrides (config wins).
+fn build_symbol_registry(fetched: Option<Vec<SymbolRule>>, overrides: &[SymbolRule]) -> SymbolRegistry {
+    let mut registry = SymbolRegistry::new();
+    if let Some(rules) = fetched {
+        registry.extend(rules);
+    }
+    registry.extend(overrides.iter().cloned());
+    registry
+}
+
 /// Order result indicating whether margin was insufficient
 #[derive(Debug)]
 enum OrderResult {
     Success,
+    /// Skipped by the per-second order cap; nothing was sent
+    Throttled,
     MarginInsufficient,
     NoOpenPosition,
+    /// SOK rejection (ERR-5003): the post-only price would have taken liquidity
+    WouldTake,
+    /// Over GMO's API rate limit (HTTP 429); the order was not accepted
+    RateLimited,
+    /// Skipped: an open order on this side already rests at (nearly) the same price
+    Duplicate,
     OtherError,
 }
 
-const ERR_MARGIN_INSUFFICIENT: &str = "ERR-201";
-const ERR_SOK_TAKER: &str = "ERR-5003";
-const ERR_NO_OPEN_POSITION: &str = "ERR-422";
-const GHOST_POSITION_COOLDOWN_SECS: u64 = 60;
+/// New orders are suppressed this long after GMO answers HTTP 429
+const RATE_LIMIT_COOLDOWN_SECS: u64 = 5;
+/// A lost-response order must show up in the order events within this long to be matched
+const UNCONFIRMED_MATCH_MS: u64 = 60_000;
+/// Reported price may differ from the sent one by this much and still match
+const UNCONFIRMED_PRICE_TOLERANCE_JPY: f64 = 1.0;
+/// Consecutive SOK rejections on one side before its open quote is pushed back from the touch
+const WOULD_TAKE_WIDEN_AFTER: u32 = 2;
+/// Extra open-quote offset per further consecutive SOK rejection, and its cap (bps of mid,
+/// so a low-priced symbol is not pushed hundreds of ticks away; 0.05 bps = 50 JPY at 10M)
+const WOULD_TAKE_WIDEN_STEP_BPS: f64 = 0.05;
+const WOULD_TAKE_WIDEN_MAX_BPS: f64 = 0.5;
+/// How often the per-level fill-rate / adverse-selection table and the order-age histogram are
+/// appended to level_stats-*.csv / order_age_hist-*.csv
+const LEVEL_STATS_DUMP_SECS: u64 = 300;
+/// Recovery probes during a maintenance pause start this far apart and back off up to the max
+const MAINTENANCE_PROBE_MIN_SECS: u64 = 5;
+const MAINTENANCE_PROBE_MAX_SECS: u64 = 300;
+/// Public WS silence after which trading pauses and /healthz reports unhealthy
+const WS_STALE_THRESHOLD_MS: i64 = 60_000;
+/// How long a (re)connect may go without a first message before the feed counts as stale:
+/// covers the 60s backoff cap plus the paced subscribes
+const WS_RECONNECT_GRACE_MS: i64 = 120_000;
+
+/// Resolve the time_in_force to send for an order type, dropping combinations GMO rejects
+/// (SOK on MARKET).
+fn time_in_force_for(config: &BotConfig, execution_type: &ChildOrderType) -> Option<TimeInForce> {
+    let tif = parse_time_in_force(config.time_in_force.as_deref()).ok().flatten()?;
+    if tif.is_valid_for(execution_type) {
+        Some(tif)
+    } else {
+        warn!("time_in_force {:?} is not allowed for {} orders, sending without it", tif, execution_type);
+        None
+    }
+}
 
 /// Reset position to zero on ghost detection.
 /// get_position polls every 5s and may temporarily overwrite with stale data;
@@ -245,65 +375,504 @@ fn reset_position(position: &Positions) {
     pos.short_open_time = None;
 }
 
+/// Public WS feed as seen by the trade loop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WsFeedState {
+    /// No message since the (re)connect began; staleness is not armed yet
+    Waiting,
+    Fresh,
+    /// Last message older than the threshold
+    Stale,
+}
+
+/// `last_ws_ts` is 0 until the first message after each (re)connect, which began at
+/// `waiting_since` (0 = unknown). A reconnect gets `WS_RECONNECT_GRACE_MS` before it is stale.
+fn ws_feed_state(last_ws_ts: i64, waiting_since: i64, now: i64, threshold_ms: i64) -> WsFeedState {
+    if last_ws_ts <= 0 {
+        if waiting_since > 0 && now - waiting_since > WS_RECONNECT_GRACE_MS {
+            WsFeedState::Stale
+        } else {
+            WsFeedState::Waiting
+        }
+    } else if now - last_ws_ts > threshold_ms {
+        WsFeedState::Stale
+    } else {
+        WsFeedState::Fresh
+    }
+}
+
+/// Disarm the staleness check for a reconnect: the age of the pre-disconnect feed says
+/// nothing about the new connection, which re-arms it with its first message. The disconnect
+/// time is kept so an outage that never recovers still goes stale.
+fn reset_ws_staleness(feed: &SymbolFeed, now: i64) {
+    *feed.last_ws_message.write() = 0;
+    *feed.ws_waiting_since.write() = now;
+}
+
+/// Sliding-window count of ghost-position detections. Past `threshold` hits within `window`
+/// the desync is not transient, so the trade loop stops instead of cycling through cooldowns.
+struct GhostTracker {
+    window: Duration,
+    threshold: usize,
+    hits: std::collections::VecDeque<Instant>,
+}
+
+impl GhostTracker {
+    fn new(window: Duration, threshold: usize) -> Self {
+        Self { window, threshold, hits: std::collections::VecDeque::new() }
+    }
+
+    /// Record a detection at `now`; returns true when the escalation threshold is reached
+    /// (never when `threshold` is 0).
+    fn record(&mut self, now: Instant) -> bool {
+        while self.hits.front().is_some_and(|t| now.duration_since(*t) > self.window) {
+            self.hits.pop_front();
+        }
+        self.hits.push_back(now);
+        self.threshold > 0 && self.hits.len() >= self.threshold
+    }
+
+    fn count(&self) -> usize {
+        self.hits.len()
+    }
+}
+
+/// Count a ghost detection and enter safe mode once they repeat. Safe mode gets its own
+/// alert: the GHOST_POSITION just before it is usually still inside the alert cooldown.
+fn record_ghost(ghost_tracker: &mut GhostTracker, safe_mode: &mut Option<&'static str>, alerts: &AlertSink, config: &BotConfig) {
+    if !ghost_tracker.record(Instant::now()) {
+        return;
+    }
+    *safe_mode = Some("repeated ghost positions");
+    error!("[GHOST_SAFE_MODE] {} ghost detections within {}s, entering safe mode",
+        ghost_tracker.count(), config.ghost_safe_mode_window_secs);
+    alerts.send("GHOST_SAFE_MODE", &format!(
+        "{} ghost positions within {}s, trading halted until restart",
+        ghost_tracker.count(), config.ghost_safe_mode_window_secs,
+    ));
+}
+
+/// Per-side streak of SOK (would-take) rejections. A streak means our open price keeps
+/// crossing the book (usually a lagging board), so the side's quote is pushed back from
+/// the touch until an order is accepted again.
+#[derive(Debug, Default)]
+struct WouldTakeGuard {
+    consecutive_buy: u32,
+    consecutive_sell: u32,
+}
+
+impl WouldTakeGuard {
+    fn streak_mut(&mut self, side: &OrderSide) -> &mut u32 {
+        if *side == OrderSide::BUY { &mut self.consecutive_buy } else { &mut self.consecutive_sell }
+    }
+
+    fn consecutive(&self, side: &OrderSide) -> u32 {
+        if *side == OrderSide::BUY { self.consecutive_buy } else { self.consecutive_sell }
+    }
+
+    /// Track the outcome of an order sent on `side`: SOK rejections extend the streak, an
+    /// accepted order ends it, anything else (throttled, margin, ...) leaves it unchanged.
+    fn record(&mut self, side: &OrderSide, result: &OrderResult) {
+        match result {
+            OrderResult::WouldTake => *self.streak_mut(side) += 1,
+            OrderResult::Success => *self.streak_mut(side) = 0,
+            _ => {}
+        }
+    }
+
+    /// Extra distance (JPY) from mid for the side's open quote: 0 below WOULD_TAKE_WIDEN_AFTER
+    /// rejections, then one step per rejection up to the cap, both scaled to `mid_price`.
+    fn widen_jpy(&self, side: &OrderSide, mid_price: f64) -> f64 {
+        let streak = self.consecutive(side);
+        if streak < WOULD_TAKE_WIDEN_AFTER {
+            return 0.0;
+        }
+        let bps = ((streak - WOULD_TAKE_WIDEN_AFTER + 1) as f64 * WOULD_TAKE_WIDEN_STEP_BPS).min(WOULD_TAKE_WIDEN_MAX_BPS);
+        mid_price * bps / 10_000.0
+    }
+}
+
+/// Per close-side count of close orders cancelled for age since the last close fill.
+/// Each one shrinks that side's close_spread_factor, pulling the close toward mid.
+#[derive(Debug, Default)]
+struct CloseEscalation {
+    cancels_buy: u32,
+    cancels_sell: u32,
+}
+
+impl CloseEscalation {
+    fn cancels_mut(&mut self, side: &OrderSide) -> &mut u32 {
+        if *side == OrderSide::BUY { &mut self.cancels_buy } else { &mut self.cancels_sell }
+    }
+
+    /// Track a close order outcome on `side` (the close order's side: SELL closes a long)
+    fn record(&mut self, side: &OrderSide, filled: bool) {
+        let cancels = self.cancels_mut(side);
+        *cancels = if filled { 0 } else { cancels.saturating_add(1) };
+    }
+
+    fn reset(&mut self, side: &OrderSide) {
+        *self.cancels_mut(side) = 0;
+    }
+
+    /// close_spread_factor for `side`: `base * step^cancels`, never below `floor` (or `base`
+    /// itself when that is already lower)
+    fn factor(&self, side: &OrderSide, base: f64, step: f64, floor: f64) -> f64 {
+        let cancels = if *side == OrderSide::BUY { self.cancels_buy } else { self.cancels_sell };
+        (base * step.powi(cancels.min(i32::MAX as u32) as i32)).max(floor.min(base))
+    }
+}
+
+/// Trade loop period: `trade_interval_ms`, else `order_interval_ms`
+fn trade_interval_ms(config: &BotConfig) -> u64 {
+    config.trade_interval_ms.unwrap_or(config.order_interval_ms)
+}
+
+/// Re-quote period for `side`: `buy_interval_ms` / `sell_interval_ms`, else the trade loop period
+fn side_interval_ms(config: &BotConfig, side: &OrderSide) -> u64 {
+    let interval = if *side == OrderSide::BUY { config.buy_interval_ms } else { config.sell_interval_ms };
+    interval.unwrap_or_else(|| trade_interval_ms(config))
+}
+
+/// How long the trade loop sleeps: often enough for the faster side to keep its cadence
+fn loop_interval_ms(config: &BotConfig) -> u64 {
+    trade_interval_ms(config)
+        .min(side_interval_ms(config, &OrderSide::BUY))
+        .min(side_interval_ms(config, &OrderSide::SELL))
+}
+
+/// Last time each side was quoted, so each side re-quotes on its own interval
+#[derive(Debug, Default)]
+struct SideCadence {
+    last_buy: Option<Instant>,
+    last_sell: Option<Instant>,
+}
+
+impl SideCadence {
+    fn last(&self, side: &OrderSide) -> Option<Instant> {
+        if *side == OrderSide::BUY { self.last_buy } else { self.last_sell }
+    }
+
+    /// True when `side` has never been quoted or was last quoted at least `interval` ago
+    fn due(&self, side: &OrderSide, now: Instant, interval: Duration) -> bool {
+        self.last(side).is_none_or(|t| now.duration_since(t) >= interval)
+    }
+
+    fn mark(&mut self, side: &OrderSide, now: Instant) {
+        if *side == OrderSide::BUY { self.last_buy = Some(now) } else { self.last_sell = Some(now) }
+    }
+}
+
+/// Startup warmup: the trade loop keeps its book and volatility windows filling but quotes
+/// nothing until `ends_at`, so the first orders are not priced off the bootstrap sample alone.
+/// P(fill) posteriors only learn from real order outcomes, so they leave warmup at the prior.
+#[derive(Debug)]
+struct Warmup {
+    ends_at: Instant,
+    complete: bool,
+}
+
+impl Warmup {
+    fn new(started: Instant, duration: Duration) -> Self {
+        Self { ends_at: started + duration, complete: duration.is_zero() }
+    }
+
+    /// True while warming up; the first call past `ends_at` logs the end and it stays over.
+    fn holds_orders(&mut self, now: Instant) -> bool {
+        if self.complete {
+            return false;
+        }
+        if now < self.ends_at {
+            return true;
+        }
+        self.complete = true;
+        info!("[WARMUP] Warmup complete, quoting starts");
+        false
+    }
+}
+
+/// (buy, sell) sides the trade loop sends this cycle: a side goes out when it is due and has
+/// a close or open to send, and neither does while warming up.
+fn quote_sides(warming_up: bool, due: (bool, bool), wanted: (bool, bool)) -> (bool, bool) {
+    if warming_up {
+        return (false, false);
+    }
+    (due.0 && wanted.0, due.1 && wanted.1)
+}
+
+/// Private-API maintenance detector shared by every symbol's trade loop and position poll.
+/// `threshold` consecutive maintenance errors pause trading; while paused only the trade loop's
+/// recovery probe calls the API, at doubling intervals, and the first success resumes.
+#[derive(Debug)]
+struct MaintenancePause {
+    consecutive_errors: u32,
+    paused: bool,
+    probe_backoff: Duration,
+    next_probe: Instant,
+}
+
+impl MaintenancePause {
+    fn new() -> Self {
+        Self {
+            consecutive_errors: 0,
+            paused: false,
+            probe_backoff: Duration::from_secs(MAINTENANCE_PROBE_MIN_SECS),
+            next_probe: Instant::now(),
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn probe_due(&self, now: Instant) -> bool {
+        self.paused && now >= self.next_probe
+    }
+
+    /// Count a maintenance error (or, while paused, any failed probe). True when this one
+    /// trips the pause; later failures push the next probe out, doubling up to the max.
+    fn record_error(&mut self, threshold: u32, now: Instant) -> bool {
+        self.consecutive_errors += 1;
+        if self.paused {
+            self.probe_backoff = (self.probe_backoff * 2).min(Duration::from_secs(MAINTENANCE_PROBE_MAX_SECS));
+            self.next_probe = now + self.probe_backoff;
+            return false;
+        }
+        if threshold == 0 || self.consecutive_errors < threshold {
+            return false;
+        }
+        self.paused = true;
+        self.probe_backoff = Duration::from_secs(MAINTENANCE_PROBE_MIN_SECS);
+        self.next_probe = now + self.probe_backoff;
+        warn!("[MAINTENANCE] {} consecutive maintenance errors, pausing trading", self.consecutive_errors);
+        true
+    }
+
+    /// A private call got through. True when this ends a pause.
+    fn record_success(&mut self) -> bool {
+        self.consecutive_errors = 0;
+        if !self.paused {
+            return false;
+        }
+        self.paused = false;
+        info!("[MAINTENANCE] Private API answering again, resuming trading");
+        true
+    }
+}
+
+/// Trailing stop: per-side peak unrealized P&L since that side opened. Fires once P&L has
+/// retraced more than `distance` JPY from a positive peak; peaks reset when the side goes flat.
+struct TrailingStop {
+    distance: f64,
+    peak_long: Option<f64>,
+    peak_short: Option<f64>,
+}
+
+impl TrailingStop {
+    fn new(distance: f64) -> Self {
+        Self { distance, peak_long: None, peak_short: None }
+    }
+
+    /// Feed the current per-side P&L (None = side flat). Returns the close side (SELL for a long,
+    /// BUY for a short) when a trailing stop is hit. The peak is kept, so a hit that is not acted
+    /// on (cooldown) fires again next cycle; `closed` restarts it once the close is sent.
+    fn update(&mut self, long_pnl: Option<f64>, short_pnl: Option<f64>) -> Option<OrderSide> {
+        let distance = self.distance;
+        let hit = |peak: &mut Option<f64>, pnl: Option<f64>| -> bool {
+            let Some(pnl) = pnl else {
+                *peak = None;
+                return false;
+            };
+            let high = peak.map_or(pnl, |p| p.max(pnl));
+            *peak = Some(high);
+            distance > 0.0 && high > 0.0 && high - pnl > distance
+        };
+        let long_hit = hit(&mut self.peak_long, long_pnl);
+        let short_hit = hit(&mut self.peak_short, short_pnl);
+        let held = if long_hit {
+            Some(OrderSide::BUY)
+        } else if short_hit {
+            Some(OrderSide::SELL)
+        } else {
+            None
+        };
+        held.map(|side| side.opposite())
+    }
+
+    /// A close on `close_side` went out: the side it closes starts a fresh peak.
+    fn closed(&mut self, close_side: &OrderSide) {
+        if *close_side == OrderSide::SELL { self.peak_long = None } else { self.peak_short = None }
+    }
+}
+
+/// Ask the exchange whether any position is still open before a MARKET close: get_position
+/// polls every 5s, so the cached position may be stale. An API error counts as open (safe
+/// default); a dry run's local position is the simulator's own, so there is nothing to ask.
+async fn position_still_open(client: &reqwest::Client, limiter: &RateLimiter, config: &BotConfig, sim: Option<&SimExchange>) -> bool {
+    if sim.is_some() {
+        return true;
+    }
+    match gmo::get_position::get_position(client, limiter, config.symbol.clone(), config.api_max_retries).await {
+        Ok(resp) => resp.data.as_ref()
+            .and_then(|d| d.list.as_ref())
+            .is_some_and(|list| !list.is_empty()),
+        Err(_) => true,
+    }
+}
+
 /// Activate ghost protection: reset position and set suppression window.
 /// Must be called atomically (reset + suppression) to prevent get_position from
 /// overwriting the reset with stale data before the suppression takes effect.
 fn activate_ghost_protection(
     position: &Positions,
     ghost_suppression: &GhostSuppression,
-    cooldown_secs: u64,
+    config: &BotConfig,
 ) -> Instant {
     reset_position(position);
-    let until = Instant::now() + Duration::from_secs(cooldown_secs);
+    let until = Instant::now() + Duration::from_secs(config.ghost_position_cooldown_secs);
     *ghost_suppression.write() = Some(until);
     until
 }
 
+/// Why a position is being MARKET closed; picks the log tag and the trade event
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CloseReason {
+    StopLoss,
+    TrailingStop,
+}
+
+impl CloseReason {
+    fn tag(self) -> &'static str {
+        match self {
+            CloseReason::StopLoss => "STOP_LOSS",
+            CloseReason::TrailingStop => "TRAILING_STOP",
+        }
+    }
+}
+
+/// How a stop-loss / trailing-stop close goes out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CloseExecution {
+    Market,
+    /// Marketable LIMIT (FAK) at the worst price still within the slippage budget
+    Limit(Price),
+}
+
+/// Average fill price of a MARKET order for `size` taking `levels` (best level first).
+/// None when the visible book is too thin to fill it all.
+fn estimate_market_fill(levels: impl Iterator<Item = (u64, f64)>, size: f64) -> Option<f64> {
+    let mut remaining = size;
+    let mut cost = 0.0;
+    for (price, level_size) in levels {
+        let take = remaining.min(level_size);
+        cost += take * price as f64;
+        remaining -= take;
+        if remaining <= 1e-12 {
+            return Some(cost / size);
+        }
+    }
+    None
+}
+
+/// MARKET unless walking the book `side` takes from (asks for BUY, bids for SELL) puts the
+/// average fill more than `max_slippage_jpy` from mid, or the book cannot fill `size` at all;
+/// then a LIMIT at mid -/+ the budget, on the tick. `max_slippage_jpy` <= 0 always sends MARKET.
+fn close_execution(
+    side: &OrderSide,
+    size: f64,
+    mid_price: f64,
+    asks: &BTreeMap<u64, f64>,
+    bids: &BTreeMap<u64, f64>,
+    max_slippage_jpy: f64,
+    tick_size: f64,
+) -> CloseExecution {
+    if max_slippage_jpy <= 0.0 {
+        return CloseExecution::Market;
+    }
+    let (fill, limit) = match side {
+        OrderSide::SELL => (
+            estimate_market_fill(bids.iter().rev().map(|(p, s)| (*p, *s)), size),
+            mid_price - max_slippage_jpy,
+        ),
+        _ => (
+            estimate_market_fill(asks.iter().map(|(p, s)| (*p, *s)), size),
+            mid_price + max_slippage_jpy,
+        ),
+    };
+    match fill {
+        Some(fill) if (fill - mid_price).abs() <= max_slippage_jpy => CloseExecution::Market,
+        // SELL rounds up / BUY down: toward mid, so the order never accepts more than the budget
+        _ => CloseExecution::Limit(Price::from_f64(round_to_tick(limit, tick_size, side))),
+    }
+}
+
 /// Returns true if ghost position detected (ERR-422)
+#[allow(clippy::too_many_arguments)]
 async fn send_market_close(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &BotConfig,
+    reason: CloseReason,
     side: &OrderSide,
     size: f64,
+    execution: CloseExecution,
     trade_logger: &Option<TradeLogger>,
     mid_price: u64,
     open_price: f64,
     unrealized_pnl: f64,
+    sim: Option<&SimExchange>,
 ) -> bool {
+    let (execution_type, price, time_in_force) = match execution {
+        CloseExecution::Market => (ChildOrderType::MARKET, None, time_in_force_for(config, &ChildOrderType::MARKET)),
+        // FAK: take what the budget allows, never leave an untracked close resting on the book
+        CloseExecution::Limit(price) => (ChildOrderType::LIMIT, Some(price.to_string()), Some(TimeInForce::FAK)),
+    };
     let parameter = gmo::close_bulk_order::CloseBulkOrderParameter {
-        symbol: Symbol::BTC_JPY,
+        symbol: config.symbol.clone(),
         side: side.clone(),
-        execution_type: ChildOrderType::MARKET,
-        price: None,
-        size: size.to_string(),
-        time_in_force: None,
+        execution_type,
+        price,
+        size: Size::from_f64(size).to_string(),
+        time_in_force,
     };
 
-    let ghost_hit = match gmo::close_bulk_order::close_bulk_order(client, &parameter).await {
-        Ok(response) => {
-            info!("[STOP_LOSS] MARKET close sent: order_id={} side={:?} size={}", response.1.data, side, size);
+    let response = match (sim, execution) {
+        (Some(sim), CloseExecution::Market) => Ok(sim.place_market(side.clone(), size, true)),
+        (Some(sim), CloseExecution::Limit(price)) => Ok(sim.place_limit(side.clone(), price.to_u64(), size, true)),
+        (None, _) => gmo::close_bulk_order::close_bulk_order(client, limiter, &parameter, config.api_max_retries)
+            .await
+            .map(|response| response.1.data),
+    };
+    let ghost_hit = match response {
+        Ok(order_id) => {
+            match execution {
+                CloseExecution::Market => {
+                    info!("[{}] MARKET close sent: order_id={} side={:?} size={}", reason.tag(), order_id, side, size)
+                }
+                CloseExecution::Limit(price) => warn!(
+                    "[CLOSE_SLIPPAGE] Book too thin for a MARKET close within {} JPY of mid {}, LIMIT close sent: order_id={} side={:?} size={} price={}",
+                    config.max_close_slippage_jpy, mid_price, order_id, side, size, price
+                ),
+            }
             false
         }
-        Err(ApiResponseError::ApiError(ref msgs))
-            if msgs.iter().any(|m| m.message_code == ERR_NO_OPEN_POSITION) =>
-        {
+        Err(ref e) if e.has_error_code(&GmoErrorCode::NoOpenPosition) => {
             warn!("[GHOST_POSITION] MARKET close ERR-422: no open positions to settle. side={:?} size={}", side, size);
             true
         }
         Err(e) => {
-            error!("[STOP_LOSS] MARKET close failed: {:?}", e);
+            error!("[{}] MARKET close failed: {:?}", reason.tag(), e);
             false
         }
     };
 
     if !ghost_hit {
         if let Some(logger) = trade_logger {
-            logger.log(TradeEvent::StopLossTriggered {
-                timestamp: Utc::now().to_rfc3339(),
-                side: side.to_string(),
-                size,
-                unrealized_pnl,
-                mid_price,
-                open_price,
+            let timestamp = Utc::now().to_rfc3339();
+            let side = side.to_string();
+            logger.log(match reason {
+                CloseReason::StopLoss => TradeEvent::StopLossTriggered { timestamp, side, size, unrealized_pnl, mid_price, open_price },
+                CloseReason::TrailingStop => TradeEvent::TrailingStopTriggered { timestamp, side, size, unrealized_pnl, mid_price, open_price },
             });
         }
     }
@@ -311,177 +880,231 @@ async fn send_market_close(
     ghost_hit
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn send_order(
     client: &reqwest::Client,
+    limiter: &RateLimiter,
     order_list: &Orders,
     side: OrderSide,
-    price: u64,
+    price: Price,
     size: f64,
     is_close_order: bool,
     config: &BotConfig,
+    symbol_rule: &SymbolRule,
     trade_logger: &Option<TradeLogger>,
-    mid_price: u64,
+    mid_price: f64,
     t_optimal_ms: u64,
     sigma_1s: f64,
     spread_pct: f64,
-    level: u32,
+    level: Option<&FloatingExp>,
     p_fill: f64,
     best_ev: f64,
     single_leg_ev_val: f64,
+    sim: Option<&SimExchange>,
 ) -> OrderResult {
     // バリデーション
-    if let Err(reason) = validate_order_params(price, size, config) {
+    if let Err(reason) = validate_order_params(price, size, config, symbol_rule) {
         warn!("Invalid Order: {} - side={:?} price={} size={}", reason, side, price, size);
         return OrderResult::Success;
     }
 
+    // Dedup: a resting open order at (nearly) this price already covers the level
+    if !is_close_order {
+        if let Some(existing) = duplicate_open_order(&order_list.lock(), &side, price.to_u64(), config.dedup_price_tolerance_jpy) {
+            debug!("[DEDUP] {:?} open at {} skipped: order at {} already pending", side, price, existing);
+            return OrderResult::Duplicate;
+        }
+    }
+
+    // Order-rate cap: skip this order, the next trade cycle requotes
+    if !limiter.try_order_slot() {
+        info!("[THROTTLE] max_orders_per_sec reached, deferring {:?} order to next cycle", side);
+        return OrderResult::Throttled;
+    }
+
+    let client_id = util::new_client_order_id();
     let mut order_id = String::new();
     let mut order_success = false;
     let mut order_error: Option<String> = None;
-    let mut margin_insufficient = false;
-    let mut no_open_position = false;
+    let mut rejection: Option<OrderResult> = None;
+    let mut lost_response = false;
 
-    if is_close_order {
+    if let Some(sim) = sim {
+        order_id = sim.place_limit(side.clone(), price.to_u64(), size, is_close_order);
+        order_success = true;
+    } else if is_close_order {
         let parameter = gmo::close_bulk_order::CloseBulkOrderParameter {
-            symbol: Symbol::BTC_JPY,
+            symbol: config.symbol.clone(),
             side: side.clone(),
             execution_type: ChildOrderType::LIMIT,
             price: Some(price.to_string()),
-            size: size.to_string(),
-            time_in_force: None,
+            size: Size::from_f64(size).to_string(),
+            time_in_force: time_in_force_for(config, &ChildOrderType::LIMIT),
         };
 
-        let response = gmo::close_bulk_order::close_bulk_order(client, &parameter).await;
+        let response = gmo::close_bulk_order::close_bulk_order(client, limiter, &parameter, config.api_max_retries).await;
         match response {
             Ok(response) => {
                 order_id = response.1.data;
                 order_success = true;
             }
-            Err(ApiResponseError::ApiError(ref msgs))
-                if msgs.iter().any(|m| m.message_code == ERR_NO_OPEN_POSITION) =>
-            {
-                warn!("[GHOST_POSITION] Close Order ERR-422: no open positions. side={:?} price={}", side, price);
-                no_open_position = true;
-                order_error = Some(format!("{:?}", msgs));
-            }
-            Err(ApiResponseError::ApiError(ref msgs))
-                if msgs.iter().any(|m| m.message_code == ERR_MARGIN_INSUFFICIENT) =>
-            {
-                warn!("Close Order rejected: margin insufficient (ERR-201)");
-                margin_insufficient = true;
-                order_error = Some(format!("{:?}", msgs));
-            }
             Err(e) => {
-                error!("Close Order Failed {:?}", e);
+                rejection = Some(order_rejection("Close Order", &e, &side, price));
+                lost_response = response_lost(&e);
                 order_error = Some(format!("{:?}", e));
             }
         }
     } else {
         let parameter = gmo::send_order::ChildOrderParameter {
-            symbol: Symbol::BTC_JPY,
+            symbol: config.symbol.clone(),
             side: side.clone(),
             execution_type: ChildOrderType::LIMIT,
             price: Some(price.to_string()),
-            size: size.to_string(),
-            time_in_force: None, // SOK disabled: leverage trading has zero fees for both Maker/Taker
+            size: Size::from_f64(size).to_string(),
+            time_in_force: time_in_force_for(config, &ChildOrderType::LIMIT),
         };
 
-        let response = gmo::send_order::post_child_order(client, &parameter).await;
+        let response = gmo::send_order::post_child_order(client, limiter, &parameter, config.api_max_retries).await;
         match response {
             Ok(response) => {
                 order_id = response.1.data;
                 order_success = true;
             }
-            Err(ApiResponseError::ApiError(ref msgs))
-                if msgs.iter().any(|m| m.message_code == ERR_MARGIN_INSUFFICIENT) =>
-            {
-                warn!("Send Order rejected: margin insufficient (ERR-201)");
-                margin_insufficient = true;
-                order_error = Some(format!("{:?}", msgs));
-            }
-            Err(ApiResponseError::ApiError(ref msgs))
-                if msgs.iter().any(|m| m.message_code == ERR_SOK_TAKER) =>
-            {
-                info!("SOK rejected (would take liquidity): side={:?} price={}", side, price);
-            }
             Err(e) => {
-                error!("Send Order Failed {:?}", e);
+                rejection = Some(order_rejection("Send Order", &e, &side, price));
+                lost_response = response_lost(&e);
                 order_error = Some(format!("{:?}", e));
             }
         }
     }
 
     let timestamp = Utc::now().to_rfc3339();
+    let level_rate = level.map_or(0, |l| l.rate as u32);
+    let order_info = model::OrderInfo {
+        price: price.to_u64(),
+        size,
+        side: side.clone(),
+        timestamp: Utc::now().timestamp_millis() as u64,
+        is_close: is_close_order,
+        mid_price,
+        t_optimal_ms,
+        sigma_1s,
+        spread_pct,
+        level: level_rate,
+        level_key: level.cloned(),
+        p_fill,
+        best_ev,
+        single_leg_ev: single_leg_ev_val,
+        client_id,
+    };
 
     // 成功した場合のみ注文リストに追加
     if order_success && !order_id.is_empty() {
-        let order_info = model::OrderInfo {
-            price,
+        if is_close_order {
+            info!("Close Order sent: id={} {:?}", order_id, order_info);
+        } else {
+            info!("Send Order sent: id={} {:?}", order_id, order_info);
+        }
+
+        order_list.lock().insert(order_id.clone(), order_info);
+
+        log_order_event(&config.symbol.to_string(), trade_logger, TradeEvent::OrderSent {
+            timestamp,
+            order_id,
+            side: side.to_string(),
+            price: price.to_u64(),
             size,
-            side: side.clone(),
-            timestamp: Utc::now().timestamp_millis() as u64,
             is_close: is_close_order,
-            mid_price,
+            mid_price: mid_price as u64,
             t_optimal_ms,
             sigma_1s,
             spread_pct,
-            level,
+            level: level_rate,
             p_fill,
             best_ev,
             single_leg_ev: single_leg_ev_val,
-        };
-
-        if is_close_order {
-            info!("Close Order sent: id={} {:?}", order_id, order_info);
-        } else {
-            info!("Send Order sent: id={} {:?}", order_id, order_info);
+        });
+    } else if let Some(err) = order_error {
+        if lost_response {
+            // The order may be live anyway: keep it for the private WS order events to claim
+            warn!("[UNCONFIRMED] No response for {:?} order at {} (client_id={}), awaiting reconciliation",
+                side, price, order_info.client_id);
+            order_list.lock().add_unconfirmed(order_info);
         }
+        log_order_event(&config.symbol.to_string(), trade_logger, TradeEvent::OrderFailed {
+            timestamp,
+            side: side.to_string(),
+            price: price.to_u64(),
+            size,
+            error: err,
+            mid_price: mid_price as u64,
+            t_optimal_ms,
+            sigma_1s,
+            spread_pct,
+        });
+    }
 
-        order_list.lock().insert(order_id.clone(), order_info);
+    match rejection {
+        Some(result) => result,
+        None if order_success => OrderResult::Success,
+        None => OrderResult::OtherError,
+    }
+}
 
-        if let Some(logger) = trade_logger {
-            logger.log(TradeEvent::OrderSent {
-                timestamp,
-                order_id,
-                side: side.to_string(),
-                price,
-                size,
-                is_close: is_close_order,
-                mid_price,
-                t_optimal_ms,
-                sigma_1s,
-                spread_pct,
-                level,
-                p_fill,
-                best_ev,
-                single_leg_ev: single_leg_ev_val,
-            });
+/// Price of a pending open order on `side` within `tolerance_jpy` of `price`; None when
+/// dedup is off (`tolerance_jpy` unset) or nothing is that close.
+fn duplicate_open_order(orders: &model::OrderMap, side: &OrderSide, price: u64, tolerance_jpy: Option<f64>) -> Option<u64> {
+    orders.open_order_near(side, price, tolerance_jpy?).map(|o| o.price)
+}
+
+/// Map a failed order request to its OrderResult. When several codes come back together,
+/// ERR-422 wins over ERR-201, which wins over SOK.
+fn rejection_result(error: &ApiResponseError) -> OrderResult {
+    match error {
+        ApiResponseError::ApiError(_) => {
+            let codes = error.error_codes();
+            if codes.contains(&GmoErrorCode::NoOpenPosition) {
+                OrderResult::NoOpenPosition
+            } else if codes.contains(&GmoErrorCode::MarginInsufficient) {
+                OrderResult::MarginInsufficient
+            } else if codes.contains(&GmoErrorCode::SokTaker) {
+                OrderResult::WouldTake
+            } else {
+                OrderResult::OtherError
+            }
         }
-    } else if let Some(err) = order_error {
-        if let Some(logger) = trade_logger {
-            logger.log(TradeEvent::OrderFailed {
-                timestamp,
-                side: side.to_string(),
-                price,
-                size,
-                error: err,
-                mid_price,
-                t_optimal_ms,
-                sigma_1s,
-                spread_pct,
-            });
+        ApiResponseError::StatusCode(status) if *status == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+            OrderResult::RateLimited
         }
+        _ => OrderResult::OtherError,
     }
+}
 
-    if no_open_position {
-        OrderResult::NoOpenPosition
-    } else if margin_insufficient {
-        OrderResult::MarginInsufficient
-    } else if order_success {
-        OrderResult::Success
-    } else {
-        OrderResult::OtherError
+/// The request may have reached GMO even though no answer came back (transport failure,
+/// 5xx or an unreadable body), so the order could be live under an id we never saw
+fn response_lost(error: &ApiResponseError) -> bool {
+    match error {
+        ApiResponseError::Reqwest(_) | ApiResponseError::Deserialize(_) => true,
+        ApiResponseError::StatusCode(code) => code.is_server_error(),
+        _ => false,
+    }
+}
+
+/// Classify and log a failed `kind` ("Send Order" / "Close Order") request
+fn order_rejection(kind: &str, error: &ApiResponseError, side: &OrderSide, price: Price) -> OrderResult {
+    let result = rejection_result(error);
+    match result {
+        OrderResult::NoOpenPosition => {
+            warn!("[GHOST_POSITION] {} ERR-422: no open positions. side={:?} price={}", kind, side, price);
+        }
+        OrderResult::MarginInsufficient => warn!("{} rejected: margin insufficient (ERR-201)", kind),
+        OrderResult::WouldTake => {
+            info!("SOK rejected (would take liquidity): side={:?} price={}", side, price);
+        }
+        OrderResult::RateLimited => warn!("{} rejected: rate limited (HTTP 429) side={:?} price={}", kind, side, price),
+        _ => error!("{} Failed {:?}", kind, error),
     }
+    result
 }
 
 fn update_order_prices(
@@ -494,89 +1117,241 @@ fn update_order_prices(
     });
 }
 
-/// Calculate optimal order lifetime in milliseconds based on spread and volatility.
-/// T_optimal = (spread_pct / sigma_1s)²
-/// Clamped between min_ms and max_ms.
-fn calculate_t_optimal(spread_pct: f64, sigma_1s: f64, min_ms: u64, max_ms: u64) -> u64 {
-    if sigma_1s <= 0.0 || spread_pct <= 0.0 {
-        return max_ms;
-    }
-    let ratio = spread_pct / sigma_1s;
-    let t_secs = ratio * ratio;
+/// Feed one resolved open order into the posterior of the exact level it was quoted at.
+/// Returns false when that level is no longer in the table (e.g. dropped by a reload).
+fn update_level_posterior(
+    buy: &mut BTreeMap<FloatingExp, (f64, BayesProb)>,
+    sell: &mut BTreeMap<FloatingExp, (f64, BayesProb)>,
+    side: &OrderSide,
+    level: &FloatingExp,
+    filled: bool,
+) -> bool {
+    let probs = if *side == OrderSide::BUY { buy } else { sell };
+    match probs.get_mut(level) {
+        Some((_, bayes)) => {
+            bayes.update(1, filled as u64);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Re-key a posterior table to `levels` after a reload: levels still configured keep their
+/// posterior, new ones start at `prior`, and dropped ones go.
+fn rekey_levels(table: &mut BTreeMap<FloatingExp, (f64, BayesProb)>, levels: &[FloatingExp], prior: &BayesProb) {
+    let mut old = std::mem::take(table);
+    for key in levels {
+        let entry = old.remove(key).unwrap_or_else(|| (0.0, prior.clone()));
+        table.insert(key.clone(), entry);
+    }
+}
+
+/// After a circuit-breaker cooldown: when `enabled`, drop every level's posterior back to its
+/// prior, since fill rates learned before the move say little about the market after it.
+/// Returns whether anything was reset.
+fn reset_probabilities_after_breaker(
+    enabled: bool,
+    buy: &mut BTreeMap<FloatingExp, (f64, BayesProb)>,
+    sell: &mut BTreeMap<FloatingExp, (f64, BayesProb)>,
+) -> bool {
+    if !enabled {
+        return false;
+    }
+    buy.values_mut().chain(sell.values_mut()).for_each(|(_, bayes)| bayes.reset_to_prior());
+    true
+}
+
+/// Calculate optimal order lifetime in milliseconds based on spread and volatility.
+/// T_optimal = (spread_pct / sigma_1s)²
+/// Clamped between min_ms and max_ms.
+fn calculate_t_optimal(spread_pct: f64, sigma_1s: f64, min_ms: u64, max_ms: u64) -> u64 {
+    if sigma_1s <= 0.0 || spread_pct <= 0.0 {
+        return max_ms;
+    }
+    let ratio = spread_pct / sigma_1s;
+    let t_secs = ratio * ratio;
     let t_ms = (t_secs * 1000.0) as u64;
     t_ms.clamp(min_ms, max_ms)
 }
 
-/// Minimum volatility as a fraction of mean price (0.1 bps = 0.001%)
-const MIN_VOLATILITY_BPS: f64 = 0.00001;
+/// Sum the sizes of pending OPEN (non-close) orders for a given side (O(1) via the OrderMap index).
+fn pending_open_size(orders: &model::OrderMap, side: &OrderSide) -> f64 {
+    orders.pending_open_size(side)
+}
 
-fn calculate_volatility(executions: &[(u64, f64, i64)]) -> f64 {
-    // Need at least 2 data points for log-returns
-    if executions.len() < 2 {
-        let mean_price = executions.first().map(|e| e.0 as f64).unwrap_or(6_500_000.0);
-        return mean_price * MIN_VOLATILITY_BPS;
+/// Most aggressive (closest to market) price among pending OPEN orders on a side.
+fn best_pending_open_price(orders: &model::OrderMap, side: &OrderSide) -> Option<u64> {
+    let prices = orders.values()
+        .filter(|o| o.side == *side && !o.is_close)
+        .map(|o| o.price);
+    match side {
+        OrderSide::BUY => prices.max(),
+        OrderSide::SELL => prices.min(),
+        OrderSide::Unknown => None,
     }
+}
 
-    let prices: Vec<f64> = executions.iter().map(|e| e.0 as f64).collect();
-    let mean_price = prices.iter().sum::<f64>() / prices.len() as f64;
+/// Improve-only requote guard: a new open quote is allowed only if it is at least as close
+/// to the market as the resting order on that side (preserves queue priority).
+fn requote_improves(side: &OrderSide, new_price: u64, pending_price: Option<u64>) -> bool {
+    match (side, pending_price) {
+        (_, None) => true,
+        (OrderSide::BUY, Some(p)) => new_price >= p,
+        (OrderSide::SELL, Some(p)) => new_price <= p,
+        (OrderSide::Unknown, Some(_)) => false,
+    }
+}
 
-    // Calculate log-returns: ln(p[i] / p[i-1])
-    let log_returns: Vec<f64> = prices
-        .windows(2)
-        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
-        .map(|w| (w[1] / w[0]).ln())
-        .collect();
+/// Check if the given UTC hour is within trading hours.
+/// Trading disabled: data-collection-only mode. Metrics logging continues.
+fn is_trading_hour(_utc_hour: u32) -> bool {
+    false
+}
 
-    if log_returns.is_empty() {
-        return mean_price * MIN_VOLATILITY_BPS;
+/// Price range (JPY, fraction of mid) of executions in the last `window_ms`, when it exceeds
+/// `threshold_frac`. None = calm (or no recent trades).
+fn circuit_breaker_trip(executions: &[(u64, f64, i64)], now: i64, window_ms: i64, threshold_frac: f64) -> Option<(u64, f64)> {
+    let recent = executions.iter().filter(|e| e.2 >= now - window_ms).map(|e| e.0);
+    let (pmin, pmax) = recent.fold(None, |acc: Option<(u64, u64)>, p| match acc {
+        Some((lo, hi)) => Some((lo.min(p), hi.max(p))),
+        None => Some((p, p)),
+    })?;
+    let mid_est = (pmin + pmax) as f64 / 2.0;
+    if mid_est <= 0.0 {
+        return None;
     }
+    let range_frac = (pmax - pmin) as f64 / mid_est;
+    (range_frac > threshold_frac).then_some((pmax - pmin, range_frac))
+}
 
-    // EWMA variance: σ²_t = λ * σ²_{t-1} + (1-λ) * r²_t
-    // RiskMetrics standard lambda = 0.94
-    // Seed with initial window variance, then EWMA from remaining data only (no double-counting)
-    // Mean-zero assumption: r² instead of (r-μ)², appropriate for HFT tick data
-    // When data <= seed_n points, falls back to simple variance (no EWMA weighting).
-    // With execution_retain_ms=30000 and typical 2-5 ticks/sec, we have 60-150 returns;
-    // seed_n=10 edge case only triggers during startup or very low activity.
-    const LAMBDA: f64 = 0.94;
-    let seed_n = log_returns.len().min(10);
-    let mut ewma_var = log_returns[..seed_n].iter().map(|r| r.powi(2)).sum::<f64>()
-        / seed_n as f64;
-    for r in &log_returns[seed_n..] {
-        ewma_var = LAMBDA * ewma_var + (1.0 - LAMBDA) * r.powi(2);
+/// Explicit `levels` when configured, else the percentage grid from `price_step_*` (L1-L3 are
+/// excluded by default: closest levels had the highest adverse selection, -13.86 JPY/trip at L1).
+fn config_step_levels(config: &BotConfig) -> Vec<FloatingExp> {
+    match &config.levels {
+        Some(levels) => levels.clone(),
+        None => step_levels(config.price_step_start, config.price_step_end, config.price_step_base, config.price_step_exp),
     }
-    let stddev = ewma_var.sqrt();
+}
 
-    // Convert log-return stddev to absolute price units
-    let volatility = mean_price * stddev;
+/// Stop-loss close for the side with the worse P&L (long on ties): (close side, size, open price)
+fn worse_side_close(position: &Position, long_pnl: f64, short_pnl: f64) -> (OrderSide, f64, f64) {
+    let (held, size, open_price) = if long_pnl <= short_pnl {
+        (OrderSide::BUY, position.long_size, position.long_open_price)
+    } else {
+        (OrderSide::SELL, position.short_size, position.short_open_price)
+    };
+    (held.opposite(), size, open_price)
+}
 
-    // Apply minimum floor
-    volatility.max(mean_price * MIN_VOLATILITY_BPS)
+/// Close side for take-profit: SELL when the long's P&L exceeds `target_jpy`, BUY for the
+/// short (the larger gain wins if both do). `target_jpy <= 0` disables.
+fn take_profit_side(long_pnl: f64, short_pnl: f64, target_jpy: f64) -> Option<OrderSide> {
+    if target_jpy <= 0.0 {
+        return None;
+    }
+    let held = match (long_pnl > target_jpy, short_pnl > target_jpy) {
+        (true, true) if short_pnl > long_pnl => OrderSide::SELL,
+        (true, _) => OrderSide::BUY,
+        (false, true) => OrderSide::SELL,
+        (false, false) => return None,
+    };
+    Some(held.opposite())
 }
 
-/// Sum the sizes of pending OPEN (non-close) orders for a given side.
-fn pending_open_size(orders: &HashMap<String, model::OrderInfo>, side: &OrderSide) -> f64 {
-    orders.values()
-        .filter(|o| o.side == *side && !o.is_close)
-        .map(|o| o.size)
-        .sum()
+/// Open-side edge gate: a side opens only when its single-leg EV at the chosen level
+/// reaches `min_ev`. Closes are never gated; they manage risk already taken.
+fn open_ev_ok(single_leg_ev: f64, min_ev: f64) -> bool {
+    single_leg_ev >= min_ev
 }
 
-/// Check if the given UTC hour is within trading hours.
-/// Trading disabled: data-collection-only mode. Metrics logging continues.
-fn is_trading_hour(_utc_hour: u32) -> bool {
-    false
+/// Pending-order cap: opens are allowed while fewer than `max_pending_orders` orders are
+/// tracked (0 = no cap). Closes are never gated, like `open_ev_ok`.
+fn pending_cap_ok(pending_orders: usize, max_pending_orders: usize) -> bool {
+    max_pending_orders == 0 || pending_orders < max_pending_orders
+}
+
+/// Per-cycle gates on new opens shared by both sides. Each one blocks opens only;
+/// a due close goes out whatever they say.
+#[derive(Debug, Clone, Copy)]
+struct OpenGates {
+    margin_ok: bool,
+    collateral_ok: bool,
+    fill_ok: bool,
+    daily_ok: bool,
+    pending_ok: bool,
+    in_trading_hours: bool,
+}
+
+impl OpenGates {
+    /// (can_open, wanted) for one side. `side_ok` carries that side's own open checks
+    /// (requote, edge, position room); `closing` is whether its close is due.
+    fn side(&self, closing: bool, side_ok: bool) -> (bool, bool) {
+        let can_open = self.margin_ok && self.collateral_ok && self.fill_ok && self.daily_ok
+            && self.pending_ok && self.in_trading_hours && side_ok;
+        (can_open, closing || can_open)
+    }
+}
+
+/// (regime, alpha, level spread range) for a cycle at `sigma_1s`: the volatility regime's
+/// parameters when `vol_regimes` is configured, else `alpha` over every level
+fn regime_quote_params(config: &BotConfig, sigma_1s: f64) -> (Option<VolRegime>, f64, (f64, f64)) {
+    match &config.vol_regimes {
+        Some(regimes) => {
+            let regime = classify_regime(sigma_1s, regimes);
+            let params = regime_params(regimes, regime);
+            (Some(regime), params.alpha, (params.min_spread, params.max_spread))
+        }
+        None => (None, config.alpha, (f64::NEG_INFINITY, f64::INFINITY)),
+    }
+}
+
+/// (level, p_fill, single_leg_ev) recorded with an order: the chosen level's values for an
+/// open, none/zeros for a close (closes are priced off the position, not a level).
+fn order_ev_fields(is_close: bool, level: &FloatingExp, p_fill: f64, leg_ev: f64) -> (Option<&FloatingExp>, f64, f64) {
+    if is_close { (None, 0.0, 0.0) } else { (Some(level), p_fill, leg_ev) }
+}
+
+/// Close distance from mid after `attempts` unfilled close cycles. Each step moves `factor` of
+/// the remaining distance toward mid, bounded to [min_step, max_step] JPY, and never gets
+/// closer than 1 JPY (the caller's no-cross floor). `factor <= 0` disables laddering.
+fn close_ladder_distance(initial: f64, attempts: u32, factor: f64, min_step: f64, max_step: f64) -> f64 {
+    if factor <= 0.0 {
+        return initial;
+    }
+    let mut distance = initial;
+    for _ in 0..attempts {
+        if distance <= 1.0 {
+            break;
+        }
+        let step = (distance * factor).max(min_step).min(max_step);
+        distance = (distance - step).max(1.0);
+    }
+    distance
 }
 
 const INVENTORY_SPREAD_ADJUSTMENT: f64 = 0.2;
 
-fn calculate_spread_adjustment(position: &Position, max_position_size: f64) -> (f64, f64) {
+/// (buy, sell) spread multipliers from inventory skew, gross exposure and book imbalance.
+/// The inventory skew measures net position against `target_net_position` (0 = flat), so
+/// being short of the target tightens buys and widens sells until it is reached.
+/// A bid-heavy book (`imbalance` > 0) tightens the buy side and widens the sell side by
+/// `imbalance * imbalance_weight`; an ask-heavy book does the opposite.
+fn calculate_spread_adjustment(
+    position: &Position,
+    max_position_size: f64,
+    imbalance: f64,
+    imbalance_weight: f64,
+    target_net_position: f64,
+) -> (f64, f64) {
     let net_position = position.long_size - position.short_size;
     let total_exposure = position.long_size + position.short_size;
 
-    // Direction-based adjustment (net inventory skew)
-    let inventory_ratio = if total_exposure > 0.0 {
-        net_position / total_exposure.max(0.001)
+    // Direction-based adjustment (net inventory skew relative to the target).
+    // With a zero target this is net / total exposure, as before.
+    let deviation = net_position - target_net_position;
+    let scale = total_exposure.max(target_net_position.abs());
+    let inventory_ratio = if scale > 0.0 {
+        (deviation / scale.max(0.001)).clamp(-1.0, 1.0)
     } else {
         0.0
     };
@@ -592,237 +1367,553 @@ fn calculate_spread_adjustment(position: &Position, max_position_size: f64) -> (
     let exposure_penalty = (exposure_ratio * INVENTORY_SPREAD_ADJUSTMENT)
         .min(INVENTORY_SPREAD_ADJUSTMENT);
 
-    // Direction adjustment + exposure penalty
-    let buy_spread_adj = 1.0 + (inventory_ratio * INVENTORY_SPREAD_ADJUSTMENT) + exposure_penalty;
-    let sell_spread_adj = 1.0 - (inventory_ratio * INVENTORY_SPREAD_ADJUSTMENT) + exposure_penalty;
+    // Book pressure: lean toward the side the book favors
+    let imbalance_skew = imbalance.clamp(-1.0, 1.0) * imbalance_weight;
+
+    // Direction adjustment + exposure penalty + imbalance skew
+    let buy_spread_adj = 1.0 + (inventory_ratio * INVENTORY_SPREAD_ADJUSTMENT) + exposure_penalty - imbalance_skew;
+    let sell_spread_adj = 1.0 - (inventory_ratio * INVENTORY_SPREAD_ADJUSTMENT) + exposure_penalty + imbalance_skew;
 
     (buy_spread_adj, sell_spread_adj)
 }
 
-fn calculate_order_prices(
-    mid_price: f64,
-    best_pair: &(FloatingExp, FloatingExp),
-    position: &Position,
-    position_penalty: f64,
-    min_lot: f64,
-) -> (f64, f64) {
-    let bid = mid_price - best_pair.0.calc() * mid_price;
-    let ask = mid_price + best_pair.1.calc() * mid_price;
-
-    // Penalty discourages adding to existing positions AND accelerates closing:
-    // Long-heavy: lower buy price (harder to buy more) + lower sell price (easier to close long)
-    // Short-heavy: raise sell price (harder to sell more) + raise buy price (easier to close short)
-    let buy_order_price = bid - position_penalty * position.long_size / min_lot
-                             + position_penalty * position.short_size / min_lot;
-    let sell_order_price = ask + position_penalty * position.short_size / min_lot
-                              - position_penalty * position.long_size / min_lot;
+/// (long, short) exposure counted against max_position, including pending opens.
+/// Net: each side only counts what it adds beyond the opposite leg.
+fn limit_exposure(position: &Position, pending_buy: f64, pending_sell: f64, basis: LimitBasis) -> (f64, f64) {
+    let long = position.long_size + pending_buy;
+    let short = position.short_size + pending_sell;
+    match basis {
+        LimitBasis::Gross => (long, short),
+        LimitBasis::Net => (
+            (long - position.short_size).max(0.0),
+            (short - position.long_size).max(0.0),
+        ),
+    }
+}
 
-    (buy_order_price, sell_order_price)
+/// Net side of the position (ignoring dust below min_lot). None when flat.
+fn net_side(position: &Position, min_lot: f64) -> Option<OrderSide> {
+    let net = position.long_size - position.short_size;
+    if net >= min_lot {
+        Some(OrderSide::BUY)
+    } else if net <= -min_lot {
+        Some(OrderSide::SELL)
+    } else {
+        None
+    }
 }
 
-fn calculate_order_sizes(
-    position: &Position,
-    max_position_size: f64,
-    min_lot: f64,
-    max_lot: f64,
-    position_ratio: f64,
-) -> (f64, f64) {
-    let remaining_long = (max_position_size - position.long_size).max(0.0);
-    let remaining_short = (max_position_size - position.short_size).max(0.0);
+/// Re-entry dampener after a position flip (long→short or short→long).
+/// Returns 1.0 right at the flip and decays exponentially with time constant `decay_ms`;
+/// 0.0 when there was no flip or decay is disabled.
+fn flip_penalty(last_flip_ts: Option<i64>, now: i64, decay_ms: f64) -> f64 {
+    match last_flip_ts {
+        Some(ts) if decay_ms > 0.0 => {
+            let elapsed = (now - ts).max(0) as f64;
+            (-elapsed / decay_ms).exp()
+        }
+        _ => 0.0,
+    }
+}
 
-    let buy_size = if remaining_long < min_lot {
-        0.0
+/// Determine effective order size: close orders close the actual opposing inventory
+/// (at least min_lot), open orders use the calculated size as-is.
+fn effective_order_size(calculated_size: f64, is_close: bool, min_lot: f64, close_position_size: f64) -> f64 {
+    if is_close {
+        util::round_size(close_position_size.max(min_lot))
     } else {
-        util::round_size(
-            max_lot * (1.0 - position.long_size.powf(position_ratio) / max_position_size),
-        )
-        .max(min_lot)
-        .min(remaining_long)
-    };
+        calculated_size
+    }
+}
 
-    let sell_size = if remaining_short < min_lot {
-        0.0
+#[derive(Debug, PartialEq, Eq)]
+enum CollateralAction {
+    Normal,
+    /// Below the soft floor: no new opens, closes continue
+    GateOpens,
+    /// Below the hard floor: flatten everything
+    Flatten,
+}
+
+/// Classify collateral against the soft (min_collateral_jpy) and hard
+/// (emergency_flatten_collateral_jpy) floors. A floor <= 0 is disabled.
+fn collateral_action(collateral: f64, soft_floor: f64, hard_floor: f64) -> CollateralAction {
+    if hard_floor > 0.0 && collateral < hard_floor {
+        CollateralAction::Flatten
+    } else if soft_floor > 0.0 && collateral < soft_floor {
+        CollateralAction::GateOpens
     } else {
-        util::round_size(
-            max_lot * (1.0 - position.short_size.powf(position_ratio) / max_position_size),
-        )
-        .max(min_lot)
-        .min(remaining_short)
+        CollateralAction::Normal
+    }
+}
+
+/// Cancel every resting order and MARKET-close both sides until the exchange reports flat.
+/// Returns whether it got there. Tracked orders are only forgotten once the cancel-all went through.
+async fn flatten_all<E: Exchange>(
+    exchange: &E,
+    config: &BotConfig,
+    order_list: &Orders,
+    position: &Positions,
+    sim: Option<&SimExchange>,
+) -> bool {
+    let Some(sim) = sim else {
+        let report = exchange::flatten(exchange, position, config.min_lot, FLATTEN_ROUNDS, FLATTEN_SETTLE).await;
+        if report.orders_cancelled {
+            order_list.lock().clear();
+        }
+        return report.flat;
     };
 
-    (buy_size, sell_size)
+    // Dry run: the simulated fills keep the local position authoritative
+    info!("[EMERGENCY_FLATTEN] Cancelled {} open orders", sim.cancel_all());
+    order_list.lock().clear();
+    let current_position = *position.read();
+    let legs = [
+        (OrderSide::BUY, current_position.long_size),
+        (OrderSide::SELL, current_position.short_size),
+    ];
+    for (held, size) in legs {
+        if size < config.min_lot {
+            continue;
+        }
+        let side = held.opposite();
+        let order_id = sim.place_market(side.clone(), size, true);
+        info!("[EMERGENCY_FLATTEN] MARKET close sent: order_id={} side={:?} size={}", order_id, side, size);
+    }
+    true
+}
+
+/// How long a side has been held (0 when flat / no entry time).
+fn position_hold_ms(open_time: Option<std::time::Instant>, now: std::time::Instant) -> u64 {
+    open_time.map_or(0, |t| now.saturating_duration_since(t).as_millis() as u64)
 }
 
-/// Determine effective order size: close orders use min_lot when calculated size is 0,
-/// open orders use the calculated size as-is.
-fn effective_order_size(calculated_size: f64, is_close: bool, min_lot: f64) -> f64 {
-    if is_close && calculated_size < min_lot {
-        min_lot
-    } else {
-        calculated_size
+/// Never close more than the inventory on that side (would flip into a new position).
+fn clamp_close_size(size: f64, position_size: f64) -> f64 {
+    util::round_size(size.min(position_size.max(0.0)))
+}
+
+/// (available JPY, available base currency) for `symbol`'s spot wallet, e.g. BTC for BTC_JPY
+fn spot_funds(balance: &gmo::get_balance::BalanceResponse, symbol: &Symbol) -> (f64, f64) {
+    let symbol = symbol.to_string();
+    let base = symbol.split('_').next().unwrap_or_default();
+    (balance.available("JPY"), balance.available(base))
+}
+
+/// (buy, sell) open sizes after the `sizing_source` funding cap: `balance` caps by the spot
+/// wallet when it is known, `margin` leaves sizes to max_position / collateral.
+fn funded_order_sizes(source: SizingSource, funds: Option<(f64, f64)>, buy_size: f64, sell_size: f64, price: f64) -> (f64, f64) {
+    match (source, funds) {
+        (SizingSource::Balance, Some((jpy, base))) => balance_capped_sizes(buy_size, sell_size, jpy, base, price),
+        _ => (buy_size, sell_size),
+    }
+}
+
+async fn fetch_spot_funds(client: &reqwest::Client, limiter: &RateLimiter, symbol: &Symbol) -> Option<(f64, f64)> {
+    match gmo::get_balance::get_balance(client, limiter).await {
+        Ok(response) => Some(spot_funds(&response, symbol)),
+        Err(e) => {
+            warn!("[BALANCE] Failed to fetch balance: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Open-quote spread multiplier for the current GMO status. None = do not quote at all.
+/// PREOPEN and unrecognised states widen rather than stop; MAINTENANCE rejects orders anyway.
+fn status_spread_multiplier(status: ExchangeStatus, preopen_multiplier: f64) -> Option<f64> {
+    match status {
+        ExchangeStatus::Open => Some(1.0),
+        ExchangeStatus::Preopen | ExchangeStatus::Unknown => Some(preopen_multiplier.max(1.0)),
+        ExchangeStatus::Maintenance => None,
+    }
+}
+
+/// A fill further than `band_bps` from the mid recorded when the order was sent points at bad
+/// data or a pricing bug rather than the market. `band_bps <= 0` (or an unknown mid) disables.
+fn implausible_fill(fill_price: f64, order_mid: f64, band_bps: f64) -> bool {
+    if band_bps <= 0.0 || order_mid <= 0.0 {
+        return false;
+    }
+    (fill_price - order_mid).abs() / order_mid * 10_000.0 > band_bps
+}
+
+/// Implausible-fill halt: the private WS detects, the trade loop stops opening until restart.
+struct FillGuard {
+    band_bps: f64,
+    halted: RwLock<Option<String>>,
+}
+
+impl FillGuard {
+    fn new(band_bps: f64) -> Self {
+        Self { band_bps, halted: RwLock::new(None) }
     }
+
+    fn check(&self, order_id: &str, fill_price: f64, order_mid: f64) {
+        if !implausible_fill(fill_price, order_mid, self.band_bps) {
+            return;
+        }
+        let deviation_bps = (fill_price - order_mid).abs() / order_mid * 10_000.0;
+        error!(
+            "[IMPLAUSIBLE_FILL] order_id={} fill={} order_mid={} deviation={:.1}bps > band={}bps, halting new opens for manual review",
+            order_id, fill_price, order_mid, deviation_bps, self.band_bps
+        );
+        let mut halted = self.halted.write();
+        if halted.is_none() {
+            *halted = Some(format!("order {} filled at {} vs mid {}", order_id, fill_price, order_mid));
+        }
+    }
+
+    fn halt_reason(&self) -> Option<String> {
+        self.halted.read().clone()
+    }
+}
+
+/// Our own two-sided quote is crossed (or locked): buy must be strictly below sell.
+fn is_self_crossed(buy_price: Price, sell_price: Price) -> bool {
+    buy_price >= sell_price
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn trade(
     client: &reqwest::Client,
-    config: &BotConfig,
+    limiter: &Arc<RateLimiter>,
+    shared_config: &SharedConfig,
     order_list: &Orders,
     position: &Positions,
     board_asks: &OrderBook,
     board_bids: &OrderBook,
     executions: &Executions,
     last_ws_message: &LastWsMessage,
+    ws_waiting_since: &LastWsMessage,
+    last_ws_trade: &LastWsMessage,
     trade_logger: &Option<TradeLogger>,
     metrics_logger: &Option<MetricsLogger>,
     current_t_optimal_ms: &SharedU64,
     ghost_suppression: &GhostSuppression,
+    exchange_status: &SharedExchangeStatus,
+    fill_guard: &SharedFillGuard,
+    pnl: &SharedPnl,
+    alerts: &AlertSink,
+    trade_status: &SharedTradeStatus,
+    maintenance: &SharedMaintenance,
+    admin_halt: &TradingHalt,
+    sim: Option<&SimExchange>,
     outcome_rx: &mut tokio::sync::mpsc::UnboundedReceiver<OrderOutcome>,
 ) -> Result<()> {
     const MAX_KEEP_BOARD_PRICE: u64 = 100_000;
-    let max_position_size: f64 = config.max_position;
-    let min_lot: f64 = config.min_lot;
-    let max_lot: f64 = config.max_lot;
-    let position_ratio: f64 = config.position_ratio;
-
-    let mut collateral = match gmo::get_collateral::get_collateral(client).await {
-        Ok(response) => response.data.actual_profit_loss,
-        Err(_) => 0.0,
+    // Startup settings; the loop re-reads the live config every cycle
+    let startup_config = shared_config.read().clone();
+    let config = &startup_config;
+    let exchange = GmoExchange::new(client.clone(), limiter.clone(), config.symbol.clone(), config.api_max_retries);
+
+    // collateral_known: floors are only enforced once a real value has been fetched
+    // Dry run never calls private endpoints, so collateral floors stay inactive
+    let (mut collateral, mut collateral_known) = if sim.is_some() {
+        (0.0, false)
+    } else {
+        match gmo::get_collateral::get_collateral(client, limiter).await {
+            Ok(response) => (response.data.actual_profit_loss, true),
+            Err(_) => (0.0, false),
+        }
     };
 
     info!("Collateral {:?}", collateral);
 
+    // Spot wallet (available JPY, base currency); only fetched for balance sizing, never in dry run
+    let use_balance = config.sizing_source == SizingSource::Balance && sim.is_none();
+    let mut spot_funds = if use_balance {
+        fetch_spot_funds(client, limiter, &config.symbol).await
+    } else {
+        None
+    };
+    if let Some((jpy, base)) = spot_funds {
+        info!("[BALANCE] available JPY={:.0} base={}", jpy, base);
+    }
+
+    let fetched_rules = match gmo::get_symbols::get_symbol_rules(client).await {
+        Ok(rules) => Some(rules),
+        Err(e) => {
+            warn!("[SYMBOL_RULES] Failed to fetch symbol rules, using defaults: {:?}", e);
+            None
+        }
+    };
+    let symbol_registry = build_symbol_registry(fetched_rules, &config.symbol_rules);
+    let symbol_rule = symbol_registry
+        .get(&config.symbol.to_string())
+        .cloned()
+        .expect("every tradable Symbol has a default registry entry");
+    info!("[SYMBOL_RULES] {:?}", symbol_rule);
+
     sleep(Duration::from_secs(5)).await;
 
     // Be(1, 10): initial P(fill)≈0.09 (matches observed fill rate ~9%)
     // 1h window: order-outcome-based P(fill) has less data than market-tick-based
-    let initial_bayes_prob = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+    let half_life = (config.p_fill_half_life_ms > 0).then(|| Duration::from_millis(config.p_fill_half_life_ms));
+    let initial_bayes_prob = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600))
+        .with_half_life(half_life);
 
     let mut buy_probabilities = BTreeMap::<FloatingExp, (f64, BayesProb)>::new();
     let mut sell_probabilities = BTreeMap::<FloatingExp, (f64, BayesProb)>::new();
 
-    // L1-L3 excluded: closest levels have highest adverse selection (-13.86 JPY/trip at L1)
-    const PRICE_STEP_START: u32 = 4;
-    const PRICE_STEP_END: u32 = 25;
-
-    for i in PRICE_STEP_START..=PRICE_STEP_END {
-        let key = FloatingExp { base: 10.0, exp: -5.0, rate: i as f64 };
-        buy_probabilities.insert(key.clone(), (0.0, initial_bayes_prob.clone()));
-        sell_probabilities.insert(key.clone(), (0.0, initial_bayes_prob.clone()));
-    }
+    // JPY-offset levels need a reference mid, so they are built on the first cycle with a book
+    // Levels the tables are keyed on, re-keyed when a reload changes levels / price_step_*
+    let mut table_levels = if config.level_offsets_jpy.is_empty() { config_step_levels(config) } else { Vec::new() };
+    rekey_levels(&mut buy_probabilities, &table_levels, &initial_bayes_prob);
+    rekey_levels(&mut sell_probabilities, &table_levels, &initial_bayes_prob);
 
     let mut collateral_refresh_count: u64 = 0;
+    // Collateral-scaled max_position (None = config.max_position as is)
+    let mut scaled_max_position: Option<f64> = None;
     let mut empty_executions_count: u64 = 0;
     let mut ws_stale_count: u64 = 0;
+    let mut ws_waiting_count: u64 = 0;
+    let mut feed_delay_count: u64 = 0;
     let mut heartbeat_count: u64 = 0;
+    // Whether the pending-order cap held opens back last cycle (for its warn rate limit)
+    let mut pending_capped = false;
+    // The fill guard halt never clears, so it is alerted once
+    let mut fill_halt_alerted = false;
     // ERR-201 margin insufficient cooldown: suppress new orders until this instant
     let mut margin_cooldown_until: Option<Instant> = None;
-    const MARGIN_COOLDOWN_SECS: u64 = 60;
+    // HTTP 429 cooldown: send nothing (opens or closes) until this instant
+    let mut rate_limit_cooldown_until: Option<Instant> = None;
+    // Consecutive SOK rejections per side, widening that side's open quote
+    let mut would_take_guard = WouldTakeGuard::default();
+    // Last quote per side for buy_interval_ms / sell_interval_ms
+    let mut side_cadence = SideCadence::default();
     // Stop-loss cooldown: prevent repeated MARKET orders while get_position polls (5s)
     let mut stop_loss_cooldown_until: Option<Instant> = None;
-    const STOP_LOSS_COOLDOWN_SECS: u64 = 10;
     // Ghost cooldown: suppress close orders after ghost detection (separate from SL cooldown)
     let mut ghost_cooldown_until: Option<Instant> = None;
-    const WS_STALE_THRESHOLD_MS: i64 = 60_000;
+    // Flip dampener: last non-flat net side, and (side just closed, flip timestamp ms)
+    let mut last_net_side: Option<OrderSide> = None;
+    let mut last_flip: Option<(OrderSide, i64)> = None;
     const HEARTBEAT_INTERVAL: u64 = 20; // ~5min (15s × 20 = 300s)
+    // Safe mode: set by emergency flatten or repeated ghosts, stops all quoting until restart
+    let mut safe_mode: Option<&'static str> = None;
+    let mut ghost_tracker = GhostTracker::new(
+        Duration::from_secs(config.ghost_safe_mode_window_secs),
+        config.ghost_safe_mode_threshold as usize,
+    );
+    let mut trailing_stop = TrailingStop::new(config.trailing_stop_jpy);
+    let mut daily_guard = model::DailyPnlGuard::new(config.daily_loss_limit_jpy);
+    // Close ladder: consecutive close attempts per side, reset when that side goes flat
+    let mut close_attempts_long: u32 = 0;
+    let mut close_attempts_short: u32 = 0;
+    // Close orders cancelled for age per side, escalating close_spread_factor
+    let mut close_escalation = CloseEscalation::default();
+    // Thompson sampling RNG (StdRng is Send, unlike thread_rng, so it can live across awaits)
+    let mut exploration_rng = util::task_rng(config.rng_seed, &format!("thompson/{}", config.symbol));
+    // Volatility regime of the previous cycle, to log transitions
+    let mut last_regime: Option<VolRegime> = None;
+    // Realized fill rate / post-fill drift per level, dumped with the metrics CSVs
+    let mut level_stats = LevelStats::new();
+    let mut order_age_hist = OrderAgeHistogram::new();
+    let mut level_stats_dumped = Instant::now();
+    let mut warmup = Warmup::new(Instant::now(), Duration::from_millis(config.warmup_ms));
+    // This symbol's orders were cancelled for the current maintenance pause
+    let mut maintenance_paused = false;
+    let started_ms = Utc::now().timestamp_millis();
+    let mut execution_source_prev = ExecutionSource::Ws;
+    if config.warmup_ms > 0 {
+        info!("[WARMUP] No quotes for the first {}ms", config.warmup_ms);
+    }
 
     loop {
-        sleep(Duration::from_millis(config.order_interval_ms)).await;
+        let interval_ms = loop_interval_ms(&shared_config.read());
+        sleep(Duration::from_millis(interval_ms)).await;
+
+        // Pick up a SIGHUP reload: everything below this point sees the new values
+        let cycle_config = shared_config.read().clone();
+        let config = &cycle_config;
+        if config.level_offsets_jpy.is_empty() {
+            let levels = config_step_levels(config);
+            if levels != table_levels {
+                info!("[LEVELS] Reloaded {} levels (was {})", levels.len(), table_levels.len());
+                rekey_levels(&mut buy_probabilities, &levels, &initial_bayes_prob);
+                rekey_levels(&mut sell_probabilities, &levels, &initial_bayes_prob);
+                table_levels = levels;
+            }
+        }
+        let max_position_size: f64 = scaled_max_position.unwrap_or(config.max_position);
+        let min_lot: f64 = config.min_lot;
+        let max_lot: f64 = config.max_lot;
+        let position_ratio: f64 = config.position_ratio;
+        trailing_stop.distance = config.trailing_stop_jpy;
+        daily_guard.set_limit(config.daily_loss_limit_jpy);
 
         // Drain order outcomes and update P(fill) via BayesProb
+        let drained_ms = Utc::now().timestamp_millis();
         while let Ok(outcome) = outcome_rx.try_recv() {
-            if outcome.is_close || outcome.level == 0 {
+            if outcome.is_close {
+                close_escalation.record(&outcome.side, outcome.filled);
                 continue;
             }
-            let key = FloatingExp { base: 10.0, exp: -5.0, rate: outcome.level as f64 };
-            let probs = if outcome.side == OrderSide::BUY {
-                &mut buy_probabilities
-            } else {
-                &mut sell_probabilities
+            order_age_hist.record(outcome.age_ms, outcome.filled);
+            let Some(key) = outcome.level else {
+                continue;
             };
-            if let Some((_, bayes)) = probs.get_mut(&key) {
-                bayes.update(1, outcome.filled as u64);
+            update_level_posterior(&mut buy_probabilities, &mut sell_probabilities, &outcome.side, &key, outcome.filled);
+            level_stats.record_outcome(key, outcome.side, outcome.filled, drained_ms);
+        }
+
+        if safe_mode.is_none() && *admin_halt.read() {
+            warn!("[ADMIN] Flatten requested, halting trading");
+            safe_mode = Some("admin flatten");
+        }
+        if let Some(reason) = safe_mode {
+            heartbeat_count += 1;
+            if heartbeat_count.is_multiple_of(HEARTBEAT_INTERVAL) {
+                warn!("[SAFE_MODE] Trading halted ({}); restart the bot to resume", reason);
+            }
+            continue;
+        }
+
+        // Private API maintenance: cancel once when the pause starts, then only probe until it ends
+        if maintenance.lock().is_paused() {
+            if !maintenance_paused {
+                maintenance_paused = true;
+                trade_status.write().maintenance_paused = true;
+                match gmo::cancel_bulk_order::cancel_bulk_order(client, limiter, std::slice::from_ref(&config.symbol)).await {
+                    Ok(response) => {
+                        info!("[MAINTENANCE] Cancelled {} open orders", response.1.data.len());
+                        order_list.lock().clear();
+                    }
+                    Err(e) => warn!("[MAINTENANCE] Cancel-all failed, the cancel loop keeps retrying: {:?}", e),
+                }
+            }
+            if maintenance.lock().probe_due(Instant::now()) {
+                let probe = gmo::get_collateral::get_collateral(client, limiter).await;
+                let mut maintenance = maintenance.lock();
+                match probe {
+                    Ok(response) => {
+                        collateral = response.data.actual_profit_loss;
+                        collateral_known = true;
+                        maintenance.record_success();
+                    }
+                    Err(e) => {
+                        debug!("[MAINTENANCE] Probe failed: {:?}", e);
+                        maintenance.record_error(config.maintenance_error_threshold, Instant::now());
+                    }
+                }
+            }
+            if maintenance.lock().is_paused() {
+                continue;
             }
         }
+        if maintenance_paused {
+            maintenance_paused = false;
+            trade_status.write().maintenance_paused = false;
+        }
 
         let now = Utc::now().timestamp_millis();
 
+        // Executions fallback: keep volatility and fill checks fed from REST while WS trades are silent
+        let source = execution_source(*last_ws_trade.read(), started_ms, now, config.execution_fallback_after_ms);
+        if source != execution_source_prev {
+            match source {
+                ExecutionSource::Rest => warn!(
+                    "[EXECUTION_FALLBACK] No WS trades for {}ms, polling /v1/trades", config.execution_fallback_after_ms
+                ),
+                ExecutionSource::Ws => info!("[EXECUTION_FALLBACK] WS trades are back, stopping REST polling"),
+            }
+            execution_source_prev = source;
+        }
+        if source == ExecutionSource::Rest {
+            match gmo::get_trades::get_trades(client, &config.symbol, gmo::get_trades::MAX_COUNT).await {
+                Ok(trades) => {
+                    let server_now = now + gmo::auth::time_offset();
+                    let added = merge_rest_executions(&mut executions.write(), &trades, server_now, config.execution_retain_ms);
+                    debug!("[EXECUTION_FALLBACK] Added {} REST trades", added);
+                }
+                Err(e) => warn!("[EXECUTION_FALLBACK] /v1/trades failed: {:?}", e),
+            }
+        }
+
         // Retain the last execution_retain_ms milliseconds of executions
         executions.write().retain(|e| e.2 >= (now - config.execution_retain_ms as i64));
 
-        let executions_snapshot = executions.read().clone();
+        let (executions_snapshot, feed_delay_ms) = {
+            let executions = executions.read();
+            let snapshot: Vec<(u64, f64, i64)> = executions.iter().map(|e| (e.0, e.1, e.2)).collect();
+            (snapshot, average_feed_delay_ms(&executions))
+        };
         let last_ws_ts = *last_ws_message.read();
-        let ws_age_ms = now - last_ws_ts;
+        let waiting_since = *ws_waiting_since.read();
+        // While reconnecting, the outage counts from the disconnect
+        let ws_age_ms = now - if last_ws_ts > 0 { last_ws_ts } else { waiting_since };
 
         // Periodic heartbeat log
         heartbeat_count += 1;
-        if heartbeat_count % HEARTBEAT_INTERVAL == 0 {
+        if heartbeat_count.is_multiple_of(HEARTBEAT_INTERVAL) {
             let current_position = *position.read();
             info!(
-                "[HEARTBEAT] alive - ws_last={}ms ago, position=long:{}/short:{}, pending_orders={}, exec_count={}",
+                "[HEARTBEAT] alive - ws_last={}ms ago, position=long:{}/short:{}, pending_orders={}, exec_count={}, feed_delay_avg={}",
                 ws_age_ms,
                 current_position.long_size,
                 current_position.short_size,
                 order_list.lock().len(),
                 executions_snapshot.len(),
+                feed_delay_ms.map_or("n/a".to_string(), |d| format!("{:.0}ms", d)),
             );
         }
 
-        // WebSocket health check - skip trading on stale data
-        if last_ws_ts > 0 && ws_age_ms > WS_STALE_THRESHOLD_MS {
+        // WebSocket health check - skip trading on stale data. While (re)connecting there is
+        // no feed to be stale yet: wait quietly for the first message instead of alerting.
+        let ws_feed = ws_feed_state(last_ws_ts, waiting_since, now, WS_STALE_THRESHOLD_MS);
+        if ws_feed == WsFeedState::Waiting {
+            ws_waiting_count += 1;
+            if ws_waiting_count.is_multiple_of(20) {
+                warn!("[WS_WAITING] No WebSocket message since (re)connect (consecutive: {}). Skipping trade.", ws_waiting_count);
+            }
+            continue;
+        }
+        ws_waiting_count = 0;
+        if ws_feed == WsFeedState::Stale {
             ws_stale_count += 1;
-            if ws_stale_count == 1 || ws_stale_count % 20 == 0 {
+            if ws_stale_count == 1 || ws_stale_count.is_multiple_of(20) {
                 error!(
                     "[WS_STALE] No WebSocket message for {}ms (threshold: {}ms, consecutive: {}). Skipping trade.",
                     ws_age_ms, WS_STALE_THRESHOLD_MS, ws_stale_count
                 );
+                alerts.send("WS_STALE", &format!("No WebSocket message for {}ms, trading paused", ws_age_ms));
             }
             continue;
         }
         ws_stale_count = 0;
 
-        // Skip trade cycle when no executions available
-        if executions_snapshot.is_empty() {
-            empty_executions_count += 1;
-            if empty_executions_count <= 3 {
+        // Feed latency: quoting off a lagging trade feed means quoting off old prices
+        if let Some(delay) = feed_delay_ms.filter(|d| config.max_feed_delay_ms > 0 && *d > config.max_feed_delay_ms as f64) {
+            feed_delay_count += 1;
+            if feed_delay_count == 1 || feed_delay_count.is_multiple_of(20) {
                 warn!(
-                    "[NO_EXECUTIONS] No executions received in last {}ms, skipping trade cycle (consecutive: {})",
-                    config.execution_retain_ms, empty_executions_count
-                );
-            } else if empty_executions_count % 10 == 0 {
-                error!(
-                    "[NO_EXECUTIONS] No executions for {} consecutive cycles (~{}s). Trading is stalled.",
-                    empty_executions_count,
-                    empty_executions_count.saturating_mul(config.order_interval_ms) / 1000
+                    "[FEED_DELAY] Average trade feed delay {:.0}ms > {}ms (consecutive: {}). Skipping trade.",
+                    delay, config.max_feed_delay_ms, feed_delay_count
                 );
             }
             continue;
         }
-        empty_executions_count = 0;
+        feed_delay_count = 0;
+
+        // Skip trade cycle when no executions available
+        if executions_stalled(&mut empty_executions_count, executions_snapshot.is_empty(), config.execution_retain_ms, loop_interval_ms(config)) {
+            continue;
+        }
 
         // Circuit breaker: skip trading when recent price range exceeds threshold
-        // Uses 5s window (independent of execution_retain_ms) to avoid false triggers
-        const CIRCUIT_BREAKER_BPS: f64 = 0.001; // 0.1% of mid price
-        const CIRCUIT_BREAKER_COOLDOWN_SECS: u64 = 30;
-        const CIRCUIT_BREAKER_WINDOW_MS: i64 = 5000;
-        {
-            let recent_prices: Vec<u64> = executions_snapshot.iter()
-                .filter(|e| e.2 >= (now - CIRCUIT_BREAKER_WINDOW_MS))
-                .map(|e| e.0)
-                .collect();
-            if let (Some(&pmin), Some(&pmax)) = (recent_prices.iter().min(), recent_prices.iter().max()) {
-                let mid_est = (pmin + pmax) as f64 / 2.0;
-                if mid_est > 0.0 {
-                    let range_bps = (pmax - pmin) as f64 / mid_est;
-                    if range_bps > CIRCUIT_BREAKER_BPS {
-                        warn!(
-                            "[CIRCUIT_BREAKER] High volatility: range={} JPY, bps={:.5}, threshold={:.5}. Pausing {}s.",
-                            pmax - pmin, range_bps, CIRCUIT_BREAKER_BPS, CIRCUIT_BREAKER_COOLDOWN_SECS
-                        );
-                        sleep(Duration::from_secs(CIRCUIT_BREAKER_COOLDOWN_SECS)).await;
-                        continue;
-                    }
-                }
+        if let Some((range, range_frac)) = circuit_breaker_trip(
+            &executions_snapshot, now, config.circuit_breaker_window_ms, config.circuit_breaker_frac,
+        ) {
+            warn!(
+                "[CIRCUIT_BREAKER] High volatility: range={} JPY, frac={:.5}, threshold={:.5}. Pausing {}s.",
+                range, range_frac, config.circuit_breaker_frac, config.circuit_breaker_cooldown_secs
+            );
+            sleep(Duration::from_secs(config.circuit_breaker_cooldown_secs)).await;
+            if reset_probabilities_after_breaker(config.reset_probs_on_circuit_breaker, &mut buy_probabilities, &mut sell_probabilities) {
+                info!("[CIRCUIT_BREAKER] Cooldown over, P(fill) posteriors reset to the prior");
             }
+            continue;
         }
 
         let volatility = calculate_volatility(&executions_snapshot);
@@ -847,13 +1938,49 @@ async fn trade(
             .unwrap_or(0.0);
 
         let mid_price = (best_ask + best_bid) / 2.0;
+        level_stats.on_mid(Utc::now().timestamp_millis(), mid_price);
+
+        // Short vs long realized vol: above 1 the market is heating up before the breaker sees it
+        let windowed = calculate_volatility_multi(
+            &executions_snapshot, &[config.vol_short_window_ms, config.vol_long_window_ms], mid_price,
+        );
+        let vol_ratio = windowed[0] / windowed[1];
+
+        if buy_probabilities.is_empty() && mid_price > 0.0 {
+            info!("[LEVELS] JPY offsets {:?} at reference mid {:.0}", config.level_offsets_jpy, mid_price);
+            for key in jpy_offset_levels(&config.level_offsets_jpy, mid_price) {
+                buy_probabilities.insert(key.clone(), (0.0, initial_bayes_prob.clone()));
+                sell_probabilities.insert(key, (0.0, initial_bayes_prob.clone()));
+            }
+        }
 
         // Update order prices (for metrics/logging; P(fill) now from order outcomes via mpsc)
         update_order_prices(&mut buy_probabilities, mid_price, |mp, calc| mp - mp * calc);
         update_order_prices(&mut sell_probabilities, mid_price, |mp, calc| mp + mp * calc);
 
+        // Volatility regime: alpha and the quoted level range follow sigma_1s
+        let sigma_1s = if mid_price > 0.0 { volatility / mid_price } else { 0.0 };
+        let (regime, alpha, spread_range) = regime_quote_params(config, sigma_1s);
+        if regime != last_regime {
+            info!("[REGIME] {:?} -> {:?} (sigma_1s={:.6}, alpha={})", last_regime, regime, sigma_1s, alpha);
+            last_regime = regime;
+        }
+
         // Find the best single-leg EV pair (independently per side)
-        let best_result = match maximize_single_leg_ev(mid_price, volatility, config.alpha, &buy_probabilities, &sell_probabilities) {
+        let select_levels = |spread_range: (f64, f64), rng: &mut StdRng| match config.exploration_mode {
+            ExplorationMode::Mean => maximize_single_leg_ev_within(
+                mid_price, volatility, alpha, &buy_probabilities, &sell_probabilities, spread_range,
+                BayesProb::calc_average,
+            ),
+            ExplorationMode::Thompson => maximize_single_leg_ev_within(
+                mid_price, volatility, alpha, &buy_probabilities, &sell_probabilities, spread_range,
+                |b| b.sample(rng),
+            ),
+        };
+        // A regime range that no level falls in quotes from every level rather than skipping the cycle
+        let best_result = select_levels(spread_range, &mut exploration_rng)
+            .or_else(|| select_levels((f64::NEG_INFINITY, f64::INFINITY), &mut exploration_rng));
+        let best_result = match best_result {
             Some(r) => r,
             None => continue,
         };
@@ -866,6 +1993,26 @@ async fn trade(
         let current_position = *position.read();
         debug!("position: {:?}", current_position);
 
+        // Flip detection: net side changed long↔short → dampen re-opening the side just closed
+        let now_ms = Utc::now().timestamp_millis();
+        let side_now = net_side(&current_position, min_lot);
+        if let (Some(prev), Some(cur)) = (&last_net_side, &side_now) {
+            if prev != cur {
+                info!("[FLIP] Position flipped {:?} -> {:?}, dampening {:?} re-entry", prev, cur, prev);
+                last_flip = Some((prev.clone(), now_ms));
+            }
+        }
+        if side_now.is_some() {
+            last_net_side = side_now;
+        }
+        let flip_ts_for = |side: OrderSide| last_flip.as_ref()
+            .filter(|(closed, _)| *closed == side)
+            .map(|(_, ts)| *ts);
+        let buy_flip_adj = config.flip_penalty_jpy
+            * flip_penalty(flip_ts_for(OrderSide::BUY), now_ms, config.flip_penalty_decay_ms as f64);
+        let sell_flip_adj = config.flip_penalty_jpy
+            * flip_penalty(flip_ts_for(OrderSide::SELL), now_ms, config.flip_penalty_decay_ms as f64);
+
         // Stop-loss cooldown check
         if let Some(until) = stop_loss_cooldown_until {
             if Instant::now() >= until {
@@ -873,113 +2020,274 @@ async fn trade(
             }
         }
 
-        // Stop-loss check: unrealized P&L exceeds threshold → MARKET close
-        if config.stop_loss_jpy > 0.0 && stop_loss_cooldown_until.is_none() {
-            let long_pnl = if current_position.long_size >= min_lot && current_position.long_open_price > 0.0 {
-                (mid_price - current_position.long_open_price) * current_position.long_size
-            } else {
-                0.0
-            };
-            let short_pnl = if current_position.short_size >= min_lot && current_position.short_open_price > 0.0 {
-                (current_position.short_open_price - mid_price) * current_position.short_size
+        // Stop-loss (unrealized P&L past -stop_loss_jpy) or trailing stop (a side gave back more
+        // than trailing_stop_jpy from its peak) → MARKET close
+        let (long_pnl, short_pnl, unrealized_pnl) = current_position.unrealized_pnl(mid_price, min_lot);
+        let market_close = if config.stop_loss_jpy > 0.0
+            && stop_loss_cooldown_until.is_none()
+            && unrealized_pnl < -config.stop_loss_jpy
+            && (current_position.long_size >= min_lot || current_position.short_size >= min_lot)
+        {
+            let (close_side, close_size, open_price) = worse_side_close(&current_position, long_pnl, short_pnl);
+            Some((CloseReason::StopLoss, close_side, close_size, open_price, unrealized_pnl))
+        } else {
+            let trailing_hit = trailing_stop.update(
+                (current_position.long_size >= min_lot).then_some(long_pnl),
+                (current_position.short_size >= min_lot).then_some(short_pnl),
+            );
+            match (trailing_hit, stop_loss_cooldown_until) {
+                (Some(close_side), None) => {
+                    let (close_size, open_price, side_pnl) = if close_side == OrderSide::SELL {
+                        (current_position.long_size, current_position.long_open_price, long_pnl)
+                    } else {
+                        (current_position.short_size, current_position.short_open_price, short_pnl)
+                    };
+                    Some((CloseReason::TrailingStop, close_side, close_size, open_price, side_pnl))
+                }
+                _ => None,
+            }
+        };
+        if let Some((reason, close_side, close_size, open_price, close_pnl)) = market_close {
+            // Ghost prevention: verify position still exists before MARKET close
+            if !position_still_open(client, limiter, config, sim).await {
+                warn!("[STALE_{}] Position already closed (get_position confirmed empty), skipping MARKET close. unrealized_pnl={:.3}",
+                    reason.tag(), close_pnl);
+                alerts.send("GHOST_POSITION", &format!("{} found no open position, local position reset", reason.tag()));
+                if reason == CloseReason::TrailingStop {
+                    trailing_stop.closed(&close_side);
+                }
+                let ghost_until = activate_ghost_protection(position, ghost_suppression, config);
+                stop_loss_cooldown_until = Some(ghost_until);
+                ghost_cooldown_until = Some(ghost_until);
+                record_ghost(&mut ghost_tracker, &mut safe_mode, alerts, config);
+                continue;
+            }
+
+            match reason {
+                CloseReason::StopLoss => {
+                    info!(
+                        "[STOP_LOSS] unrealized_pnl={:.3} (long={:.3} short={:.3}) threshold=-{} side={:?} size={} open_price={:.0} mid={:.0}",
+                        close_pnl, long_pnl, short_pnl, config.stop_loss_jpy, close_side, close_size, open_price, mid_price
+                    );
+                    alerts.send("STOP_LOSS", &format!(
+                        "unrealized_pnl={:.0} JPY, MARKET {:?} {} at mid={:.0}", close_pnl, close_side, close_size, mid_price
+                    ));
+                }
+                CloseReason::TrailingStop => info!(
+                    "[TRAILING_STOP] unrealized_pnl={:.3} retraced > {} from peak, side={:?} size={} open_price={:.0} mid={:.0}",
+                    close_pnl, config.trailing_stop_jpy, close_side, close_size, open_price, mid_price
+                ),
+            }
+            let execution = close_execution(
+                &close_side, close_size, mid_price, &board_asks.read(), &board_bids.read(),
+                config.max_close_slippage_jpy, symbol_rule.tick_size,
+            );
+            let ghost_hit = send_market_close(
+                client, limiter, config, reason, &close_side, close_size, execution, trade_logger,
+                mid_price as u64, open_price, close_pnl, sim,
+            ).await;
+            if reason == CloseReason::TrailingStop {
+                trailing_stop.closed(&close_side);
+            }
+            if ghost_hit {
+                warn!("[GHOST_POSITION] Resetting position to zero, cooldown {}s", config.ghost_position_cooldown_secs);
+                alerts.send("GHOST_POSITION", "MARKET close found no open position, local position reset");
+                let ghost_until = activate_ghost_protection(position, ghost_suppression, config);
+                stop_loss_cooldown_until = Some(ghost_until);
+                margin_cooldown_until = Some(ghost_until);
+                ghost_cooldown_until = Some(ghost_until);
+                record_ghost(&mut ghost_tracker, &mut safe_mode, alerts, config);
             } else {
-                0.0
-            };
-            let unrealized_pnl = long_pnl + short_pnl;
-
-            if unrealized_pnl < -config.stop_loss_jpy
-                && (current_position.long_size >= min_lot || current_position.short_size >= min_lot)
-            {
-                // Ghost SL prevention: verify position still exists before MARKET close
-                // get_position polls every 5s, so cached position may be stale
-                let fresh_position = gmo::get_position::get_position(client, Symbol::BTC_JPY).await;
-                let has_position = match &fresh_position {
-                    Ok(resp) => resp.data.as_ref()
-                        .and_then(|d| d.list.as_ref())
-                        .map_or(false, |list| !list.is_empty()),
-                    Err(_) => true, // On API error, assume position exists (safe default)
-                };
-                if !has_position {
-                    warn!("[STALE_SL] Position already closed (get_position confirmed empty), skipping SL. unrealized_pnl={:.3}", unrealized_pnl);
-                    let ghost_until = activate_ghost_protection(position, ghost_suppression, GHOST_POSITION_COOLDOWN_SECS);
-                    stop_loss_cooldown_until = Some(ghost_until);
-                    ghost_cooldown_until = Some(ghost_until);
+                stop_loss_cooldown_until = Some(Instant::now() + Duration::from_secs(config.stop_loss_cooldown_secs));
+            }
+            continue; // skip normal order cycle
+        }
+
+        // Take-profit: a side's unrealized P&L above target → LIMIT close at the touch.
+        // Not during ghost cooldown (cached position may be stale), and never stacked on a pending close.
+        let tp_ghost_blocked = ghost_cooldown_until.is_some_and(|until| Instant::now() < until);
+        if !tp_ghost_blocked {
+            let (long_pnl, short_pnl, _) = current_position.unrealized_pnl(mid_price, min_lot);
+            if let Some(close_side) = take_profit_side(long_pnl, short_pnl, config.take_profit_jpy) {
+                if order_list.lock().has_pending_close(&close_side) {
+                    debug!("[TAKE_PROFIT] {:?} close already pending, waiting", close_side);
                     continue;
                 }
-
-                // Close the side with the worse P&L
-                let (close_side, close_size, open_price) = if long_pnl <= short_pnl {
-                    (OrderSide::SELL, current_position.long_size, current_position.long_open_price)
+                let (close_size, price, side_pnl) = if close_side == OrderSide::SELL {
+                    (current_position.long_size, best_ask, long_pnl)
                 } else {
-                    (OrderSide::BUY, current_position.short_size, current_position.short_open_price)
+                    (current_position.short_size, best_bid, short_pnl)
                 };
+                let price = Price::from_f64(symbol_rule.round_price(price, &close_side));
                 info!(
-                    "[STOP_LOSS] unrealized_pnl={:.3} (long={:.3} short={:.3}) threshold=-{} side={:?} size={} open_price={:.0} mid={:.0}",
-                    unrealized_pnl, long_pnl, short_pnl, config.stop_loss_jpy, close_side, close_size, open_price, mid_price
+                    "[TAKE_PROFIT] unrealized_pnl={:.3} target={} side={:?} size={} price={} mid={:.0}",
+                    side_pnl, config.take_profit_jpy, close_side, close_size, price, mid_price
                 );
-                let ghost_hit = send_market_close(
-                    client, &close_side, close_size, trade_logger,
-                    mid_price as u64, open_price, unrealized_pnl,
+                let res = send_order(
+                    client, limiter, order_list, close_side, price, util::round_size(close_size), true,
+                    config, &symbol_rule, trade_logger,
+                    mid_price, config.order_cancel_ms, volatility / mid_price,
+                    (price.to_f64() - mid_price).abs() / mid_price, None, 0.0, combined_ev, 0.0, sim,
                 ).await;
-                if ghost_hit {
-                    warn!("[GHOST_POSITION] Resetting position to zero, cooldown {}s", GHOST_POSITION_COOLDOWN_SECS);
-                    let ghost_until = activate_ghost_protection(position, ghost_suppression, GHOST_POSITION_COOLDOWN_SECS);
-                    stop_loss_cooldown_until = Some(ghost_until);
-                    margin_cooldown_until = Some(ghost_until);
-                    ghost_cooldown_until = Some(ghost_until);
-                } else {
-                    stop_loss_cooldown_until = Some(Instant::now() + Duration::from_secs(STOP_LOSS_COOLDOWN_SECS));
+                if matches!(res, OrderResult::NoOpenPosition) {
+                    info!("[CLOSE_NO_POSITION] Take-profit ERR-422: position already settled, resetting");
+                    reset_position(position);
                 }
                 continue; // skip normal order cycle
             }
         }
 
         // Position penalty: penalize prices to discourage adding to existing positions
-        let position_penalty = 50.0;
-        debug!("position_penalty: {:?}", position_penalty);
-
         let (base_buy_price, base_sell_price) = calculate_order_prices(
             mid_price,
             &best_pair,
             &current_position,
-            position_penalty,
+            config.position_penalty,
             min_lot,
         );
 
-        // Inventory-based spread adjustment
-        let (buy_spread_adj, sell_spread_adj) = calculate_spread_adjustment(&current_position, max_position_size);
+        // Exchange status: widen open quotes during PREOPEN / unknown states, stand aside in MAINTENANCE
+        let current_status = *exchange_status.read();
+        let Some(status_mult) = status_spread_multiplier(current_status, config.preopen_spread_multiplier) else {
+            debug!("[EXCHANGE_STATUS] {:?}, skipping quotes", current_status);
+            continue;
+        };
+
+        // Inventory- and book-imbalance-based spread adjustment
+        let imbalance = order_book_imbalance(&board_bids.read(), &board_asks.read(), config.imbalance_depth_levels);
+        let (buy_spread_adj, sell_spread_adj) = calculate_spread_adjustment(
+            &current_position, max_position_size, imbalance, config.imbalance_weight,
+            config.target_net_position,
+        );
+        // Toxic flow: back off the side repeated same-side executions are hitting
+        let flow = trade_flow_imbalance(&executions_snapshot, now, config.trade_flow_window_ms);
+        let (buy_flow_adj, sell_flow_adj) = trade_flow_widening(flow, config.trade_flow_weight);
+        // Volatility expansion: widen both sides early instead of waiting for the circuit breaker
+        let vol_adj = vol_expansion_widening(vol_ratio, config.vol_expansion_threshold, config.vol_expansion_weight);
+        if vol_adj > 0.0 {
+            debug!("[VOL_EXPANSION] vol_ratio={:.2}, widening both sides by {:.3}", vol_ratio, vol_adj);
+        }
+        let (buy_spread_adj, sell_spread_adj) = (
+            (buy_spread_adj + buy_flow_adj + vol_adj) * status_mult,
+            (sell_spread_adj + sell_flow_adj + vol_adj) * status_mult,
+        );
         let buy_spread = mid_price - base_buy_price;
         let sell_spread = base_sell_price - mid_price;
         let adj_buy_price = mid_price - (buy_spread * buy_spread_adj);
         let adj_sell_price = mid_price + (sell_spread * sell_spread_adj);
 
-        // Open orders: clamp to prevent spread-crossing (SOK compliance)
-        let buy_order_price = adj_buy_price.min(best_bid);
-        let sell_order_price = adj_sell_price.max(best_ask);
+        // Open orders: clamp to prevent spread-crossing (SOK compliance),
+        // then widen the side just closed by a flip (adj is 0 when no recent flip)
+        // and any side whose recent opens keep getting SOK-rejected
+        let buy_order_price = adj_buy_price.min(best_bid) - buy_flip_adj - would_take_guard.widen_jpy(&OrderSide::BUY, mid_price);
+        let sell_order_price = adj_sell_price.max(best_ask) + sell_flip_adj + would_take_guard.widen_jpy(&OrderSide::SELL, mid_price);
+        // Hard floor: whatever the adjustments above did, never quote at or through the other side
+        let (buy_order_price, sell_order_price) = passive_quotes(
+            buy_order_price, sell_order_price, best_bid, best_ask, config.min_touch_offset_jpy,
+        );
 
         // Close orders: reduced spread for faster fill, NO best_bid/best_ask clamp
         // Safety: never cross mid_price (at least 1 JPY from mid)
-        let close_buy_price = (mid_price - (buy_spread * config.close_spread_factor)).min(mid_price - 1.0);
-        let close_sell_price = (mid_price + (sell_spread * config.close_spread_factor)).max(mid_price + 1.0);
-
-        let (buy_size, sell_size) = calculate_order_sizes(
-            &current_position,
+        // Laddered toward mid on each unfilled close attempt (bounded steps, see close_ladder_distance),
+        // starting from a factor that shrinks with every close cancelled for age
+        let close_factor = |side: OrderSide| close_escalation.factor(
+            &side, config.close_spread_factor, config.close_escalation_step, config.close_spread_factor_floor,
+        );
+        let close_buy_distance = close_ladder_distance(
+            buy_spread * close_factor(OrderSide::BUY), close_attempts_short,
+            config.close_ladder_factor, config.close_ladder_min_step_jpy, config.close_ladder_max_step_jpy,
+        );
+        let close_sell_distance = close_ladder_distance(
+            sell_spread * close_factor(OrderSide::SELL), close_attempts_long,
+            config.close_ladder_factor, config.close_ladder_min_step_jpy, config.close_ladder_max_step_jpy,
+        );
+        let close_buy_price = (mid_price - close_buy_distance).min(mid_price - 1.0);
+        let close_sell_price = (mid_price + close_sell_distance).max(mid_price + 1.0);
+
+        // Size against the exposure max_position actually caps (per-side or net)
+        let (limit_long, limit_short) = limit_exposure(&current_position, 0.0, 0.0, config.position_limit_basis);
+        let (buy_size, sell_size) = order_sizes(
+            &config.sizing_mode,
+            &Position { long_size: limit_long, short_size: limit_short, ..current_position },
             max_position_size,
             min_lot,
             max_lot,
             position_ratio,
+            collateral,
+            mid_price,
         );
+        let (buy_size, sell_size) = funded_order_sizes(config.sizing_source, spot_funds, buy_size, sell_size, mid_price);
+        // A size that rounds below the symbol's min lot opens nothing (0 fails the open gate)
+        let buy_size = symbol_rule.round_size(buy_size).unwrap_or(0.0);
+        let sell_size = symbol_rule.round_size(sell_size).unwrap_or(0.0);
 
         // Refresh collateral periodically (every ~10 cycles)
         collateral_refresh_count += 1;
-        if collateral_refresh_count % 10 == 0 {
-            if let Ok(response) = gmo::get_collateral::get_collateral(client).await {
-                collateral = response.data.actual_profit_loss;
+        let mut collateral_refreshed = false;
+        if sim.is_none() && collateral_refresh_count.is_multiple_of(10) {
+            match gmo::get_collateral::get_collateral(client, limiter).await {
+                Ok(response) => {
+                    collateral = response.data.actual_profit_loss;
+                    collateral_known = true;
+                    collateral_refreshed = true;
+                    maintenance.lock().record_success();
+                }
+                Err(e) if e.is_maintenance() => {
+                    maintenance.lock().record_error(config.maintenance_error_threshold, Instant::now());
+                }
+                Err(_) => {}
+            }
+            if use_balance {
+                if let Some(funds) = fetch_spot_funds(client, limiter, &config.symbol).await {
+                    spot_funds = Some(funds);
+                }
+            }
+        }
+        // Rescale max_position on each collateral refresh (and once the startup value is in);
+        // takes effect from the next cycle
+        if collateral_known && (collateral_refreshed || scaled_max_position.is_none()) {
+            let scaled = collateral_max_position(
+                collateral, config.max_position_pct_of_collateral, mid_price,
+                config.max_position_floor, config.max_position_ceiling,
+            )
+            // A cap below the symbol's min lot leaves no room to open
+            .map(|cap| symbol_rule.round_size(cap).unwrap_or(0.0));
+            if let Some(cap) = scaled.filter(|&cap| Some(cap) != scaled_max_position) {
+                info!("[MAX_POSITION] collateral={:.0} mid={:.0} -> max_position={}", collateral, mid_price, cap);
             }
+            scaled_max_position = scaled;
         }
+        *trade_status.write() = TradeStatus { mid_price, collateral, best_ev: combined_ev, maintenance_paused: false };
 
-        // Compute trade context (used for metrics, shared T_optimal, and send_order logging)
-        let sigma_1s = if mid_price > 0.0 { volatility / mid_price } else { 0.0 };
+        // Collateral floors: hard floor flattens everything and enters safe mode,
+        // soft floor only blocks new opens
+        let collateral_state = if collateral_known {
+            collateral_action(collateral, config.min_collateral_jpy, config.emergency_flatten_collateral_jpy)
+        } else {
+            CollateralAction::Normal
+        };
+        if collateral_state == CollateralAction::Flatten {
+            error!(
+                "[EMERGENCY_FLATTEN] collateral={:.0} < floor={:.0}, flattening",
+                collateral, config.emergency_flatten_collateral_jpy
+            );
+            if flatten_all(&exchange, config, order_list, position, sim).await {
+                error!("[EMERGENCY_FLATTEN] Flat, entering safe mode");
+                safe_mode = Some("emergency flatten");
+            } else {
+                // Safe mode would stop retrying and leave the position open: try again next cycle
+                error!("[EMERGENCY_FLATTEN] Position still open, retrying next cycle");
+                alerts.send("EMERGENCY_FLATTEN", "Flatten did not reach flat, retrying next cycle");
+            }
+            continue;
+        }
+        let collateral_ok = collateral_state == CollateralAction::Normal;
+        if !collateral_ok {
+            debug!("[MIN_COLLATERAL] collateral={:.0} < {:.0}, suppressing new orders",
+                collateral, config.min_collateral_jpy);
+        }
+
+        // Compute trade context (used for metrics, shared T_optimal, and send_order logging)
         let avg_spread_pct = (best_pair.0.calc() + best_pair.1.calc()) / 2.0;
         let buy_spread_raw = best_pair.0.calc();
         let sell_spread_raw = best_pair.1.calc();
@@ -1011,6 +2319,19 @@ async fn trade(
             let sell_spread_pct = if mid_price > 0.0 { sell_spread_raw * 100.0 } else { 0.0 };
 
             let best_ev = combined_ev;
+            let (realized_pnl, round_trips) = {
+                let pnl = pnl.read();
+                (pnl.realized_pnl, pnl.round_trips)
+            };
+
+            // Posterior lower bound at the chosen levels: how confident the model is in P(fill)
+            let p_fill_lower = |probs: &BTreeMap<FloatingExp, (f64, BayesProb)>, level: &FloatingExp| {
+                if config.metrics_credible_level > 0.0 {
+                    probs.get(level).map(|(_, b)| b.credible_interval(config.metrics_credible_level).0)
+                } else {
+                    None
+                }
+            };
 
             logger.log(MetricsSnapshot {
                 timestamp: Utc::now().to_rfc3339(),
@@ -1029,14 +2350,28 @@ async fn trade(
                 sell_prob_avg,
                 sigma_1s,
                 t_optimal_ms: t_opt_ms as f64,
+                long_hold_ms: position_hold_ms(current_position.long_open_time, std::time::Instant::now()),
+                short_hold_ms: position_hold_ms(current_position.short_open_time, std::time::Instant::now()),
+                buy_p_fill_lower: p_fill_lower(&buy_probabilities, &best_pair.0),
+                sell_p_fill_lower: p_fill_lower(&sell_probabilities, &best_pair.1),
+                realized_pnl,
+                round_trips,
+                vol_ratio,
             });
+
+            if level_stats_dumped.elapsed() >= Duration::from_secs(LEVEL_STATS_DUMP_SECS) {
+                level_stats_dumped = Instant::now();
+                let timestamp = Utc::now().to_rfc3339();
+                logger.log_level_stats(level_stats.rows(&timestamp));
+                logger.log_order_age_hist(order_age_hist.rows(&timestamp));
+            }
         }
 
         // Close orders are gated by position size only - ghost cooldown does not block closes
         // v0.13.1: Ghost cooldown blocking close caused +60s hold time → mid逆行 → loss
         // Safety: position=(0,0) blocks via min_lot check; ERR-422 loops self-limit (7-8 rounds)
         let ghost_cooldown_active = ghost_cooldown_until
-            .map_or(false, |until| Instant::now() < until);
+            .is_some_and(|until| Instant::now() < until);
         if !ghost_cooldown_active && ghost_cooldown_until.is_some() {
             info!("[GHOST_COOLDOWN] Ghost cooldown expired, clearing state");
             ghost_cooldown_until = None;
@@ -1044,12 +2379,21 @@ async fn trade(
         // Min hold: suppress close until min_hold_ms has elapsed since position open
         let min_hold = std::time::Duration::from_millis(config.min_hold_ms);
         let min_hold_elapsed_long = current_position.long_open_time
-            .map_or(true, |t| t.elapsed() >= min_hold);
+            .is_none_or(|t| t.elapsed() >= min_hold);
         let min_hold_elapsed_short = current_position.short_open_time
-            .map_or(true, |t| t.elapsed() >= min_hold);
+            .is_none_or(|t| t.elapsed() >= min_hold);
 
         let should_close_short = current_position.short_size >= min_lot && min_hold_elapsed_short;
         let should_close_long = current_position.long_size >= min_lot && min_hold_elapsed_long;
+        // Ladder and escalation restart once the side is flat
+        if current_position.short_size < min_lot {
+            close_attempts_short = 0;
+            close_escalation.reset(&OrderSide::BUY);
+        }
+        if current_position.long_size < min_lot {
+            close_attempts_long = 0;
+            close_escalation.reset(&OrderSide::SELL);
+        }
 
         // Log min_hold suppression
         if current_position.long_size >= min_lot && !min_hold_elapsed_long {
@@ -1069,11 +2413,17 @@ async fn trade(
 
         // New orders: gated by max_position + pending order check (Bug B fix)
         // Include pending open order sizes to prevent race with get_position polling
-        let orders_snapshot = order_list.lock().clone();
-        let pending_buy = pending_open_size(&orders_snapshot, &OrderSide::BUY);
-        let pending_sell = pending_open_size(&orders_snapshot, &OrderSide::SELL);
-        let effective_long = current_position.long_size + pending_buy;
-        let effective_short = current_position.short_size + pending_sell;
+        let (pending_buy, pending_sell, best_pending_buy, best_pending_sell) = {
+            let orders = order_list.lock();
+            (
+                pending_open_size(&orders, &OrderSide::BUY),
+                pending_open_size(&orders, &OrderSide::SELL),
+                best_pending_open_price(&orders, &OrderSide::BUY),
+                best_pending_open_price(&orders, &OrderSide::SELL),
+            )
+        };
+        let (effective_long, effective_short) =
+            limit_exposure(&current_position, pending_buy, pending_sell, config.position_limit_basis);
 
         // Margin cooldown: suppress new (open) orders when margin is insufficient
         let now = Instant::now();
@@ -1095,17 +2445,115 @@ async fn trade(
         // Close orders are allowed 24h to manage existing risk
         let in_trading_hours = is_trading_hour(Utc::now().hour());
 
-        let can_open_long = margin_ok && in_trading_hours && effective_long + buy_size <= max_position_size && buy_size >= min_lot;
-        let can_open_short = margin_ok && in_trading_hours && effective_short + sell_size <= max_position_size && sell_size >= min_lot;
+        // Improve-only: never requote an open order to a price further from the market
+        let buy_requote_ok = !config.quote_improve_only || requote_improves(
+            &OrderSide::BUY, buy_order_price as u64, best_pending_buy);
+        let sell_requote_ok = !config.quote_improve_only || requote_improves(
+            &OrderSide::SELL, sell_order_price as u64, best_pending_sell);
+        if !buy_requote_ok {
+            debug!("[IMPROVE_ONLY] Buy requote skipped: new={} would worsen resting order", buy_order_price as u64);
+        }
+        if !sell_requote_ok {
+            debug!("[IMPROVE_ONLY] Sell requote skipped: new={} would worsen resting order", sell_order_price as u64);
+        }
+
+        // Implausible fill: opens stay halted until restart, closes keep managing existing risk
+        let fill_halt = fill_guard.halt_reason();
+        if let Some(reason) = &fill_halt {
+            if !fill_halt_alerted {
+                alerts.send("IMPLAUSIBLE_FILL", &format!("New opens halted until restart: {}", reason));
+                fill_halt_alerted = true;
+            }
+            if heartbeat_count.is_multiple_of(HEARTBEAT_INTERVAL) {
+                warn!("[IMPLAUSIBLE_FILL] New opens halted ({}); review and restart the bot to resume", reason);
+            }
+        }
+        let fill_ok = fill_halt.is_none();
+
+        // Daily loss limit: opens stop for the rest of the UTC day, closes keep running
+        let realized_total = pnl.read().realized_pnl;
+        let (_, _, unrealized_total) = current_position.unrealized_pnl(mid_price, min_lot);
+        if daily_guard.update(Utc::now().date_naive(), realized_total, unrealized_total) {
+            error!(
+                "[DAILY_LOSS_LIMIT] day_pnl={:.3} reached -{} JPY, halting new opens until next UTC day",
+                daily_guard.day_pnl(), config.daily_loss_limit_jpy
+            );
+            if let Some(logger) = trade_logger {
+                logger.log(TradeEvent::DailyLossLimitTriggered {
+                    timestamp: Utc::now().to_rfc3339(),
+                    day_pnl: daily_guard.day_pnl(),
+                    limit_jpy: config.daily_loss_limit_jpy,
+                    mid_price: mid_price as u64,
+                });
+            }
+        }
+        let daily_ok = daily_guard.allows_open();
+
+        // Pending cap: when cancels lag, stop stacking opens on the backlog; closes still go out
+        let pending_orders = order_list.lock().len();
+        let pending_ok = pending_cap_ok(pending_orders, config.max_pending_orders);
+        // Logged on entering the cap and then once per heartbeat, not every cycle
+        if !pending_ok && (!pending_capped || heartbeat_count.is_multiple_of(HEARTBEAT_INTERVAL)) {
+            warn!(
+                "[PENDING_CAP] {} orders pending (max_pending_orders={}), new opens gated",
+                pending_orders, config.max_pending_orders
+            );
+        } else if pending_ok && pending_capped {
+            info!("[PENDING_CAP] {} orders pending, opens resumed", pending_orders);
+        }
+        pending_capped = !pending_ok;
+
+        // Minimum edge: never open at a level whose single-leg EV is below min_ev
+        let buy_open_ev = single_leg_ev(mid_price, volatility, alpha, &best_pair.0, buy_p_fill);
+        let sell_open_ev = single_leg_ev(mid_price, volatility, alpha, &best_pair.1, sell_p_fill);
+        let buy_edge_ok = open_ev_ok(buy_open_ev, config.min_ev);
+        let sell_edge_ok = open_ev_ok(sell_open_ev, config.min_ev);
+        if !buy_edge_ok || !sell_edge_ok {
+            debug!(
+                "[MIN_EV] Opens skipped below min_ev={}: buy_ev={:.4}{} sell_ev={:.4}{}",
+                config.min_ev, buy_open_ev, if buy_edge_ok { "" } else { " (skip)" },
+                sell_open_ev, if sell_edge_ok { "" } else { " (skip)" },
+            );
+        }
+
+        let open_gates = OpenGates { margin_ok, collateral_ok, fill_ok, daily_ok, pending_ok, in_trading_hours };
+        let (can_open_long, buy_wanted) = open_gates.side(
+            should_close_short,
+            buy_requote_ok && buy_edge_ok && effective_long + buy_size <= max_position_size && buy_size >= min_lot,
+        );
+        let (can_open_short, sell_wanted) = open_gates.side(
+            should_close_long,
+            sell_requote_ok && sell_edge_ok && effective_short + sell_size <= max_position_size && sell_size >= min_lot,
+        );
 
-        // Effective order sizes: close uses min_lot, open uses calculated size
-        let eff_buy_size = effective_order_size(buy_size, should_close_short, min_lot);
-        let eff_sell_size = effective_order_size(sell_size, should_close_long, min_lot);
+        // Effective order sizes: close uses the position being closed, open uses calculated size
+        let eff_buy_size = effective_order_size(buy_size, should_close_short, min_lot, current_position.short_size);
+        let eff_sell_size = effective_order_size(sell_size, should_close_long, min_lot, current_position.long_size);
+        let (eff_buy_size, eff_sell_size) = if config.clamp_close_to_position {
+            (
+                if should_close_short { clamp_close_size(eff_buy_size, current_position.short_size) } else { eff_buy_size },
+                if should_close_long { clamp_close_size(eff_sell_size, current_position.long_size) } else { eff_sell_size },
+            )
+        } else {
+            (eff_buy_size, eff_sell_size)
+        };
 
         // When both close and open are possible, close takes priority
         // (send_order receives is_close_order=should_close_*, using close_bulk_order API)
-        let should_buy = should_close_short || can_open_long;
-        let should_sell = should_close_long || can_open_short;
+        // Per-side cadence: a side not yet due keeps its resting quote this cycle
+        let cadence_now = Instant::now();
+        let buy_due = side_cadence.due(&OrderSide::BUY, cadence_now, Duration::from_millis(side_interval_ms(config, &OrderSide::BUY)));
+        let sell_due = side_cadence.due(&OrderSide::SELL, cadence_now, Duration::from_millis(side_interval_ms(config, &OrderSide::SELL)));
+        // Warmup: everything above still ran and updated, only the quotes are held back
+        let warming_up = warmup.holds_orders(cadence_now);
+        if warming_up {
+            debug!("[WARMUP] Holding quotes for {}ms more", warmup.ends_at.saturating_duration_since(cadence_now).as_millis());
+        }
+        let (should_buy, should_sell) = quote_sides(
+            warming_up,
+            (buy_due, sell_due),
+            (buy_wanted, sell_wanted),
+        );
 
         info!(
             "[ORDER] buy={} (close_short={}, open_long={}), sell={} (close_long={}, open_short={}), pos=({}/{}), eff_pos=({:.4}/{:.4}), pending_open=({:.4}/{:.4}), margin_ok={}, size=(buy:{:.4}->{:.4}, sell:{:.4}->{:.4}), min_hold=({}, {})",
@@ -1120,69 +2568,104 @@ async fn trade(
         );
 
         // Select price based on whether the order is a close or open
-        let eff_buy_price = if should_close_short { close_buy_price as u64 } else { buy_order_price as u64 };
-        let eff_sell_price = if should_close_long { close_sell_price as u64 } else { sell_order_price as u64 };
+        // Rounded onto the tick away from the touch (buys down, sells up), kept at the tick's
+        // precision so sub-yen ticks (XRP 0.001) survive into the order
+        let eff_buy_price = Price::from_f64(symbol_rule.round_price(
+            if should_close_short { close_buy_price } else { buy_order_price }, &OrderSide::BUY,
+        ));
+        let eff_sell_price = Price::from_f64(symbol_rule.round_price(
+            if should_close_long { close_sell_price } else { sell_order_price }, &OrderSide::SELL,
+        ));
+
+        // Penalty/spread/flip adjustments and tick rounding can push our bid through our ask
+        if config.skip_self_crossed_quotes && should_buy && should_sell && is_self_crossed(eff_buy_price, eff_sell_price) {
+            warn!(
+                "[SELF_CROSSED] Skipping cycle: buy={} >= sell={} (close_short={}, close_long={}, mid={:.0})",
+                eff_buy_price, eff_sell_price, should_close_short, should_close_long, mid_price,
+            );
+            continue;
+        }
 
         // EV params: close orders get level=0 and zero EV; open orders get actual values
-        let buy_level = if should_close_short { 0 } else { best_pair.0.rate as u32 };
-        let buy_ev = if should_close_short { 0.0 } else {
-            single_leg_ev(mid_price, volatility, config.alpha, &best_pair.0, buy_p_fill)
-        };
-        let sell_level = if should_close_long { 0 } else { best_pair.1.rate as u32 };
-        let sell_ev = if should_close_long { 0.0 } else {
-            single_leg_ev(mid_price, volatility, config.alpha, &best_pair.1, sell_p_fill)
-        };
-        let eff_buy_p_fill = if should_close_short { 0.0 } else { buy_p_fill };
-        let eff_sell_p_fill = if should_close_long { 0.0 } else { sell_p_fill };
+        let (buy_level, eff_buy_p_fill, buy_ev) = order_ev_fields(should_close_short, &best_pair.0, buy_p_fill, buy_open_ev);
+        let (sell_level, eff_sell_p_fill, sell_ev) = order_ev_fields(should_close_long, &best_pair.1, sell_p_fill, sell_open_ev);
+
+        // Rate limited: every order would bounce off GMO too, so sit the cycle out
+        if let Some(until) = rate_limit_cooldown_until {
+            if Instant::now() < until {
+                debug!("[RATE_LIMIT_COOLDOWN] Suppressing orders for {}ms more", (until - Instant::now()).as_millis());
+                continue;
+            }
+            info!("[RATE_LIMIT_COOLDOWN] Cooldown expired, resuming orders");
+            rate_limit_cooldown_until = None;
+        }
 
-        let (margin_hit, ghost_hit) = match (should_buy, should_sell) {
+        let (buy_res, sell_res) = match (should_buy, should_sell) {
             (true, true) => {
                 let buy_fut = send_order(
-                    client, order_list, OrderSide::BUY,
-                    eff_buy_price, eff_buy_size, should_close_short, config, trade_logger,
-                    mid_price as u64, t_opt_ms, sigma_1s, buy_spread_raw,
-                    buy_level, eff_buy_p_fill, combined_ev, buy_ev,
+                    client, limiter, order_list, OrderSide::BUY,
+                    eff_buy_price, eff_buy_size, should_close_short, config, &symbol_rule, trade_logger,
+                    mid_price, t_opt_ms, sigma_1s, buy_spread_raw,
+                    buy_level, eff_buy_p_fill, combined_ev, buy_ev, sim,
                 );
                 let sell_fut = send_order(
-                    client, order_list, OrderSide::SELL,
-                    eff_sell_price, eff_sell_size, should_close_long, config, trade_logger,
-                    mid_price as u64, t_opt_ms, sigma_1s, sell_spread_raw,
-                    sell_level, eff_sell_p_fill, combined_ev, sell_ev,
+                    client, limiter, order_list, OrderSide::SELL,
+                    eff_sell_price, eff_sell_size, should_close_long, config, &symbol_rule, trade_logger,
+                    mid_price, t_opt_ms, sigma_1s, sell_spread_raw,
+                    sell_level, eff_sell_p_fill, combined_ev, sell_ev, sim,
                 );
                 let (buy_res, sell_res) = tokio::join!(buy_fut, sell_fut);
-                (
-                    matches!(buy_res, OrderResult::MarginInsufficient)
-                        || matches!(sell_res, OrderResult::MarginInsufficient),
-                    matches!(buy_res, OrderResult::NoOpenPosition)
-                        || matches!(sell_res, OrderResult::NoOpenPosition),
-                )
+                (Some(buy_res), Some(sell_res))
             }
             (true, false) => {
                 let res = send_order(
-                    client, order_list, OrderSide::BUY,
-                    eff_buy_price, eff_buy_size, should_close_short, config, trade_logger,
-                    mid_price as u64, t_opt_ms, sigma_1s, buy_spread_raw,
-                    buy_level, eff_buy_p_fill, combined_ev, buy_ev,
+                    client, limiter, order_list, OrderSide::BUY,
+                    eff_buy_price, eff_buy_size, should_close_short, config, &symbol_rule, trade_logger,
+                    mid_price, t_opt_ms, sigma_1s, buy_spread_raw,
+                    buy_level, eff_buy_p_fill, combined_ev, buy_ev, sim,
                 ).await;
-                (
-                    matches!(res, OrderResult::MarginInsufficient),
-                    matches!(res, OrderResult::NoOpenPosition),
-                )
+                (Some(res), None)
             }
             (false, true) => {
                 let res = send_order(
-                    client, order_list, OrderSide::SELL,
-                    eff_sell_price, eff_sell_size, should_close_long, config, trade_logger,
-                    mid_price as u64, t_opt_ms, sigma_1s, sell_spread_raw,
-                    sell_level, eff_sell_p_fill, combined_ev, sell_ev,
+                    client, limiter, order_list, OrderSide::SELL,
+                    eff_sell_price, eff_sell_size, should_close_long, config, &symbol_rule, trade_logger,
+                    mid_price, t_opt_ms, sigma_1s, sell_spread_raw,
+                    sell_level, eff_sell_p_fill, combined_ev, sell_ev, sim,
                 ).await;
-                (
-                    matches!(res, OrderResult::MarginInsufficient),
-                    matches!(res, OrderResult::NoOpenPosition),
-                )
+                (None, Some(res))
             }
-            (false, false) => (false, false),
+            (false, false) => (None, None),
         };
+        if buy_res.is_some() {
+            side_cadence.mark(&OrderSide::BUY, cadence_now);
+        }
+        if sell_res.is_some() {
+            side_cadence.mark(&OrderSide::SELL, cadence_now);
+        }
+        let results = [&buy_res, &sell_res];
+        let any_result = |pred: fn(&OrderResult) -> bool| results.iter().any(|r| r.as_ref().is_some_and(pred));
+        let margin_hit = any_result(|r| matches!(r, OrderResult::MarginInsufficient));
+        let ghost_hit = any_result(|r| matches!(r, OrderResult::NoOpenPosition));
+        let rate_limited = any_result(|r| matches!(r, OrderResult::RateLimited));
+        for (side, res) in [(OrderSide::BUY, &buy_res), (OrderSide::SELL, &sell_res)] {
+            if let Some(res) = res {
+                would_take_guard.record(&side, res);
+                let streak = would_take_guard.consecutive(&side);
+                if matches!(res, OrderResult::WouldTake) && streak >= WOULD_TAKE_WIDEN_AFTER {
+                    warn!(
+                        "[WOULD_TAKE] {:?} rejected by SOK {} times in a row, widening open quote by {:.3} JPY",
+                        side, streak, would_take_guard.widen_jpy(&side, mid_price)
+                    );
+                }
+            }
+        }
+        if should_buy && should_close_short {
+            close_attempts_short = close_attempts_short.saturating_add(1);
+        }
+        if should_sell && should_close_long {
+            close_attempts_long = close_attempts_long.saturating_add(1);
+        }
 
         // Close order ERR-422: position already settled by another order.
         // This is normal operation (not a ghost), so reset position without cooldown.
@@ -1195,25 +2678,65 @@ async fn trade(
 
         // Activate margin cooldown if any order got ERR-201
         if margin_hit {
-            let cooldown = Instant::now() + Duration::from_secs(MARGIN_COOLDOWN_SECS);
-            warn!("[MARGIN_COOLDOWN] Margin insufficient detected, suppressing new orders for {}s", MARGIN_COOLDOWN_SECS);
+            let cooldown = Instant::now() + Duration::from_secs(config.margin_cooldown_secs);
+            warn!("[MARGIN_COOLDOWN] Margin insufficient detected, suppressing new orders for {}s", config.margin_cooldown_secs);
+            alerts.send("MARGIN_COOLDOWN", &format!("Insufficient margin, new orders suppressed for {}s", config.margin_cooldown_secs));
             margin_cooldown_until = Some(cooldown);
         }
+
+        // Back off all order traffic after HTTP 429
+        if rate_limited {
+            warn!("[RATE_LIMIT_COOLDOWN] Rate limited by GMO, suppressing orders for {}s", RATE_LIMIT_COOLDOWN_SECS);
+            rate_limit_cooldown_until = Some(Instant::now() + Duration::from_secs(RATE_LIMIT_COOLDOWN_SECS));
+        }
     }
 }
 
-async fn get_position(client: &reqwest::Client, position: &Positions, ghost_suppression: &GhostSuppression) -> Result<()> {
+/// Largest per-leg size difference between the locally tracked and exchange positions.
+fn position_drift(local: &Position, remote: &Position) -> f64 {
+    let long_drift = (local.long_size - remote.long_size).abs();
+    let short_drift = (local.short_size - remote.short_size).abs();
+    util::round_size(long_drift.max(short_drift))
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn get_position(
+    client: &reqwest::Client,
+    limiter: &Arc<RateLimiter>,
+    config: &BotConfig,
+    order_list: &Orders,
+    position: &Positions,
+    ghost_suppression: &GhostSuppression,
+    resync: &ResyncSignal,
+    maintenance: &SharedMaintenance,
+) -> Result<()> {
+    let exchange = GmoExchange::new(client.clone(), limiter.clone(), config.symbol.clone(), config.api_max_retries);
+
     loop {
-        sleep(Duration::from_secs(5)).await;
+        if sleep_or_notified(Duration::from_millis(config.position_poll_ms), &resync.position).await {
+            info!("[RESYNC] WS reconnected, refreshing position now");
+        }
 
-        let response =
-            match gmo::get_position::get_position(client, Symbol::BTC_JPY).await {
-                Ok(response) => response.data.unwrap_or_default().list.unwrap_or_default(),
-                Err(e) => {
-                    error!("Position fetch error: {:?}", e);
-                    continue;
-                }
-            };
+        // The trade loop's probe is the only private call during a maintenance pause
+        if maintenance.lock().is_paused() {
+            continue;
+        }
+        let remote = match exchange.get_position().await {
+            Ok(remote) => {
+                maintenance.lock().record_success();
+                remote
+            }
+            Err(ExchangeError::Maintenance) => {
+                warn!("Position fetch failed: exchange under maintenance");
+                maintenance.lock().record_error(config.maintenance_error_threshold, Instant::now());
+                continue;
+            }
+            Err(e) => {
+                error!("Position fetch error: {}", e);
+                continue;
+            }
+        };
+        let is_flat = remote.long_size <= 0.0 && remote.short_size <= 0.0;
 
         // Ghost suppression: during cooldown, only write if API returns a non-empty position
         // (non-empty proves the position is real, not stale ghost data)
@@ -1223,7 +2746,7 @@ async fn get_position(client: &reqwest::Client, position: &Positions, ghost_supp
         let suppression_until = *ghost_suppression.read();
         if let Some(until) = suppression_until {
             let now = Instant::now();
-            if now < until && response.is_empty() {
+            if now < until && is_flat {
                 debug!("[GHOST_SUPPRESSION] Skipping empty position update, {}s remaining",
                     (until - now).as_secs());
                 continue;
@@ -1234,88 +2757,307 @@ async fn get_position(client: &reqwest::Client, position: &Positions, ghost_supp
             }
         }
 
-        // Track gross positions (both sides independently) with weighted average open price
-        let mut long_total = 0.0;
-        let mut short_total = 0.0;
-        let mut long_price_sum = 0.0;
-        let mut short_price_sum = 0.0;
-        for x in &response {
-            if x.side == "BUY" {
-                long_total += x.size;
-                long_price_sum += x.price * x.size;
-            } else {
-                short_total += x.size;
-                short_price_sum += x.price * x.size;
+        // Drift check: only meaningful when the private WS keeps the local position live between polls
+        // (without it, local is just the previous poll and every fill would look like drift)
+        if config.private_ws_enabled && config.position_drift_tolerance > 0.0 {
+            let local = *position.read();
+            let drift = position_drift(&local, &remote);
+            if drift > config.position_drift_tolerance {
+                warn!(
+                    "[POSITION_DRIFT] local=({}/{}) exchange=({}/{}) drift={} > tolerance={}",
+                    local.long_size, local.short_size, remote.long_size, remote.short_size,
+                    drift, config.position_drift_tolerance,
+                );
+                if config.position_drift_cancel_orders {
+                    match gmo::cancel_bulk_order::cancel_bulk_order(client, limiter, std::slice::from_ref(&config.symbol)).await {
+                        Ok(response) => {
+                            info!("[POSITION_DRIFT] Cancelled {} open orders to resync", response.1.data.len());
+                            order_list.lock().clear();
+                        }
+                        // Keep tracking them: the cancel loop still has to sweep whatever is resting
+                        Err(e) => error!("[POSITION_DRIFT] Cancel-all failed: {:?}", e),
+                    }
+                }
             }
         }
 
-        {
-            let mut pos = position.write();
-            let prev_long = pos.long_size;
-            let prev_short = pos.short_size;
+        // Gross positions (both sides independently) with weighted average open price
+        exchange::apply_remote_position(position, remote);
+    }
+}
 
-            pos.long_size = util::round_size(long_total);
-            pos.short_size = util::round_size(short_total);
-            pos.long_open_price = if long_total > 0.0 { long_price_sum / long_total } else { 0.0 };
-            pos.short_open_price = if short_total > 0.0 { short_price_sum / short_total } else { 0.0 };
+/// Per-channel counters for WebSocket messages that failed to deserialize.
+/// A sudden rise means the exchange changed its message schema.
+#[derive(Debug, Default, Clone)]
+struct WsParseFailures {
+    orderbooks: u64,
+    trades: u64,
+}
 
-            // Track open time: set when position transitions from 0 to non-zero
-            if prev_long <= 0.0 && pos.long_size > 0.0 && pos.long_open_time.is_none() {
-                pos.long_open_time = Some(std::time::Instant::now());
-            }
-            if pos.long_size <= 0.0 {
-                pos.long_open_time = None;
-            }
-            if prev_short <= 0.0 && pos.short_size > 0.0 && pos.short_open_time.is_none() {
-                pos.short_open_time = Some(std::time::Instant::now());
-            }
-            if pos.short_size <= 0.0 {
-                pos.short_open_time = None;
-            }
+const WS_PARSE_FAIL_LOG_INTERVAL: u64 = 100;
+const WS_PARSE_FAIL_SAMPLE_CHARS: usize = 200;
+
+/// Count a parse failure and log the first one and every WS_PARSE_FAIL_LOG_INTERVAL-th after that
+/// with a truncated sample of the offending payload.
+fn record_parse_failure(
+    parse_failures: &ParseFailures,
+    channel: &ws::Channel,
+    msg: &str,
+    err: &serde_json::Error,
+) {
+    let count = {
+        let mut failures = parse_failures.write();
+        let counter = match channel {
+            ws::Channel::Orderbooks => &mut failures.orderbooks,
+            ws::Channel::Trades => &mut failures.trades,
+        };
+        *counter += 1;
+        *counter
+    };
+
+    if count == 1 || count.is_multiple_of(WS_PARSE_FAIL_LOG_INTERVAL) {
+        let sample: String = msg.chars().take(WS_PARSE_FAIL_SAMPLE_CHARS).collect();
+        warn!(
+            "[WS_PARSE_FAIL] channel={:?} count={} error={} sample={}",
+            channel, count, err, sample
+        );
+    }
+}
+
+/// Timestamp (ms) of the newest board applied to each side of the book on this connection
+#[derive(Debug, Default)]
+struct BoardWatermark {
+    asks_ms: i64,
+    bids_ms: i64,
+}
+
+/// Apply one side of a board message: size 0 deletes the level, anything else replaces it.
+/// Boards older than the side's watermark are dropped so a late message cannot resurrect
+/// levels a newer one already moved. Returns false when dropped.
+fn apply_board_side(book: &OrderBook, watermark_ms: &mut i64, timestamp_ms: i64, levels: &[ws::BoardItem]) -> bool {
+    if levels.is_empty() {
+        return true;
+    }
+    if timestamp_ms < *watermark_ms {
+        return false;
+    }
+    let mut book = book.write();
+    for level in levels {
+        let price = level.price as u64;
+        if level.size > 0.0 {
+            book.insert(price, level.size);
+        } else {
+            book.remove(&price);
         }
     }
+    *watermark_ms = timestamp_ms;
+    true
 }
 
-async fn handle_board_data(board_asks: &OrderBook, board_bids: &OrderBook, msg: &str) {
+async fn handle_board_data(
+    board_asks: &OrderBook,
+    board_bids: &OrderBook,
+    watermark: &Mutex<BoardWatermark>,
+    parse_failures: &ParseFailures,
+    msg: &str,
+) {
     let board: ws::Board = match serde_json::from_str(msg) {
         Ok(board) => board,
-        _ => return,
+        Err(e) => {
+            record_parse_failure(parse_failures, &ws::Channel::Orderbooks, msg, &e);
+            return;
+        }
+    };
+
+    let timestamp_ms = board.timestamp.get_timestamp();
+    let mut watermark = watermark.lock();
+    let asks_applied = apply_board_side(board_asks, &mut watermark.asks_ms, timestamp_ms, &board.asks);
+    let bids_applied = apply_board_side(board_bids, &mut watermark.bids_ms, timestamp_ms, &board.bids);
+    if !(asks_applied && bids_applied) {
+        debug!(
+            "[BOARD_OUT_OF_ORDER] Dropped board ts={} (asks watermark={}, bids watermark={})",
+            timestamp_ms, watermark.asks_ms, watermark.bids_ms
+        );
+    }
+}
+
+async fn handle_trade_data(executions: &Executions, parse_failures: &ParseFailures, msg: &str) {
+    let item: ws::ExecutionItem = match serde_json::from_str(msg) {
+        Ok(execution) => execution,
+        Err(e) => {
+            record_parse_failure(parse_failures, &ws::Channel::Trades, msg, &e);
+            return;
+        }
     };
 
-    let ask_pairs = board
-        .asks
-        .par_iter()
-        .map(|x| (x.price as u64, x.size))
-        .collect::<Vec<(u64, f64)>>();
+    let exchange_ts = item.timestamp.get_timestamp();
+    let delay = feed_delay_ms(Utc::now().timestamp_millis(), gmo::auth::time_offset(), exchange_ts);
+    let size = if item.side == ws::Side::BUY { item.size } else { -item.size };
+    executions.write().push((item.price as u64, size, exchange_ts, delay));
+}
 
-    board_asks.write().extend(ask_pairs);
+/// Exchange-to-bot latency of one trade: local receive time moved onto the server clock
+/// (`time_offset_ms` = server - local) minus the exchange timestamp.
+fn feed_delay_ms(received_ms: i64, time_offset_ms: i64, exchange_ts: i64) -> i64 {
+    received_ms + time_offset_ms - exchange_ts
+}
 
-    let bid_pairs = board
-        .bids
-        .par_iter()
-        .map(|x| (x.price as u64, x.size))
-        .collect::<Vec<(u64, f64)>>();
+/// Executions seeded from `/v1/trades` so volatility has history before the first WS trade.
+///
+/// Keeps trades within `retain_ms` of `now_ms` (server clock), oldest first like the live feed.
+/// REST history has no feed latency, so the delay column is 0.
+fn seed_executions(trades: &[gmo::get_trades::TradeItem], now_ms: i64, retain_ms: u64) -> Vec<(u64, f64, i64, i64)> {
+    let mut seeded: Vec<(u64, f64, i64, i64)> = trades
+        .iter()
+        .map(|t| {
+            let size = if t.side == ws::Side::BUY { t.size } else { -t.size };
+            (t.price as u64, size, t.timestamp.get_timestamp(), 0)
+        })
+        .filter(|e| e.2 >= now_ms - retain_ms as i64)
+        .collect();
+    seeded.sort_by_key(|e| e.2);
+    seeded
+}
 
-    board_bids.write().extend(bid_pairs);
+/// Where this cycle's executions come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExecutionSource {
+    Ws,
+    /// WS trades silent past `execution_fallback_after_ms`: poll `/v1/trades` instead
+    Rest,
 }
 
-async fn handle_trade_data(executions: &Executions, msg: &str) {
-    let item: ws::ExecutionItem = match serde_json::from_str(msg) {
-        Ok(execution) => execution,
+/// REST once the WS trades channel has been silent for `fallback_after_ms` (0 = WS only).
+/// Silence counts from `started_ms` until the first WS trade.
+fn execution_source(last_ws_trade_ms: i64, started_ms: i64, now_ms: i64, fallback_after_ms: u64) -> ExecutionSource {
+    if fallback_after_ms > 0 && now_ms - last_ws_trade_ms.max(started_ms) >= fallback_after_ms as i64 {
+        ExecutionSource::Rest
+    } else {
+        ExecutionSource::Ws
+    }
+}
+
+/// Append polled REST trades newer than the newest execution held (so re-polls and WS
+/// trades are not counted twice). Returns how many were added.
+fn merge_rest_executions(
+    executions: &mut Vec<(u64, f64, i64, i64)>,
+    trades: &[gmo::get_trades::TradeItem],
+    now_ms: i64,
+    retain_ms: u64,
+) -> usize {
+    let newest = executions.last().map_or(i64::MIN, |e| e.2);
+    let fresh: Vec<_> = seed_executions(trades, now_ms, retain_ms).into_iter().filter(|e| e.2 > newest).collect();
+    let added = fresh.len();
+    executions.extend(fresh);
+    added
+}
+
+/// Count a cycle against the empty-executions stall and log it. True = skip the cycle;
+/// a non-empty window resets the count.
+fn executions_stalled(empty_count: &mut u64, is_empty: bool, retain_ms: u64, interval_ms: u64) -> bool {
+    if !is_empty {
+        *empty_count = 0;
+        return false;
+    }
+    *empty_count += 1;
+    if *empty_count <= 3 {
+        warn!(
+            "[NO_EXECUTIONS] No executions received in last {}ms, skipping trade cycle (consecutive: {})",
+            retain_ms, empty_count
+        );
+    } else if empty_count.is_multiple_of(10) {
+        error!(
+            "[NO_EXECUTIONS] No executions for {} consecutive cycles (~{}s). Trading is stalled.",
+            empty_count,
+            empty_count.saturating_mul(interval_ms) / 1000
+        );
+    }
+    true
+}
+
+/// Mean feed delay over the retained executions (the rolling window); None when empty
+fn average_feed_delay_ms(executions: &[(u64, f64, i64, i64)]) -> Option<f64> {
+    if executions.is_empty() {
+        return None;
+    }
+    Some(executions.iter().map(|e| e.3 as f64).sum::<f64>() / executions.len() as f64)
+}
+
+/// Read loop with keepalive: forwards text frames to `on_text`, answers server Pings,
+/// and sends our own Ping every `ping_interval` so GMO does not drop an idle socket.
+async fn ws_read_loop<R, W, F, Fut>(
+    read: &mut R,
+    write: &mut W,
+    ping_interval: Duration,
+    mut on_text: F,
+) -> Result<()>
+where
+    R: futures::Stream<Item = Result<Message>> + Unpin,
+    W: futures::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let mut ping_timer = tokio::time::interval_at(Instant::now() + ping_interval, ping_interval);
+    ping_timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                let Some(msg) = msg else {
+                    return Ok(());
+                };
+                match msg? {
+                    Message::Text(s) => on_text(s).await,
+                    Message::Ping(payload) => write.send(Message::Pong(payload)).await?,
+                    _ => {}
+                }
+            }
+            _ = ping_timer.tick() => {
+                write.send(Message::Ping(Vec::new())).await?;
+            }
+        }
+    }
+}
+
+/// Dispatch one public WS message to its symbol's feed; symbols not in `feeds` are dropped
+async fn route_public_message(
+    feeds: &SymbolFeeds,
+    watermarks: &HashMap<Symbol, Mutex<BoardWatermark>>,
+    parse_failures: &ParseFailures,
+    msg: &str,
+) {
+    let parsed: ws::Message = match serde_json::from_str(msg) {
+        Ok(parsed) => parsed,
         _ => return,
     };
+    let Ok(symbol) = parsed.symbol.parse::<Symbol>() else {
+        return;
+    };
+    let (Some(feed), Some(watermark)) = (feeds.get(&symbol), watermarks.get(&symbol)) else {
+        return;
+    };
 
-    let now = Utc::now().timestamp_millis();
-    let size = if item.side == ws::Side::BUY { item.size } else { -item.size };
-    executions.write().push((item.price as u64, size, now));
+    // WebSocket最終受信時刻を更新
+    *feed.last_ws_message.write() = Utc::now().timestamp_millis();
+
+    match parsed.channel {
+        ws::Channel::Orderbooks => {
+            handle_board_data(&feed.board_asks, &feed.board_bids, watermark, parse_failures, msg).await;
+        }
+        ws::Channel::Trades => {
+            *feed.last_ws_trade.write() = Utc::now().timestamp_millis();
+            handle_trade_data(&feed.executions, parse_failures, msg).await;
+        }
+    }
 }
 
 /// WebSocket接続を確立し、メッセージを処理する内部関数
+/// One connection carries every symbol in `feeds`; on a reconnect each symbol's resync is fired.
 async fn connect_and_process_websocket(
-    board_asks: &OrderBook,
-    board_bids: &OrderBook,
-    executions: &Executions,
-    last_ws_message: &LastWsMessage,
+    feeds: &SymbolFeeds,
+    parse_failures: &ParseFailures,
+    ping_interval: Duration,
+    reconnected: bool,
 ) -> Result<()> {
     let ws_url = Url::parse("wss://api.coin.z.com/ws/public/v1")
         .expect("Invalid WebSocket URL");
@@ -1330,213 +3072,842 @@ async fn connect_and_process_websocket(
         "trades",
     ];
 
-    for channel in &channels {
-        let data = serde_json::json!({
-            "command": "subscribe",
-            "channel": channel,
-            "symbol": "BTC_JPY"
-        });
+    for symbol in feeds.keys() {
+        for channel in &channels {
+            let data = serde_json::json!({
+                "command": "subscribe",
+                "channel": channel,
+                "symbol": symbol.to_string()
+            });
 
-        write.send(Message::Text(data.to_string())).await?;
-        info!("Subscribed to {}", channel);
+            write.send(Message::Text(data.to_string())).await?;
+            info!("Subscribed to {} {}", symbol, channel);
 
-        // GMO coin requires a few seconds delay due to subscription limit
-        sleep(Duration::from_millis(5000)).await;
+            // GMO coin requires a few seconds delay due to subscription limit
+            sleep(Duration::from_millis(5000)).await;
+        }
     }
 
-    while let Some(msg) = read.next().await {
-        let msg = msg?;
-
-        let msg = match msg {
-            tokio_tungstenite::tungstenite::Message::Text(s) => s,
-            _ => continue,
-        };
-
-        let parsed: ws::Message = match serde_json::from_str(&msg) {
-            Ok(parsed) => parsed,
-            _ => continue,
-        };
-
-        // WebSocket最終受信時刻を更新
-        *last_ws_message.write() = Utc::now().timestamp_millis();
-
-        match parsed.channel {
-            ws::Channel::Orderbooks => {
-                handle_board_data(board_asks, board_bids, &msg).await;
-            }
-            ws::Channel::Trades => {
-                handle_trade_data(executions, &msg).await;
-            }
+    if reconnected {
+        info!("[RESYNC] WebSocket reconnected, forcing position refresh and order sweep");
+        for feed in feeds.values() {
+            feed.resync.fire();
         }
     }
-    Ok(())
+
+    let watermarks: HashMap<Symbol, Mutex<BoardWatermark>> =
+        feeds.keys().map(|symbol| (symbol.clone(), Mutex::new(BoardWatermark::default()))).collect();
+    let watermarks = &watermarks;
+    ws_read_loop(&mut read, &mut write, ping_interval, |msg| async move {
+        route_public_message(feeds, watermarks, parse_failures, &msg).await;
+    }).await
 }
 
 /// WebSocket購読（自動再接続機能付き）
 async fn subscribe_websocket(
-    board_asks: &OrderBook,
-    board_bids: &OrderBook,
-    executions: &Executions,
-    last_ws_message: &LastWsMessage,
+    feeds: &SymbolFeeds,
+    parse_failures: &ParseFailures,
+    ping_interval: Duration,
+    rng: &mut StdRng,
 ) -> Result<()> {
-    const MAX_RECONNECT_DELAY_SECS: u64 = 60;
-    let mut reconnect_delay = Duration::from_secs(1);
+    let mut backoff = ReconnectBackoff::new();
+    let mut first_connect = true;
 
     loop {
-        match connect_and_process_websocket(board_asks, board_bids, executions, last_ws_message).await {
-            Ok(_) => {
-                warn!("WebSocket connection closed normally, reconnecting...");
-                reconnect_delay = Duration::from_secs(1); // リセット
-            }
-            Err(e) => {
-                error!("WebSocket error: {:?}, reconnecting in {:?}...", e, reconnect_delay);
-            }
+        let result = connect_and_process_websocket(feeds, parse_failures, ping_interval, !first_connect).await;
+        first_connect = false;
+        let disconnected_at = Utc::now().timestamp_millis();
+        for feed in feeds.values() {
+            reset_ws_staleness(feed, disconnected_at);
         }
 
-        sleep(reconnect_delay).await;
+        // 指数バックオフ（最大60秒、±20%ジッター、再接続ストーム時は延長）
+        let reconnect_delay = backoff.next_delay(result.is_ok(), Instant::now().into_std(), rng);
+        match result {
+            Ok(_) => warn!("WebSocket connection closed normally, reconnecting in {:?}...", reconnect_delay),
+            Err(e) => error!("WebSocket error: {:?}, reconnecting in {:?}...", e, reconnect_delay),
+        }
+        if backoff.in_storm() {
+            warn!("[RECONNECT_STORM] Too many WebSocket reconnects, cooling down for {:?}", reconnect_delay);
+        }
 
-        // 指数バックオフ（最大60秒）
-        reconnect_delay = std::cmp::min(
-            reconnect_delay * 2,
-            Duration::from_secs(MAX_RECONNECT_DELAY_SECS)
-        );
+        sleep(reconnect_delay).await;
     }
 }
 
-async fn run(config: &BotConfig) {
-    let trade_logger: Option<TradeLogger> = if config.trade_log_enabled {
-        Some(TradeLogger::new(&config.log_dir))
-    } else {
-        None
-    };
-
-    let metrics_logger: Option<MetricsLogger> = if config.metrics_log_enabled {
-        Some(MetricsLogger::new(&config.log_dir))
-    } else {
-        None
-    };
-
-    let orders = Arc::new(Mutex::new(HashMap::new()));
-    let orders_ref = orders.clone();
-
-    let position = Arc::new(RwLock::new(model::Position::new()));
-    let position_ref = position.clone();
-
-    let board_asks = Arc::new(RwLock::new(BTreeMap::new()));
-    let board_asks_ref = board_asks.clone();
-
-    let board_bids = Arc::new(RwLock::new(BTreeMap::new()));
-    let board_bids_ref = board_bids.clone();
-
-    let executions = Arc::new(RwLock::new(Vec::<(u64, f64, i64)>::new()));
-    let executions_ref = executions.clone();
-
-    let last_ws_message: LastWsMessage = Arc::new(RwLock::new(0i64));
-    let last_ws_message_ws = last_ws_message.clone();
-    let last_ws_message_trade = last_ws_message.clone();
-
-    let config_ref = config.clone();
-    let config_ref2 = config.clone();
-
-    // Shared T_optimal for dynamic cancel interval (written by trade loop, read by cancel loop)
-    let t_optimal_shared: SharedU64 = Arc::new(RwLock::new(config.order_cancel_ms));
-    let t_optimal_cancel = t_optimal_shared.clone();
-    let t_optimal_trade = t_optimal_shared;
-
-    let trade_logger_cancel = trade_logger.clone();
-    let trade_logger_trade = trade_logger.clone();
-
-    // Order outcome channel: cancel_child_order sends outcomes, trade() drains to update P(fill)
-    let (outcome_tx, mut outcome_rx) = tokio::sync::mpsc::unbounded_channel::<OrderOutcome>();
-
-    // Shared ghost suppression: trade() sets it on ghost detection, get_position() skips writes during window
-    let ghost_suppression: GhostSuppression = Arc::new(RwLock::new(None));
-    let ghost_suppression_trade = ghost_suppression.clone();
-    let ghost_suppression_position = ghost_suppression;
-
-    // Share a single reqwest::Client across all tasks (connection pool reuse)
-    let shared_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .connect_timeout(std::time::Duration::from_secs(5))
-        .build()
-        .expect("Failed to create HTTP client");
-    let client_cancel = shared_client.clone();
-    let client_trade = shared_client.clone();
-    let client_position = shared_client;
+/// Apply a private-WS execution to the local position.
+/// get_position polling still overwrites this every 5s as the source of truth.
+fn apply_execution(position: &mut Position, event: &ws_private::ExecutionEvent) {
+    let is_close = matches!(event.settle_type, ws_private::SettleType::Close);
+    apply_fill(position, &event.side, event.execution_price, event.execution_size, is_close);
+}
 
-    tokio::select! {
-        result = tokio::spawn(async move {
-            if let Err(e) = cancel_child_order(&client_cancel, &config_ref, &orders, &trade_logger_cancel, &t_optimal_cancel, &outcome_tx).await {
-                error!("cancel_child_order error: {:?}", e);
-            }
-        }) => {
-            if let Err(e) = result {
-                error!("cancel_child_order task panicked: {:?}", e);
+/// Apply one fill (real or simulated) to the local position.
+fn apply_fill(position: &mut Position, side: &OrderSide, price: f64, size: f64, is_close: bool) {
+    match (is_close, side) {
+        (false, OrderSide::BUY) => {
+            let total = position.long_size + size;
+            position.long_open_price =
+                (position.long_open_price * position.long_size + price * size) / total;
+            if position.long_size <= 0.0 {
+                position.long_open_time = Some(std::time::Instant::now());
             }
+            position.long_size = util::round_size(total);
         }
-        result = tokio::spawn(async move {
-            if let Err(e) = trade(&client_trade, &config_ref2, &orders_ref, &position, &board_asks, &board_bids, &executions, &last_ws_message_trade, &trade_logger_trade, &metrics_logger, &t_optimal_trade, &ghost_suppression_trade, &mut outcome_rx).await {
-                error!("trade error: {:?}", e);
-            }
-        }) => {
-            if let Err(e) = result {
-                error!("trade task panicked: {:?}", e);
+        (false, _) => {
+            let total = position.short_size + size;
+            position.short_open_price =
+                (position.short_open_price * position.short_size + price * size) / total;
+            if position.short_size <= 0.0 {
+                position.short_open_time = Some(std::time::Instant::now());
             }
+            position.short_size = util::round_size(total);
         }
-        result = tokio::spawn(async move {
-            if let Err(e) = get_position(&client_position, &position_ref, &ghost_suppression_position).await {
-                error!("get_position error: {:?}", e);
-            }
-        }) => {
-            if let Err(e) = result {
-                error!("get_position task panicked: {:?}", e);
+        // Close SELL settles a long, close BUY settles a short
+        (true, OrderSide::SELL) => {
+            position.long_size = util::round_size((position.long_size - size).max(0.0));
+            if position.long_size <= 0.0 {
+                position.long_open_price = 0.0;
+                position.long_open_time = None;
             }
         }
-        result = tokio::spawn(async move {
-            if let Err(e) = subscribe_websocket(&board_asks_ref, &board_bids_ref, &executions_ref, &last_ws_message_ws).await {
-                error!("subscribe_websocket error: {:?}", e);
-            }
-        }) => {
-            if let Err(e) = result {
-                error!("subscribe_websocket task panicked: {:?}", e);
+        (true, _) => {
+            position.short_size = util::round_size((position.short_size - size).max(0.0));
+            if position.short_size <= 0.0 {
+                position.short_open_price = 0.0;
+                position.short_open_time = None;
             }
         }
     }
 }
 
-fn main() {
-    // トレーシング初期化 (RUST_LOG環境変数でログレベル制御)
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("trading_bot=info".parse().unwrap())
-        )
-        .init();
+fn handle_execution_event(
+    order_list: &Orders,
+    position: &Positions,
+    trade_logger: &Option<TradeLogger>,
+    outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    fill_guard: &FillGuard,
+    pnl: &RwLock<model::PnlTracker>,
+    event: &ws_private::ExecutionEvent,
+) {
+    apply_execution(&mut position.write(), event);
+    pnl.write().on_fill(
+        &event.side,
+        event.execution_price,
+        event.execution_size,
+        matches!(event.settle_type, ws_private::SettleType::Close),
+    );
+    debug!("[PRIVATE_WS] Execution order_id={} side={} size={} price={}",
+        event.order_id, event.side, event.execution_size, event.execution_price);
+
+    let order_id = event.order_id.to_string();
+    // Checked on every execution (partial fills too) while the order is still tracked
+    let order_mid = order_list.lock().get(&order_id).map(|info| info.mid_price);
+    if let Some(order_mid) = order_mid {
+        fill_guard.check(&order_id, event.execution_price, order_mid);
+    }
 
-    // Note: 指定された注文がすでに変更中、取消中、取消済、全量約定、失効のいずれかの状態である場合、以下のエラーメッセージが表示されます。
-    // "message_code":"ERR-5122","message_string":"The request is invalid due to the status of the specified order."
-    let runtime = Builder::new_multi_thread()
-        .worker_threads(4)
-        .enable_all()
-        .build()
+    if !event.is_fully_filled() {
+        return;
+    }
+    let Some(info) = order_list.lock().remove(&order_id) else {
+        return;
+    };
+    let order_age = (event.execution_timestamp.get_timestamp() as u64).saturating_sub(info.timestamp);
+    info!("[PRIVATE_WS] Order filled: {} (age={}ms)", order_id, order_age);
+
+    let _ = outcome_tx.send(info.outcome(true, order_age));
+    log_order_event(&event.symbol, trade_logger, order_filled_event(Utc::now().to_rfc3339(), &order_id, &info, order_age));
+}
+
+/// Dry-run counterpart of `handle_execution_event`: same position / outcome / log updates.
+fn handle_sim_fill(
+    symbol: &str,
+    order_list: &Orders,
+    position: &Positions,
+    trade_logger: &Option<TradeLogger>,
+    outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    pnl: &RwLock<model::PnlTracker>,
+    fill: &SimFill,
+) {
+    apply_fill(&mut position.write(), &fill.side, fill.price, fill.size, fill.is_close);
+    pnl.write().on_fill(&fill.side, fill.price, fill.size, fill.is_close);
+    info!("[DRY_RUN] Simulated fill: {} {} {} @ {} (close={})",
+        fill.order_id, fill.side, fill.size, fill.price, fill.is_close);
+
+    // MARKET closes (stop loss / flatten) are not tracked in the order list
+    let Some(info) = order_list.lock().remove(&fill.order_id) else {
+        return;
+    };
+    let order_age = (Utc::now().timestamp_millis() as u64).saturating_sub(info.timestamp);
+    let _ = outcome_tx.send(info.outcome(true, order_age));
+    log_order_event(symbol, trade_logger, order_filled_event(Utc::now().to_rfc3339(), &fill.order_id, &info, order_age));
+}
+
+/// Dry run: match simulated orders against the public executions stream.
+#[allow(clippy::too_many_arguments)]
+async fn simulate_fills(
+    symbol: &str,
+    sim: &SimExchange,
+    executions: &Executions,
+    order_list: &Orders,
+    position: &Positions,
+    trade_logger: &Option<TradeLogger>,
+    outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    pnl: &RwLock<model::PnlTracker>,
+) -> Result<()> {
+    const SIM_MATCH_INTERVAL_MS: u64 = 200;
+    loop {
+        sleep(Duration::from_millis(SIM_MATCH_INTERVAL_MS)).await;
+        let trades: Vec<(u64, f64, i64)> = executions.read().iter().map(|e| (e.0, e.1, e.2)).collect();
+        let fills = sim.match_executions(&trades);
+        for fill in &fills {
+            handle_sim_fill(symbol, order_list, position, trade_logger, outcome_tx, pnl, fill);
+        }
+    }
+}
+
+fn handle_order_event(
+    order_list: &Orders,
+    trade_logger: &Option<TradeLogger>,
+    outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    event: &ws_private::OrderEvent,
+) {
+    let order_id = event.order_id.to_string();
+    {
+        // An order we hold no id for may be one whose placement response was lost
+        let mut orders = order_list.lock();
+        if orders.get(&order_id).is_none() && orders.unconfirmed_len() > 0 {
+            let reported = ReportedOrder {
+                side: event.side.clone(),
+                price: event.order_price,
+                size: event.order_size,
+                seen_ms: Utc::now().timestamp_millis() as u64,
+            };
+            if let Some(info) = orders.adopt_unconfirmed(&order_id, &reported, UNCONFIRMED_MATCH_MS, UNCONFIRMED_PRICE_TOLERANCE_JPY) {
+                info!("[RECONCILE] Order {} matched unconfirmed client_id={} ({:?} {} @ {})",
+                    order_id, info.client_id, info.side, info.size, info.price);
+            }
+        }
+    }
+    if !event.is_terminated() {
+        return;
+    }
+    let Some(info) = order_list.lock().remove(&order_id) else {
+        return;
+    };
+    let order_age = (Utc::now().timestamp_millis() as u64).saturating_sub(info.timestamp);
+    info!("[PRIVATE_WS] Order {:?}: {} (age={}ms)", event.order_status, order_id, order_age);
+
+    let _ = outcome_tx.send(info.outcome(false, order_age));
+    log_order_event(&event.symbol, trade_logger, TradeEvent::OrderCancelled {
+        timestamp: Utc::now().to_rfc3339(),
+        order_id,
+        order_age_ms: order_age,
+        level: info.level,
+        side: info.side.to_string(),
+        is_close: info.is_close,
+    });
+}
+
+fn handle_private_message(
+    order_list: &Orders,
+    position: &Positions,
+    trade_logger: &Option<TradeLogger>,
+    outcome_tx: &tokio::sync::mpsc::UnboundedSender<OrderOutcome>,
+    fill_guard: &FillGuard,
+    pnl: &RwLock<model::PnlTracker>,
+    msg: &str,
+) {
+    let parsed: ws_private::PrivateMessage = match serde_json::from_str(msg) {
+        Ok(parsed) => parsed,
+        _ => return,
+    };
+
+    match parsed.channel {
+        ws_private::PrivateChannel::ExecutionEvents => match serde_json::from_str(msg) {
+            Ok(event) => handle_execution_event(order_list, position, trade_logger, outcome_tx, fill_guard, pnl, &event),
+            Err(e) => warn!("[PRIVATE_WS] Failed to parse execution event: {} ({})", e, msg),
+        },
+        ws_private::PrivateChannel::OrderEvents => match serde_json::from_str(msg) {
+            Ok(event) => handle_order_event(order_list, trade_logger, outcome_tx, &event),
+            Err(e) => warn!("[PRIVATE_WS] Failed to parse order event: {} ({})", e, msg),
+        },
+    }
+}
+
+/// Hand a private-WS message to the symbol it belongs to. Events for symbols this process
+/// does not trade (e.g. orders placed by hand) are dropped instead of touching a position.
+fn route_private_message(routes: &PrivateRoutes, msg: &str) {
+    let parsed: ws_private::PrivateMessage = match serde_json::from_str(msg) {
+        Ok(parsed) => parsed,
+        _ => return,
+    };
+    let Some(route) = parsed.symbol.parse::<Symbol>().ok().and_then(|symbol| routes.get(&symbol)) else {
+        debug!("[PRIVATE_WS] Ignoring {} event for untraded symbol {:?}", parsed.channel.as_str(), parsed.symbol);
+        return;
+    };
+    handle_private_message(
+        &route.orders, &route.position, &route.trade_logger, &route.outcome_tx, &route.fill_guard, &route.pnl, msg,
+    );
+}
+
+/// Private WebSocket: connect with an access token and process order/execution events
+async fn connect_and_process_private_websocket(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    token: &str,
+    routes: &PrivateRoutes,
+) -> Result<()> {
+    // Token is valid for 60 minutes; extend well before expiry
+    const TOKEN_EXTEND_INTERVAL_SECS: u64 = 1800;
+
+    let ws_url = Url::parse(&ws_private::private_ws_url(token))
+        .expect("Invalid WebSocket URL");
+    let (socket, _) = connect_async(ws_url).await?;
+
+    info!("[PRIVATE_WS] Connected");
+
+    let (mut write, mut read) = socket.split();
+
+    for channel in [ws_private::PrivateChannel::ExecutionEvents, ws_private::PrivateChannel::OrderEvents] {
+        write.send(Message::Text(ws_private::subscribe_message(&channel))).await?;
+        info!("[PRIVATE_WS] Subscribed to {}", channel.as_str());
+
+        // GMO coin requires a delay between subscriptions (1 request/sec)
+        sleep(Duration::from_millis(1000)).await;
+    }
+
+    let extend_period = Duration::from_secs(TOKEN_EXTEND_INTERVAL_SECS);
+    let mut extend_timer = tokio::time::interval_at(Instant::now() + extend_period, extend_period);
+
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                let Some(msg) = msg else {
+                    return Ok(());
+                };
+                if let Message::Text(text) = msg? {
+                    route_private_message(routes, &text);
+                }
+            }
+            _ = extend_timer.tick() => {
+                if let Err(e) = ws_private::extend_ws_token(client, limiter, token).await {
+                    warn!("[PRIVATE_WS] Failed to extend access token: {:?}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Private WebSocket購読（自動再接続機能付き、接続ごとにトークンを取得）
+async fn subscribe_private_websocket(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    routes: &PrivateRoutes,
+    rng: &mut StdRng,
+) -> Result<()> {
+    let mut backoff = ReconnectBackoff::new();
+
+    loop {
+        let reconnect_delay = match ws_private::get_ws_token(client, limiter).await {
+            Ok(token) => {
+                let result = connect_and_process_private_websocket(client, limiter, &token, routes).await;
+                let delay = backoff.next_delay(result.is_ok(), Instant::now().into_std(), rng);
+                match result {
+                    Ok(_) => warn!("[PRIVATE_WS] Connection closed normally, reconnecting in {:?}...", delay),
+                    Err(e) => error!("[PRIVATE_WS] WebSocket error: {:?}, reconnecting in {:?}...", e, delay),
+                }
+                delay
+            }
+            Err(e) => {
+                let delay = backoff.next_delay(false, Instant::now().into_std(), rng);
+                error!("[PRIVATE_WS] Failed to get access token: {:?}, retrying in {:?}...", e, delay);
+                delay
+            }
+        };
+        if backoff.in_storm() {
+            warn!("[RECONNECT_STORM] Too many private WebSocket reconnects, cooling down for {:?}", reconnect_delay);
+        }
+
+        sleep(reconnect_delay).await;
+    }
+}
+
+const TIME_SYNC_INTERVAL_SECS: u64 = 600;
+const TIME_OFFSET_WARN_MS: i64 = 1000;
+
+/// Fetch GMO server time and apply the clock offset to API-TIMESTAMP signing.
+async fn sync_server_time_once(client: &reqwest::Client) {
+    match gmo::get_server_status::sync_server_time(client).await {
+        Ok(Some(ms)) if ms.abs() >= TIME_OFFSET_WARN_MS => {
+            warn!("[TIME_SYNC] Large clock drift vs GMO server: offset={}ms (applied)", ms);
+        }
+        Ok(Some(ms)) => info!("[TIME_SYNC] offset={}ms", ms),
+        Ok(None) => warn!("[TIME_SYNC] Unparseable server responsetime, keeping offset={}ms", gmo::auth::time_offset()),
+        Err(e) => warn!("[TIME_SYNC] Failed to fetch server time, keeping offset={}ms: {:?}", gmo::auth::time_offset(), e),
+    }
+}
+
+const STATUS_POLL_INTERVAL_SECS: u64 = 30;
+
+/// Poll /v1/status and publish the latest exchange status for the trade loop.
+async fn poll_exchange_status(client: &reqwest::Client, exchange_status: &SharedExchangeStatus) -> Result<()> {
+    loop {
+        match gmo::get_server_status::get_server_status(client).await {
+            Ok(response) => {
+                let status = response.data.exchange_status();
+                let previous = std::mem::replace(&mut *exchange_status.write(), status);
+                if previous != status {
+                    info!("[EXCHANGE_STATUS] {:?} -> {:?} ({})", previous, status, response.data.status);
+                }
+            }
+            Err(e) => warn!("[EXCHANGE_STATUS] Failed to get server status: {:?}", e),
+        }
+        sleep(Duration::from_secs(STATUS_POLL_INTERVAL_SECS)).await;
+    }
+}
+
+/// Periodically refresh the server time offset (VPS clocks drift).
+async fn sync_server_time(client: &reqwest::Client) -> Result<()> {
+    loop {
+        sleep(Duration::from_secs(TIME_SYNC_INTERVAL_SECS)).await;
+        sync_server_time_once(client).await;
+    }
+}
+
+/// Run every symbol in `symbols` in this process. The HTTP client, rate limiter, clock sync,
+/// status poll and both WebSocket connections are shared; each symbol gets its own orders,
+/// position, book and trade / cancel / position tasks.
+async fn run(config: &BotConfig, symbols: Vec<SymbolConfig>, config_path: &str) {
+    // Dry run: orders go to an in-process SimExchange and logs land under `<log_dir>/dry_run`
+    if config.dry_run {
+        info!("[DRY_RUN] Paper trading enabled: no orders will be sent to GMO");
+    }
+    let log_dir = if config.dry_run {
+        format!("{}/dry_run", config.log_dir)
+    } else {
+        config.log_dir.clone()
+    };
+
+    // Share a single reqwest::Client across all tasks and symbols (connection pool reuse)
+    let shared_client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .connect_timeout(std::time::Duration::from_secs(5))
+        .build()
+        .expect("Failed to create HTTP client");
+
+    // Sync clock offset before any signed request goes out
+    sync_server_time_once(&shared_client).await;
+
+    // One token bucket for all private API calls (every symbol's cancel / trade / position share GMO's limit)
+    let rate_limiter = Arc::new(
+        RateLimiter::new(config.rate_limit_capacity, config.rate_limit_refill_per_sec)
+            .with_max_in_flight(config.max_in_flight_orders)
+            .with_max_orders_per_sec(config.max_orders_per_sec)
+            .with_jitter_rng(util::task_rng(config.rng_seed, "api_retry")),
+    );
+
+    // Latest GMO status (OPEN until the first poll says otherwise)
+    let exchange_status: SharedExchangeStatus = Arc::new(RwLock::new(ExchangeStatus::Open));
+    // Maintenance is account-wide, so every symbol pauses and resumes together
+    let maintenance: SharedMaintenance = Arc::new(Mutex::new(MaintenancePause::new()));
+    let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+    let ws_ping_interval = Duration::from_secs(config.ws_ping_interval_secs.max(1));
+
+    let mut tasks: Vec<(String, tokio::task::JoinHandle<()>)> = Vec::new();
+    let mut feeds = SymbolFeeds::new();
+    let mut routes = PrivateRoutes::new();
+    let mut shared_configs: Vec<SharedConfig> = Vec::new();
+    let mut loggers: Vec<(Option<TradeLogger>, Option<MetricsLogger>)> = Vec::new();
+    // Health endpoints report every symbol; the admin command only ever gets a single one
+    let mut health_states: Vec<HealthState> = Vec::new();
+    let mut primary: Option<(Orders, Arc<Positions>)> = None;
+    // Raised by POST /flatten: every trade loop stops quoting until restart
+    let admin_halt: TradingHalt = Arc::new(RwLock::new(false));
+
+    for symbol_config in &symbols {
+        let config = config.for_symbol(symbol_config);
+        let symbol = config.symbol.clone();
+        let span = tracing::info_span!("symbol", %symbol);
+
+        // Each symbol simulates its own fills against its own trades
+        let sim: Option<Arc<SimExchange>> = config.dry_run.then(|| Arc::new(SimExchange::new()));
+        // Several symbols log into one subdirectory each
+        let log_dir = if symbols.len() > 1 { format!("{}/{}", log_dir, symbol) } else { log_dir.clone() };
+
+        let trade_logger: Option<TradeLogger> = if config.trade_log_enabled {
+            Some(TradeLogger::new(&log_dir, config.log_retain_days, config.log_format, config.dry_run))
+        } else {
+            None
+        };
+
+        let metrics_logger: Option<MetricsLogger> = if config.metrics_log_enabled {
+            Some(MetricsLogger::new(&log_dir, config.log_retain_days, config.log_format, config.dry_run))
+        } else {
+            None
+        };
+        loggers.push((trade_logger.clone(), metrics_logger.clone()));
+
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let position = Arc::new(RwLock::new(model::Position::new()));
+        // Public WS reconnects wake the position poll and the order sweep immediately
+        let feed = SymbolFeed::default();
+        *feed.ws_waiting_since.write() = Utc::now().timestamp_millis();
+
+        // Bootstrap volatility from recent public trades instead of sitting at the floor until WS fills the window
+        match gmo::get_trades::get_trades(&shared_client, &symbol, gmo::get_trades::MAX_COUNT).await {
+            Ok(trades) => {
+                let now = Utc::now().timestamp_millis() + gmo::auth::time_offset();
+                let seeded = seed_executions(&trades, now, config.execution_retain_ms);
+                info!("Seeded {} {} executions from /v1/trades ({} fetched)", seeded.len(), symbol, trades.len());
+                feed.executions.write().extend(seeded);
+            }
+            Err(e) => warn!("Failed to fetch recent {} trades, volatility starts from WS only: {:?}", symbol, e),
+        }
+
+        let shared_config: SharedConfig = Arc::new(RwLock::new(config.clone()));
+        shared_configs.push(shared_config.clone());
+
+        // Shared T_optimal for dynamic cancel interval (written by trade loop, read by cancel loop)
+        let t_optimal: SharedU64 = Arc::new(RwLock::new(config.order_cancel_ms));
+
+        // Order outcome channel: cancel_child_order sends outcomes, trade() drains to update P(fill)
+        let (outcome_tx, mut outcome_rx) = tokio::sync::mpsc::unbounded_channel::<OrderOutcome>();
+
+        // Shared ghost suppression: trade() sets it on ghost detection, get_position() skips writes during window
+        let ghost_suppression: GhostSuppression = Arc::new(RwLock::new(None));
+
+        // Implausible-fill halt: set by the private WS, obeyed by trade()
+        let fill_guard: SharedFillGuard = Arc::new(FillGuard::new(config.implausible_fill_band_bps));
+
+        // Realized PnL: fed by private WS fills, read by trade() for metrics
+        let pnl: SharedPnl = Arc::new(RwLock::new(model::PnlTracker::new()));
+
+        // Health endpoints read the trade loop's latest mid / collateral from here
+        let trade_status: SharedTradeStatus = Arc::new(RwLock::new(TradeStatus::default()));
+        health_states.push(HealthState {
+            symbol: symbol.to_string(),
+            last_ws_message: feed.last_ws_message.clone(),
+            ws_stale_threshold_ms: WS_STALE_THRESHOLD_MS,
+            position: position.clone(),
+            orders: orders.clone(),
+            trade_status: trade_status.clone(),
+            counters: metrics_exporter::order_counters(&symbol.to_string()),
+        });
+        if primary.is_none() {
+            primary = Some((orders.clone(), position.clone()));
+        }
+        let alerts = AlertSink::new(config.alert_webhook_url.as_ref().map(|url| url.expose().to_string()), Duration::from_secs(config.alert_cooldown_secs));
+        if alerts.is_enabled() {
+            info!("[ALERT] Webhook alerts enabled for {} (cooldown {}s)", symbol, config.alert_cooldown_secs);
+        }
+
+        routes.insert(symbol.clone(), PrivateRoute {
+            orders: orders.clone(),
+            position: position.clone(),
+            trade_logger: trade_logger.clone(),
+            outcome_tx: outcome_tx.clone(),
+            fill_guard: fill_guard.clone(),
+            pnl: pnl.clone(),
+        });
+        feeds.insert(symbol.clone(), feed.clone());
+
+        tasks.push((format!("cancel_child_order {}", symbol), tokio::spawn({
+            let (client, limiter, config, orders, trade_logger) =
+                (shared_client.clone(), rate_limiter.clone(), config.clone(), orders.clone(), trade_logger.clone());
+            let (t_optimal, outcome_tx, resync, sim) = (t_optimal.clone(), outcome_tx.clone(), feed.resync.clone(), sim.clone());
+            async move {
+                if let Err(e) = cancel_child_order(&client, &limiter, &config, &orders, &trade_logger, &t_optimal, &outcome_tx, &resync, sim.as_deref()).await {
+                    error!("cancel_child_order error: {:?}", e);
+                }
+            }
+            .instrument(span.clone())
+        })));
+        tasks.push((format!("trade {}", symbol), tokio::spawn({
+            let (client, limiter, orders, position, feed) =
+                (shared_client.clone(), rate_limiter.clone(), orders.clone(), position.clone(), feed.clone());
+            let (trade_logger, ghost_suppression, exchange_status) =
+                (trade_logger.clone(), ghost_suppression.clone(), exchange_status.clone());
+            let (fill_guard, pnl, trade_status, sim) = (fill_guard.clone(), pnl.clone(), trade_status.clone(), sim.clone());
+            let (maintenance, admin_halt) = (maintenance.clone(), admin_halt.clone());
+            async move {
+                if let Err(e) = trade(&client, &limiter, &shared_config, &orders, &position, &feed.board_asks, &feed.board_bids, &feed.executions, &feed.last_ws_message, &feed.ws_waiting_since, &feed.last_ws_trade, &trade_logger, &metrics_logger, &t_optimal, &ghost_suppression, &exchange_status, &fill_guard, &pnl, &alerts, &trade_status, &maintenance, &admin_halt, sim.as_deref(), &mut outcome_rx).await {
+                    error!("trade error: {:?}", e);
+                }
+            }
+            .instrument(span.clone())
+        })));
+
+        if let Some(sim) = sim {
+            // Dry run: fills come from the simulator instead of REST positions / the private WS
+            let executions = feed.executions.clone();
+            tasks.push((format!("simulate_fills {}", symbol), tokio::spawn(async move {
+                if let Err(e) = simulate_fills(&symbol.to_string(), &sim, &executions, &orders, &position, &trade_logger, &outcome_tx, &pnl).await {
+                    error!("simulate_fills error: {:?}", e);
+                }
+            }.instrument(span))));
+        } else {
+            let (client, limiter, resync, maintenance) =
+                (shared_client.clone(), rate_limiter.clone(), feed.resync.clone(), maintenance.clone());
+            tasks.push((format!("get_position {}", symbol), tokio::spawn(async move {
+                if let Err(e) = get_position(&client, &limiter, &config, &orders, &position, &ghost_suppression, &resync, &maintenance).await {
+                    error!("get_position error: {:?}", e);
+                }
+            }.instrument(span))));
+        }
+    }
+
+    tasks.push(("sync_server_time".to_string(), tokio::spawn({
+        let client = shared_client.clone();
+        async move {
+            if let Err(e) = sync_server_time(&client).await {
+                error!("sync_server_time error: {:?}", e);
+            }
+        }
+    })));
+    tasks.push(("poll_exchange_status".to_string(), tokio::spawn({
+        let client = shared_client.clone();
+        async move {
+            if let Err(e) = poll_exchange_status(&client, &exchange_status).await {
+                error!("poll_exchange_status error: {:?}", e);
+            }
+        }
+    })));
+    // One public connection subscribes every symbol's orderbooks / trades
+    let rng_seed = config.rng_seed;
+    tasks.push(("subscribe_websocket".to_string(), tokio::spawn(async move {
+        if let Err(e) = subscribe_websocket(&feeds, &parse_failures, ws_ping_interval, &mut util::task_rng(rng_seed, "public_ws")).await {
+            error!("subscribe_websocket error: {:?}", e);
+        }
+    })));
+
+    if let (true, Some((orders, position))) = (config.health_port > 0, primary) {
+        let (addr, port) = (config.health_bind_addr, config.health_port);
+        // The admin command talks to the real exchange, so it is never mounted in a dry run,
+        // and it flattens one symbol, so not with several. The bearer secret crosses the wire
+        // in plaintext, so only a loopback listener gets it
+        let admin = match (&config.admin_secret, config.dry_run, symbols.as_slice()) {
+            (Some(_), false, _) if !addr.is_loopback() => {
+                warn!("[ADMIN] POST /flatten is loopback-only, not mounted on {}", addr);
+                None
+            }
+            (Some(secret), false, [symbol_config]) => {
+                info!("[ADMIN] POST /flatten enabled on the health port");
+                Some(health::admin_router(Arc::new(AdminState {
+                    secret: secret.expose().to_string(),
+                    exchange: GmoExchange::new(shared_client.clone(), rate_limiter.clone(), symbol_config.symbol.clone(), config.api_max_retries),
+                    orders,
+                    position,
+                    min_lot: config.for_symbol(symbol_config).min_lot,
+                    halt: admin_halt.clone(),
+                })))
+            }
+            (Some(_), false, _) => {
+                warn!("[ADMIN] POST /flatten is single-symbol only, not mounted for {} symbols", symbols.len());
+                None
+            }
+            _ => None,
+        };
+        let health_states: HealthStates = Arc::new(health_states);
+        tasks.push(("health_server".to_string(), tokio::spawn(async move {
+            if let Err(e) = health::serve(addr, port, health_states, admin).await {
+                error!("health_server error: {:?}", e);
+            }
+        })));
+    }
+
+    #[cfg(unix)]
+    {
+        let config_path = config_path.to_string();
+        tasks.push(("reload_on_sighup".to_string(), tokio::spawn(reload_on_sighup(shared_configs, config_path))));
+    }
+    #[cfg(not(unix))]
+    let _ = (shared_configs, config_path);
+
+    if config.private_ws_enabled && !config.dry_run {
+        // One private connection; events are routed by symbol
+        let (client, limiter) = (shared_client.clone(), rate_limiter.clone());
+        tasks.push(("subscribe_private_websocket".to_string(), tokio::spawn(async move {
+            if let Err(e) = subscribe_private_websocket(&client, &limiter, &routes, &mut util::task_rng(rng_seed, "private_ws")).await {
+                error!("subscribe_private_websocket error: {:?}", e);
+            }
+        })));
+    }
+
+    let shutdown_requested = supervise(tasks, shutdown_signal()).await;
+    if shutdown_requested && !config.dry_run {
+        let symbols: Vec<Symbol> = symbols.into_iter().map(|s| s.symbol).collect();
+        cancel_all_orders(&shared_client, &rate_limiter, &symbols).await;
+    }
+    // Write out whatever the loggers still have queued before the runtime goes away
+    for (trade_logger, metrics_logger) in loggers {
+        if let Some(logger) = trade_logger {
+            logger.shutdown().await;
+        }
+        if let Some(logger) = metrics_logger {
+            logger.shutdown().await;
+        }
+    }
+    if shutdown_requested {
+        info!("[SHUTDOWN] Shutdown complete");
+    }
+}
+
+/// Re-read `path` and swap it into every symbol's `shared` config if it parses and validates;
+/// otherwise the current configs stay. The symbols cannot change at runtime, and settings only
+/// read at startup (ports, log paths, cancel/position loops, level_offsets_jpy, the P(fill)
+/// prior and half-life, warmup_ms, ghost_safe_mode_*) still need a restart. `levels` / `price_step_*` re-key the
+/// level tables and the loop interval follows the new cadence from the next cycle.
+fn reload_config(shared: &[SharedConfig], path: &str) -> std::result::Result<(), Vec<String>> {
+    let new_config = BotConfig::load(path)?;
+    let symbols = new_config.symbol_configs();
+    let join = |symbols: Vec<Symbol>| symbols.iter().map(|s| s.to_string()).collect::<Vec<_>>().join(",");
+    let running: Vec<Symbol> = shared.iter().map(|config| config.read().symbol.clone()).collect();
+    let reloaded: Vec<Symbol> = symbols.iter().map(|s| s.symbol.clone()).collect();
+    if reloaded != running {
+        return Err(vec![format!("symbol ({}) cannot change on reload (running {})", join(reloaded), join(running))]);
+    }
+    for (config, symbol) in shared.iter().zip(&symbols) {
+        *config.write() = new_config.for_symbol(symbol);
+    }
+    Ok(())
+}
+
+/// Reload the config from `path` on every SIGHUP. Never returns.
+#[cfg(unix)]
+async fn reload_on_sighup(shared: Vec<SharedConfig>, path: String) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(sig) => sig,
+        Err(e) => {
+            error!("Failed to listen for SIGHUP, config reload disabled: {:?}", e);
+            return std::future::pending::<()>().await;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        match reload_config(&shared, &path) {
+            Ok(()) => {
+                for config in &shared {
+                    info!("[CONFIG] Reloaded {}: {:?}", path, *config.read());
+                }
+            }
+            Err(errors) => {
+                for e in &errors {
+                    error!("[CONFIG] {}", e);
+                }
+                error!("[CONFIG] Reload of {} rejected, keeping the current config", path);
+            }
+        }
+    }
+    std::future::pending::<()>().await
+}
+
+/// Resolves on SIGINT (Ctrl+C) or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {:?}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {:?}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("[SHUTDOWN] SIGINT received"),
+        _ = terminate => info!("[SHUTDOWN] SIGTERM received"),
+    }
+}
+
+/// Run until any task exits or `shutdown` resolves, then abort the remaining tasks
+/// so nothing places orders after this returns. Returns true if shutdown was requested.
+async fn supervise<F>(tasks: Vec<(String, tokio::task::JoinHandle<()>)>, shutdown: F) -> bool
+where
+    F: std::future::Future<Output = ()>,
+{
+    let (names, handles): (Vec<_>, Vec<_>) = tasks.into_iter().unzip();
+    let aborts: Vec<_> = handles.iter().map(|h| h.abort_handle()).collect();
+
+    let shutdown_requested = tokio::select! {
+        (result, index, _) = futures::future::select_all(handles) => {
+            match result {
+                Ok(()) => error!("{} task exited", names[index]),
+                Err(e) => error!("{} task panicked: {:?}", names[index], e),
+            }
+            false
+        }
+        _ = shutdown => true,
+    };
+
+    for abort in &aborts {
+        abort.abort();
+    }
+    shutdown_requested
+}
+
+/// Cancel every resting order on shutdown so nothing is left live on the exchange.
+async fn cancel_all_orders(client: &reqwest::Client, limiter: &RateLimiter, symbols: &[Symbol]) {
+    match gmo::cancel_bulk_order::cancel_bulk_order(client, limiter, symbols).await {
+        Ok(response) => info!("[SHUTDOWN] Cancelled {} open orders", response.1.data.len()),
+        Err(e) => error!("[SHUTDOWN] Cancel-all failed: {:?}", e),
+    }
+}
+
+fn main() {
+    // トレーシング初期化 (RUST_LOG環境変数でログレベル制御)
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive("trading_bot=info".parse().unwrap())
+        )
+        .init();
+
+    // Note: 指定された注文がすでに変更中、取消中、取消済、全量約定、失効のいずれかの状態である場合、以下のエラーメッセージが表示されます。
+    // "message_code":"ERR-5122","message_string":"The request is invalid due to the status of the specified order."
+    let runtime = Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
         .expect("Failed to build tokio runtime");
 
     let config_path = std::env::var("BOT_CONFIG_PATH")
         .unwrap_or_else(|_| "src/trade-config.yaml".to_string());
 
-    let yaml_str = fs::read_to_string(&config_path)
-        .unwrap_or_else(|_| panic!("Failed to read config file: {}", config_path));
-    let config: BotConfig = serde_yaml::from_str(&yaml_str)
-        .expect("Failed to parse config file");
+    let config = match BotConfig::load(&config_path) {
+        Ok(config) => config,
+        Err(errors) => {
+            for e in &errors {
+                error!("[CONFIG] {}", e);
+            }
+            error!("[CONFIG] {} invalid setting(s) in {}, refusing to start", errors.len(), config_path);
+            std::process::exit(1);
+        }
+    };
 
     info!("Config loaded: {:?}", config);
-    runtime.block_on(run(&config));
+    let symbols = config.symbol_configs();
+    runtime.block_on(run(&config, symbols, &config_path));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::fs;
     use crate::model::Position;
+    use crate::strategy::{calculate_order_sizes, maximize_single_leg_ev, MIN_VOLATILITY_BPS};
 
     #[test]
     fn rust_default_decimal_check1() {
@@ -1732,7 +4103,7 @@ mod tests {
     #[test]
     fn test_spread_adj_neutral_position() {
         let pos = Position { long_size: 0.0, short_size: 0.0, ..Default::default() };
-        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002);
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, 0.0, 0.0, 0.0);
         assert_eq!(buy_adj, 1.0);
         assert_eq!(sell_adj, 1.0);
     }
@@ -1740,7 +4111,7 @@ mod tests {
     #[test]
     fn test_spread_adj_long_heavy() {
         let pos = Position { long_size: 0.002, short_size: 0.0, ..Default::default() };
-        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002);
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, 0.0, 0.0, 0.0);
 
         // ロング過多: 買スプレッド広がる(>1)
         assert!(buy_adj > 1.0, "buy spread should widen when long-heavy, got {}", buy_adj);
@@ -1752,7 +4123,7 @@ mod tests {
     fn test_spread_adj_equal_positions_should_widen() {
         // Bug #3: 両建て均等でもスプレッドが広がるべき
         let pos = Position { long_size: 0.004, short_size: 0.004, ..Default::default() };
-        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002);
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, 0.0, 0.0, 0.0);
 
         // 両建て均等でも総エクスポージャーが大きいのでスプレッド広がるべき
         assert!(buy_adj > 1.0,
@@ -1767,7 +4138,7 @@ mod tests {
     fn test_spread_adj_half_max_meaningful_penalty() {
         // exposure_penaltyがmax_position_sizeで正規化され実効性があること
         let pos = Position { long_size: 0.001, short_size: 0.001, ..Default::default() };
-        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002);
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, 0.0, 0.0, 0.0);
 
         // 半分のポジション: 0.001/0.002 = 0.5 → penalty = 0.5 * 0.2 = 0.1
         // 両側均等なのでinventory_ratio=0, adj = 1.0 + 0 + 0.1 = 1.1
@@ -1840,6 +4211,39 @@ mod tests {
             short_sell, neutral_sell);
     }
 
+    #[test]
+    fn test_order_prices_skew_grows_with_penalty() {
+        let mid_price = 10_000_000.0;
+        let best_pair = (FloatingExp::new(10.0, -4.0, 1.0), FloatingExp::new(10.0, -4.0, 1.0));
+        let long_pos = Position { long_size: 0.002, ..Default::default() };
+
+        let mut prev = calculate_order_prices(mid_price, &best_pair, &long_pos, 0.0, 0.001);
+        for penalty in [10.0, 50.0, 200.0, 1000.0] {
+            let (buy, sell) = calculate_order_prices(mid_price, &best_pair, &long_pos, penalty, 0.001);
+            assert!(buy < prev.0 && sell < prev.1, "penalty {}: {:?} vs {:?}", penalty, (buy, sell), prev);
+            prev = (buy, sell);
+        }
+    }
+
+    #[test]
+    fn test_order_prices_clamped_at_extreme_penalty() {
+        let mid_price = 10_000_000.0;
+        let best_pair = (FloatingExp::new(10.0, -4.0, 1.0), FloatingExp::new(10.0, -4.0, 1.0));
+
+        let long_pos = Position { long_size: 0.1, ..Default::default() };
+        let (buy, sell) = calculate_order_prices(mid_price, &best_pair, &long_pos, 1e9, 0.001);
+        assert_eq!((buy, sell), (0.0, 0.0));
+
+        let short_pos = Position { short_size: 0.1, ..Default::default() };
+        let (buy, sell) = calculate_order_prices(mid_price, &best_pair, &short_pos, 1e9, 0.001);
+        assert_eq!((buy, sell), (2.0 * mid_price, 2.0 * mid_price));
+    }
+
+    #[test]
+    fn test_position_penalty_config_default() {
+        assert_eq!(symbol_test_config().position_penalty, 50.0);
+    }
+
     // ================================================================
     // Bug #4: maxロット保持時に決済注文が出せなくなる
     // calculate_order_sizes が 0 を返すと should_buy/should_sell が
@@ -1847,7 +4251,7 @@ mod tests {
     // ================================================================
 
     /// 決済注文に使うサイズを決定するヘルパー関数のテスト
-    /// maxポジション時でも決済にはmin_lotを使うべき
+    /// maxポジション時でも決済は保有ポジション全量を使うべき
     #[test]
     fn test_close_order_size_at_max_position() {
         let pos = Position { long_size: 0.01, short_size: 0.01, ..Default::default() };
@@ -1864,11 +4268,11 @@ mod tests {
         assert_eq!(buy_size, 0.0);
         assert_eq!(sell_size, 0.0);
 
-        // 決済用サイズはmin_lotであるべき
-        let close_buy_size = effective_order_size(buy_size, true, min_lot);
-        let close_sell_size = effective_order_size(sell_size, true, min_lot);
-        assert_eq!(close_buy_size, min_lot, "close buy should use min_lot even when open size is 0");
-        assert_eq!(close_sell_size, min_lot, "close sell should use min_lot even when open size is 0");
+        // 決済用サイズは保有ポジション全量であるべき
+        let close_buy_size = effective_order_size(buy_size, true, min_lot, pos.short_size);
+        let close_sell_size = effective_order_size(sell_size, true, min_lot, pos.long_size);
+        assert_eq!(close_buy_size, 0.01, "close buy should close the whole short even when open size is 0");
+        assert_eq!(close_sell_size, 0.01, "close sell should close the whole long even when open size is 0");
     }
 
     #[test]
@@ -1887,13 +4291,13 @@ mod tests {
         assert_eq!(buy_size, 0.0, "buy should be 0 at max long");
         assert!(sell_size >= min_lot, "sell should have positive size: {}", sell_size);
 
-        // Close buy (to close short): min_lot fallback since buy_size is 0
-        let eff_buy = effective_order_size(buy_size, true, min_lot);
-        assert_eq!(eff_buy, min_lot, "close buy should fallback to min_lot");
+        // Close buy (to close short): closes the 0.002 short even though buy_size is 0
+        let eff_buy = effective_order_size(buy_size, true, min_lot, pos.short_size);
+        assert_eq!(eff_buy, 0.002, "close buy should close the short inventory");
 
-        // Close sell (to close long): uses calculated size since sell_size >= min_lot
-        let eff_sell = effective_order_size(sell_size, true, min_lot);
-        assert_eq!(eff_sell, sell_size, "close sell should use calculated size");
+        // Close sell (to close long): closes the long inventory, not the open sizing
+        let eff_sell = effective_order_size(sell_size, true, min_lot, pos.long_size);
+        assert_eq!(eff_sell, 0.01, "close sell should close the long inventory");
     }
 
     #[test]
@@ -1909,24 +4313,62 @@ mod tests {
         );
 
         // 新規注文は計算されたサイズを使う
-        let open_size = effective_order_size(buy_size, false, min_lot);
+        let open_size = effective_order_size(buy_size, false, min_lot, pos.short_size);
         assert_eq!(open_size, buy_size, "open order should use calculated size");
     }
 
-    // ================================================================
-    // Volatility計算テスト (log-return stddev)
-    // ================================================================
+    #[test]
+    fn test_close_size_full_inventory() {
+        // Full close: 0.003 long is closed in one order
+        assert_eq!(effective_order_size(0.0, true, 0.001, 0.003), 0.003);
+        assert_eq!(effective_order_size(0.001, true, 0.001, 0.0037), 0.0037);
+    }
 
     #[test]
-    fn test_volatility_empty_executions_returns_floor() {
-        let executions: Vec<(u64, f64, i64)> = vec![];
-        let vol = calculate_volatility(&executions);
-        // 空のときはフロアを返す
-        assert!(vol > 0.0, "empty executions should return volatility floor, got {}", vol);
+    fn test_close_size_partial_inventory_floors_at_min_lot() {
+        // Partially filled / dust position below min_lot still sends a min_lot close
+        assert_eq!(effective_order_size(0.0, true, 0.001, 0.0004), 0.001);
+        assert_eq!(effective_order_size(0.0, true, 0.001, 0.0), 0.001);
     }
 
     #[test]
-    fn test_volatility_single_execution_returns_floor() {
+    fn test_close_size_never_exceeds_position() {
+        let min_lot = 0.001;
+        for &position_size in &[0.0, 0.0004, 0.001, 0.0015, 0.003, 0.01] {
+            for &calculated in &[0.0, 0.001, 0.002, 0.05] {
+                let size = clamp_close_size(
+                    effective_order_size(calculated, true, min_lot, position_size),
+                    position_size,
+                );
+                assert!(size <= position_size,
+                    "close size {} exceeds position {}", size, position_size);
+            }
+        }
+        // Dust below min_lot is clamped to the dust, not inflated to min_lot
+        assert_eq!(clamp_close_size(0.001, 0.0004), 0.0004);
+        assert_eq!(clamp_close_size(0.002, 0.002), 0.002);
+    }
+
+    #[test]
+    fn test_open_size_ignores_position() {
+        assert_eq!(effective_order_size(0.0, false, 0.001, 0.005), 0.0);
+        assert_eq!(effective_order_size(0.002, false, 0.001, 0.005), 0.002);
+    }
+
+    // ================================================================
+    // Volatility計算テスト (log-return stddev)
+    // ================================================================
+
+    #[test]
+    fn test_volatility_empty_executions_returns_floor() {
+        let executions: Vec<(u64, f64, i64)> = vec![];
+        let vol = calculate_volatility(&executions);
+        // 空のときはフロアを返す
+        assert!(vol > 0.0, "empty executions should return volatility floor, got {}", vol);
+    }
+
+    #[test]
+    fn test_volatility_single_execution_returns_floor() {
         let executions = vec![(6_500_000u64, 0.001, 1i64)];
         let vol = calculate_volatility(&executions);
         // 1データポイントでは標準偏差を計算できないのでフロアを返す
@@ -2010,24 +4452,24 @@ mod tests {
 
     #[test]
     fn test_pending_open_size_counts_open_orders_only() {
-        let mut orders = HashMap::new();
+        let mut orders = model::OrderMap::new();
         orders.insert("ord-1".to_string(), model::OrderInfo {
             price: 6_500_000, size: 0.001, side: OrderSide::BUY,
             timestamp: 0, is_close: false,
-            mid_price: 6_500_000, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
-            level: 5, p_fill: 0.5, best_ev: 0.0, single_leg_ev: 0.0,
+            mid_price: 6_500_000.0, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
+            level: 5, level_key: None, p_fill: 0.5, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
         });
         orders.insert("ord-2".to_string(), model::OrderInfo {
             price: 6_500_000, size: 0.001, side: OrderSide::BUY,
             timestamp: 0, is_close: true, // close order
-            mid_price: 6_500_000, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
-            level: 0, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0,
+            mid_price: 6_500_000.0, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
         });
         orders.insert("ord-3".to_string(), model::OrderInfo {
             price: 6_500_000, size: 0.001, side: OrderSide::SELL,
             timestamp: 0, is_close: false,
-            mid_price: 6_500_000, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
-            level: 5, p_fill: 0.5, best_ev: 0.0, single_leg_ev: 0.0,
+            mid_price: 6_500_000.0, t_optimal_ms: 3000, sigma_1s: 0.0001, spread_pct: 0.005,
+            level: 5, level_key: None, p_fill: 0.5, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
         });
 
         let buy_pending = pending_open_size(&orders, &OrderSide::BUY);
@@ -2040,7 +4482,7 @@ mod tests {
 
     #[test]
     fn test_pending_open_size_empty_orders() {
-        let orders = HashMap::new();
+        let orders = model::OrderMap::new();
         assert_eq!(pending_open_size(&orders, &OrderSide::BUY), 0.0);
         assert_eq!(pending_open_size(&orders, &OrderSide::SELL), 0.0);
     }
@@ -2139,8 +4581,8 @@ mod tests {
         assert_eq!(sell_size, 0.0);
 
         // 決済注文はmin_lotで出せる
-        let close_buy = effective_order_size(buy_size, true, min_lot);
-        let close_sell = effective_order_size(sell_size, true, min_lot);
+        let close_buy = effective_order_size(buy_size, true, min_lot, pos.short_size);
+        let close_sell = effective_order_size(sell_size, true, min_lot, pos.long_size);
         assert_eq!(close_buy, min_lot, "close buy should work at single-slot max");
         assert_eq!(close_sell, min_lot, "close sell should work at single-slot max");
     }
@@ -2186,7 +4628,7 @@ mod tests {
     fn test_single_slot_spread_adjustment() {
         // 単一スロットでのスプレッド調整
         let pos = Position { long_size: 0.001, short_size: 0.0, ..Default::default() };
-        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.001);
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.001, 0.0, 0.0, 0.0);
 
         // ロング保持 → 買スプレッド拡大
         assert!(buy_adj > 1.0,
@@ -2267,6 +4709,65 @@ mod tests {
         assert!(cev > 0.0, "combined EV should be positive: {}", cev);
     }
 
+    #[test]
+    fn test_p_fill_half_life_weights_recent_outcomes_more() {
+        // One fill and one miss in the window; only their order differs
+        let outcomes = |half_life: Option<Duration>, fill_first: bool| {
+            let mut bayes = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600)).with_half_life(half_life);
+            bayes.update(1, fill_first as u64);
+            std::thread::sleep(Duration::from_millis(200));
+            bayes.update(1, !fill_first as u64);
+            bayes.calc_average()
+        };
+
+        // Unweighted (default): order does not matter
+        assert_eq!(outcomes(None, true), outcomes(None, false));
+
+        // 50ms half-life: the 200ms-old outcome is worth ~1/16 of the fresh one
+        let old_fill = outcomes(Some(Duration::from_millis(50)), true);
+        let recent_fill = outcomes(Some(Duration::from_millis(50)), false);
+        assert!(recent_fill > old_fill, "recent fill {} should beat old fill {}", recent_fill, old_fill);
+        // Float pseudo-counts: the old outcome still counts for its <= 1/16 weight
+        assert!((2.0 / 12.0625 - 1e-3..2.0 / 12.0).contains(&recent_fill), "~Be(1+1, 10+1/16): {}", recent_fill);
+        assert!((1.0 / 12.0..1.0625 / 12.0625 + 1e-3).contains(&old_fill), "~Be(1+1/16, 10+1): {}", old_fill);
+
+        // Decay happens at read time: a lone fill fades with no further update
+        let mut bayes = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600))
+            .with_half_life(Some(Duration::from_millis(50)));
+        bayes.update(1, 1);
+        let fresh = bayes.calc_average();
+        std::thread::sleep(Duration::from_millis(200));
+        let faded = bayes.calc_average();
+        assert!(faded < fresh && faded < 1.0625 / 11.0625, "fresh {} faded {}", fresh, faded);
+    }
+
+    #[test]
+    fn test_reset_to_prior_after_circuit_breaker() {
+        let prior = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+        let prior_mean = prior.calc_average();
+        let key = FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 };
+        let learned = || {
+            let mut bayes = prior.clone();
+            for _ in 0..10 {
+                bayes.update(1, 1);
+            }
+            BTreeMap::from([(key.clone(), (0.0, bayes))])
+        };
+
+        // Flag off: the trade loop keeps what it learned
+        let (mut buy, mut sell) = (learned(), learned());
+        assert!(!reset_probabilities_after_breaker(false, &mut buy, &mut sell));
+        assert!(buy[&key].1.calc_average() > prior_mean);
+
+        // Flag on: both sides go back to the prior mean, and the old fills are gone for good
+        assert!(reset_probabilities_after_breaker(true, &mut buy, &mut sell));
+        assert_eq!(buy[&key].1.calc_average(), prior_mean);
+        assert_eq!(sell[&key].1.calc_average(), prior_mean);
+        let bayes = &mut buy.get_mut(&key).unwrap().1;
+        bayes.update(1, 0);
+        assert_eq!(bayes.posterior(), (1.0, 11.0));
+    }
+
     #[test]
     fn test_maximize_single_leg_ev_empty_maps() {
         let buy = BTreeMap::new();
@@ -2286,7 +4787,7 @@ mod tests {
         let spread_pct = 0.00005; // 0.005% as fraction
         let sigma_1s = 0.00003;   // 0.003% as fraction
         let t = calculate_t_optimal(spread_pct, sigma_1s, 2000, 30000);
-        assert!(t >= 2000 && t <= 3000,
+        assert!((2000..=3000).contains(&t),
             "Level 5 normal vol should be ~2780ms, got {}ms", t);
     }
 
@@ -2297,7 +4798,7 @@ mod tests {
         let spread_pct = 0.0001;
         let sigma_1s = 0.00003;
         let t = calculate_t_optimal(spread_pct, sigma_1s, 2000, 30000);
-        assert!(t >= 10000 && t <= 12000,
+        assert!((10000..=12000).contains(&t),
             "Level 10 normal vol should be ~11111ms, got {}ms", t);
     }
 
@@ -2463,6 +4964,17 @@ mod tests {
         assert!(long_pnl <= short_pnl, "long should be worse");
     }
 
+    #[test]
+    fn test_stop_loss_closing_long_uses_sell() {
+        let pos = Position { long_size: 0.002, long_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, _) = pos.unrealized_pnl(13_995_000.0, 0.001);
+        assert_eq!(worse_side_close(&pos, long_pnl, short_pnl), (OrderSide::SELL, 0.002, 14_000_000.0));
+
+        let pos = Position { short_size: 0.001, short_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, _) = pos.unrealized_pnl(14_005_000.0, 0.001);
+        assert_eq!(worse_side_close(&pos, long_pnl, short_pnl), (OrderSide::BUY, 0.001, 14_000_000.0));
+    }
+
     #[test]
     fn test_stop_loss_no_trigger_within_threshold() {
         let pos = Position {
@@ -2476,6 +4988,34 @@ mod tests {
         assert!(pnl >= -threshold, "pnl {} should NOT trigger stop-loss (threshold={})", pnl, threshold);
     }
 
+    #[test]
+    fn test_close_slippage_walks_the_book() {
+        let asks = BTreeMap::from([(10_000_010, 0.001), (10_000_030, 0.002), (10_000_100, 0.01)]);
+        let bids = BTreeMap::from([(9_999_990, 0.002), (9_999_900, 0.01)]);
+        let levels = |book: &BTreeMap<u64, f64>| book.iter().map(|(p, s)| (*p, *s)).collect::<Vec<_>>();
+
+        // 0.003 BUY takes 0.001 @ +10 and 0.002 @ +30: average +23.33 from mid
+        let fill = estimate_market_fill(levels(&asks).into_iter(), 0.003).unwrap();
+        assert!((fill - (10_000_010.0 + 2.0 * 10_000_030.0) / 3.0).abs() < 1e-6);
+        // Deeper than the visible book: no estimate
+        assert_eq!(estimate_market_fill(levels(&asks).into_iter(), 1.0), None);
+
+        let mid = 10_000_000.0;
+        let close = |side, size, budget| close_execution(&side, size, mid, &asks, &bids, budget, 1.0);
+        // Right at the boundary MARKET is still fine; a yen tighter falls back to LIMIT at mid + budget
+        let slippage = fill - mid;
+        assert_eq!(close(OrderSide::BUY, 0.003, slippage), CloseExecution::Market);
+        assert_eq!(close(OrderSide::BUY, 0.003, slippage - 1.0), CloseExecution::Limit(Price::from_f64(10_000_022.0)));
+        // SELL walks the bids down: 0.002 @ -10 then 0.001 @ -100 = -40 on average
+        let sell_fill = estimate_market_fill(levels(&bids).into_iter().rev(), 0.003).unwrap();
+        assert!((mid - sell_fill - 40.0).abs() < 1e-6);
+        assert_eq!(close(OrderSide::SELL, 0.003, mid - sell_fill), CloseExecution::Market);
+        assert_eq!(close(OrderSide::SELL, 0.003, 39.5), CloseExecution::Limit(Price::from_f64(9_999_961.0)));
+        // A book too thin to fill at all also goes LIMIT; a budget of 0 disables the guard
+        assert_eq!(close(OrderSide::SELL, 1.0, 500.0), CloseExecution::Limit(Price::from_f64(9_999_500.0)));
+        assert_eq!(close(OrderSide::SELL, 1.0, 0.0), CloseExecution::Market);
+    }
+
     #[test]
     fn test_stop_loss_zero_open_price_skips() {
         // open_price=0 means position not yet tracked → should not compute P&L
@@ -2566,8 +5106,8 @@ mod tests {
     // ================================================================
 
     #[test]
-    fn test_err_no_open_position_constant() {
-        assert_eq!(ERR_NO_OPEN_POSITION, "ERR-422");
+    fn test_err_no_open_position_code() {
+        assert_eq!(GmoErrorCode::from_code("ERR-422"), GmoErrorCode::NoOpenPosition);
     }
 
     #[test]
@@ -2626,14 +5166,75 @@ mod tests {
         assert!(matches!(result, OrderResult::NoOpenPosition));
     }
 
+    fn api_error(codes: &[&str]) -> ApiResponseError {
+        ApiResponseError::ApiError(
+            codes
+                .iter()
+                .map(|code| gmo::api::ApiErrorMessage { message_code: code.to_string(), message_string: String::new() })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_rejection_result_maps_sok_and_rate_limit() {
+        assert!(matches!(rejection_result(&api_error(&["ERR-5003"])), OrderResult::WouldTake));
+        assert!(matches!(
+            rejection_result(&ApiResponseError::StatusCode(reqwest::StatusCode::TOO_MANY_REQUESTS)),
+            OrderResult::RateLimited
+        ));
+        // Existing codes keep their variants and priority
+        assert!(matches!(rejection_result(&api_error(&["ERR-201", "ERR-5003"])), OrderResult::MarginInsufficient));
+        assert!(matches!(rejection_result(&api_error(&["ERR-201", "ERR-422"])), OrderResult::NoOpenPosition));
+        assert!(matches!(rejection_result(&api_error(&["ERR-5122"])), OrderResult::OtherError));
+        assert!(matches!(
+            rejection_result(&ApiResponseError::StatusCode(reqwest::StatusCode::BAD_GATEWAY)),
+            OrderResult::OtherError
+        ));
+    }
+
+    #[test]
+    fn test_would_take_streak_widens_open_quote() {
+        let mut guard = WouldTakeGuard::default();
+        let sok = rejection_result(&api_error(&["ERR-5003"]));
+        let (btc, xrp) = (10_000_000.0, 80.0);
+
+        guard.record(&OrderSide::BUY, &sok);
+        assert_eq!(guard.consecutive(&OrderSide::BUY), 1);
+        assert_eq!(guard.widen_jpy(&OrderSide::BUY, btc), 0.0, "a single rejection is not a streak");
+
+        guard.record(&OrderSide::BUY, &sok);
+        guard.record(&OrderSide::BUY, &sok);
+        assert_eq!(guard.consecutive(&OrderSide::BUY), 3);
+        assert!((guard.widen_jpy(&OrderSide::BUY, btc) - 100.0).abs() < 1e-6);
+        // Same streak, same bps: an 80 JPY symbol moves by a fraction of a yen, not 100 JPY
+        assert!((guard.widen_jpy(&OrderSide::BUY, xrp) - 0.0008).abs() < 1e-12);
+        // The other side is tracked separately
+        assert_eq!(guard.consecutive(&OrderSide::SELL), 0);
+        assert_eq!(guard.widen_jpy(&OrderSide::SELL, btc), 0.0);
+
+        // Throttled / rate-limited sends say nothing about our price: streak kept
+        guard.record(&OrderSide::BUY, &OrderResult::Throttled);
+        guard.record(&OrderSide::BUY, &OrderResult::RateLimited);
+        assert_eq!(guard.consecutive(&OrderSide::BUY), 3);
+
+        // Capped, then reset once an order is accepted
+        for _ in 0..100 {
+            guard.record(&OrderSide::BUY, &sok);
+        }
+        assert!((guard.widen_jpy(&OrderSide::BUY, btc) - 500.0).abs() < 1e-6);
+        guard.record(&OrderSide::BUY, &OrderResult::Success);
+        assert_eq!(guard.consecutive(&OrderSide::BUY), 0);
+        assert_eq!(guard.widen_jpy(&OrderSide::BUY, btc), 0.0);
+    }
+
     #[test]
     fn test_ghost_cooldown_extended_to_60s() {
-        // ゴースト検出時のクールダウンはSTOP_LOSSの10秒ではなく60秒
-        assert_eq!(GHOST_POSITION_COOLDOWN_SECS, 60);
-        // STOP_LOSS_COOLDOWN_SECS=10 (trade loop内ローカル定数) より長いこと
-        assert!(GHOST_POSITION_COOLDOWN_SECS > 10,
-            "ghost cooldown {}s should exceed stop-loss cooldown 10s",
-            GHOST_POSITION_COOLDOWN_SECS);
+        // ゴースト検出時のクールダウンはSTOP_LOSSの10秒ではなく60秒 (デフォルト)
+        let config = symbol_test_config();
+        assert_eq!(config.ghost_position_cooldown_secs, 60);
+        assert_eq!(config.stop_loss_cooldown_secs, 10);
+        assert_eq!(config.margin_cooldown_secs, 60);
+        assert!(config.ghost_position_cooldown_secs > config.stop_loss_cooldown_secs);
     }
 
     // ================================================================
@@ -2743,7 +5344,7 @@ mod tests {
         // v0.13.1: Ghost cooldown does NOT block close orders - only position size matters
         let ghost_cooldown_until = Some(Instant::now() + Duration::from_secs(60));
         let ghost_cooldown_active = ghost_cooldown_until
-            .map_or(false, |until| Instant::now() < until);
+            .is_some_and(|until| Instant::now() < until);
         assert!(ghost_cooldown_active, "ghost cooldown should be active");
 
         let current_position = Position {
@@ -2805,7 +5406,7 @@ mod tests {
         // v0.13.1: Ghost cooldown中でもposition=0ならclose=false（min_lotチェック）
         let ghost_cooldown_until = Some(Instant::now() + Duration::from_secs(60));
         let ghost_cooldown_active = ghost_cooldown_until
-            .map_or(false, |until| Instant::now() < until);
+            .is_some_and(|until| Instant::now() < until);
         assert!(ghost_cooldown_active);
 
         let current_position = Position {
@@ -2862,7 +5463,7 @@ mod tests {
 
         let min_hold = StdDuration::from_millis(180000);
         let elapsed = pos.long_open_time
-            .map_or(true, |t| t.elapsed() >= min_hold);
+            .is_none_or(|t| t.elapsed() >= min_hold);
 
         assert!(!elapsed, "min_hold should suppress close immediately after open");
     }
@@ -2877,7 +5478,7 @@ mod tests {
 
         let min_hold = StdDuration::from_millis(180000);
         let elapsed = pos.long_open_time
-            .map_or(true, |t| t.elapsed() >= min_hold);
+            .is_none_or(|t| t.elapsed() >= min_hold);
 
         assert!(elapsed, "min_hold should allow close when open_time is unknown");
     }
@@ -2892,7 +5493,7 @@ mod tests {
 
         let min_hold = StdDuration::from_millis(0);
         let elapsed = pos.long_open_time
-            .map_or(true, |t| t.elapsed() >= min_hold);
+            .is_none_or(|t| t.elapsed() >= min_hold);
 
         assert!(elapsed, "min_hold=0 should always allow close");
     }
@@ -2908,7 +5509,2283 @@ mod tests {
 
     #[test]
     fn test_sl_err422_still_activates_ghost_protection() {
-        assert_eq!(GHOST_POSITION_COOLDOWN_SECS, 60,
-            "SL ghost cooldown should remain 60s");
+        let mut config = symbol_test_config();
+        config.ghost_position_cooldown_secs = 120;
+        let position: Positions = RwLock::new(Position { long_size: 0.001, ..Position::new() });
+        let suppression: GhostSuppression = Arc::new(RwLock::new(None));
+
+        let before = Instant::now();
+        let until = activate_ghost_protection(&position, &suppression, &config);
+        assert_eq!(position.read().long_size, 0.0);
+        assert_eq!(*suppression.read(), Some(until));
+        // The configured 120s, not the 60s default
+        assert!(until >= before + Duration::from_secs(120));
+        assert!(until <= Instant::now() + Duration::from_secs(120));
+    }
+
+    // ================================================================
+    // WebSocket parse failure counters
+    // ================================================================
+
+    #[tokio::test]
+    async fn test_malformed_board_increments_parse_failure() {
+        let board_asks: OrderBook = RwLock::new(BTreeMap::new());
+        let board_bids: OrderBook = RwLock::new(BTreeMap::new());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        // asks is missing and price is not a string: schema drift
+        let malformed = r#"{"channel":"orderbooks","bids":[{"price":1,"size":"0.1"}],"symbol":"BTC_JPY"}"#;
+        handle_board_data(&board_asks, &board_bids, &Mutex::new(BoardWatermark::default()), &parse_failures, malformed).await;
+        handle_board_data(&board_asks, &board_bids, &Mutex::new(BoardWatermark::default()), &parse_failures, malformed).await;
+
+        assert_eq!(parse_failures.read().orderbooks, 2);
+        assert_eq!(parse_failures.read().trades, 0);
+        assert!(board_asks.read().is_empty());
+        assert!(board_bids.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_valid_board_does_not_count_failure() {
+        let board_asks: OrderBook = RwLock::new(BTreeMap::new());
+        let board_bids: OrderBook = RwLock::new(BTreeMap::new());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        let valid = r#"{"channel":"orderbooks","asks":[{"price":"10000010","size":"0.1"}],"bids":[{"price":"10000000","size":"0.2"}],"symbol":"BTC_JPY","timestamp":"2024-01-15T10:30:00.000Z"}"#;
+        handle_board_data(&board_asks, &board_bids, &Mutex::new(BoardWatermark::default()), &parse_failures, valid).await;
+
+        assert_eq!(parse_failures.read().orderbooks, 0);
+        assert_eq!(board_asks.read().get(&10_000_010), Some(&0.1));
+        assert_eq!(board_bids.read().get(&10_000_000), Some(&0.2));
+    }
+
+    fn board_msg(timestamp: &str, asks: &[(u64, &str)], bids: &[(u64, &str)]) -> String {
+        let levels = |levels: &[(u64, &str)]| {
+            levels.iter().map(|(p, s)| format!(r#"{{"price":"{}","size":"{}"}}"#, p, s)).collect::<Vec<_>>().join(",")
+        };
+        format!(
+            r#"{{"channel":"orderbooks","asks":[{}],"bids":[{}],"symbol":"BTC_JPY","timestamp":"{}"}}"#,
+            levels(asks), levels(bids), timestamp
+        )
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_board_is_dropped() {
+        let board_asks: OrderBook = RwLock::new(BTreeMap::new());
+        let board_bids: OrderBook = RwLock::new(BTreeMap::new());
+        let watermark = Mutex::new(BoardWatermark::default());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        let newer = board_msg("2024-01-15T10:30:01.000Z", &[(10_000_010, "0.3")], &[(10_000_000, "0.4")]);
+        let older = board_msg("2024-01-15T10:30:00.000Z", &[(10_000_010, "0.1"), (10_000_020, "0.1")], &[(10_000_000, "0.2")]);
+        handle_board_data(&board_asks, &board_bids, &watermark, &parse_failures, &newer).await;
+        handle_board_data(&board_asks, &board_bids, &watermark, &parse_failures, &older).await;
+
+        // The late, older board neither overwrote sizes nor added levels
+        assert_eq!(*board_asks.read(), BTreeMap::from([(10_000_010, 0.3)]));
+        assert_eq!(*board_bids.read(), BTreeMap::from([(10_000_000, 0.4)]));
+
+        // Same-timestamp and newer boards still apply
+        let same = board_msg("2024-01-15T10:30:01.000Z", &[(10_000_010, "0.5")], &[]);
+        handle_board_data(&board_asks, &board_bids, &watermark, &parse_failures, &same).await;
+        assert_eq!(board_asks.read().get(&10_000_010), Some(&0.5));
+    }
+
+    #[tokio::test]
+    async fn test_zero_size_board_level_is_removed() {
+        let board_asks: OrderBook = RwLock::new(BTreeMap::new());
+        let board_bids: OrderBook = RwLock::new(BTreeMap::new());
+        let watermark = Mutex::new(BoardWatermark::default());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        let first = board_msg("2024-01-15T10:30:00.000Z", &[(10_000_010, "0.1"), (10_000_020, "0.2")], &[(10_000_000, "0.2")]);
+        let second = board_msg("2024-01-15T10:30:01.000Z", &[(10_000_010, "0")], &[(10_000_000, "0"), (9_999_990, "0.1")]);
+        handle_board_data(&board_asks, &board_bids, &watermark, &parse_failures, &first).await;
+        handle_board_data(&board_asks, &board_bids, &watermark, &parse_failures, &second).await;
+
+        assert_eq!(*board_asks.read(), BTreeMap::from([(10_000_020, 0.2)]));
+        assert_eq!(*board_bids.read(), BTreeMap::from([(9_999_990, 0.1)]));
+    }
+
+    #[tokio::test]
+    async fn test_public_messages_route_to_their_symbol() {
+        let feeds: SymbolFeeds = [Symbol::BTC_JPY, Symbol::ETH_JPY].into_iter().map(|s| (s, SymbolFeed::default())).collect();
+        let watermarks = feeds.keys().map(|s| (s.clone(), Mutex::new(BoardWatermark::default()))).collect();
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        let btc_board = board_msg("2024-01-15T10:30:00.000Z", &[(10_000_010, "0.1")], &[(10_000_000, "0.2")]);
+        // An older ETH board is not held back by BTC's watermark
+        let eth_board = btc_board.replace("BTC_JPY", "ETH_JPY").replace("10:30:00", "10:29:00").replace("10000010", "500010");
+        let eth_trade = r#"{"channel":"trades","price":"500000","side":"SELL","size":"0.5","timestamp":"2024-01-15T10:30:00.000Z","symbol":"ETH_JPY"}"#;
+        let untraded = btc_board.replace("BTC_JPY", "XRP_JPY");
+        for msg in [btc_board.as_str(), eth_board.as_str(), eth_trade, untraded.as_str()] {
+            route_public_message(&feeds, &watermarks, &parse_failures, msg).await;
+        }
+
+        let (btc, eth) = (&feeds[&Symbol::BTC_JPY], &feeds[&Symbol::ETH_JPY]);
+        assert_eq!(*btc.board_asks.read(), BTreeMap::from([(10_000_010, 0.1)]));
+        assert_eq!(*eth.board_asks.read(), BTreeMap::from([(500_010, 0.1)]));
+        assert!(btc.executions.read().is_empty());
+        assert_eq!(eth.executions.read().len(), 1);
+        assert_eq!(eth.executions.read()[0].1, -0.5);
+        assert!(*btc.last_ws_message.read() > 0 && *eth.last_ws_message.read() > 0);
+        assert_eq!(parse_failures.read().orderbooks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_trade_increments_parse_failure() {
+        let executions: Executions = RwLock::new(Vec::new());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        handle_trade_data(&executions, &parse_failures, r#"{"channel":"trades","side":"HOLD"}"#).await;
+
+        assert_eq!(parse_failures.read().trades, 1);
+        assert!(executions.read().is_empty());
+    }
+
+    #[test]
+    fn test_side_intervals_default_to_trade_loop_period() {
+        let mut config = symbol_test_config();
+        config.order_interval_ms = 3000;
+        assert_eq!(side_interval_ms(&config, &OrderSide::BUY), 3000);
+        assert_eq!(side_interval_ms(&config, &OrderSide::SELL), 3000);
+        assert_eq!(loop_interval_ms(&config), 3000);
+
+        config.trade_interval_ms = Some(1500);
+        assert_eq!(side_interval_ms(&config, &OrderSide::SELL), 1500);
+        config.buy_interval_ms = Some(500);
+        assert_eq!(side_interval_ms(&config, &OrderSide::BUY), 500);
+        assert_eq!(loop_interval_ms(&config), 500, "loop wakes for the faster side");
+    }
+
+    #[test]
+    fn test_asymmetric_side_cadence_quotes_faster_side_more_often() {
+        let mut config = symbol_test_config();
+        config.order_interval_ms = 3000;
+        config.buy_interval_ms = Some(1000);
+        let tick = loop_interval_ms(&config);
+        assert_eq!(tick, 1000);
+
+        // 30s of trade loop cycles; each side is quoted whenever it is due
+        let start = Instant::now();
+        let mut cadence = SideCadence::default();
+        let (mut buys, mut sells) = (0, 0);
+        for cycle in 0..30 {
+            let now = start + Duration::from_millis(cycle * tick);
+            for (side, count) in [(OrderSide::BUY, &mut buys), (OrderSide::SELL, &mut sells)] {
+                if cadence.due(&side, now, Duration::from_millis(side_interval_ms(&config, &side))) {
+                    cadence.mark(&side, now);
+                    *count += 1;
+                }
+            }
+        }
+        assert_eq!((buys, sells), (30, 10));
+    }
+
+    #[test]
+    fn test_reconnect_does_not_trigger_ws_stale() {
+        let feed = SymbolFeed::default();
+        let state = |now| ws_feed_state(*feed.last_ws_message.read(), *feed.ws_waiting_since.read(), now, WS_STALE_THRESHOLD_MS);
+        let t0 = 1_700_000_000_000;
+        *feed.last_ws_message.write() = t0;
+        assert_eq!(state(t0 + 1_000), WsFeedState::Fresh);
+
+        // Connection drops; the reconnect (backoff + 10s of subscribes) outlasts the threshold
+        reset_ws_staleness(&feed, t0 + 1_000);
+        let cycle = t0 + WS_STALE_THRESHOLD_MS + 30_000;
+        assert_eq!(state(cycle), WsFeedState::Waiting);
+
+        // First message on the new connection re-arms the check: the next cycle trades
+        *feed.last_ws_message.write() = cycle + 200;
+        assert_eq!(state(cycle + 500), WsFeedState::Fresh);
+
+        // Without the reset the same cycle would have been a stale skip
+        assert_eq!(ws_feed_state(t0, 0, cycle, WS_STALE_THRESHOLD_MS), WsFeedState::Stale);
+    }
+
+    #[test]
+    fn test_reconnect_that_never_delivers_goes_stale() {
+        let feed = SymbolFeed::default();
+        let t0 = 1_700_000_000_000;
+        reset_ws_staleness(&feed, t0);
+        let state = |now| ws_feed_state(*feed.last_ws_message.read(), *feed.ws_waiting_since.read(), now, WS_STALE_THRESHOLD_MS);
+        assert_eq!(state(t0 + WS_RECONNECT_GRACE_MS), WsFeedState::Waiting);
+        assert_eq!(state(t0 + WS_RECONNECT_GRACE_MS + 1), WsFeedState::Stale, "a long outage must alert");
+        // Unknown start (never reset): keep waiting rather than guess
+        assert_eq!(ws_feed_state(0, 0, t0, WS_STALE_THRESHOLD_MS), WsFeedState::Waiting);
+    }
+
+    #[tokio::test]
+    async fn test_trade_data_keeps_exchange_timestamp_and_delay() {
+        let executions: Executions = RwLock::new(Vec::new());
+        let parse_failures: ParseFailures = Arc::new(RwLock::new(WsParseFailures::default()));
+
+        let exchange_ts = Utc::now().timestamp_millis() - 250;
+        let exchange_time = chrono::DateTime::from_timestamp_millis(exchange_ts).unwrap().to_rfc3339();
+        let msg = format!(
+            r#"{{"channel":"trades","price":"10000000","side":"SELL","size":"0.01","timestamp":"{}","symbol":"BTC_JPY"}}"#,
+            exchange_time
+        );
+        handle_trade_data(&executions, &parse_failures, &msg).await;
+
+        let executions = executions.read();
+        assert_eq!(executions.len(), 1);
+        let (price, size, ts, delay) = executions[0];
+        assert_eq!((price, size, ts), (10_000_000, -0.01, exchange_ts));
+        assert!((250..1_250).contains(&(delay - gmo::auth::time_offset())), "delay {}", delay);
+    }
+
+    #[test]
+    fn test_feed_delay_uses_server_clock_offset() {
+        assert_eq!(feed_delay_ms(10_000, 0, 9_700), 300);
+        // Local clock 200ms behind the server: the raw difference understates the delay
+        assert_eq!(feed_delay_ms(10_000, 200, 9_700), 500);
+    }
+
+    #[test]
+    fn test_average_feed_delay() {
+        assert_eq!(average_feed_delay_ms(&[]), None);
+        let executions = [(1, 0.01, 0, 100), (1, 0.01, 0, 300), (1, 0.01, 0, 200)];
+        assert_eq!(average_feed_delay_ms(&executions), Some(200.0));
+    }
+
+    #[test]
+    fn test_seeded_executions_give_non_floor_volatility() {
+        // /v1/trades is newest first; the oldest trade is outside the retain window
+        let json = r#"{"status":0,"data":{"pagination":{"currentPage":1,"count":5},"list":[
+            {"price":"10003000","side":"SELL","size":"0.02","timestamp":"2024-01-15T10:30:04.000Z"},
+            {"price":"9998000","side":"BUY","size":"0.01","timestamp":"2024-01-15T10:30:03.000Z"},
+            {"price":"10004000","side":"BUY","size":"0.05","timestamp":"2024-01-15T10:30:02.000Z"},
+            {"price":"9997000","side":"SELL","size":"0.01","timestamp":"2024-01-15T10:30:01.000Z"},
+            {"price":"12000000","side":"BUY","size":"0.01","timestamp":"2024-01-15T10:00:00.000Z"}
+        ]}}"#;
+        let trades = serde_json::from_str::<gmo::get_trades::TradesResponse>(json).unwrap().data.list;
+        let now = chrono::DateTime::parse_from_rfc3339("2024-01-15T10:30:05Z").unwrap().timestamp_millis();
+
+        let seeded = seed_executions(&trades, now, 10_000);
+        assert_eq!(seeded.len(), 4);
+        assert!(seeded.windows(2).all(|w| w[0].2 < w[1].2), "oldest first");
+        assert_eq!(seeded[0], (9_997_000, -0.01, now - 4_000, 0));
+        assert_eq!(seeded[3].1, -0.02);
+
+        let projected: Vec<(u64, f64, i64)> = seeded.iter().map(|e| (e.0, e.1, e.2)).collect();
+        let mean = projected.iter().map(|e| e.0 as f64).sum::<f64>() / projected.len() as f64;
+        assert!(calculate_volatility(&projected) > mean * MIN_VOLATILITY_BPS);
+    }
+
+    #[test]
+    fn test_rest_fallback_fills_an_empty_ws_executions_window() {
+        let json = r#"{"status":0,"data":{"pagination":{"currentPage":1,"count":3},"list":[
+            {"price":"10003000","side":"SELL","size":"0.02","timestamp":"2024-01-15T10:30:04.000Z"},
+            {"price":"9998000","side":"BUY","size":"0.01","timestamp":"2024-01-15T10:30:03.000Z"},
+            {"price":"10004000","side":"BUY","size":"0.05","timestamp":"2024-01-15T10:30:02.000Z"}
+        ]}}"#;
+        let trades = serde_json::from_str::<gmo::get_trades::TradesResponse>(json).unwrap().data.list;
+        let now = chrono::DateTime::parse_from_rfc3339("2024-01-15T10:30:05Z").unwrap().timestamp_millis();
+        let started = now - 60_000;
+
+        // WS trades silent (never arrived, or 30s ago) with a 10s fallback: poll REST
+        assert_eq!(execution_source(0, started, now, 10_000), ExecutionSource::Rest);
+        assert_eq!(execution_source(now - 30_000, started, now, 10_000), ExecutionSource::Rest);
+        // Off, just started, or WS trades recent again: stay on / switch back to WS
+        assert_eq!(execution_source(0, started, now, 0), ExecutionSource::Ws);
+        assert_eq!(execution_source(0, now - 1_000, now, 10_000), ExecutionSource::Ws);
+        assert_eq!(execution_source(now - 500, started, now, 10_000), ExecutionSource::Ws);
+
+        // Empty WS window: the stall counter climbs
+        let mut executions: Vec<(u64, f64, i64, i64)> = Vec::new();
+        let mut empty_count = 0;
+        for _ in 0..5 {
+            assert!(executions_stalled(&mut empty_count, executions.is_empty(), 10_000, 1_000));
+        }
+        assert_eq!(empty_count, 5);
+
+        // REST supplies the window and the stall clears
+        assert_eq!(merge_rest_executions(&mut executions, &trades, now, 10_000), 3);
+        assert!(executions.windows(2).all(|w| w[0].2 < w[1].2), "oldest first");
+        assert!(!executions_stalled(&mut empty_count, executions.is_empty(), 10_000, 1_000));
+        assert_eq!(empty_count, 0);
+
+        // Re-polling the same page adds nothing; a WS trade after it is kept in order
+        assert_eq!(merge_rest_executions(&mut executions, &trades, now, 10_000), 0);
+        executions.push((10_001_000, 0.01, now, 50));
+        assert_eq!(merge_rest_executions(&mut executions, &trades, now, 10_000), 0);
+        assert_eq!(executions.len(), 4);
+    }
+
+    // ================================================================
+    // Improve-only requote guard
+    // ================================================================
+
+    fn pending_order(side: OrderSide, price: u64, is_close: bool) -> model::OrderInfo {
+        model::OrderInfo {
+            price, size: 0.001, side, timestamp: 0, is_close,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_best_pending_open_price_ignores_closes() {
+        let mut orders = model::OrderMap::new();
+        orders.insert("b1".to_string(), pending_order(OrderSide::BUY, 9_999_000, false));
+        orders.insert("b2".to_string(), pending_order(OrderSide::BUY, 9_999_500, false));
+        orders.insert("b3".to_string(), pending_order(OrderSide::BUY, 9_999_900, true));
+        orders.insert("s1".to_string(), pending_order(OrderSide::SELL, 10_001_000, false));
+        orders.insert("s2".to_string(), pending_order(OrderSide::SELL, 10_000_500, false));
+
+        assert_eq!(best_pending_open_price(&orders, &OrderSide::BUY), Some(9_999_500));
+        assert_eq!(best_pending_open_price(&orders, &OrderSide::SELL), Some(10_000_500));
+        assert_eq!(best_pending_open_price(&model::OrderMap::new(), &OrderSide::BUY), None);
+    }
+
+    #[test]
+    fn test_requote_improve_allowed() {
+        // Buy moving up toward the market, sell moving down toward the market
+        assert!(requote_improves(&OrderSide::BUY, 9_999_600, Some(9_999_500)));
+        assert!(requote_improves(&OrderSide::SELL, 10_000_400, Some(10_000_500)));
+        // Same price keeps priority and is allowed
+        assert!(requote_improves(&OrderSide::BUY, 9_999_500, Some(9_999_500)));
+        // No resting order: always allowed
+        assert!(requote_improves(&OrderSide::SELL, 10_000_900, None));
+    }
+
+    #[test]
+    fn test_requote_worsen_skipped() {
+        assert!(!requote_improves(&OrderSide::BUY, 9_999_400, Some(9_999_500)));
+        assert!(!requote_improves(&OrderSide::SELL, 10_000_600, Some(10_000_500)));
+    }
+
+    // ================================================================
+    // time_in_force config
+    // ================================================================
+
+    #[test]
+    fn test_time_in_force_from_config_yaml() {
+        let yaml = r#"
+order_cancel_ms: 10000
+order_interval_ms: 3000
+position_ratio: 0.9
+min_lot: 0.001
+max_lot: 0.001
+max_position: 0.001
+time_in_force: FAK
+"#;
+        let config: BotConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(time_in_force_for(&config, &ChildOrderType::LIMIT), Some(TimeInForce::FAK));
+        assert_eq!(time_in_force_for(&config, &ChildOrderType::MARKET), Some(TimeInForce::FAK));
+    }
+
+    #[test]
+    fn test_sok_rejected_for_market() {
+        let yaml = r#"
+order_cancel_ms: 10000
+order_interval_ms: 3000
+position_ratio: 0.9
+min_lot: 0.001
+max_lot: 0.001
+max_position: 0.001
+time_in_force: SOK
+"#;
+        let config: BotConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(time_in_force_for(&config, &ChildOrderType::LIMIT), Some(TimeInForce::SOK));
+        assert_eq!(time_in_force_for(&config, &ChildOrderType::MARKET), None);
+    }
+
+    #[test]
+    fn test_time_in_force_serialized_field() {
+        let with_tif = gmo::send_order::ChildOrderParameter {
+            symbol: Symbol::BTC_JPY,
+            side: OrderSide::BUY,
+            execution_type: ChildOrderType::LIMIT,
+            price: Some("10000000".to_string()),
+            size: "0.001".to_string(),
+            time_in_force: Some(TimeInForce::FAK),
+        };
+        let json = serde_json::to_string(&with_tif).unwrap();
+        assert!(json.contains(r#""timeInForce":"FAK""#), "got {}", json);
+
+        let close = gmo::close_bulk_order::CloseBulkOrderParameter {
+            symbol: Symbol::BTC_JPY,
+            side: OrderSide::SELL,
+            execution_type: ChildOrderType::LIMIT,
+            price: Some("10000000".to_string()),
+            size: "0.001".to_string(),
+            time_in_force: None,
+        };
+        let json = serde_json::to_string(&close).unwrap();
+        assert!(!json.contains("timeInForce"), "None must be omitted: {}", json);
+    }
+
+    // ================================================================
+    // Symbol registry: tick / size step validation
+    // ================================================================
+
+    fn symbol_test_config() -> BotConfig {
+        let yaml = "order_cancel_ms: 1\norder_interval_ms: 1\nposition_ratio: 0.9\nmin_lot: 0.001\nmax_lot: 0.001\nmax_position: 0.002\n";
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_validate_order_params_btc_rule() {
+        let config = symbol_test_config();
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let btc = registry.get("BTC_JPY").unwrap();
+
+        assert!(validate_order_params(Price::from_f64(10_000_000.0), 0.001, &config, btc).is_ok());
+        assert!(validate_order_params(Price::from_f64(10_000_000.0), 0.0012, &config, btc).is_ok());
+        assert!(validate_order_params(Price::from_f64(10_000_000.0), 0.00125, &config, btc).is_err());
+        assert!(validate_order_params(Price::from_f64(0.0), 0.001, &config, btc).is_err());
+        assert!(validate_order_params(Price::from_f64(10_000_000.0), 0.0005, &config, btc).is_err());
+    }
+
+    #[test]
+    fn test_validate_order_params_second_instrument() {
+        let yaml = "order_cancel_ms: 1\norder_interval_ms: 1\nposition_ratio: 0.9\nmin_lot: 0.01\nmax_lot: 1.0\nmax_position: 2.0\nsymbol_rules:\n  - symbol: ETH_JPY\n    tick_size: 1\n    size_step: 0.01\n    min_lot: 0.01\n    max_lot: 5\n";
+        let config: BotConfig = serde_yaml::from_str(yaml).unwrap();
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let eth = registry.get("ETH_JPY").unwrap();
+
+        assert!(validate_order_params(Price::from_f64(400_000.0), 0.05, &config, eth).is_ok());
+        // BTC step (0.0001) is too fine for ETH
+        assert_eq!(validate_order_params(Price::from_f64(400_000.0), 0.0105, &config, eth), Err("Size is not a multiple of the size step"));
+        // Exchange max wins over config.max_lot * 10
+        assert_eq!(validate_order_params(Price::from_f64(400_000.0), 6.0, &config, eth), Err("Size exceeds maximum allowed"));
+    }
+
+    #[test]
+    fn test_validate_order_params_per_configured_symbol() {
+        // (symbol, min_lot, valid size, off-step size, price)
+        let cases = [
+            ("BTC_JPY", 0.001, 0.0013, 0.00135, 10_000_000),
+            ("ETH_JPY", 0.1, 0.3, 0.15, 400_000),
+            ("XRP_JPY", 10.0, 30.0, 15.0, 85),
+            ("BCH_JPY", 0.1, 0.2, 0.25, 60_000),
+        ];
+        for (name, min_lot, valid, off_step, price) in cases {
+            let yaml = format!(
+                "order_cancel_ms: 1\norder_interval_ms: 1\nposition_ratio: 0.9\nmin_lot: {}\nmax_lot: {}\nmax_position: {}\nsymbol: {}\n",
+                min_lot, min_lot * 10.0, min_lot * 20.0, name
+            );
+            let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(config.symbol.to_string(), name);
+
+            let registry = build_symbol_registry(None, &config.symbol_rules);
+            let rule = registry.get(&config.symbol.to_string()).unwrap();
+            assert!(validate_order_params(Price::from_f64(price as f64), valid, &config, rule).is_ok(), "{} {}", name, valid);
+            assert_eq!(
+                validate_order_params(Price::from_f64(price as f64), off_step, &config, rule),
+                Err("Size is not a multiple of the size step"),
+                "{} {}", name, off_step
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_order_params_rejects_off_tick_price() {
+        let config = symbol_test_config();
+        let rule = SymbolRule {
+            symbol: "BTC_JPY".to_string(),
+            tick_size: 5.0,
+            size_step: 0.0001,
+            min_lot: 0.0001,
+            max_lot: 5.0,
+        };
+        assert!(validate_order_params(Price::from_f64(10_000_005.0), 0.001, &config, &rule).is_ok());
+        assert_eq!(validate_order_params(Price::from_f64(10_000_003.0), 0.001, &config, &rule), Err("Price is not a multiple of the tick size"));
+    }
+
+    #[test]
+    fn test_build_symbol_registry_config_overrides_fetched() {
+        let fetched = vec![SymbolRule {
+            symbol: "BTC_JPY".to_string(),
+            tick_size: 1.0,
+            size_step: 0.01,
+            min_lot: 0.01,
+            max_lot: 5.0,
+        }];
+        let overrides = vec![SymbolRule {
+            symbol: "BTC_JPY".to_string(),
+            tick_size: 1.0,
+            size_step: 0.001,
+            min_lot: 0.001,
+            max_lot: 1.0,
+        }];
+
+        let from_api = build_symbol_registry(Some(fetched.clone()), &[]);
+        assert_eq!(from_api.get("BTC_JPY").unwrap().size_step, 0.01);
+
+        let overridden = build_symbol_registry(Some(fetched), &overrides);
+        assert_eq!(overridden.get("BTC_JPY").unwrap(), &overrides[0]);
+    }
+
+    // ================================================================
+    // Flip dampener
+    // ================================================================
+
+    #[test]
+    fn test_flip_penalty_no_flip_is_zero() {
+        assert_eq!(flip_penalty(None, 1_000_000, 30_000.0), 0.0);
+    }
+
+    #[test]
+    fn test_flip_penalty_full_at_flip_then_decays() {
+        let flip = 1_000_000;
+        assert_eq!(flip_penalty(Some(flip), flip, 30_000.0), 1.0);
+
+        let one_tau = flip_penalty(Some(flip), flip + 30_000, 30_000.0);
+        assert!((one_tau - (-1.0f64).exp()).abs() < 1e-12);
+
+        let later = flip_penalty(Some(flip), flip + 300_000, 30_000.0);
+        assert!(later < 1e-4);
+        assert!(later < one_tau);
+    }
+
+    #[test]
+    fn test_flip_penalty_disabled_or_clock_skew() {
+        // decay <= 0 disables the dampener
+        assert_eq!(flip_penalty(Some(1_000), 1_000, 0.0), 0.0);
+        // now before flip (clock skew) is treated as "just flipped", never > 1
+        assert_eq!(flip_penalty(Some(2_000), 1_000, 30_000.0), 1.0);
+    }
+
+    #[test]
+    fn test_net_side() {
+        let pos = |long_size, short_size| Position { long_size, short_size, ..Position::default() };
+        assert_eq!(net_side(&pos(0.002, 0.0), 0.001), Some(OrderSide::BUY));
+        assert_eq!(net_side(&pos(0.0, 0.001), 0.001), Some(OrderSide::SELL));
+        assert_eq!(net_side(&pos(0.001, 0.001), 0.001), None);
+        assert_eq!(net_side(&pos(0.0005, 0.0), 0.001), None);
+    }
+
+    // ================================================================
+    // Graceful shutdown
+    // ================================================================
+
+    #[tokio::test]
+    async fn test_shutdown_resolves_supervisor_and_aborts_tasks() {
+        let worker = tokio::spawn(std::future::pending::<()>());
+        let abort = worker.abort_handle();
+
+        let shutdown = supervise(vec![("worker".to_string(), worker)], async {}).await;
+
+        assert!(shutdown, "shutdown future should win the select");
+        tokio::task::yield_now().await;
+        assert!(abort.is_finished(), "remaining tasks must be aborted");
+    }
+
+    #[tokio::test]
+    async fn test_task_exit_resolves_supervisor_without_shutdown() {
+        let finished = tokio::spawn(async {});
+        let pending = tokio::spawn(std::future::pending::<()>());
+        let abort = pending.abort_handle();
+
+        let shutdown = supervise(
+            vec![("finished".to_string(), finished), ("pending".to_string(), pending)],
+            std::future::pending::<()>(),
+        ).await;
+
+        assert!(!shutdown);
+        tokio::task::yield_now().await;
+        assert!(abort.is_finished());
+    }
+
+    // ================================================================
+    // Private WebSocket: execution / order events
+    // ================================================================
+
+    fn execution_event(settle_type: &str, side: &str, size: &str, order_size: &str, executed: &str) -> ws_private::ExecutionEvent {
+        let json = format!(
+            r#"{{"channel":"executionEvents","orderId":42,"executionId":1,"symbol":"BTC_JPY","settleType":"{}","executionType":"LIMIT","side":"{}","executionPrice":"10000000","executionSize":"{}","positionId":7,"orderTimestamp":"2024-01-15T10:30:00.000Z","executionTimestamp":"2024-01-15T10:30:01.000Z","lossGain":"0","fee":"0","orderPrice":"10000000","orderSize":"{}","orderExecutedSize":"{}","timeInForce":"SOK","msgType":"ER"}}"#,
+            settle_type, side, size, order_size, executed
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn private_test_order(side: OrderSide, is_close: bool) -> model::OrderInfo {
+        model::OrderInfo {
+            price: 10_000_000,
+            size: 0.001,
+            side,
+            timestamp: 1705314600000,
+            is_close,
+            mid_price: 10_000_050.0,
+            t_optimal_ms: 5000,
+            sigma_1s: 0.0001,
+            spread_pct: 0.00005,
+            level: 5,
+            level_key: (!is_close).then(|| FloatingExp::new(10.0, -5.0, 5.0)),
+            p_fill: 0.1,
+            best_ev: 0.0,
+            single_leg_ev: 0.0,
+            client_id: "test-client-id".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_private_fill_updates_orders_position_and_outcome() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        orders.lock().insert("42".to_string(), private_test_order(OrderSide::BUY, false));
+        let position: Positions = RwLock::new(Position::default());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let event = execution_event("OPEN", "BUY", "0.001", "0.001", "0.001");
+        handle_execution_event(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), &event);
+
+        assert!(orders.lock().is_empty(), "fully filled order must be removed");
+        let pos = *position.read();
+        assert_eq!(pos.long_size, 0.001);
+        assert_eq!(pos.long_open_price, 10_000_000.0);
+        assert!(pos.long_open_time.is_some());
+
+        let outcome = rx.try_recv().unwrap();
+        assert!(outcome.filled);
+        assert_eq!(outcome.level, Some(FloatingExp::new(10.0, -5.0, 5.0)));
+        assert!(rx.try_recv().is_err(), "exactly one outcome per order");
+    }
+
+    fn private_route() -> (PrivateRoute, tokio::sync::mpsc::UnboundedReceiver<OrderOutcome>) {
+        let (outcome_tx, outcome_rx) = tokio::sync::mpsc::unbounded_channel();
+        let route = PrivateRoute {
+            orders: Arc::new(Mutex::new(model::OrderMap::new())),
+            position: Arc::new(RwLock::new(Position::default())),
+            trade_logger: None,
+            outcome_tx,
+            fill_guard: Arc::new(FillGuard::new(0.0)),
+            pnl: Arc::new(RwLock::new(model::PnlTracker::new())),
+        };
+        (route, outcome_rx)
+    }
+
+    #[test]
+    fn test_private_events_route_to_their_symbol() {
+        let (btc, mut btc_rx) = private_route();
+        let (eth, mut eth_rx) = private_route();
+        btc.orders.lock().insert("42".to_string(), private_test_order(OrderSide::BUY, false));
+        eth.orders.lock().insert("42".to_string(), private_test_order(OrderSide::BUY, false));
+        let routes: PrivateRoutes = HashMap::from([(Symbol::BTC_JPY, btc.clone()), (Symbol::ETH_JPY, eth.clone())]);
+
+        let fill = |symbol: &str| format!(
+            r#"{{"channel":"executionEvents","orderId":42,"executionId":1,"symbol":"{}","settleType":"OPEN","executionType":"LIMIT","side":"BUY","executionPrice":"10000000","executionSize":"0.001","positionId":7,"orderTimestamp":"2024-01-15T10:30:00.000Z","executionTimestamp":"2024-01-15T10:30:01.000Z","lossGain":"0","fee":"0","orderPrice":"10000000","orderSize":"0.001","orderExecutedSize":"0.001","timeInForce":"SOK","msgType":"ER"}}"#,
+            symbol
+        );
+        route_private_message(&routes, &fill("ETH_JPY"));
+        // A symbol this process does not trade touches nothing
+        route_private_message(&routes, &fill("XRP_JPY"));
+
+        assert_eq!(eth.position.read().long_size, 0.001);
+        assert!(eth.orders.lock().is_empty());
+        assert!(eth_rx.try_recv().unwrap().filled);
+
+        assert_eq!(btc.position.read().long_size, 0.0, "same order id, other symbol");
+        assert_eq!(btc.orders.lock().len(), 1);
+        assert!(btc_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_private_fills_feed_realized_pnl() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let position: Positions = RwLock::new(Position::default());
+        let pnl = RwLock::new(model::PnlTracker::new());
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let guard = FillGuard::new(0.0);
+
+        handle_execution_event(&orders, &position, &None, &tx, &guard, &pnl, &execution_event("OPEN", "SELL", "0.001", "0.001", "0.001"));
+        handle_execution_event(&orders, &position, &None, &tx, &guard, &pnl, &execution_event("CLOSE", "BUY", "0.001", "0.001", "0.001"));
+
+        // Same price both legs: one round trip, zero PnL
+        assert_eq!(pnl.read().round_trips, 1);
+        assert_eq!(pnl.read().realized_pnl, 0.0);
+    }
+
+    #[test]
+    fn test_private_partial_fill_keeps_order() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        orders.lock().insert("42".to_string(), private_test_order(OrderSide::SELL, false));
+        let position: Positions = RwLock::new(Position::default());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let event = execution_event("OPEN", "SELL", "0.001", "0.002", "0.001");
+        handle_execution_event(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), &event);
+
+        assert_eq!(orders.lock().len(), 1);
+        assert_eq!(position.read().short_size, 0.001);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_private_close_fill_reduces_position() {
+        let mut pos = Position {
+            long_size: 0.002,
+            long_open_price: 10_000_000.0,
+            long_open_time: Some(std::time::Instant::now()),
+            ..Position::default()
+        };
+        apply_execution(&mut pos, &execution_event("CLOSE", "SELL", "0.001", "0.001", "0.001"));
+        assert_eq!(pos.long_size, 0.001);
+        assert!(pos.long_open_time.is_some());
+
+        apply_execution(&mut pos, &execution_event("CLOSE", "SELL", "0.001", "0.001", "0.001"));
+        assert_eq!(pos.long_size, 0.0);
+        assert_eq!(pos.long_open_price, 0.0);
+        assert!(pos.long_open_time.is_none());
+    }
+
+    #[test]
+    fn test_hold_ms_across_open_and_return_to_flat() {
+        let mut pos = Position::default();
+        let t0 = std::time::Instant::now();
+        assert_eq!(position_hold_ms(pos.long_open_time, t0), 0, "flat has no hold time");
+
+        apply_execution(&mut pos, &execution_event("OPEN", "BUY", "0.001", "0.001", "0.001"));
+        let opened = pos.long_open_time.unwrap();
+        assert_eq!(position_hold_ms(pos.long_open_time, opened + Duration::from_millis(1500)), 1500);
+        assert_eq!(position_hold_ms(pos.short_open_time, opened + Duration::from_millis(1500)), 0);
+
+        // Adding to the position keeps the original entry time
+        apply_execution(&mut pos, &execution_event("OPEN", "BUY", "0.001", "0.001", "0.001"));
+        assert_eq!(pos.long_open_time, Some(opened));
+
+        apply_execution(&mut pos, &execution_event("CLOSE", "SELL", "0.002", "0.002", "0.002"));
+        assert_eq!(position_hold_ms(pos.long_open_time, opened + Duration::from_millis(3000)), 0,
+            "hold time resets on return to flat");
+    }
+
+    #[test]
+    fn test_private_order_cancel_event_records_unfilled_once() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        orders.lock().insert("42".to_string(), private_test_order(OrderSide::BUY, false));
+        let position: Positions = RwLock::new(Position::default());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let msg = r#"{"channel":"orderEvents","orderId":42,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"BUY","orderStatus":"CANCELED","cancelType":"USER","orderTimestamp":"2024-01-15T10:30:00.000Z","orderPrice":"10000000","orderSize":"0.001","orderExecutedSize":"0","losscutPrice":"0","timeInForce":"SOK","msgType":"COR"}"#;
+        handle_private_message(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), msg);
+        handle_private_message(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), msg);
+
+        assert!(orders.lock().is_empty());
+        assert!(!rx.try_recv().unwrap().filled);
+        assert!(rx.try_recv().is_err(), "duplicate event must not double-count");
+    }
+
+    #[test]
+    fn test_lost_response_order_is_claimed_by_its_order_event() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let now = Utc::now().timestamp_millis() as u64;
+        let lost = model::OrderInfo { timestamp: now, ..private_test_order(OrderSide::BUY, false) };
+        let other = model::OrderInfo { timestamp: now, client_id: "other".to_string(), ..private_test_order(OrderSide::SELL, false) };
+        orders.lock().add_unconfirmed(lost);
+        orders.lock().add_unconfirmed(other);
+        let position: Positions = RwLock::new(Position::default());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let event = |status: &str| format!(
+            r#"{{"channel":"orderEvents","orderId":77,"symbol":"BTC_JPY","settleType":"OPEN","executionType":"LIMIT","side":"BUY","orderStatus":"{}","orderTimestamp":"2024-01-15T10:30:00.000Z","orderPrice":"10000000","orderSize":"0.001","orderExecutedSize":"0","losscutPrice":"0","timeInForce":"SOK","msgType":"NOR"}}"#,
+            status
+        );
+
+        // The exchange reports an id we never got back: matched by side / price / size / time
+        handle_private_message(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), &event("ORDERED"));
+        {
+            let orders = orders.lock();
+            assert_eq!(orders.get("77").unwrap().client_id, "test-client-id");
+            assert_eq!(orders.unconfirmed_len(), 1, "the SELL stays unconfirmed");
+            assert_eq!(orders.pending_open_size(&OrderSide::BUY), 0.001);
+        }
+        assert!(rx.try_recv().is_err(), "adoption alone is no outcome");
+
+        // From then on it is an ordinary tracked order
+        handle_private_message(&orders, &position, &None, &tx, &FillGuard::new(0.0), &RwLock::new(model::PnlTracker::new()), &event("CANCELED"));
+        assert!(orders.lock().get("77").is_none());
+        assert!(!rx.try_recv().unwrap().filled);
+    }
+
+    #[test]
+    fn test_response_lost_only_when_the_order_may_be_live() {
+        let unreadable = serde_json::from_str::<u64>("{").unwrap_err();
+        assert!(response_lost(&ApiResponseError::Deserialize(unreadable)));
+        assert!(response_lost(&ApiResponseError::StatusCode(reqwest::StatusCode::BAD_GATEWAY)));
+        assert!(!response_lost(&ApiResponseError::StatusCode(reqwest::StatusCode::TOO_MANY_REQUESTS)));
+        assert!(!response_lost(&ApiResponseError::ApiError(Vec::new())), "an API rejection placed nothing");
+    }
+
+    // ================================================================
+    // WebSocket keepalive (ping / pong)
+    // ================================================================
+
+    type TestWsSink = futures::sink::SinkMapErr<
+        futures::channel::mpsc::UnboundedSender<Message>,
+        fn(futures::channel::mpsc::SendError) -> tokio_tungstenite::tungstenite::Error,
+    >;
+
+    fn test_ws_sink() -> (TestWsSink, futures::channel::mpsc::UnboundedReceiver<Message>) {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Message>();
+        let map_err: fn(futures::channel::mpsc::SendError) -> tokio_tungstenite::tungstenite::Error =
+            |_| tokio_tungstenite::tungstenite::Error::ConnectionClosed;
+        (tx.sink_map_err(map_err), rx)
+    }
+
+    fn count_pings(rx: &mut futures::channel::mpsc::UnboundedReceiver<Message>) -> usize {
+        let mut pings = 0;
+        while let Ok(msg) = rx.try_recv() {
+            if matches!(msg, Message::Ping(_)) {
+                pings += 1;
+            }
+        }
+        pings
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ws_ping_fires_at_configured_cadence() {
+        let (read_tx, mut read_rx) = futures::channel::mpsc::unbounded::<Result<Message>>();
+        let (mut write, mut sent) = test_ws_sink();
+
+        let task = tokio::spawn(async move {
+            ws_read_loop(&mut read_rx, &mut write, Duration::from_secs(30), |_| async {}).await
+        });
+
+        sleep(Duration::from_secs(29)).await;
+        assert_eq!(count_pings(&mut sent), 0, "no ping before the first interval");
+
+        sleep(Duration::from_secs(2)).await; // t=31s
+        assert_eq!(count_pings(&mut sent), 1);
+
+        sleep(Duration::from_secs(60)).await; // t=91s
+        assert_eq!(count_pings(&mut sent), 2, "one ping per 30s");
+
+        task.abort();
+        drop(read_tx);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_ws_server_ping_answered_with_pong() {
+        let (read_tx, mut read_rx) = futures::channel::mpsc::unbounded::<Result<Message>>();
+        let (mut write, mut sent) = test_ws_sink();
+        let texts = Arc::new(Mutex::new(Vec::new()));
+        let texts_ref = texts.clone();
+
+        read_tx.unbounded_send(Ok(Message::Ping(vec![7]))).unwrap();
+        read_tx.unbounded_send(Ok(Message::Text("hello".to_string()))).unwrap();
+        drop(read_tx); // stream ends -> loop returns Ok
+
+        let result = ws_read_loop(&mut read_rx, &mut write, Duration::from_secs(30), |msg| {
+            let texts = texts_ref.clone();
+            async move { texts.lock().push(msg) }
+        }).await;
+
+        assert!(result.is_ok());
+        assert_eq!(sent.try_recv().unwrap(), Message::Pong(vec![7]));
+        assert_eq!(*texts.lock(), vec!["hello".to_string()]);
+    }
+
+    // ================================================================
+    // Collateral floors: soft gate vs emergency flatten
+    // ================================================================
+
+    #[test]
+    fn test_hard_floor_triggers_flatten() {
+        // soft 100k, hard 50k
+        assert_eq!(collateral_action(49_999.0, 100_000.0, 50_000.0), CollateralAction::Flatten);
+        assert_eq!(collateral_action(0.0, 100_000.0, 50_000.0), CollateralAction::Flatten);
+    }
+
+    #[test]
+    fn test_soft_floor_only_gates_opens() {
+        assert_eq!(collateral_action(80_000.0, 100_000.0, 50_000.0), CollateralAction::GateOpens);
+        assert_eq!(collateral_action(50_000.0, 100_000.0, 50_000.0), CollateralAction::GateOpens);
+        assert_eq!(collateral_action(100_000.0, 100_000.0, 50_000.0), CollateralAction::Normal);
+    }
+
+    #[test]
+    fn test_collateral_floors_disabled_by_default() {
+        assert_eq!(collateral_action(1.0, 0.0, 0.0), CollateralAction::Normal);
+        // Hard floor alone still works without a soft floor
+        assert_eq!(collateral_action(1.0, 0.0, 10.0), CollateralAction::Flatten);
+        assert_eq!(collateral_action(20.0, 0.0, 10.0), CollateralAction::Normal);
+    }
+
+    // ================================================================
+    // Self-crossed quote guard
+    // ================================================================
+
+    #[test]
+    fn test_is_self_crossed_boundaries() {
+        assert!(!is_self_crossed(Price::from_f64(9_999_000.0), Price::from_f64(10_001_000.0)));
+        assert!(!is_self_crossed(Price::from_f64(9_999_999.0), Price::from_f64(10_000_000.0)));
+        // Locked (equal) counts as crossed: buy must be strictly below sell
+        assert!(is_self_crossed(Price::from_f64(10_000_000.0), Price::from_f64(10_000_000.0)));
+        assert!(is_self_crossed(Price::from_f64(10_000_500.0), Price::from_f64(10_000_000.0)));
+    }
+
+    #[test]
+    fn test_sub_yen_quotes_stay_apart_on_low_priced_symbol() {
+        // XRP-like prices: a sub-yen spread used to collapse when both quotes were cast to u64
+        let registry = SymbolRegistry::new();
+        let xrp = registry.get("XRP_JPY").unwrap();
+        let mid_price = 85.5;
+        let best_pair = (
+            FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 },
+            FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 },
+        );
+        let (buy, sell) = calculate_order_prices(mid_price, &best_pair, &Position::new(), 0.0, 10.0);
+        assert!(buy < sell, "float prices are not crossed: {} {}", buy, sell);
+
+        let buy_price = Price::from_f64(round_to_tick(buy, xrp.tick_size, &OrderSide::BUY));
+        let sell_price = Price::from_f64(round_to_tick(sell, xrp.tick_size, &OrderSide::SELL));
+        assert!(!is_self_crossed(buy_price, sell_price), "{} {}", buy_price, sell_price);
+        assert!(buy_price.is_multiple_of(xrp.tick_size) && sell_price.is_multiple_of(xrp.tick_size));
+        assert!(buy_price.to_f64() < 85.5 && sell_price.to_f64() > 85.5, "{} {}", buy_price, sell_price);
+        assert_eq!(Price::from_f64(85.123).to_string(), "85.123", "the order string keeps the sub-yen part");
+    }
+
+    #[test]
+    fn test_maintenance_errors_pause_until_a_probe_succeeds() {
+        let min = Duration::from_secs(MAINTENANCE_PROBE_MIN_SECS);
+        let t0 = Instant::now();
+        let mut pause = MaintenancePause::new();
+
+        // A success in between resets the run of errors
+        assert!(!pause.record_error(3, t0));
+        assert!(!pause.record_error(3, t0));
+        assert!(!pause.record_success());
+        assert!(!pause.record_error(3, t0));
+        assert!(!pause.record_error(3, t0));
+        assert!(!pause.is_paused());
+
+        // Third consecutive error trips the pause; the first probe waits the minimum backoff
+        assert!(pause.record_error(3, t0));
+        assert!(pause.is_paused());
+        assert!(!pause.probe_due(t0));
+        assert!(pause.probe_due(t0 + min));
+
+        // Failed probes double the wait, up to the max
+        assert!(!pause.record_error(3, t0 + min));
+        assert!(!pause.probe_due(t0 + min * 2));
+        assert!(pause.probe_due(t0 + min * 3));
+        let mut now = t0 + min * 3;
+        for _ in 0..20 {
+            pause.record_error(3, now);
+        }
+        assert!(pause.probe_due(now + Duration::from_secs(MAINTENANCE_PROBE_MAX_SECS)));
+        assert!(pause.is_paused());
+
+        // A successful probe resumes trading and the next pause needs a full run of errors again
+        now += Duration::from_secs(MAINTENANCE_PROBE_MAX_SECS);
+        assert!(pause.record_success());
+        assert!(!pause.is_paused() && !pause.probe_due(now));
+        assert!(!pause.record_error(3, now));
+        assert!(!pause.is_paused());
+
+        // 0 = never pause
+        let mut never = MaintenancePause::new();
+        for _ in 0..10 {
+            assert!(!never.record_error(0, t0));
+        }
+        assert!(!never.is_paused());
+    }
+
+    #[test]
+    fn test_gmo_maintenance_error_maps_to_exchange_error() {
+        let maintenance = ApiResponseError::ApiError(vec![gmo::api::ApiErrorMessage {
+            message_code: "ERR-5201".to_string(),
+            message_string: "MAINTENANCE. Please wait for a while".to_string(),
+        }]);
+        assert_eq!(ExchangeError::from(maintenance), ExchangeError::Maintenance);
+        let other = ApiResponseError::StatusCode(reqwest::StatusCode::BAD_GATEWAY);
+        assert!(matches!(ExchangeError::from(other), ExchangeError::Other(_)));
+    }
+
+    #[test]
+    fn test_warmup_holds_orders_until_it_ends() {
+        let started = Instant::now();
+        let mut warmup = Warmup::new(started, Duration::from_millis(30_000));
+        assert!(warmup.holds_orders(started));
+        assert!(warmup.holds_orders(started + Duration::from_millis(29_999)));
+        // Orders resume once it has run out, and stay resumed
+        assert!(!warmup.holds_orders(started + Duration::from_millis(30_000)));
+        assert!(!warmup.holds_orders(started));
+
+        let mut off = Warmup::new(started, Duration::ZERO);
+        assert!(!off.holds_orders(started));
+    }
+
+    #[test]
+    fn test_warmup_sends_no_order_until_it_ends() {
+        let started = Instant::now();
+        let mut warmup = Warmup::new(started, Duration::from_millis(30_000));
+        // Both sides due with closes / opens to send: still nothing goes out
+        let (due, wanted) = ((true, true), (true, true));
+        assert_eq!(quote_sides(warmup.holds_orders(started + Duration::from_millis(1_000)), due, wanted), (false, false));
+        assert_eq!(quote_sides(warmup.holds_orders(started + Duration::from_millis(30_000)), due, wanted), (true, true));
+
+        // Past warmup the per-side cadence and wants still gate each side
+        assert_eq!(quote_sides(false, (true, false), (true, true)), (true, false));
+        assert_eq!(quote_sides(false, (true, true), (false, true)), (false, true));
+    }
+
+    #[test]
+    fn test_round_to_tick_rounds_away_from_the_touch() {
+        // Buys round down, sells round up, both onto a multiple of the tick
+        for (price, tick, buy, sell) in [
+            (10_000_123.4, 1.0, 10_000_123.0, 10_000_124.0),
+            (10_000_123.0, 5.0, 10_000_120.0, 10_000_125.0),
+            (85.1234, 0.001, 85.123, 85.124),
+            (10_000_120.0, 5.0, 10_000_120.0, 10_000_120.0),
+        ] {
+            let rounded_buy = round_to_tick(price, tick, &OrderSide::BUY);
+            let rounded_sell = round_to_tick(price, tick, &OrderSide::SELL);
+            assert_eq!((rounded_buy, rounded_sell), (buy, sell), "price={} tick={}", price, tick);
+            let rule = SymbolRule { symbol: "T".to_string(), tick_size: tick, size_step: 0.0001, min_lot: 0.0001, max_lot: 1.0 };
+            assert!(rule.is_valid_price(rounded_buy) && rule.is_valid_price(rounded_sell));
+            assert!(rounded_buy <= price && rounded_sell >= price);
+        }
+        // Float noise just under a tick is not a sub-tick price
+        assert_eq!(round_to_tick(10_000_000.0 - 2e-9, 1.0, &OrderSide::SELL), 10_000_000.0);
+    }
+
+    #[test]
+    fn test_passive_quotes_hold_against_adversarial_inputs() {
+        // 1 JPY book; a large negative penalty drags the buy through the ask (long) or the sell
+        // through the bid (short), and a negative flip adjustment pulls further in
+        let (best_bid, best_ask) = (9_999_999.0, 10_000_000.0);
+        let mid_price = (best_bid + best_ask) / 2.0;
+        let pair = (FloatingExp { base: 10.0, exp: -5.0, rate: 1.0 }, FloatingExp { base: 10.0, exp: -5.0, rate: 1.0 });
+        let long = Position { long_size: 0.01, ..Position::new() };
+        let short = Position { short_size: 0.01, ..Position::new() };
+        let (crossed_buy, _) = calculate_order_prices(mid_price, &pair, &long, -1_000.0, 0.001);
+        let (_, crossed_sell) = calculate_order_prices(mid_price, &pair, &short, -1_000.0, 0.001);
+        assert!(crossed_buy > best_ask && crossed_sell < best_bid, "inputs really cross: {} {}", crossed_buy, crossed_sell);
+
+        for (buy, sell) in [
+            (crossed_buy, crossed_sell),
+            (best_bid + 3.0, best_ask - 3.0),
+            (best_ask, best_bid),
+            (f64::NAN, f64::NAN),
+        ] {
+            for (offset, tick) in [(0.0, 1.0), (2.0, 1.0), (0.0, 5.0), (2.5, 0.001)] {
+                let (guarded_buy, guarded_sell) = passive_quotes(buy, sell, best_bid, best_ask, offset);
+                let buy_price = round_to_tick(guarded_buy, tick, &OrderSide::BUY);
+                let sell_price = round_to_tick(guarded_sell, tick, &OrderSide::SELL);
+                assert!(buy_price <= best_bid - offset, "buy {} offset {} tick {}", buy_price, offset, tick);
+                assert!(sell_price >= best_ask + offset, "sell {} offset {} tick {}", sell_price, offset, tick);
+                assert!(buy_price < sell_price);
+            }
+        }
+
+        // Already passive quotes are left alone
+        assert_eq!(passive_quotes(9_999_900.0, 10_000_100.0, best_bid, best_ask, 2.0), (9_999_900.0, 10_000_100.0));
+    }
+
+    #[test]
+    fn test_collateral_max_position_follows_collateral_within_clamps() {
+        let (pct, price, floor, ceiling) = (0.5, 10_000_000.0, 0.001, 0.01);
+        let cap = |collateral| collateral_max_position(collateral, pct, price, floor, ceiling);
+        // 100k JPY * 0.5 / 10M = 0.005 BTC; doubling collateral doubles the cap
+        assert_eq!(cap(100_000.0), Some(0.005));
+        assert_eq!(cap(150_000.0), Some(0.0075));
+        // Clamped to the ceiling on the way up and to the floor on the way down
+        assert_eq!(cap(1_000_000.0), Some(ceiling));
+        assert_eq!(cap(10_000.0), Some(floor));
+        assert_eq!(cap(-50_000.0), Some(floor));
+        // Off, or no price yet: the fixed max_position stays in force
+        assert_eq!(collateral_max_position(100_000.0, 0.0, price, floor, ceiling), None);
+        assert_eq!(collateral_max_position(100_000.0, pct, 0.0, floor, ceiling), None);
+
+        // The scaled cap gates opens the same way max_position does
+        let pos = Position { long_size: 0.004, ..Position::new() };
+        let (buy_size, _) = calculate_order_sizes(&pos, cap(100_000.0).unwrap(), 0.001, 0.001, 1.0);
+        assert!(buy_size > 0.0);
+        let (buy_size, _) = calculate_order_sizes(&pos, cap(10_000.0).unwrap(), 0.001, 0.001, 1.0);
+        assert_eq!(buy_size, 0.0);
+    }
+
+    #[test]
+    fn test_self_crossed_from_position_penalty_with_close() {
+        // Hedged book with a heavy short leg: the penalty lifts the raw buy quote above mid,
+        // while closing the small long leg quotes the sell at mid + 1 -> buy above own sell
+        let mid_price = 10_000_000.0;
+        let best_pair = (
+            FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 },
+            FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 },
+        );
+        let short_heavy = Position { long_size: 0.001, short_size: 0.05, ..Default::default() };
+        let (buy, _) = calculate_order_prices(mid_price, &best_pair, &short_heavy, 50.0, 0.001);
+        let close_sell = mid_price + 1.0;
+        assert!(buy > mid_price);
+        assert!(is_self_crossed(Price::from_f64(buy), Price::from_f64(close_sell)));
+    }
+
+    // ================================================================
+    // Position drift
+    // ================================================================
+
+    #[test]
+    fn test_position_drift_identical_is_zero() {
+        let pos = Position { long_size: 0.003, short_size: 0.001, ..Default::default() };
+        assert_eq!(position_drift(&pos, &pos), 0.0);
+    }
+
+    #[test]
+    fn test_position_drift_takes_largest_leg() {
+        let local = Position { long_size: 0.003, short_size: 0.001, ..Default::default() };
+        let remote = Position { long_size: 0.002, short_size: 0.004, ..Default::default() };
+        assert_eq!(position_drift(&local, &remote), 0.003);
+        assert_eq!(position_drift(&remote, &local), 0.003);
+    }
+
+    #[test]
+    fn test_position_drift_missed_fill_vs_tolerance() {
+        // Exchange filled a min_lot we never saw locally
+        let local = Position::new();
+        let remote = Position { long_size: 0.001, ..Default::default() };
+        let drift = position_drift(&local, &remote);
+        assert_eq!(drift, 0.001);
+        assert!(drift > 0.0005);
+        assert!(drift <= 0.001);
+    }
+
+    // ================================================================
+    // Ghost frequency escalation
+    // ================================================================
+
+    #[test]
+    fn test_ghost_tracker_escalates_at_threshold_within_window() {
+        let start = Instant::now();
+        let mut tracker = GhostTracker::new(Duration::from_secs(600), 3);
+        assert!(!tracker.record(start));
+        assert!(!tracker.record(start + Duration::from_secs(60)));
+        assert!(tracker.record(start + Duration::from_secs(120)));
+        assert_eq!(tracker.count(), 3);
+    }
+
+    #[test]
+    fn test_ghost_tracker_forgets_hits_outside_window() {
+        let start = Instant::now();
+        let mut tracker = GhostTracker::new(Duration::from_secs(600), 3);
+        assert!(!tracker.record(start));
+        assert!(!tracker.record(start + Duration::from_secs(300)));
+        // First hit has aged out: only 2 in the window
+        assert!(!tracker.record(start + Duration::from_secs(700)));
+        assert_eq!(tracker.count(), 2);
+        assert!(tracker.record(start + Duration::from_secs(800)));
+    }
+
+    #[test]
+    fn test_record_ghost_enters_safe_mode_at_threshold() {
+        let config = symbol_test_config();
+        let alerts = AlertSink::disabled();
+        let mut tracker = GhostTracker::new(Duration::from_secs(600), 2);
+        let mut safe_mode = None;
+        record_ghost(&mut tracker, &mut safe_mode, &alerts, &config);
+        assert_eq!(safe_mode, None);
+        record_ghost(&mut tracker, &mut safe_mode, &alerts, &config);
+        assert_eq!(safe_mode, Some("repeated ghost positions"));
+    }
+
+    #[test]
+    fn test_ghost_tracker_zero_threshold_never_escalates() {
+        let start = Instant::now();
+        let mut tracker = GhostTracker::new(Duration::from_secs(600), 0);
+        for i in 0..10 {
+            assert!(!tracker.record(start + Duration::from_secs(i)));
+        }
+    }
+
+    // ================================================================
+    // JPY-offset levels
+    // ================================================================
+
+    #[test]
+    fn test_jpy_offset_levels_map_to_expected_prices() {
+        let mid_price = 14_000_000.0;
+        let levels = jpy_offset_levels(&[300, 700], mid_price);
+        let best_pair = (levels[0].clone(), levels[1].clone());
+        let (buy, sell) = calculate_order_prices(mid_price, &best_pair, &Position::new(), 50.0, 0.001);
+        assert!((buy - 13_999_700.0).abs() < 1e-6, "buy={}", buy);
+        assert!((sell - 14_000_700.0).abs() < 1e-6, "sell={}", sell);
+        // Logged level is the JPY offset itself
+        assert_eq!(best_pair.0.rate as u32, 300);
+        assert_eq!(best_pair.1.rate as u32, 700);
+    }
+
+    #[test]
+    fn test_jpy_offset_levels_scale_with_mid() {
+        // Keys stay fixed after startup; a 1% mid move shifts the JPY distance by 1%
+        let levels = jpy_offset_levels(&[1000], 10_000_000.0);
+        let moved_mid = 10_100_000.0;
+        let offset = moved_mid * levels[0].calc();
+        assert!((offset - 1010.0).abs() < 1e-6, "offset={}", offset);
+    }
+
+    #[test]
+    fn test_jpy_offset_outcome_updates_its_own_posterior() {
+        let prior = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+        let levels = jpy_offset_levels(&[300, 700], 14_000_000.0);
+        let table = || levels.iter().map(|k| (k.clone(), (0.0, prior.clone()))).collect::<BTreeMap<_, _>>();
+        let (mut buy, mut sell) = (table(), table());
+
+        // The outcome carries the exact quoted key, not one rebuilt from price_step_*
+        let info = model::OrderInfo {
+            level_key: Some(levels[1].clone()),
+            ..private_test_order(OrderSide::BUY, false)
+        };
+        let outcome = info.outcome(true, 1_500);
+        let key = outcome.level.unwrap();
+        assert_eq!((key.base, key.exp), (14_000_000.0, -1.0));
+        assert!(update_level_posterior(&mut buy, &mut sell, &outcome.side, &key, outcome.filled));
+
+        assert!(buy[&levels[1]].1.calc_average() > prior.calc_average());
+        assert_eq!(buy[&levels[0]].1.calc_average(), prior.calc_average());
+        assert_eq!(sell[&levels[1]].1.calc_average(), prior.calc_average());
+    }
+
+    #[test]
+    fn test_percent_levels_match_previous_grid() {
+        let levels = crate::strategy::percent_levels(4, 25);
+        assert_eq!(levels.len(), 22);
+        assert_eq!(levels[0], FloatingExp { base: 10.0, exp: -5.0, rate: 4.0 });
+        assert_eq!(levels[21].rate, 25.0);
+        assert_eq!(config_step_levels(&symbol_test_config()), levels);
+    }
+
+    #[test]
+    fn test_config_step_levels_span_configured_range() {
+        let mut config = symbol_test_config();
+        config.price_step_start = 2;
+        config.price_step_end = 6;
+        config.price_step_base = 10.0;
+        config.price_step_exp = -4.0;
+
+        let levels = config_step_levels(&config);
+        let rates: Vec<f64> = levels.iter().map(|k| k.rate).collect();
+        assert_eq!(rates, vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+        for k in &levels {
+            assert!((k.calc() - k.rate * 1e-4).abs() < 1e-15, "{:?}", k);
+        }
+
+        config.price_step_end = 2;
+        assert_eq!(config_step_levels(&config).len(), 1);
+
+        // An explicit list replaces the grid as-is
+        let explicit = vec![FloatingExp::new(10.0, -5.0, 7.0), FloatingExp::new(10.0, -5.0, 12.0)];
+        config.levels = Some(explicit.clone());
+        assert_eq!(config_step_levels(&config), explicit);
+    }
+
+    // ================================================================
+    // Exchange status spread multiplier
+    // ================================================================
+
+    #[test]
+    fn test_status_spread_multiplier_per_status() {
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Open, 2.0), Some(1.0));
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Preopen, 2.0), Some(2.0));
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Unknown, 2.0), Some(2.0));
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Maintenance, 2.0), None);
+    }
+
+    #[test]
+    fn test_status_spread_multiplier_never_narrows() {
+        // A misconfigured multiplier below 1.0 must not tighten quotes in a degraded state
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Preopen, 0.5), Some(1.0));
+        assert_eq!(status_spread_multiplier(ExchangeStatus::Open, 0.5), Some(1.0));
+    }
+
+    // ================================================================
+    // Implausible fill halt
+    // ================================================================
+
+    #[test]
+    fn test_implausible_fill_band() {
+        let mid = 10_000_000.0;
+        // 50 bps band = 50,000 JPY at 10M
+        assert!(!implausible_fill(mid + 49_999.0, mid, 50.0));
+        assert!(!implausible_fill(mid - 50_000.0, mid, 50.0), "boundary is still plausible");
+        assert!(implausible_fill(mid + 50_001.0, mid, 50.0));
+        assert!(implausible_fill(mid - 60_000.0, mid, 50.0));
+    }
+
+    #[test]
+    fn test_implausible_fill_disabled_or_unknown_mid() {
+        assert!(!implausible_fill(1.0, 10_000_000.0, 0.0), "band 0 disables the check");
+        assert!(!implausible_fill(10_000_000.0, 0.0, 50.0), "no recorded mid, nothing to compare");
+    }
+
+    #[test]
+    fn test_fill_guard_keeps_sub_yen_mid() {
+        // 80.45 vs 80.4 is ~6 bps; against a mid truncated to 80 it would be ~56 bps
+        let guard = FillGuard::new(50.0);
+        guard.check("1", 80.45, 80.4);
+        assert!(guard.halt_reason().is_none());
+    }
+
+    #[test]
+    fn test_private_implausible_fill_sets_halt() {
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let mut order = private_test_order(OrderSide::BUY, false);
+        order.mid_price = 11_000_000.0; // fill at 10M is ~909 bps away
+        orders.lock().insert("42".to_string(), order);
+        let position: Positions = RwLock::new(Position::default());
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+        let guard = FillGuard::new(50.0);
+
+        // Partial fill is enough to halt
+        handle_execution_event(&orders, &position, &None, &tx, &guard, &RwLock::new(model::PnlTracker::new()), &execution_event("OPEN", "BUY", "0.001", "0.002", "0.001"));
+        assert!(guard.halt_reason().is_some());
+
+        let calm = FillGuard::new(50.0);
+        orders.lock().insert("42".to_string(), private_test_order(OrderSide::BUY, false));
+        handle_execution_event(&orders, &position, &None, &tx, &calm, &RwLock::new(model::PnlTracker::new()), &execution_event("OPEN", "BUY", "0.001", "0.001", "0.001"));
+        assert!(calm.halt_reason().is_none());
+    }
+
+    // ================================================================
+    // Close ladder
+    // ================================================================
+
+    #[test]
+    fn test_close_ladder_bounded_step_progression() {
+        // 40% of the remaining distance, each step bounded to [5, 20] JPY
+        let distances: Vec<f64> = (0..7).map(|n| close_ladder_distance(100.0, n, 0.4, 5.0, 20.0)).collect();
+        // 100 -> 80 (capped at 20) -> 60 -> 40 -> 24 (16) -> 14.4 (9.6) -> 8.64 (5.76)
+        let expected = [100.0, 80.0, 60.0, 40.0, 24.0, 14.4, 8.64];
+        for (n, (d, e)) in distances.iter().zip(expected).enumerate() {
+            assert!((d - e).abs() < 1e-9, "attempt {}: {} != {}", n, d, e);
+        }
+        for pair in distances.windows(2) {
+            let step = pair[0] - pair[1];
+            assert!((5.0 - 1e-9..=20.0 + 1e-9).contains(&step), "step {} out of bounds", step);
+        }
+    }
+
+    #[test]
+    fn test_close_ladder_never_crosses_mid() {
+        // Large min step would overshoot: distance floors at 1 JPY and stays there
+        assert_eq!(close_ladder_distance(12.0, 1, 0.1, 30.0, 50.0), 1.0);
+        assert_eq!(close_ladder_distance(12.0, 50, 0.5, 5.0, 50.0), 1.0);
+        assert_eq!(close_ladder_distance(0.5, 3, 0.5, 5.0, 50.0), 0.5, "already inside the floor: untouched");
+        // Min step applies once the proportional step gets small
+        assert_eq!(close_ladder_distance(10.0, 1, 0.1, 5.0, 50.0), 5.0);
+    }
+
+    #[test]
+    fn test_close_ladder_disabled() {
+        assert_eq!(close_ladder_distance(100.0, 10, 0.0, 5.0, 20.0), 100.0);
+        assert_eq!(close_ladder_distance(100.0, 0, 0.5, 5.0, 20.0), 100.0);
+    }
+
+    #[test]
+    fn test_close_factor_escalates_on_cancels_and_resets_on_fill() {
+        let mut escalation = CloseEscalation::default();
+        let factor = |e: &CloseEscalation, side: OrderSide| e.factor(&side, 0.5, 0.8, 0.2);
+        assert_eq!(factor(&escalation, OrderSide::SELL), 0.5);
+
+        // Each close cancelled for age ratchets the factor toward the floor
+        let mut previous = 0.5;
+        for cancels in 1..=4 {
+            escalation.record(&OrderSide::SELL, false);
+            let current = factor(&escalation, OrderSide::SELL);
+            assert!(current < previous, "cancel {}: {} !< {}", cancels, current, previous);
+            assert!((current - 0.5 * 0.8f64.powi(cancels)).abs() < 1e-12);
+            previous = current;
+        }
+        // ...and stops there
+        for _ in 0..20 {
+            escalation.record(&OrderSide::SELL, false);
+        }
+        assert_eq!(factor(&escalation, OrderSide::SELL), 0.2);
+        // The other close side is independent
+        assert_eq!(factor(&escalation, OrderSide::BUY), 0.5);
+
+        // A close fill resets it
+        escalation.record(&OrderSide::SELL, true);
+        assert_eq!(factor(&escalation, OrderSide::SELL), 0.5);
+
+        // Step 1 disables escalation
+        escalation.record(&OrderSide::BUY, false);
+        assert_eq!(escalation.factor(&OrderSide::BUY, 0.5, 1.0, 0.2), 0.5);
+        // A base already under the floor is left alone
+        assert_eq!(escalation.factor(&OrderSide::BUY, 0.05, 1.0, 0.1), 0.05);
+    }
+
+    // ================================================================
+    // Position limit basis (gross vs net)
+    // ================================================================
+
+    #[test]
+    fn test_hedged_position_blocked_under_gross_allowed_under_net() {
+        let max_position = 0.002;
+        let min_lot = 0.001;
+        let hedged = Position { long_size: 0.002, short_size: 0.002, ..Position::new() };
+
+        let (gross_long, gross_short) = limit_exposure(&hedged, 0.0, 0.0, LimitBasis::Gross);
+        assert!(gross_long + min_lot > max_position, "gross: long side full");
+        assert!(gross_short + min_lot > max_position, "gross: short side full");
+
+        let (net_long, net_short) = limit_exposure(&hedged, 0.0, 0.0, LimitBasis::Net);
+        assert_eq!((net_long, net_short), (0.0, 0.0));
+        assert!(net_long + min_lot <= max_position);
+
+        let sizing = Position { long_size: net_long, short_size: net_short, ..hedged };
+        let (buy_size, sell_size) = calculate_order_sizes(&sizing, max_position, min_lot, 0.001, 0.9);
+        assert_eq!((buy_size, sell_size), (0.001, 0.001), "net basis sizes as if flat");
+    }
+
+    #[test]
+    fn test_net_limit_exposure_counts_pending_and_skew() {
+        let pos = Position { long_size: 0.003, short_size: 0.001, ..Position::new() };
+        // Net long 0.002 plus a pending buy; shorts only add once they exceed the long leg
+        let (long, short) = limit_exposure(&pos, 0.001, 0.001, LimitBasis::Net);
+        assert!((long - 0.003).abs() < 1e-12, "long={}", long);
+        assert_eq!(short, 0.0);
+
+        let (long, short) = limit_exposure(&pos, 0.001, 0.001, LimitBasis::Gross);
+        assert!((long - 0.004).abs() < 1e-12 && (short - 0.002).abs() < 1e-12);
+    }
+
+    // ================================================================
+    // Dry run: SimExchange instead of the private API
+    // ================================================================
+
+    async fn dry_run_send(sim: &SimExchange, limiter: &RateLimiter, orders: &Orders, side: OrderSide, price: u64, is_close: bool) -> OrderResult {
+        let config = symbol_test_config();
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let rule = registry.get(&config.symbol.to_string()).unwrap();
+        send_order(
+            &reqwest::Client::new(), limiter, orders, side, Price::from_f64(price as f64), 0.001, is_close, &config, rule, &None,
+            10_000_500.0, 5000, 0.0001, 0.00005, Some(&FloatingExp::new(10.0, -5.0, 5.0)), 0.1, 0.0, 0.0, Some(sim),
+        ).await
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_send_order_makes_no_api_calls() {
+        let sim = SimExchange::new();
+        // Single token, effectively no refill: any private call would consume it
+        let limiter = RateLimiter::new(1.0, 0.001);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+
+        assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::BUY, 10_000_000, false).await, OrderResult::Success));
+        assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::SELL, 10_001_000, true).await, OrderResult::Success));
+
+        assert_eq!(sim.open_order_count(), 2);
+        {
+            let orders = orders.lock();
+            assert_eq!(orders.len(), 2);
+            assert!(orders.get("dry-1").is_some_and(|o| o.side == OrderSide::BUY && !o.is_close));
+            assert!(orders.get("dry-2").is_some_and(|o| o.side == OrderSide::SELL && o.is_close));
+        }
+        assert!(
+            tokio::time::timeout(Duration::from_millis(5), limiter.acquire()).await.is_ok(),
+            "dry run must not take a rate-limit token"
+        );
+    }
+
+    #[test]
+    fn test_vol_regime_boundaries_select_alpha_and_levels() {
+        let mut config = symbol_test_config();
+        assert_eq!(regime_quote_params(&config, 0.001), (None, config.alpha, (f64::NEG_INFINITY, f64::INFINITY)));
+
+        config.vol_regimes = Some(serde_yaml::from_str(
+            "low_sigma_1s: 0.00003\nhigh_sigma_1s: 0.0001\n\
+             low: { alpha: 0.3, max_spread: 0.0001 }\nmid: { alpha: 0.5 }\nhigh: { alpha: 0.9, min_spread: 0.0002 }\n",
+        ).unwrap());
+        let regimes = config.vol_regimes.clone().unwrap();
+        for (sigma_1s, expected) in [
+            (0.0, VolRegime::Low),
+            (0.0000299, VolRegime::Low),
+            (0.00003, VolRegime::Mid),
+            (0.0000999, VolRegime::Mid),
+            (0.0001, VolRegime::High),
+            (0.01, VolRegime::High),
+        ] {
+            assert_eq!(classify_regime(sigma_1s, &regimes), expected, "sigma_1s={}", sigma_1s);
+        }
+        assert_eq!(regime_quote_params(&config, 0.00001), (Some(VolRegime::Low), 0.3, (0.0, 0.0001)));
+        assert_eq!(regime_quote_params(&config, 0.00005), (Some(VolRegime::Mid), 0.5, (0.0, f64::MAX)));
+        assert_eq!(regime_quote_params(&config, 0.0002), (Some(VolRegime::High), 0.9, (0.0002, f64::MAX)));
+
+        // EV is scored with the regime's alpha over the regime's levels
+        let mid_price = 10_000_000.0;
+        let volatility = 1_000.0;
+        let probabilities: BTreeMap<FloatingExp, (f64, BayesProb)> = [5.0, 10.0, 30.0]
+            .iter()
+            .map(|&rate| (FloatingExp::new(10.0, -5.0, rate), (0.0, BayesProb::new(BetaDistribution::new(5, 5), Duration::from_secs(300)))))
+            .collect();
+        let select = |sigma_1s: f64| {
+            let (_, alpha, range) = regime_quote_params(&config, sigma_1s);
+            let best = maximize_single_leg_ev_within(
+                mid_price, volatility, alpha, &probabilities, &probabilities, range, BayesProb::calc_average,
+            ).unwrap();
+            (alpha, best)
+        };
+        let (low_alpha, low) = select(0.00001);
+        assert_eq!(low.0.rate, 10.0, "low regime caps quotes at 0.01% from mid");
+        let expected = single_leg_ev(mid_price, volatility, low_alpha, &low.0, low.1)
+            + single_leg_ev(mid_price, volatility, low_alpha, &low.2, low.3);
+        assert!((low.4 - expected).abs() < 1e-9);
+        let (high_alpha, high) = select(0.0002);
+        assert_eq!(high.0.rate, 30.0);
+        let expected = 2.0 * single_leg_ev(mid_price, volatility, high_alpha, &high.0, high.1);
+        assert!((high.4 - expected).abs() < 1e-9, "{} vs {}", high.4, expected);
+        assert!(high.4 < 2.0 * single_leg_ev(mid_price, volatility, low_alpha, &high.0, high.1));
+    }
+
+    #[tokio::test]
+    async fn test_order_info_carries_selected_level_and_ev() {
+        let mid_price = 10_000_000.0;
+        // L10 fills 90% of the time, L20 only 30%: the levels differ per side only by P(fill)
+        let probabilities: BTreeMap<FloatingExp, (f64, BayesProb)> = [(10.0, 9, 1), (20.0, 3, 7)]
+            .iter()
+            .map(|&(rate, a, b)| {
+                let prob = BayesProb::new(BetaDistribution::new(a, b), Duration::from_secs(300));
+                (FloatingExp::new(10.0, -5.0, rate), (0.0, prob))
+            })
+            .collect();
+        let (buy_key, buy_p, sell_key, sell_p, combined_ev) =
+            maximize_single_leg_ev(mid_price, 100.0, 0.5, &probabilities, &probabilities).unwrap();
+        let buy_ev = single_leg_ev(mid_price, 100.0, 0.5, &buy_key, buy_p);
+        let sell_ev = single_leg_ev(mid_price, 100.0, 0.5, &sell_key, sell_p);
+
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let config = symbol_test_config();
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let rule = registry.get(&config.symbol.to_string()).unwrap();
+        let client = reqwest::Client::new();
+        // Open buy at the chosen level; the sell is a close and must not claim one
+        for (side, price, is_close, key, p, ev) in [
+            (OrderSide::BUY, 9_990_000, false, &buy_key, buy_p, buy_ev),
+            (OrderSide::SELL, 10_010_000, true, &sell_key, sell_p, sell_ev),
+        ] {
+            let (level, p_fill, leg_ev) = order_ev_fields(is_close, key, p, ev);
+            send_order(
+                &client, &limiter, &orders, side, Price::from_f64(price as f64), 0.001, is_close, &config, rule, &None,
+                mid_price, 5000, 0.0001, 0.00005, level, p_fill, combined_ev, leg_ev, Some(&sim),
+            ).await;
+        }
+
+        let orders = orders.lock();
+        let open = orders.get("dry-1").unwrap();
+        assert_eq!(open.level, buy_key.rate as u32);
+        assert_eq!(open.level_key.as_ref(), Some(&buy_key));
+        assert_eq!(open.p_fill, probabilities[&buy_key].1.calc_average());
+        assert_eq!((open.best_ev, open.single_leg_ev), (combined_ev, buy_ev));
+        assert!(open.level > 0 && open.p_fill > 0.0 && open.single_leg_ev != 0.0);
+        let close = orders.get("dry-2").unwrap();
+        assert_eq!((close.level, close.p_fill, close.single_leg_ev), (0, 0.0, 0.0));
+        assert!(close.level_key.is_none());
+        assert_eq!(close.best_ev, combined_ev);
+    }
+
+    #[tokio::test]
+    async fn test_near_duplicate_open_is_suppressed() {
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let mut config = symbol_test_config();
+        config.dedup_price_tolerance_jpy = Some(100.0);
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let rule = registry.get(&config.symbol.to_string()).unwrap();
+        let client = reqwest::Client::new();
+        let level = FloatingExp::new(10.0, -5.0, 5.0);
+        let send = |side: OrderSide, price: u64, is_close: bool| {
+            send_order(
+                &client, &limiter, &orders, side, Price::from_f64(price as f64), 0.001, is_close, &config, rule, &None,
+                10_000_500.0, 5000, 0.0001, 0.00005, Some(&level), 0.1, 0.0, 0.0, Some(&sim),
+            )
+        };
+
+        assert!(matches!(send(OrderSide::BUY, 10_000_000, false).await, OrderResult::Success));
+        // Within 100 JPY of the resting buy: suppressed, nothing placed
+        assert!(matches!(send(OrderSide::BUY, 10_000_060, false).await, OrderResult::Duplicate));
+        assert!(matches!(send(OrderSide::BUY, 9_999_900, false).await, OrderResult::Duplicate));
+        assert_eq!(sim.open_order_count(), 1);
+
+        // Far enough away, the other side, or a close: all allowed
+        assert!(matches!(send(OrderSide::BUY, 9_999_899, false).await, OrderResult::Success));
+        assert!(matches!(send(OrderSide::SELL, 10_000_050, false).await, OrderResult::Success));
+        assert!(matches!(send(OrderSide::BUY, 10_000_000, true).await, OrderResult::Success));
+        assert_eq!(orders.lock().len(), 4);
+
+        // Unset tolerance: no dedup at all
+        assert_eq!(duplicate_open_order(&orders.lock(), &OrderSide::BUY, 10_000_000, None), None);
+        assert_eq!(duplicate_open_order(&orders.lock(), &OrderSide::BUY, 10_000_000, Some(0.0)), Some(10_000_000));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_crossing_execution_simulates_fill() {
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(1.0, 0.001);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let position: Positions = RwLock::new(Position::default());
+        let pnl = RwLock::new(model::PnlTracker::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        dry_run_send(&sim, &limiter, &orders, OrderSide::BUY, 10_000_000, false).await;
+
+        // Trade at our price does not cross; one through it does
+        assert!(sim.match_executions(&[(10_000_000, 0.01, 1)]).is_empty());
+        let fills = sim.match_executions(&[(9_999_995, 0.01, 2)]);
+        assert_eq!(fills.len(), 1);
+        for fill in &fills {
+            handle_sim_fill("BTC_JPY", &orders, &position, &None, &tx, &pnl, fill);
+        }
+
+        assert!(orders.lock().is_empty());
+        let pos = *position.read();
+        assert_eq!(pos.long_size, 0.001);
+        assert_eq!(pos.long_open_price, 10_000_000.0);
+        let outcome = rx.try_recv().unwrap();
+        assert!(outcome.filled);
+        assert_eq!(outcome.level, Some(FloatingExp::new(10.0, -5.0, 5.0)));
+    }
+
+    #[test]
+    fn test_reload_rekeys_levels_and_keeps_surviving_posteriors() {
+        let mut config = symbol_test_config();
+        config.levels = Some(vec![FloatingExp::new(10.0, -5.0, 4.0), FloatingExp::new(10.0, -5.0, 6.0)]);
+        let prior = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+        let mut buy = BTreeMap::new();
+        rekey_levels(&mut buy, &config_step_levels(&config), &prior);
+        let kept = FloatingExp::new(10.0, -5.0, 6.0);
+        buy.get_mut(&kept).unwrap().1.update(5, 5);
+        let learned = buy[&kept].1.calc_average();
+
+        config.levels = Some(vec![kept.clone(), FloatingExp::new(10.0, -5.0, 8.0)]);
+        rekey_levels(&mut buy, &config_step_levels(&config), &prior);
+        assert_eq!(buy.keys().cloned().collect::<Vec<_>>(), config_step_levels(&config));
+        assert_eq!(buy[&kept].1.calc_average(), learned);
+        assert_eq!(buy[&FloatingExp::new(10.0, -5.0, 8.0)].1.calc_average(), prior.calc_average());
+    }
+
+    #[tokio::test]
+    async fn test_fill_on_explicit_level_moves_that_posterior() {
+        // Explicit levels off the price_step_* grid: another exp, a fractional rate, and two
+        // levels sharing a rate
+        let mut config = symbol_test_config();
+        config.levels = Some(vec![
+            FloatingExp::new(10.0, -4.0, 2.0),
+            FloatingExp::new(10.0, -4.0, 2.5),
+            FloatingExp::new(10.0, -5.0, 2.5),
+        ]);
+        let prior = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+        let table = || config_step_levels(&config).into_iter().map(|k| (k, (0.0, prior.clone()))).collect::<BTreeMap<_, _>>();
+        let (mut buy, mut sell) = (table(), table());
+        assert_eq!(buy.len(), 3);
+
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        let registry = build_symbol_registry(None, &config.symbol_rules);
+        let rule = registry.get(&config.symbol.to_string()).unwrap();
+        let quoted = FloatingExp::new(10.0, -4.0, 2.5);
+        send_order(
+            &reqwest::Client::new(), &limiter, &orders, OrderSide::BUY, Price::from_f64(10_000_000.0), 0.001, false, &config, rule, &None,
+            10_002_500.0, 5000, 0.0001, 0.00025, Some(&quoted), 0.1, 0.0, 0.0, Some(&sim),
+        ).await;
+
+        let position: Positions = RwLock::new(Position::default());
+        let pnl = RwLock::new(model::PnlTracker::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        for fill in &sim.match_executions(&[(9_999_995, 0.01, 1)]) {
+            handle_sim_fill("BTC_JPY", &orders, &position, &None, &tx, &pnl, fill);
+        }
+        let outcome = rx.try_recv().unwrap();
+        let key = outcome.level.unwrap();
+        assert!(update_level_posterior(&mut buy, &mut sell, &outcome.side, &key, outcome.filled));
+
+        let mean = |probs: &BTreeMap<FloatingExp, (f64, BayesProb)>, k: &FloatingExp| probs[k].1.calc_average();
+        assert!(mean(&buy, &quoted) > prior.calc_average());
+        assert_eq!(mean(&buy, &FloatingExp::new(10.0, -4.0, 2.0)), prior.calc_average());
+        assert_eq!(mean(&buy, &FloatingExp::new(10.0, -5.0, 2.5)), prior.calc_average());
+        assert_eq!(mean(&sell, &quoted), prior.calc_average());
+    }
+
+    // ================================================================
+    // Circuit breaker thresholds
+    // ================================================================
+
+    #[test]
+    fn test_circuit_breaker_threshold_from_config() {
+        // 2,000 JPY range on ~10M = 0.02%
+        let executions = [(10_000_000, 0.01, 96_000), (10_002_000, 0.01, 98_000), (10_001_000, 0.01, 99_000)];
+        let now = 100_000;
+
+        let mut config = symbol_test_config();
+        config.circuit_breaker_frac = 0.0001;
+        let tripped = circuit_breaker_trip(&executions, now, config.circuit_breaker_window_ms, config.circuit_breaker_frac);
+        assert_eq!(tripped.map(|t| t.0), Some(2_000));
+
+        config.circuit_breaker_frac = 0.01;
+        assert_eq!(circuit_breaker_trip(&executions, now, config.circuit_breaker_window_ms, config.circuit_breaker_frac), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker_window_excludes_old_trades() {
+        let executions = [(9_000_000, 0.01, 10_000), (10_000_000, 0.01, 99_000), (10_000_100, 0.01, 99_500)];
+        assert_eq!(circuit_breaker_trip(&executions, 100_000, 5_000, 0.0001), None);
+        assert!(circuit_breaker_trip(&executions, 100_000, 100_000, 0.0001).is_some());
+        assert_eq!(circuit_breaker_trip(&[], 100_000, 5_000, 0.0001), None);
+    }
+
+    #[test]
+    fn test_circuit_breaker_config_defaults() {
+        let config = symbol_test_config();
+        assert_eq!(config.circuit_breaker_frac, 0.001);
+        assert_eq!(config.circuit_breaker_cooldown_secs, 30);
+        assert_eq!(config.circuit_breaker_window_ms, 5000);
+    }
+
+    // ================================================================
+    // Take-profit trigger
+    // ================================================================
+
+    #[test]
+    fn test_take_profit_long_and_short() {
+        let min_lot = 0.001;
+        let long = Position { long_size: 0.001, long_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, _) = long.unrealized_pnl(14_006_000.0, min_lot);
+        assert!((long_pnl - 6.0).abs() < 1e-9);
+        assert_eq!(short_pnl, 0.0);
+        assert_eq!(take_profit_side(long_pnl, short_pnl, 5.0), Some(OrderSide::SELL));
+
+        let short = Position { short_size: 0.002, short_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, _) = short.unrealized_pnl(13_997_000.0, min_lot);
+        assert!((short_pnl - 6.0).abs() < 1e-9);
+        assert_eq!(take_profit_side(long_pnl, short_pnl, 5.0), Some(OrderSide::BUY));
+    }
+
+    #[test]
+    fn test_take_profit_no_trigger_below_threshold_or_disabled() {
+        let long = Position { long_size: 0.001, long_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, _) = long.unrealized_pnl(14_004_000.0, 0.001);
+        assert_eq!(take_profit_side(long_pnl, short_pnl, 5.0), None);
+        assert_eq!(take_profit_side(long_pnl, short_pnl, 4.0), None, "exactly at target does not trigger");
+        assert_eq!(take_profit_side(100.0, 0.0, 0.0), None, "0 disables take-profit");
+        assert_eq!(take_profit_side(6.0, 8.0, 5.0), Some(OrderSide::BUY), "larger gain wins");
+    }
+
+    // ================================================================
+    // Trailing stop
+    // ================================================================
+
+    #[test]
+    fn test_trailing_stop_ratchets_then_fires_on_retrace() {
+        let mut stop = TrailingStop::new(3.0);
+        // Long P&L path: ratchets up to +8, then gives back
+        let path = [-1.0, 2.0, 5.0, 8.0, 6.0, 5.5];
+        for pnl in path {
+            assert_eq!(stop.update(Some(pnl), None), None, "pnl {}", pnl);
+        }
+        assert_eq!(stop.peak_long, Some(8.0));
+        // 8.0 - 4.9 = 3.1 > 3.0
+        assert_eq!(stop.update(Some(4.9), None), Some(OrderSide::SELL));
+        stop.closed(&OrderSide::SELL);
+        assert_eq!(stop.peak_long, None);
+    }
+
+    #[test]
+    fn test_trailing_stop_keeps_firing_until_the_close_is_sent() {
+        let mut stop = TrailingStop::new(3.0);
+        stop.update(Some(8.0), None);
+        // Hit while the loop cannot act (stop-loss cooldown): the peak must survive
+        assert_eq!(stop.update(Some(4.0), None), Some(OrderSide::SELL));
+        assert_eq!(stop.update(Some(4.5), None), Some(OrderSide::SELL), "still retraced from 8");
+        assert_eq!(stop.peak_long, Some(8.0));
+
+        stop.closed(&OrderSide::SELL);
+        assert_eq!(stop.update(Some(4.5), None), None, "fresh peak after the close");
+        assert_eq!(stop.peak_long, Some(4.5));
+    }
+
+    #[test]
+    fn test_trailing_stop_short_side_and_flat_reset() {
+        let mut stop = TrailingStop::new(2.0);
+        stop.update(None, Some(4.0));
+        assert_eq!(stop.update(None, Some(1.5)), Some(OrderSide::BUY));
+
+        // Going flat forgets the peak: a new position starts from scratch
+        stop.update(None, Some(6.0));
+        stop.update(None, None);
+        assert_eq!(stop.update(None, Some(3.0)), None);
+        assert_eq!(stop.peak_short, Some(3.0));
+    }
+
+    #[test]
+    fn test_trailing_stop_needs_positive_peak_and_distance() {
+        // Never in profit: losses are the fixed stop-loss's job
+        let mut stop = TrailingStop::new(1.0);
+        stop.update(Some(-1.0), None);
+        assert_eq!(stop.update(Some(-5.0), None), None);
+
+        let mut disabled = TrailingStop::new(0.0);
+        disabled.update(Some(10.0), None);
+        assert_eq!(disabled.update(Some(0.0), None), None);
+    }
+
+    // ================================================================
+    // Order-rate throttle
+    // ================================================================
+
+    #[tokio::test(start_paused = true)]
+    async fn test_send_order_throttled_past_max_orders_per_sec() {
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0).with_max_orders_per_sec(2);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+
+        let mut results = Vec::new();
+        for i in 0..5 {
+            results.push(dry_run_send(&sim, &limiter, &orders, OrderSide::BUY, 10_000_000 - i, false).await);
+        }
+        assert_eq!(results.iter().filter(|r| matches!(r, OrderResult::Throttled)).count(), 3);
+        assert_eq!(sim.open_order_count(), 2);
+
+        tokio::time::advance(Duration::from_millis(1000)).await;
+        assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::SELL, 10_001_000, false).await, OrderResult::Success));
+    }
+
+    // ================================================================
+    // Kelly sizing
+    // ================================================================
+
+    fn kelly_mode(win_prob: f64, win_loss_ratio: f64) -> model::SizingMode {
+        model::SizingMode::Kelly { win_prob, win_loss_ratio }
+    }
+
+    #[test]
+    fn test_kelly_size_for_known_inputs() {
+        // f = 0.6 - 0.4 / 1.0 = 0.2 -> 0.2 * 1,000,000 JPY / 10,000,000 = 0.02 BTC
+        assert!((kelly_mode(0.6, 1.0).kelly_fraction().unwrap() - 0.2).abs() < 1e-12);
+        let flat = Position::new();
+        let (buy, sell) = order_sizes(&kelly_mode(0.6, 1.0), &flat, 1.0, 0.001, 0.05, 0.9, 1_000_000.0, 10_000_000.0);
+        assert_eq!((buy, sell), (0.02, 0.02));
+
+        // f = 0.5 - 0.5 / 2.0 = 0.25, clamped to max_lot / min_lot
+        let (buy, _) = order_sizes(&kelly_mode(0.5, 2.0), &flat, 1.0, 0.001, 0.01, 0.9, 1_000_000.0, 10_000_000.0);
+        assert_eq!(buy, 0.01);
+        let (buy, _) = order_sizes(&kelly_mode(0.5, 2.0), &flat, 1.0, 0.001, 0.01, 0.9, 1_000.0, 10_000_000.0);
+        assert_eq!(buy, 0.001);
+    }
+
+    #[test]
+    fn test_kelly_size_respects_remaining_capacity() {
+        let mode = kelly_mode(0.6, 1.0);
+        // 0.02 BTC Kelly size, but only 0.005 long room and no short room left
+        let position = Position { long_size: 0.045, short_size: 0.05, ..Position::new() };
+        let (buy, sell) = order_sizes(&mode, &position, 0.05, 0.001, 0.05, 0.9, 1_000_000.0, 10_000_000.0);
+        assert!((buy - 0.005).abs() < 1e-12);
+        assert_eq!(sell, 0.0);
+    }
+
+    #[test]
+    fn test_power_law_mode_matches_calculate_order_sizes() {
+        let position = Position { long_size: 0.01, ..Position::new() };
+        assert_eq!(
+            order_sizes(&model::SizingMode::PowerLaw, &position, 0.05, 0.001, 0.01, 0.9, 1_000_000.0, 10_000_000.0),
+            calculate_order_sizes(&position, 0.05, 0.001, 0.01, 0.9),
+        );
+    }
+
+    // ================================================================
+    // Order book imbalance
+    // ================================================================
+
+    fn book(levels: &[(u64, f64)]) -> BTreeMap<u64, f64> {
+        levels.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_order_book_imbalance_known_values() {
+        let bids = book(&[(9_999_990, 3.0), (9_999_980, 1.0), (9_999_000, 100.0)]);
+        let asks = book(&[(10_000_010, 1.0), (10_000_020, 1.0), (10_001_000, 100.0)]);
+        // Top 2: bids 4, asks 2 -> (4 - 2) / 6
+        assert!((order_book_imbalance(&bids, &asks, 2) - 1.0 / 3.0).abs() < 1e-12);
+        // Top 3 pulls in the deep levels: (104 - 102) / 206
+        assert!((order_book_imbalance(&bids, &asks, 3) - 2.0 / 206.0).abs() < 1e-12);
+        // Ask-heavy book is negative
+        let heavy_asks = book(&[(10_000_010, 3.0), (10_000_020, 1.0)]);
+        let light_bids = book(&[(9_999_990, 1.0), (9_999_980, 1.0)]);
+        assert!((order_book_imbalance(&light_bids, &heavy_asks, 2) + 1.0 / 3.0).abs() < 1e-12);
+        // One-sided and empty books
+        assert_eq!(order_book_imbalance(&bids, &BTreeMap::new(), 2), 1.0);
+        assert_eq!(order_book_imbalance(&BTreeMap::new(), &BTreeMap::new(), 2), 0.0);
+        assert_eq!(order_book_imbalance(&bids, &asks, 0), 0.0);
+    }
+
+    /// Spread multipliers after the flow widening from `executions` (5s window, weight 0.2)
+    fn flow_adjusted(executions: &[(u64, f64, i64)], now: i64) -> (f64, f64) {
+        let (buy_adj, sell_adj) = trade_flow_widening(trade_flow_imbalance(executions, now, 5_000), 0.2);
+        (1.0 + buy_adj, 1.0 + sell_adj)
+    }
+
+    #[tokio::test]
+    async fn test_all_negative_ev_blocks_opens_not_closes() {
+        let mid_price = 10_000_000.0;
+        // Levels quote 10-50 JPY from mid while adverse selection costs 200 JPY: every EV < 0
+        let probabilities: BTreeMap<FloatingExp, (f64, BayesProb)> = [1.0, 3.0, 5.0]
+            .iter()
+            .map(|&rate| {
+                let prob = BayesProb::new(BetaDistribution::new(8, 2), Duration::from_secs(300));
+                (FloatingExp::new(10.0, -6.0, rate), (0.0, prob))
+            })
+            .collect();
+        let (buy_level, buy_p, sell_level, sell_p, _) =
+            maximize_single_leg_ev(mid_price, 400.0, 0.5, &probabilities, &probabilities).unwrap();
+        let buy_ev = single_leg_ev(mid_price, 400.0, 0.5, &buy_level, buy_p);
+        let sell_ev = single_leg_ev(mid_price, 400.0, 0.5, &sell_level, sell_p);
+        assert!(buy_ev < 0.0 && sell_ev < 0.0);
+
+        // Through the loop's gating with default min_ev = 0 and a long to close: every other
+        // gate passes, so EV alone decides the opens
+        let gates = OpenGates {
+            margin_ok: true, collateral_ok: true, fill_ok: true, daily_ok: true, pending_ok: true, in_trading_hours: true,
+        };
+        let (can_open_long, buy_wanted) = gates.side(false, open_ev_ok(buy_ev, 0.0));
+        let (can_open_short, sell_wanted) = gates.side(true, open_ev_ok(sell_ev, 0.0));
+        assert!(!can_open_long && !can_open_short);
+        let (should_buy, should_sell) = quote_sides(false, (true, true), (buy_wanted, sell_wanted));
+
+        // Only the close reaches the (simulated) exchange
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        for (send, side, price, is_close) in [(should_buy, OrderSide::BUY, 9_999_000, false), (should_sell, OrderSide::SELL, 10_001_000, true)] {
+            if send {
+                assert!(matches!(dry_run_send(&sim, &limiter, &orders, side, price, is_close).await, OrderResult::Success));
+            }
+        }
+        let sent: Vec<(OrderSide, bool)> = orders.lock().values().map(|o| (o.side.clone(), o.is_close)).collect();
+        assert_eq!(sent, [(OrderSide::SELL, true)]);
+
+        // A negative threshold deliberately admits the opens again
+        assert_eq!(gates.side(false, open_ev_ok(buy_ev, buy_ev - 1.0)), (true, true));
+    }
+
+    #[test]
+    fn test_seeded_thompson_sampling_is_reproducible() {
+        let probabilities: BTreeMap<FloatingExp, (f64, BayesProb)> = [1.0, 3.0, 5.0, 8.0]
+            .iter()
+            .map(|&rate| {
+                let prob = BayesProb::new(BetaDistribution::new(2, 8), Duration::from_secs(300));
+                (FloatingExp::new(10.0, -5.0, rate), (0.0, prob))
+            })
+            .collect();
+        let run = |seed| -> Vec<(u32, f64, u32, f64)> {
+            let mut rng = util::seeded_rng(seed);
+            (0..20)
+                .map(|_| {
+                    let (buy, buy_p, sell, sell_p, _) = maximize_single_leg_ev_within(
+                        10_000_000.0, 400.0, 0.5, &probabilities, &probabilities,
+                        (f64::NEG_INFINITY, f64::INFINITY), |b| b.sample(&mut rng),
+                    ).unwrap();
+                    (buy.rate as u32, buy_p, sell.rate as u32, sell_p)
+                })
+                .collect()
+        };
+        assert_eq!(run(Some(7)), run(Some(7)));
+        assert_ne!(run(Some(7)), run(Some(8)));
+    }
+
+    #[tokio::test]
+    async fn test_pending_cap_blocks_opens_not_closes() {
+        let sim = SimExchange::new();
+        let limiter = RateLimiter::new(100.0, 100.0);
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        for price in [9_999_000, 9_998_000] {
+            assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::BUY, price, false).await, OrderResult::Success));
+        }
+
+        // Below the cap, and with no cap, opens are fine
+        assert!(pending_cap_ok(orders.lock().len(), 3));
+        assert!(pending_cap_ok(orders.lock().len(), 0));
+        let gates = |pending_ok| OpenGates {
+            margin_ok: true, collateral_ok: true, fill_ok: true, daily_ok: true, pending_ok, in_trading_hours: true,
+        };
+        let open = gates(pending_cap_ok(orders.lock().len(), 3));
+        assert_eq!(open.side(false, true), (true, true));
+        // The side's own checks still apply under the cap
+        assert_eq!(open.side(false, false), (false, false));
+
+        // At the cap: the open buy is dropped, the closing sell still goes out
+        let capped = gates(pending_cap_ok(orders.lock().len(), 2));
+        assert_eq!(capped.side(false, true), (false, false), "no open buy");
+        assert_eq!(capped.side(true, true), (false, true), "closing sell still sent");
+        assert_eq!(quote_sides(false, (true, true), (capped.side(false, true).1, capped.side(true, true).1)), (false, true));
+        assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::SELL, 10_001_000, true).await, OrderResult::Success));
+        assert_eq!(orders.lock().len(), 3);
+    }
+
+    /// Ticks every 100ms alternating +/-`step` JPY around 10M, from `start` to `end` (ms)
+    fn alternating_ticks(start: i64, end: i64, step: u64) -> Vec<(u64, f64, i64)> {
+        (start..end)
+            .step_by(100)
+            .enumerate()
+            .map(|(i, ts)| (if i % 2 == 0 { 10_000_000 } else { 10_000_000 + step }, 0.01, ts))
+            .collect()
+    }
+
+    #[test]
+    fn test_recent_shock_raises_short_window_vol_more() {
+        let windows = [1_000, 5_000];
+        // Steady 50 JPY chop: both windows agree
+        let calm = alternating_ticks(0, 5_000, 50);
+        let vols = calculate_volatility_multi(&calm, &windows, 10_000_000.0);
+        assert_eq!(vols.len(), 2);
+        assert!((vols[0] / vols[1] - 1.0).abs() < 0.05, "calm ratio {:?}", vols);
+
+        // Same tape with the last second swinging 500 JPY
+        let mut shocked = alternating_ticks(0, 4_000, 50);
+        shocked.extend(alternating_ticks(4_000, 5_000, 500));
+        let shocked_vols = calculate_volatility_multi(&shocked, &windows, 10_000_000.0);
+        let short_rise = shocked_vols[0] / vols[0];
+        let long_rise = shocked_vols[1] / vols[1];
+        assert!(short_rise > long_rise && long_rise > 1.0, "short x{:.2} vs long x{:.2}", short_rise, long_rise);
+        let ratio = shocked_vols[0] / shocked_vols[1];
+        assert!(ratio > 1.5, "ratio {}", ratio);
+
+        // Past the threshold both sides widen, capped at doubling; weight 0 only logs
+        assert_eq!(vol_expansion_widening(1.2, 1.5, 0.5), 0.0);
+        assert!(vol_expansion_widening(ratio, 1.5, 0.5) > 0.0);
+        assert_eq!(vol_expansion_widening(100.0, 1.5, 0.5), 1.0);
+        assert_eq!(vol_expansion_widening(ratio, 1.5, 0.0), 0.0);
+        assert_eq!(vol_expansion_widening(f64::NAN, 1.5, 0.5), 0.0);
+
+        // No executions: every window sits at the floor of the current mid, not a hardcoded BTC price
+        let empty = calculate_volatility_multi(&[], &windows, 85.0);
+        assert_eq!(empty[0], empty[1]);
+        assert_eq!(empty[0], 85.0 * MIN_VOLATILITY_BPS);
+    }
+
+    #[test]
+    fn test_trade_flow_widens_the_side_being_hit() {
+        let now = 100_000;
+        // All aggressive buys lift the asks: sells widen, buys stay put
+        let buys = [(10_000_000, 0.01, 96_000), (10_000_100, 0.02, 98_000), (10_000_200, 0.01, 99_000)];
+        assert_eq!(trade_flow_imbalance(&buys, now, 5_000), 1.0);
+        assert_eq!(flow_adjusted(&buys, now), (1.0, 1.2));
+
+        // All aggressive sells hit the bids: buys widen
+        let sells = [(10_000_000, -0.01, 96_000), (9_999_900, -0.03, 99_000)];
+        assert_eq!(trade_flow_imbalance(&sells, now, 5_000), -1.0);
+        assert_eq!(flow_adjusted(&sells, now), (1.2, 1.0));
+
+        // Balanced flow leaves both sides alone
+        let balanced = [(10_000_000, 0.02, 97_000), (10_000_000, -0.01, 98_000), (10_000_000, -0.01, 99_000)];
+        assert_eq!(trade_flow_imbalance(&balanced, now, 5_000), 0.0);
+        assert_eq!(flow_adjusted(&balanced, now), (1.0, 1.0));
+
+        // Partial imbalance scales; trades older than the window are ignored
+        let mixed = [(10_000_000, -1.0, 90_000), (10_000_000, 0.03, 97_000), (10_000_000, -0.01, 99_000)];
+        assert!((trade_flow_imbalance(&mixed, now, 5_000) - 0.5).abs() < 1e-12);
+        let (buy_adj, sell_adj) = flow_adjusted(&mixed, now);
+        assert_eq!(buy_adj, 1.0);
+        assert!((sell_adj - 1.1).abs() < 1e-12);
+        assert_eq!(trade_flow_imbalance(&[], now, 5_000), 0.0);
+    }
+
+    #[test]
+    fn test_imbalance_tightens_favored_side() {
+        let pos = Position::new();
+        // Bid-heavy: buy side tightens, sell side widens
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, 0.5, 0.2, 0.0);
+        assert!((buy_adj - 0.9).abs() < 1e-12 && (sell_adj - 1.1).abs() < 1e-12, "{} {}", buy_adj, sell_adj);
+        // Ask-heavy: the reverse
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&pos, 0.002, -0.5, 0.2, 0.0);
+        assert!(buy_adj > 1.0 && sell_adj < 1.0);
+        // Weight 0 ignores the book
+        assert_eq!(calculate_spread_adjustment(&pos, 0.002, 0.9, 0.0, 0.0), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_spread_adj_pulls_toward_target_net_position() {
+        // Flat with a long target: buys tighten, sells widen to accumulate long
+        let flat = Position::new();
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&flat, 0.002, 0.0, 0.0, 0.001);
+        assert!((buy_adj - 0.8).abs() < 1e-12 && (sell_adj - 1.2).abs() < 1e-12, "{} {}", buy_adj, sell_adj);
+
+        // At the target the skew is neutral; only the exposure penalty remains, on both sides
+        let at_target = Position { long_size: 0.001, ..Default::default() };
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&at_target, 0.002, 0.0, 0.0, 0.001);
+        assert!((buy_adj - sell_adj).abs() < 1e-12, "{} {}", buy_adj, sell_adj);
+        assert!((buy_adj - 1.1).abs() < 1e-12);
+
+        // Past the target the skew flips to shed the excess
+        let over = Position { long_size: 0.002, ..Default::default() };
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&over, 0.002, 0.0, 0.0, 0.001);
+        assert!(buy_adj > sell_adj);
+
+        // Zero target keeps the flat-seeking behavior
+        let long = Position { long_size: 0.002, short_size: 0.001, ..Default::default() };
+        let (buy_adj, sell_adj) = calculate_spread_adjustment(&long, 0.002, 0.0, 0.0, 0.0);
+        assert!((buy_adj - (1.0 + 0.2 / 3.0 + 0.2)).abs() < 1e-12);
+        assert!((sell_adj - (1.0 - 0.2 / 3.0 + 0.2)).abs() < 1e-12);
+    }
+
+    // ================================================================
+    // Spot balance sizing
+    // ================================================================
+
+    fn balance(entries: &[(&str, f64)]) -> gmo::get_balance::BalanceResponse {
+        gmo::get_balance::BalanceResponse {
+            data: entries
+                .iter()
+                .map(|&(currency, available)| gmo::get_balance::BalanceDetail {
+                    currency: currency.to_string(), amount: available, available,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_balance_cap_shrinks_sizes_when_funds_are_low() {
+        let position = Position::new();
+        let (buy, sell) = order_sizes(&model::SizingMode::PowerLaw, &position, 0.05, 0.001, 0.01, 0.9, 0.0, 10_000_000.0);
+        assert_eq!((buy, sell), (0.01, 0.01));
+
+        // 30,000 JPY buys 0.003 BTC at 10M; only 0.002 BTC to sell
+        let (jpy, base) = spot_funds(&balance(&[("JPY", 30_000.0), ("BTC", 0.002)]), &Symbol::BTC_JPY);
+        let (capped_buy, capped_sell) = balance_capped_sizes(buy, sell, jpy, base, 10_000_000.0);
+        assert!((capped_buy - 0.003).abs() < 1e-12, "{}", capped_buy);
+        assert!((capped_sell - 0.002).abs() < 1e-12, "{}", capped_sell);
+
+        // Ample funds never raise a size; no funds at all leaves nothing to open
+        assert_eq!(balance_capped_sizes(buy, sell, 1e9, 10.0, 10_000_000.0), (buy, sell));
+        assert_eq!(balance_capped_sizes(buy, sell, 0.0, 0.0, 10_000_000.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_spot_funds_reads_symbol_base_currency() {
+        let wallet = balance(&[("JPY", 50_000.0), ("BTC", 0.01), ("ETH", 0.5)]);
+        assert_eq!(spot_funds(&wallet, &Symbol::BTC_JPY), (50_000.0, 0.01));
+        assert_eq!(spot_funds(&wallet, &Symbol::ETH_JPY), (50_000.0, 0.5));
+        // Missing currencies count as zero
+        assert_eq!(spot_funds(&wallet, &Symbol::XRP_JPY), (50_000.0, 0.0));
+    }
+
+    #[test]
+    fn test_balance_cap_ignored_in_margin_mode() {
+        let low_funds = Some((1_000.0, 0.0));
+        assert_eq!(symbol_test_config().sizing_source, SizingSource::Margin);
+        assert_eq!(funded_order_sizes(SizingSource::Margin, low_funds, 0.01, 0.01, 10_000_000.0), (0.01, 0.01));
+        // Balance mode applies the same funds as a cap
+        assert_eq!(funded_order_sizes(SizingSource::Balance, low_funds, 0.01, 0.01, 10_000_000.0), (0.0001, 0.0));
+        // ...until the wallet has been fetched at least once
+        assert_eq!(funded_order_sizes(SizingSource::Balance, None, 0.01, 0.01, 10_000_000.0), (0.01, 0.01));
+    }
+
+    // ================================================================
+    // Config hot-reload
+    // ================================================================
+
+    fn write_config(name: &str, extra: &str) -> String {
+        let path = std::env::temp_dir().join(format!("reload_{}_{}.yaml", name, std::process::id()));
+        let yaml = "order_cancel_ms: 1\norder_interval_ms: 1\nposition_ratio: 0.9\nmin_lot: 0.001\nmax_lot: 0.001\nmax_position: 0.002\n";
+        fs::write(&path, format!("{}{}", yaml, extra)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_reload_config_swaps_valid_config() {
+        let shared: [SharedConfig; 1] = [Arc::new(RwLock::new(symbol_test_config()))];
+        let path = write_config("valid", "alpha: 0.25\nstop_loss_jpy: 12.0\n");
+        assert_eq!(reload_config(&shared, &path), Ok(()));
+        let config = shared[0].read();
+        assert_eq!((config.alpha, config.stop_loss_jpy), (0.25, 12.0));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_reload_config_rejects_invalid_and_keeps_current() {
+        let shared: [SharedConfig; 1] = [Arc::new(RwLock::new(symbol_test_config()))];
+        let before = format!("{:?}", *shared[0].read());
+
+        // Fails validation (negative stop_loss_jpy)
+        let invalid = write_config("invalid", "stop_loss_jpy: -1.0\n");
+        let errors = reload_config(&shared, &invalid).unwrap_err();
+        assert!(errors[0].starts_with("stop_loss_jpy"), "{:?}", errors);
+        // Unparseable, missing, and a symbol switch
+        let garbage = write_config("garbage", "alpha: [\n");
+        assert!(reload_config(&shared, &garbage).is_err());
+        assert!(reload_config(&shared, "/nonexistent/trade-config.yaml").is_err());
+        let other_symbol = write_config("symbol", "symbol: ETH_JPY\n");
+        assert!(reload_config(&shared, &other_symbol).unwrap_err()[0].starts_with("symbol"));
+
+        assert_eq!(format!("{:?}", *shared[0].read()), before);
+        for path in [invalid, garbage, other_symbol] {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    #[test]
+    fn test_reload_config_keeps_each_symbol_sizing() {
+        let symbols = "symbols:\n  - symbol: BTC_JPY\n  - symbol: ETH_JPY\n    min_lot: 0.1\n    max_lot: 0.1\n    max_position: 0.3\n";
+        let path = write_config("multi", &format!("alpha: 0.25\n{}", symbols));
+        let config = BotConfig::load(&path).unwrap();
+        let shared: Vec<SharedConfig> = config.symbol_configs().iter()
+            .map(|symbol| Arc::new(RwLock::new(config.for_symbol(symbol))))
+            .collect();
+
+        let reload = write_config("multi_reload", &format!("alpha: 0.4\n{}", symbols));
+        assert_eq!(reload_config(&shared, &reload), Ok(()));
+        assert_eq!((shared[0].read().alpha, shared[0].read().min_lot), (0.4, 0.001));
+        assert_eq!((shared[1].read().alpha, shared[1].read().min_lot), (0.4, 0.1));
+
+        // Dropping a symbol needs a restart
+        let fewer = write_config("multi_fewer", "alpha: 0.5\n");
+        assert!(reload_config(&shared, &fewer).unwrap_err()[0].starts_with("symbol (BTC_JPY) cannot change"));
+        assert_eq!(shared[1].read().alpha, 0.4);
+        for path in [path, reload, fewer] {
+            let _ = fs::remove_file(&path);
+        }
+    }
+
+    // ================================================================
+    // Reconnect resync
+    // ================================================================
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resync_wakes_sleep_before_interval() {
+        let resync = ResyncSignal::default();
+        resync.fire();
+        let started = Instant::now();
+        assert!(sleep_or_notified(Duration::from_secs(5), &resync.position).await);
+        assert!(started.elapsed() < Duration::from_secs(5), "woke after {:?}", started.elapsed());
+        // The orders permit is independent of the position one
+        assert!(sleep_or_notified(Duration::from_secs(5), &resync.orders).await);
+
+        // Without a signal the full interval elapses
+        assert!(!sleep_or_notified(Duration::from_secs(5), &resync.position).await);
+        assert!(started.elapsed() >= Duration::from_secs(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resync_sweeps_young_orders_immediately() {
+        let sim = Arc::new(SimExchange::new());
+        let limiter = Arc::new(RateLimiter::new(100.0, 100.0));
+        let orders: Orders = Arc::new(Mutex::new(model::OrderMap::new()));
+        assert!(matches!(dry_run_send(&sim, &limiter, &orders, OrderSide::BUY, 10_000_000, false).await, OrderResult::Success));
+
+        let resync = Arc::new(ResyncSignal::default());
+        let (outcome_tx, mut outcome_rx) = tokio::sync::mpsc::unbounded_channel::<OrderOutcome>();
+        let mut config = symbol_test_config();
+        config.cancel_interval_ms = 60_000;
+        let task = {
+            let (sim, limiter, orders, resync) = (sim.clone(), limiter.clone(), orders.clone(), resync.clone());
+            tokio::spawn(async move {
+                let t_optimal: SharedU64 = Arc::new(RwLock::new(0));
+                let _ = cancel_child_order(
+                    &reqwest::Client::new(), &limiter, &config, &orders, &None, &t_optimal, &outcome_tx, &resync, Some(&sim),
+                ).await;
+            })
+        };
+
+        // Young order (t_optimal 5s) survives a regular wait well short of the 60s sweep interval...
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(sim.open_order_count(), 1);
+
+        // ...but a reconnect cancels it right away
+        resync.fire();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(sim.open_order_count(), 0);
+        assert!(orders.lock().is_empty());
+        assert!(outcome_rx.try_recv().is_ok_and(|o| !o.filled));
+        task.abort();
     }
 }
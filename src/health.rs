@@ -0,0 +1,382 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::exchange::{self, Exchange, FlattenReport, FLATTEN_ROUNDS, FLATTEN_SETTLE};
+use crate::metrics_exporter::OrderCounters;
+use crate::model::{OrderMap, OrderSide, Position};
+
+/// Values only the trade loop knows, published once per cycle for `/status`
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq)]
+pub struct TradeStatus {
+    pub mid_price: f64,
+    pub collateral: f64,
+    /// Combined single-leg EV of the levels chosen that cycle
+    pub best_ev: f64,
+    /// Trading paused while the private API reports maintenance
+    pub maintenance_paused: bool,
+}
+
+/// Read-only handles into one symbol's shared state for the health endpoints
+#[derive(Debug, Clone)]
+pub struct HealthState {
+    pub symbol: String,
+    /// Timestamp (ms) of the latest public WS message; 0 until the first one
+    pub last_ws_message: Arc<RwLock<i64>>,
+    pub ws_stale_threshold_ms: i64,
+    pub position: Arc<RwLock<Position>>,
+    pub orders: Arc<Mutex<OrderMap>>,
+    pub trade_status: Arc<RwLock<TradeStatus>>,
+    /// This symbol's order lifecycle counts for `/metrics`
+    pub counters: Arc<OrderCounters>,
+}
+
+/// Every traded symbol's health state, in config order
+pub type HealthStates = Arc<Vec<HealthState>>;
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HealthBody {
+    pub symbol: String,
+    pub healthy: bool,
+    /// None until the first WS message arrives
+    pub ws_age_ms: Option<i64>,
+}
+
+/// `/healthz` body: healthy only while every symbol is
+#[derive(Debug, Serialize, PartialEq)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub symbols: Vec<HealthBody>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StatusBody {
+    pub symbol: String,
+    pub long_size: f64,
+    pub short_size: f64,
+    pub long_open_price: f64,
+    pub short_open_price: f64,
+    pub pending_orders: usize,
+    pub pending_buy_size: f64,
+    pub pending_sell_size: f64,
+    pub mid_price: f64,
+    pub collateral: f64,
+    pub best_ev: f64,
+    pub maintenance_paused: bool,
+    pub ws_age_ms: Option<i64>,
+}
+
+impl HealthState {
+    fn ws_age_ms(&self, now_ms: i64) -> Option<i64> {
+        let last = *self.last_ws_message.read();
+        (last > 0).then(|| now_ms - last)
+    }
+
+    /// 200 while the public WS is fresh, 503 when stale or never connected
+    pub fn health(&self, now_ms: i64) -> (StatusCode, HealthBody) {
+        let ws_age_ms = self.ws_age_ms(now_ms);
+        let healthy = ws_age_ms.is_some_and(|age| age < self.ws_stale_threshold_ms);
+        let code = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        (code, HealthBody { symbol: self.symbol.clone(), healthy, ws_age_ms })
+    }
+
+    pub fn status(&self, now_ms: i64) -> StatusBody {
+        let position = *self.position.read();
+        let (pending_orders, pending_buy_size, pending_sell_size) = {
+            let orders = self.orders.lock();
+            let size = |side: OrderSide| orders.pending(&side, false).size + orders.pending(&side, true).size;
+            (orders.len(), size(OrderSide::BUY), size(OrderSide::SELL))
+        };
+        let trade_status = *self.trade_status.read();
+        StatusBody {
+            symbol: self.symbol.clone(),
+            long_size: position.long_size,
+            short_size: position.short_size,
+            long_open_price: position.long_open_price,
+            short_open_price: position.short_open_price,
+            pending_orders,
+            pending_buy_size,
+            pending_sell_size,
+            mid_price: trade_status.mid_price,
+            collateral: trade_status.collateral,
+            best_ev: trade_status.best_ev,
+            maintenance_paused: trade_status.maintenance_paused,
+            ws_age_ms: self.ws_age_ms(now_ms),
+        }
+    }
+}
+
+/// 200 only while every symbol's public WS is fresh; one stale feed is enough for 503
+pub fn health_all(states: &[HealthState], now_ms: i64) -> (StatusCode, HealthReport) {
+    let symbols: Vec<HealthBody> = states.iter().map(|state| state.health(now_ms).1).collect();
+    let healthy = !symbols.is_empty() && symbols.iter().all(|body| body.healthy);
+    let code = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (code, HealthReport { healthy, symbols })
+}
+
+/// Raised by `POST /flatten`; the trade loops stop quoting once they see it (restart to resume)
+pub type TradingHalt = Arc<RwLock<bool>>;
+
+/// Handles for the `POST /flatten` admin command
+pub struct AdminState<E> {
+    /// Shared secret, expected as `Authorization: Bearer <secret>`
+    pub secret: String,
+    pub exchange: E,
+    pub orders: Arc<Mutex<OrderMap>>,
+    pub position: Arc<RwLock<Position>>,
+    /// Legs smaller than this are left alone (the exchange rejects them)
+    pub min_lot: f64,
+    pub halt: TradingHalt,
+}
+
+/// Compare without bailing at the first differing byte, so timing does not leak the secret
+fn secret_matches(given: &[u8], secret: &[u8]) -> bool {
+    given.len() == secret.len() && given.iter().zip(secret).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+impl<E: Exchange> AdminState<E> {
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|token| secret_matches(token.as_bytes(), self.secret.as_bytes()))
+    }
+
+    /// Halt trading, then cancel every open order and MARKET-close both legs. The halt goes up
+    /// first so the trade loop cannot requote behind the flatten.
+    /// 401 (and no exchange calls) without the secret, 502 when any step failed.
+    pub async fn flatten(&self, headers: &HeaderMap) -> (StatusCode, Option<FlattenReport>) {
+        if !self.authorized(headers) {
+            warn!("[ADMIN] Rejected /flatten: missing or wrong secret");
+            return (StatusCode::UNAUTHORIZED, None);
+        }
+        warn!("[ADMIN] Flatten requested, halting trading");
+        *self.halt.write() = true;
+        let report = exchange::flatten(&self.exchange, &self.position, self.min_lot, FLATTEN_ROUNDS, FLATTEN_SETTLE).await;
+        if report.orders_cancelled {
+            self.orders.lock().clear();
+        }
+        let code = if report.flat && report.orders_cancelled { StatusCode::OK } else { StatusCode::BAD_GATEWAY };
+        (code, Some(report))
+    }
+}
+
+async fn healthz(State(states): State<HealthStates>) -> (StatusCode, Json<HealthReport>) {
+    let (code, report) = health_all(&states, chrono::Utc::now().timestamp_millis());
+    (code, Json(report))
+}
+
+async fn status(State(states): State<HealthStates>) -> Json<Vec<StatusBody>> {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    Json(states.iter().map(|state| state.status(now_ms)).collect())
+}
+
+async fn flatten<E: Exchange + 'static>(State(state): State<Arc<AdminState<E>>>, headers: HeaderMap) -> Response {
+    match state.flatten(&headers).await {
+        (code, Some(report)) => (code, Json(report)).into_response(),
+        (code, None) => code.into_response(),
+    }
+}
+
+pub fn router(states: HealthStates) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/status", get(status))
+        .with_state(states)
+}
+
+pub fn admin_router<E: Exchange + 'static>(state: Arc<AdminState<E>>) -> Router {
+    Router::new().route("/flatten", post(flatten::<E>)).with_state(state)
+}
+
+/// Serve `/healthz`, `/status`, `/metrics` and `admin` (if any) on `addr:port` until the task is aborted
+pub async fn serve(addr: IpAddr, port: u16, states: HealthStates, admin: Option<Router>) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind((addr, port)).await?;
+    info!("[HEALTH] Listening on {}", listener.local_addr()?);
+    let mut app = router(states.clone()).merge(crate::metrics_exporter::router(states));
+    if let Some(admin) = admin {
+        app = app.merge(admin);
+    }
+    axum::serve(listener, app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{Call, MockExchange};
+    use crate::exchange::NewOrder;
+    use crate::model::OrderInfo;
+
+    const NOW_MS: i64 = 1_700_000_000_000;
+
+    fn state(last_ws_message: i64) -> HealthState {
+        HealthState {
+            symbol: "BTC_JPY".to_string(),
+            last_ws_message: Arc::new(RwLock::new(last_ws_message)),
+            ws_stale_threshold_ms: 60_000,
+            position: Arc::new(RwLock::new(Position::new())),
+            orders: Arc::new(Mutex::new(OrderMap::new())),
+            trade_status: Arc::new(RwLock::new(TradeStatus::default())),
+            counters: Arc::new(OrderCounters::new()),
+        }
+    }
+
+    #[test]
+    fn test_healthz_fresh_ws_is_healthy() {
+        let (code, body) = state(NOW_MS - 1_000).health(NOW_MS);
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body, HealthBody { symbol: "BTC_JPY".to_string(), healthy: true, ws_age_ms: Some(1_000) });
+    }
+
+    #[test]
+    fn test_healthz_unhealthy_when_any_symbol_is_stale() {
+        let eth = HealthState { symbol: "ETH_JPY".to_string(), ..state(NOW_MS - 60_000) };
+        let (code, report) = health_all(&[state(NOW_MS - 1_000), eth], NOW_MS);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!report.healthy);
+        let per_symbol: Vec<(&str, bool)> = report.symbols.iter().map(|body| (body.symbol.as_str(), body.healthy)).collect();
+        assert_eq!(per_symbol, vec![("BTC_JPY", true), ("ETH_JPY", false)]);
+
+        let (code, report) = health_all(&[state(NOW_MS - 1_000)], NOW_MS);
+        assert_eq!(code, StatusCode::OK);
+        assert!(report.healthy);
+    }
+
+    #[test]
+    fn test_healthz_stale_ws_is_unhealthy() {
+        let (code, body) = state(NOW_MS - 60_000).health(NOW_MS);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!body.healthy);
+
+        // Never connected counts as unhealthy too
+        let (code, body) = state(0).health(NOW_MS);
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.ws_age_ms, None);
+    }
+
+    #[test]
+    fn test_status_reports_position_orders_and_trade_values() {
+        let state = state(NOW_MS - 500);
+        {
+            let mut position = state.position.write();
+            position.long_size = 0.002;
+            position.long_open_price = 10_000_000.0;
+        }
+        state.orders.lock().insert("1".to_string(), OrderInfo {
+            price: 9_990_000, size: 0.001, side: OrderSide::BUY, timestamp: 0, is_close: false,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        });
+        *state.trade_status.write() =
+            TradeStatus { mid_price: 10_000_500.0, collateral: 123_456.0, best_ev: 0.5, maintenance_paused: true };
+
+        let body = state.status(NOW_MS);
+        assert_eq!(body.symbol, "BTC_JPY");
+        assert_eq!(body.long_size, 0.002);
+        assert_eq!(body.long_open_price, 10_000_000.0);
+        assert_eq!(body.pending_orders, 1);
+        assert_eq!(body.pending_buy_size, 0.001);
+        assert_eq!(body.pending_sell_size, 0.0);
+        assert_eq!(body.mid_price, 10_000_500.0);
+        assert_eq!(body.collateral, 123_456.0);
+        assert_eq!(body.best_ev, 0.5);
+        assert!(body.maintenance_paused);
+        assert_eq!(body.ws_age_ms, Some(500));
+
+        let json = serde_json::to_value(&body).unwrap();
+        assert!(json.get("pending_orders").is_some() && json.get("collateral").is_some());
+    }
+
+    fn admin_state() -> AdminState<MockExchange> {
+        AdminState {
+            secret: "s3cret".to_string(),
+            exchange: MockExchange {
+                position: Mutex::new(Position { long_size: 0.002, short_size: 0.001, ..Position::new() }),
+                ..Default::default()
+            },
+            orders: Arc::new(Mutex::new(OrderMap::new())),
+            position: Arc::new(RwLock::new(Position { long_size: 0.002, short_size: 0.001, ..Position::new() })),
+            min_lot: 0.001,
+            halt: Arc::new(RwLock::new(false)),
+        }
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_flatten_cancels_and_closes_both_sides() {
+        let state = admin_state();
+        state.orders.lock().insert("1".to_string(), OrderInfo {
+            price: 9_990_000, size: 0.001, side: OrderSide::BUY, timestamp: 0, is_close: false,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        });
+
+        let (code, report) = state.flatten(&bearer("s3cret")).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(state.exchange.calls(), vec![
+            Call::CancelAll,
+            Call::GetPosition,
+            Call::Close(NewOrder { side: OrderSide::SELL, price: None, size: 0.002 }),
+            Call::Close(NewOrder { side: OrderSide::BUY, price: None, size: 0.001 }),
+            Call::GetPosition,
+        ]);
+        let report = report.unwrap();
+        assert_eq!(report.close_order_ids.len(), 2);
+        assert!(report.errors.is_empty());
+        assert!(state.orders.lock().is_empty());
+        assert!(*state.halt.read(), "the trade loop must stop quoting");
+    }
+
+    #[tokio::test]
+    async fn test_flatten_keeps_orders_when_cancel_all_fails() {
+        let mut state = admin_state();
+        state.exchange.cancel_all_fails = true;
+        *state.exchange.position.lock() = Position::new();
+        state.orders.lock().insert("1".to_string(), OrderInfo {
+            price: 9_990_000, size: 0.001, side: OrderSide::BUY, timestamp: 0, is_close: false,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        });
+
+        let (code, report) = state.flatten(&bearer("s3cret")).await;
+        assert_eq!(code, StatusCode::BAD_GATEWAY);
+        assert!(report.unwrap().flat);
+        assert_eq!(state.orders.lock().len(), 1, "the cancel loop still has to deal with it");
+        // Position comes from the exchange, not the stale cache
+        assert_eq!(state.position.read().long_size, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_flatten_rejects_wrong_or_missing_secret() {
+        let state = admin_state();
+        for headers in [bearer("wrong"), bearer("s3cret2"), bearer(""), HeaderMap::new()] {
+            let (code, report) = state.flatten(&headers).await;
+            assert_eq!(code, StatusCode::UNAUTHORIZED);
+            assert!(report.is_none());
+        }
+        // A bare secret without the Bearer scheme is not accepted either
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, "s3cret".parse().unwrap());
+        assert_eq!(state.flatten(&headers).await.0, StatusCode::UNAUTHORIZED);
+
+        assert!(state.exchange.calls().is_empty());
+        assert_eq!(state.position.read().long_size, 0.002);
+        assert!(!*state.halt.read());
+    }
+}
@@ -3,9 +3,17 @@
 //! このクレートは、GMOコインAPIを使用した高頻度取引botの
 //! コア機能を提供します。
 
+pub mod alerting;
 pub mod api;
 pub mod bayes_prob;
+pub mod decimal;
+pub mod exchange;
+pub mod health;
 pub mod logging;
+pub mod metrics_exporter;
 pub mod model;
+pub mod reconnect;
+pub mod sim_exchange;
+pub mod strategy;
 pub mod time_queue;
 pub mod util;
@@ -0,0 +1,426 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use crate::logging::{jsonl, retention};
+use crate::model::LogFormat;
+
+/// Buffered records are written out at least this often...
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// ...or as soon as this many are waiting
+pub const MAX_BATCH_ROWS: usize = 256;
+/// How long `WriterHandle::shutdown` waits for the queue to be written out
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A record written by one of the daily loggers: one CSV row, or one JSONL object
+pub trait LogRecord: Serialize + Send + 'static {
+    fn csv_row(&self) -> Vec<String>;
+}
+
+/// Where and how a logger writes its daily files
+#[derive(Clone)]
+pub struct LogTarget {
+    /// Logger name for log messages, e.g. "TradeLogger"
+    pub name: &'static str,
+    pub dir: PathBuf,
+    pub path_for: fn(&Path, NaiveDate, LogFormat) -> PathBuf,
+    pub csv_header: &'static [&'static str],
+    pub format: LogFormat,
+    /// Delete daily files older than this many days (0 = keep forever)
+    pub retain_days: u32,
+    /// Every record gets a trailing `dry_run` column / field with this value, so simulated
+    /// rows can never pass for live ones
+    pub dry_run: bool,
+}
+
+/// Column / field appended to every record
+const DRY_RUN_FIELD: &str = "dry_run";
+
+fn csv_line<S: AsRef<[u8]>>(fields: &[S]) -> io::Result<Vec<u8>> {
+    let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    wtr.write_record(fields)?;
+    wtr.into_inner().map_err(|e| e.into_error())
+}
+
+fn encode<T: LogRecord>(record: &T, target: &LogTarget) -> io::Result<Vec<u8>> {
+    match target.format {
+        LogFormat::Csv => {
+            let mut row = record.csv_row();
+            row.push(target.dry_run.to_string());
+            csv_line(&row)
+        }
+        LogFormat::Jsonl => {
+            let mut value = serde_json::to_value(record)?;
+            if let Some(fields) = value.as_object_mut() {
+                fields.insert(DRY_RUN_FIELD.to_string(), target.dry_run.into());
+            }
+            let mut line = jsonl::to_line(&value)?.into_bytes();
+            line.push(b'\n');
+            Ok(line)
+        }
+    }
+}
+
+/// First line of `path`, newline included (empty for an empty file)
+fn first_line(path: &Path) -> io::Result<Vec<u8>> {
+    let mut line = Vec::new();
+    BufReader::new(fs::File::open(path)?).read_until(b'\n', &mut line)?;
+    Ok(line)
+}
+
+/// Where a daily file with an outdated header goes: `trades-2024-01-15.csv` ->
+/// `schema-<HHMMSS>-trades-2024-01-15.csv`, keeping the date last so retention still
+/// compresses and prunes it
+fn schema_rotated_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("schema-{}-{}", Utc::now().format("%H%M%S"), name))
+}
+
+/// Append handle on the current day's file, kept open across batches and
+/// reopened on date rollover. Blocking; only used from `spawn_blocking`.
+struct DailyFile {
+    target: LogTarget,
+    open: Option<(NaiveDate, BufWriter<fs::File>)>,
+}
+
+impl DailyFile {
+    fn new(target: LogTarget) -> Self {
+        Self { target, open: None }
+    }
+
+    fn open_file(&self, date: NaiveDate) -> io::Result<BufWriter<fs::File>> {
+        let path = (self.target.path_for)(&self.target.dir, date, self.target.format);
+        let header: Vec<&str> = self.target.csv_header.iter().copied().chain([DRY_RUN_FIELD]).collect();
+        let header = csv_line(&header)?;
+        let mut is_new = !path.exists();
+        // A file started under another column layout (older build) is moved aside so no
+        // file ever mixes two schemas under one header
+        if !is_new && self.target.format == LogFormat::Csv && first_line(&path)? != header {
+            let rotated = schema_rotated_path(&path);
+            fs::rename(&path, &rotated)?;
+            warn!(
+                "{}: {} has a different header, moved to {} and starting a new file",
+                self.target.name, path.display(), rotated.display()
+            );
+            is_new = true;
+        }
+        let mut writer = BufWriter::new(fs::OpenOptions::new().create(true).append(true).open(&path)?);
+        if is_new && self.target.format == LogFormat::Csv {
+            writer.write_all(&header)?;
+        }
+        Ok(writer)
+    }
+
+    fn write_batch(&mut self, batch: &[(NaiveDate, Vec<u8>)]) -> io::Result<()> {
+        for (date, bytes) in batch {
+            if self.open.as_ref().map(|(d, _)| d) != Some(date) {
+                self.close()?;
+                self.open = Some((*date, self.open_file(*date)?));
+            }
+            if let Some((_, writer)) = self.open.as_mut() {
+                writer.write_all(bytes)?;
+            }
+        }
+        match self.open.as_mut() {
+            Some((_, writer)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        match self.open.take() {
+            Some((_, mut writer)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Write `batch` (and optionally close the file) off the async runtime.
+async fn flush_batch(file: &mut Option<DailyFile>, target: &LogTarget, batch: &mut Vec<(NaiveDate, Vec<u8>)>, close: bool) {
+    let mut daily = file.take().unwrap_or_else(|| DailyFile::new(target.clone()));
+    let rows = std::mem::take(batch);
+    let written = tokio::task::spawn_blocking(move || {
+        let result = daily.write_batch(&rows).and_then(|_| if close { daily.close() } else { Ok(()) });
+        (daily, result)
+    })
+    .await;
+    match written {
+        Ok((daily, result)) => {
+            if let Err(e) = result {
+                error!("{}: failed to write log batch: {}", target.name, e);
+            }
+            *file = Some(daily);
+        }
+        Err(e) => error!("{}: log write task panicked: {}", target.name, e),
+    }
+}
+
+/// Handle on a spawned writer task, shared by every clone of its logger
+#[derive(Clone)]
+pub struct WriterHandle {
+    name: &'static str,
+    close: Arc<Notify>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl WriterHandle {
+    /// Stop accepting records and wait up to `timeout` for everything already queued to be
+    /// written. Works while other clones of the sender are still alive. Returns false if the
+    /// writer did not finish in time; a second call after a successful one returns true.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.close.notify_one();
+        let Some(task) = self.task.lock().take() else {
+            return true;
+        };
+        match tokio::time::timeout(timeout, task).await {
+            Ok(Ok(())) => true,
+            Ok(Err(e)) => {
+                error!("{}: writer task panicked: {}", self.name, e);
+                false
+            }
+            Err(_) => {
+                warn!("{}: log writer still draining after {:?}, giving up", self.name, timeout);
+                false
+            }
+        }
+    }
+}
+
+/// Spawn `run` for `target` on a channel of `buffer` records
+pub fn spawn<T: LogRecord>(target: LogTarget, buffer: usize) -> (mpsc::Sender<T>, WriterHandle) {
+    let (sender, receiver) = mpsc::channel(buffer);
+    let close = Arc::new(Notify::new());
+    let name = target.name;
+    let task = tokio::spawn(run(target, receiver, close.clone()));
+    (sender, WriterHandle { name, close, task: Arc::new(Mutex::new(Some(task))) })
+}
+
+/// Drain `receiver` into the target's daily files until every sender is dropped or `close`
+/// is notified.
+///
+/// Records are buffered and written every `FLUSH_INTERVAL` or `MAX_BATCH_ROWS`, whichever
+/// comes first, through one file handle per day. Whatever is buffered or still queued when
+/// the channel closes is written before returning.
+pub async fn run<T: LogRecord>(target: LogTarget, mut receiver: mpsc::Receiver<T>, close: Arc<Notify>) {
+    if let Err(e) = fs::create_dir_all(&target.dir) {
+        error!("Failed to create {} log directory: {}", target.name, e);
+        return;
+    }
+
+    info!("{} started: {}", target.name, target.dir.display());
+
+    let mut file: Option<DailyFile> = None;
+    let mut batch: Vec<(NaiveDate, Vec<u8>)> = Vec::new();
+    let mut last_rotation: Option<NaiveDate> = None;
+    let mut ticker = tokio::time::interval(FLUSH_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut closing = false;
+
+    loop {
+        tokio::select! {
+            // Refuse new records; recv() keeps returning the queued ones, then None
+            _ = close.notified(), if !closing => {
+                closing = true;
+                receiver.close();
+            }
+            record = receiver.recv() => {
+                let Some(record) = record else { break };
+                // Rotate once per day: on the first record and again after each date rollover.
+                // The finished day is written out and closed first so it can be gzipped.
+                let today = Utc::now().date_naive();
+                if last_rotation != Some(today) {
+                    flush_batch(&mut file, &target, &mut batch, true).await;
+                    last_rotation = Some(today);
+                    let (dir, retain_days) = (target.dir.clone(), target.retain_days);
+                    if let Err(e) = tokio::task::spawn_blocking(move || {
+                        retention::rotate_dir(&dir, retain_days, today);
+                    }).await {
+                        error!("Log rotation task panicked: {}", e);
+                    }
+                }
+
+                match encode(&record, &target) {
+                    Ok(bytes) => batch.push((today, bytes)),
+                    Err(e) => error!("{}: failed to encode record: {}", target.name, e),
+                }
+                if batch.len() >= MAX_BATCH_ROWS {
+                    flush_batch(&mut file, &target, &mut batch, false).await;
+                }
+            }
+            _ = ticker.tick() => {
+                if !batch.is_empty() {
+                    flush_batch(&mut file, &target, &mut batch, false).await;
+                }
+            }
+        }
+    }
+
+    flush_batch(&mut file, &target, &mut batch, true).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        id: u32,
+        note: String,
+    }
+
+    impl LogRecord for Row {
+        fn csv_row(&self) -> Vec<String> {
+            vec![self.id.to_string(), self.note.clone()]
+        }
+    }
+
+    fn row_path(dir: &Path, date: NaiveDate, format: LogFormat) -> PathBuf {
+        dir.join(format!("rows-{}.{}", date.format("%Y-%m-%d"), format.extension()))
+    }
+
+    fn target(test: &str, format: LogFormat) -> LogTarget {
+        LogTarget {
+            name: "TestLogger",
+            dir: std::env::temp_dir().join(format!("batch_writer_{}_{}", test, std::process::id())),
+            path_for: row_path,
+            csv_header: &["id", "note"],
+            format,
+            retain_days: 0,
+            dry_run: false,
+        }
+    }
+
+    fn read_today(target: &LogTarget) -> String {
+        fs::read_to_string(row_path(&target.dir, Utc::now().date_naive(), target.format)).unwrap_or_default()
+    }
+
+    fn row(id: u32) -> Row {
+        Row { id, note: format!("note, {}", id) }
+    }
+
+    #[tokio::test]
+    async fn test_buffered_rows_persisted_after_flush_interval() {
+        let target = target("interval", LogFormat::Csv);
+        let (sender, receiver) = mpsc::channel(16);
+        let task = tokio::spawn(run(target.clone(), receiver, Arc::default()));
+        for id in 0..3 {
+            sender.send(row(id)).await.unwrap();
+        }
+
+        // Sender still open: only the timer can have written these
+        tokio::time::sleep(FLUSH_INTERVAL + Duration::from_millis(300)).await;
+        let content = read_today(&target);
+        assert_eq!(
+            content.lines().collect::<Vec<_>>(),
+            ["id,note,dry_run", "0,\"note, 0\",false", "1,\"note, 1\",false", "2,\"note, 2\",false"]
+        );
+
+        drop(sender);
+        task.await.unwrap();
+        let _ = fs::remove_dir_all(&target.dir);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_tags_every_row() {
+        for format in [LogFormat::Csv, LogFormat::Jsonl] {
+            let target = LogTarget { dry_run: true, ..target("dry_run", format) };
+            let (sender, receiver) = mpsc::channel(16);
+            let task = tokio::spawn(run(target.clone(), receiver, Arc::default()));
+            sender.send(row(7)).await.unwrap();
+            drop(sender);
+            task.await.unwrap();
+
+            let content = read_today(&target);
+            match format {
+                LogFormat::Csv => assert_eq!(content.lines().collect::<Vec<_>>(), ["id,note,dry_run", "7,\"note, 7\",true"]),
+                LogFormat::Jsonl => {
+                    let json: serde_json::Value = serde_json::from_str(content.trim_end()).unwrap();
+                    assert_eq!((json["id"].as_u64(), json["dry_run"].as_bool()), (Some(7), Some(true)));
+                }
+            }
+            let _ = fs::remove_dir_all(&target.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_header_change_starts_a_new_file() {
+        let target = target("schema", LogFormat::Csv);
+        fs::create_dir_all(&target.dir).unwrap();
+        let today = row_path(&target.dir, Utc::now().date_naive(), LogFormat::Csv);
+        // Written by a build without the dry_run column
+        fs::write(&today, "id,note\n1,old\n").unwrap();
+
+        let (sender, receiver) = mpsc::channel(16);
+        let task = tokio::spawn(run(target.clone(), receiver, Arc::default()));
+        sender.send(row(2)).await.unwrap();
+        drop(sender);
+        task.await.unwrap();
+
+        assert_eq!(read_today(&target).lines().collect::<Vec<_>>(), ["id,note,dry_run", "2,\"note, 2\",false"]);
+        let rotated: Vec<String> = fs::read_dir(&target.dir).unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p != &today)
+            .map(|p| fs::read_to_string(p).unwrap())
+            .collect();
+        assert_eq!(rotated, ["id,note\n1,old\n"]);
+
+        // Same header: appended to, not rotated
+        let (sender, receiver) = mpsc::channel(16);
+        let task = tokio::spawn(run(target.clone(), receiver, Arc::default()));
+        sender.send(row(3)).await.unwrap();
+        drop(sender);
+        task.await.unwrap();
+        assert_eq!(read_today(&target).lines().count(), 3);
+        assert_eq!(fs::read_dir(&target.dir).unwrap().count(), 2);
+        let _ = fs::remove_dir_all(&target.dir);
+    }
+
+    #[tokio::test]
+    async fn test_all_rows_persisted_on_shutdown() {
+        let target = target("shutdown", LogFormat::Jsonl);
+        let (sender, receiver) = mpsc::channel(1000);
+        let task = tokio::spawn(run(target.clone(), receiver, Arc::default()));
+        // More than one full batch plus a partial one
+        let count = MAX_BATCH_ROWS as u32 * 2 + 7;
+        for id in 0..count {
+            sender.send(row(id)).await.unwrap();
+        }
+        drop(sender);
+        task.await.unwrap();
+
+        let content = read_today(&target);
+        let ids: Vec<u64> = content
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["id"].as_u64().unwrap())
+            .collect();
+        assert_eq!(ids, (0..count as u64).collect::<Vec<_>>());
+        let _ = fs::remove_dir_all(&target.dir);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_queue_while_senders_live() {
+        let target = target("close", LogFormat::Csv);
+        let (sender, writer) = spawn(target.clone(), 1000);
+        let count = MAX_BATCH_ROWS as u32 + 10;
+        for id in 0..count {
+            sender.send(row(id)).await.unwrap();
+        }
+
+        // `sender` is still alive: only the close signal can end the writer
+        assert!(writer.shutdown(SHUTDOWN_TIMEOUT).await);
+        assert_eq!(read_today(&target).lines().count(), count as usize + 1, "header + every row");
+        assert!(sender.send(row(count)).await.is_err(), "closed channel refuses new rows");
+        assert!(writer.shutdown(SHUTDOWN_TIMEOUT).await, "second shutdown is a no-op");
+        let _ = fs::remove_dir_all(&target.dir);
+    }
+}
@@ -0,0 +1,19 @@
+use serde::Serialize;
+
+/// One compact JSON object, no trailing newline
+pub fn to_line<T: Serialize>(value: &T) -> serde_json::Result<String> {
+    serde_json::to_string(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_line_is_single_line() {
+        let value = serde_json::json!({"a": 1, "nested": {"text": "two\nlines"}});
+        let line = to_line(&value).unwrap();
+        assert!(!line.contains('\n'));
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&line).unwrap(), value);
+    }
+}
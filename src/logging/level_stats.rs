@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use crate::model::{FloatingExp, OrderSide};
+
+/// Mid-price drift is measured this long after a fill
+pub const MARKOUT_HORIZON_MS: i64 = 1000;
+
+/// Realized fill rate and post-fill drift for one (level, side)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LevelStat {
+    /// Open orders resolved at this level (filled or cancelled)
+    pub orders: u64,
+    pub fills: u64,
+    /// Fills whose markout has been measured
+    pub markouts: u64,
+    /// Running mean of the adverse mid move MARKOUT_HORIZON_MS after a fill (JPY).
+    /// Positive = the market moved against the fill (fell after a buy, rose after a sell).
+    pub avg_adverse_jpy: f64,
+}
+
+impl LevelStat {
+    pub fn fill_rate(&self) -> f64 {
+        if self.orders == 0 { 0.0 } else { self.fills as f64 / self.orders as f64 }
+    }
+
+    fn add_markout(&mut self, adverse_jpy: f64) {
+        self.markouts += 1;
+        self.avg_adverse_jpy += (adverse_jpy - self.avg_adverse_jpy) / self.markouts as f64;
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingMarkout {
+    level: FloatingExp,
+    side: OrderSide,
+    fill_ms: i64,
+    /// Mid at the first cycle after the fill was seen
+    fill_mid: Option<f64>,
+}
+
+/// Per-level fill-rate / adverse-selection accumulator fed by the trade loop.
+///
+/// Fills are timed when their outcome reaches the trade loop, so the markout window starts
+/// at that cycle's mid and closes at the first cycle at least MARKOUT_HORIZON_MS later.
+#[derive(Debug, Default)]
+pub struct LevelStats {
+    buy: BTreeMap<FloatingExp, LevelStat>,
+    sell: BTreeMap<FloatingExp, LevelStat>,
+    pending: Vec<PendingMarkout>,
+}
+
+impl LevelStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn side_mut(&mut self, side: &OrderSide) -> &mut BTreeMap<FloatingExp, LevelStat> {
+        if *side == OrderSide::BUY { &mut self.buy } else { &mut self.sell }
+    }
+
+    pub fn get(&self, side: &OrderSide, level: &FloatingExp) -> Option<&LevelStat> {
+        if *side == OrderSide::BUY { self.buy.get(level) } else { self.sell.get(level) }
+    }
+
+    /// Count a resolved open order; a fill also starts its markout window at `now_ms`
+    pub fn record_outcome(&mut self, level: FloatingExp, side: OrderSide, filled: bool, now_ms: i64) {
+        let stat = self.side_mut(&side).entry(level.clone()).or_default();
+        stat.orders += 1;
+        if filled {
+            stat.fills += 1;
+            self.pending.push(PendingMarkout { level, side, fill_ms: now_ms, fill_mid: None });
+        }
+    }
+
+    /// Feed the current mid: anchors new fills and settles markouts past the horizon
+    pub fn on_mid(&mut self, now_ms: i64, mid_price: f64) {
+        let mut settled = Vec::new();
+        self.pending.retain_mut(|p| match p.fill_mid {
+            None => {
+                p.fill_mid = Some(mid_price);
+                true
+            }
+            Some(fill_mid) if now_ms - p.fill_ms >= MARKOUT_HORIZON_MS => {
+                let adverse = if p.side == OrderSide::BUY { fill_mid - mid_price } else { mid_price - fill_mid };
+                settled.push((p.level.clone(), p.side.clone(), adverse));
+                false
+            }
+            Some(_) => true,
+        });
+        for (level, side, adverse) in settled {
+            self.side_mut(&side).entry(level).or_default().add_markout(adverse);
+        }
+    }
+
+    /// CSV rows for every (level, side) seen so far, BUY then SELL, levels ascending
+    pub fn rows(&self, timestamp: &str) -> Vec<Vec<String>> {
+        [(OrderSide::BUY, &self.buy), (OrderSide::SELL, &self.sell)]
+            .iter()
+            .flat_map(|(side, stats)| {
+                stats.iter().map(move |(level, stat)| {
+                    vec![
+                        timestamp.to_string(),
+                        side.to_string(),
+                        level.rate.to_string(),
+                        stat.orders.to_string(),
+                        stat.fills.to_string(),
+                        format!("{:.4}", stat.fill_rate()),
+                        stat.markouts.to_string(),
+                        format!("{:.3}", stat.avg_adverse_jpy),
+                    ]
+                })
+            })
+            .collect()
+    }
+}
+
+const CSV_HEADER: &[&str] = &[
+    "timestamp", "side", "level", "orders", "fills", "fill_rate", "markouts", "avg_adverse_jpy",
+];
+
+fn csv_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("level_stats-{}.csv", date.format("%Y-%m-%d")))
+}
+
+/// Append one dump to `level_stats-<date>.csv`, writing the header on first use. Blocking.
+pub fn append_csv(dir: &Path, date: NaiveDate, rows: &[Vec<String>]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = csv_file_path(dir, date);
+    let is_new = !path.exists();
+    let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+    if is_new {
+        wtr.write_record(CSV_HEADER)?;
+    }
+    for row in rows {
+        wtr.write_record(row)?;
+    }
+    wtr.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(rate: f64) -> FloatingExp {
+        FloatingExp::new(10.0, -5.0, rate)
+    }
+
+    #[test]
+    fn test_counts_orders_and_fills_per_level_and_side() {
+        let mut stats = LevelStats::new();
+        stats.record_outcome(level(4.0), OrderSide::BUY, true, 0);
+        stats.record_outcome(level(4.0), OrderSide::BUY, false, 0);
+        stats.record_outcome(level(4.0), OrderSide::BUY, false, 0);
+        stats.record_outcome(level(4.0), OrderSide::BUY, true, 0);
+        stats.record_outcome(level(5.0), OrderSide::SELL, false, 0);
+
+        let buy4 = stats.get(&OrderSide::BUY, &level(4.0)).unwrap();
+        assert_eq!((buy4.orders, buy4.fills), (4, 2));
+        assert!((buy4.fill_rate() - 0.5).abs() < 1e-12);
+        let sell5 = stats.get(&OrderSide::SELL, &level(5.0)).unwrap();
+        assert_eq!((sell5.orders, sell5.fills, sell5.fill_rate()), (1, 0, 0.0));
+        assert!(stats.get(&OrderSide::SELL, &level(4.0)).is_none());
+    }
+
+    #[test]
+    fn test_markout_running_average() {
+        let mut stats = LevelStats::new();
+        // Buy filled at mid 10,000,000; mid 9,999,900 a second later: 100 JPY adverse
+        stats.record_outcome(level(4.0), OrderSide::BUY, true, 0);
+        stats.on_mid(0, 10_000_000.0);
+        stats.on_mid(500, 9_999_000.0); // before the horizon: not settled
+        assert_eq!(stats.get(&OrderSide::BUY, &level(4.0)).unwrap().markouts, 0);
+        stats.on_mid(1_000, 9_999_900.0);
+
+        // Second buy: mid rose 40 JPY, i.e. -40 adverse
+        stats.record_outcome(level(4.0), OrderSide::BUY, true, 2_000);
+        stats.on_mid(2_000, 10_000_000.0);
+        stats.on_mid(3_500, 10_000_040.0);
+
+        let buy4 = stats.get(&OrderSide::BUY, &level(4.0)).unwrap();
+        assert_eq!(buy4.markouts, 2);
+        assert!((buy4.avg_adverse_jpy - 30.0).abs() < 1e-9, "(100 - 40) / 2, got {}", buy4.avg_adverse_jpy);
+
+        // Sells are adverse when mid rises
+        stats.record_outcome(level(6.0), OrderSide::SELL, true, 4_000);
+        stats.on_mid(4_000, 10_000_000.0);
+        stats.on_mid(5_000, 10_000_025.0);
+        assert!((stats.get(&OrderSide::SELL, &level(6.0)).unwrap().avg_adverse_jpy - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rows_and_csv_append() {
+        let mut stats = LevelStats::new();
+        stats.record_outcome(level(5.0), OrderSide::SELL, true, 0);
+        stats.record_outcome(level(4.0), OrderSide::BUY, false, 0);
+        let rows = stats.rows("2024-01-15T10:30:00Z");
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], ["2024-01-15T10:30:00Z", "BUY", "4", "1", "0", "0.0000", "0", "0.000"]);
+        assert_eq!(rows[1][1], "SELL");
+
+        let dir = std::env::temp_dir().join(format!("level_stats_test_{}", std::process::id()));
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        append_csv(&dir, date, &rows).unwrap();
+        append_csv(&dir, date, &rows).unwrap();
+        let content = fs::read_to_string(csv_file_path(&dir, date)).unwrap();
+        assert_eq!(content.lines().count(), 5, "one header + two dumps of two rows");
+        assert!(content.starts_with("timestamp,side,level,"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
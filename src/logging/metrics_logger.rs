@@ -1,14 +1,18 @@
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use chrono::{NaiveDate, Utc};
+use serde::Serialize;
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tracing::{error, warn};
+
+use crate::logging::batch_writer::{self, LogRecord, LogTarget, WriterHandle};
+use crate::logging::level_stats;
+use crate::logging::order_age_hist;
+use crate::model::LogFormat;
 
 const CHANNEL_BUFFER_SIZE: usize = 1000;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MetricsSnapshot {
     pub timestamp: String,
     pub mid_price: f64,
@@ -26,6 +30,16 @@ pub struct MetricsSnapshot {
     pub sell_prob_avg: f64,
     pub sigma_1s: f64,
     pub t_optimal_ms: f64,
+    pub long_hold_ms: u64,
+    pub short_hold_ms: u64,
+    /// Lower credible bound of P(fill) at the chosen level (None = not recorded)
+    pub buy_p_fill_lower: Option<f64>,
+    pub sell_p_fill_lower: Option<f64>,
+    /// Realized PnL from fills since startup (JPY, before fees)
+    pub realized_pnl: f64,
+    pub round_trips: u64,
+    /// Short-window over long-window realized volatility (> 1 = volatility expanding)
+    pub vol_ratio: f64,
 }
 
 impl MetricsSnapshot {
@@ -47,27 +61,53 @@ impl MetricsSnapshot {
             self.sell_prob_avg.to_string(),
             self.sigma_1s.to_string(),
             self.t_optimal_ms.to_string(),
+            self.long_hold_ms.to_string(),
+            self.short_hold_ms.to_string(),
+            self.buy_p_fill_lower.map(|p| format!("{:.6}", p)).unwrap_or_default(),
+            self.sell_p_fill_lower.map(|p| format!("{:.6}", p)).unwrap_or_default(),
+            self.realized_pnl.to_string(),
+            self.round_trips.to_string(),
+            self.vol_ratio.to_string(),
         ]
     }
 }
 
+impl LogRecord for MetricsSnapshot {
+    fn csv_row(&self) -> Vec<String> {
+        self.to_csv_row()
+    }
+}
+
 const CSV_HEADER: &[&str] = &[
     "timestamp", "mid_price", "best_bid", "best_ask", "spread", "volatility",
     "best_ev", "buy_spread_pct", "sell_spread_pct", "long_size", "short_size",
     "collateral", "buy_prob_avg", "sell_prob_avg", "sigma_1s", "t_optimal_ms",
+    "long_hold_ms", "short_hold_ms", "buy_p_fill_lower", "sell_p_fill_lower",
+    "realized_pnl", "round_trips", "vol_ratio",
 ];
 
 #[derive(Clone)]
 pub struct MetricsLogger {
     sender: mpsc::Sender<MetricsSnapshot>,
+    writer: WriterHandle,
+    metrics_dir: PathBuf,
 }
 
 impl MetricsLogger {
-    pub fn new(log_dir: &str) -> Self {
-        let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_SIZE);
+    /// `retain_days`: delete daily log files older than this many days (0 = keep forever).
+    pub fn new(log_dir: &str, retain_days: u32, format: LogFormat, dry_run: bool) -> Self {
         let metrics_dir = PathBuf::from(log_dir).join("metrics");
-        tokio::spawn(writer_task(metrics_dir, receiver));
-        Self { sender }
+        let target = LogTarget {
+            name: "MetricsLogger",
+            dir: metrics_dir.clone(),
+            path_for: log_file_path,
+            csv_header: CSV_HEADER,
+            format,
+            retain_days,
+            dry_run,
+        };
+        let (sender, writer) = batch_writer::spawn(target, CHANNEL_BUFFER_SIZE);
+        Self { sender, writer, metrics_dir }
     }
 
     pub fn log(&self, snapshot: MetricsSnapshot) {
@@ -75,80 +115,55 @@ impl MetricsLogger {
             warn!("Metrics logger buffer full, dropping snapshot: {}", e);
         }
     }
-}
-
-fn csv_file_path(dir: &PathBuf, date: NaiveDate) -> PathBuf {
-    dir.join(format!("metrics-{}.csv", date.format("%Y-%m-%d")))
-}
 
-fn ensure_csv_with_header(path: &PathBuf) -> io::Result<()> {
-    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
-        Ok(file) => {
-            let mut wtr = csv::Writer::from_writer(file);
-            wtr.write_record(CSV_HEADER)?;
-            wtr.flush()?;
-        }
-        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
-        Err(e) => return Err(e),
+    /// Close the channel (for every clone) and wait for queued snapshots to reach the file
+    pub async fn shutdown(self) {
+        self.writer.shutdown(batch_writer::SHUTDOWN_TIMEOUT).await;
     }
-    Ok(())
-}
-
-fn write_csv_row(metrics_dir: &PathBuf, row: &[String]) {
-    let today = Utc::now().date_naive();
-    let file_path = csv_file_path(metrics_dir, today);
 
-    if let Err(e) = ensure_csv_with_header(&file_path) {
-        error!("Failed to create metrics CSV header: {}", e);
-        return;
+    /// Append a `LevelStats` dump to `level_stats-<date>.csv` next to the metrics CSVs
+    pub fn log_level_stats(&self, rows: Vec<Vec<String>>) {
+        let dir = self.metrics_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = level_stats::append_csv(&dir, Utc::now().date_naive(), &rows) {
+                error!("Failed to write level stats: {}", e);
+            }
+        });
     }
 
-    let file = match fs::OpenOptions::new().append(true).open(&file_path) {
-        Ok(f) => f,
-        Err(e) => {
-            error!("Failed to open metrics log file: {}", e);
-            return;
-        }
-    };
-
-    let mut wtr = csv::WriterBuilder::new()
-        .has_headers(false)
-        .from_writer(file);
-
-    if let Err(e) = wtr.write_record(row) {
-        error!("Failed to write metrics snapshot: {}", e);
-    }
-    if let Err(e) = wtr.flush() {
-        error!("Failed to flush metrics log: {}", e);
+    /// Append an `OrderAgeHistogram` dump to `order_age_hist-<date>.csv` next to the metrics CSVs
+    pub fn log_order_age_hist(&self, rows: Vec<Vec<String>>) {
+        let dir = self.metrics_dir.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Err(e) = order_age_hist::append_csv(&dir, Utc::now().date_naive(), &rows) {
+                error!("Failed to write order age histogram: {}", e);
+            }
+        });
     }
 }
 
-async fn writer_task(metrics_dir: PathBuf, mut receiver: mpsc::Receiver<MetricsSnapshot>) {
-    if let Err(e) = fs::create_dir_all(&metrics_dir) {
-        error!("Failed to create metrics log directory: {}", e);
-        return;
-    }
+fn csv_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("metrics-{}.csv", date.format("%Y-%m-%d")))
+}
 
-    info!("MetricsLogger started: {}", metrics_dir.display());
+fn jsonl_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("metrics-{}.jsonl", date.format("%Y-%m-%d")))
+}
 
-    while let Some(snapshot) = receiver.recv().await {
-        let row = snapshot.to_csv_row();
-        let dir = metrics_dir.clone();
-        if let Err(e) = tokio::task::spawn_blocking(move || {
-            write_csv_row(&dir, &row);
-        }).await {
-            error!("Metrics log write task panicked: {}", e);
-        }
+fn log_file_path(dir: &Path, date: NaiveDate, format: LogFormat) -> PathBuf {
+    match format {
+        LogFormat::Csv => csv_file_path(dir, date),
+        LogFormat::Jsonl => jsonl_file_path(dir, date),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::logging::jsonl;
 
-    #[test]
-    fn test_metrics_snapshot_csv_row() {
-        let snapshot = MetricsSnapshot {
+    fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
             timestamp: "2024-01-15T10:30:00Z".to_string(),
             mid_price: 6505000.0,
             best_bid: 6500000.0,
@@ -165,14 +180,32 @@ mod tests {
             sell_prob_avg: 0.52,
             sigma_1s: 0.00077,
             t_optimal_ms: 4200.0,
-        };
+            long_hold_ms: 185000,
+            short_hold_ms: 0,
+            buy_p_fill_lower: Some(0.0123),
+            sell_p_fill_lower: None,
+            realized_pnl: 1.25,
+            round_trips: 3,
+            vol_ratio: 2.5,
+        }
+    }
 
-        let row = snapshot.to_csv_row();
-        assert_eq!(row.len(), 16);
+    #[test]
+    fn test_metrics_snapshot_csv_row() {
+        let row = snapshot().to_csv_row();
+        assert_eq!(row.len(), 23);
+        assert_eq!(row.len(), CSV_HEADER.len());
         assert_eq!(row[0], "2024-01-15T10:30:00Z");
         assert_eq!(row[1], "6505000");
         assert_eq!(row[14], "0.00077");
         assert_eq!(row[15], "4200");
+        assert_eq!(row[16], "185000");
+        assert_eq!(row[17], "0");
+        assert_eq!(row[18], "0.012300");
+        assert_eq!(row[19], "");
+        assert_eq!(row[20], "1.25");
+        assert_eq!(row[21], "3");
+        assert_eq!(row[22], "2.5");
     }
 
     #[test]
@@ -182,4 +215,34 @@ mod tests {
         let path = csv_file_path(&dir, date);
         assert_eq!(path, PathBuf::from("logs/metrics/metrics-2024-01-15.csv"));
     }
+
+    #[test]
+    fn test_metrics_snapshot_jsonl_round_trip() {
+        let line = jsonl::to_line(&snapshot()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        let fields = json.as_object().unwrap();
+        assert_eq!(fields.len(), CSV_HEADER.len(), "same fields as the CSV columns");
+        for column in CSV_HEADER {
+            assert!(fields.contains_key(*column), "missing {}", column);
+        }
+        assert_eq!(json["mid_price"], 6505000.0);
+        assert_eq!(json["buy_p_fill_lower"], 0.0123);
+        assert!(json["sell_p_fill_lower"].is_null());
+        assert_eq!(json["round_trips"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_writes_every_queued_snapshot() {
+        let log_dir = std::env::temp_dir().join(format!("metrics_logger_shutdown_{}", std::process::id()));
+        let logger = MetricsLogger::new(log_dir.to_str().unwrap(), 0, LogFormat::Csv, false);
+        let count = 300;
+        for _ in 0..count {
+            logger.log(snapshot());
+        }
+        logger.shutdown().await;
+
+        let content = std::fs::read_to_string(csv_file_path(&log_dir.join("metrics"), Utc::now().date_naive())).unwrap();
+        assert_eq!(content.lines().count(), count + 1, "header + every snapshot");
+        let _ = std::fs::remove_dir_all(&log_dir);
+    }
 }
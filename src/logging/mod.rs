@@ -1,2 +1,7 @@
 pub mod trade_logger;
 pub mod metrics_logger;
+pub mod level_stats;
+pub mod order_age_hist;
+pub mod jsonl;
+pub mod batch_writer;
+pub mod retention;
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+/// Upper bounds (inclusive, ms) of the order-age buckets; older orders land in a final overflow bucket
+pub const ORDER_AGE_BUCKETS_MS: [u64; 10] = [250, 500, 1_000, 2_000, 3_000, 5_000, 10_000, 20_000, 30_000, 60_000];
+
+/// Lifetimes of resolved open orders, split by fill vs cancel, for tuning the t_optimal
+/// cancel threshold: fills piling up just under it suggest it cuts orders off too early.
+///
+/// Counts accumulate from startup; each dump is a full snapshot, not a delta.
+#[derive(Debug, Default)]
+pub struct OrderAgeHistogram {
+    filled: [u64; ORDER_AGE_BUCKETS_MS.len() + 1],
+    cancelled: [u64; ORDER_AGE_BUCKETS_MS.len() + 1],
+}
+
+impl OrderAgeHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the first bucket whose bound is >= `age_ms` (the overflow bucket past the last)
+    fn bucket(age_ms: u64) -> usize {
+        ORDER_AGE_BUCKETS_MS.partition_point(|&bound| bound < age_ms)
+    }
+
+    pub fn record(&mut self, age_ms: u64, filled: bool) {
+        let bucket = Self::bucket(age_ms);
+        if filled {
+            self.filled[bucket] += 1;
+        } else {
+            self.cancelled[bucket] += 1;
+        }
+    }
+
+    /// (filled, cancelled) counts of the bucket holding `age_ms`
+    pub fn counts(&self, age_ms: u64) -> (u64, u64) {
+        let bucket = Self::bucket(age_ms);
+        (self.filled[bucket], self.cancelled[bucket])
+    }
+
+    /// One CSV row per bucket, empty ones included, so every dump has the same shape
+    pub fn rows(&self, timestamp: &str) -> Vec<Vec<String>> {
+        let bounds = ORDER_AGE_BUCKETS_MS.iter().map(|b| b.to_string()).chain(std::iter::once("inf".to_string()));
+        bounds
+            .zip(self.filled.iter().zip(&self.cancelled))
+            .map(|(le_ms, (filled, cancelled))| {
+                vec![timestamp.to_string(), le_ms, filled.to_string(), cancelled.to_string()]
+            })
+            .collect()
+    }
+}
+
+const CSV_HEADER: &[&str] = &["timestamp", "le_ms", "filled", "cancelled"];
+
+fn csv_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("order_age_hist-{}.csv", date.format("%Y-%m-%d")))
+}
+
+/// Append one dump to `order_age_hist-<date>.csv`, writing the header on first use. Blocking.
+pub fn append_csv(dir: &Path, date: NaiveDate, rows: &[Vec<String>]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = csv_file_path(dir, date);
+    let is_new = !path.exists();
+    let file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    let mut wtr = csv::WriterBuilder::new().has_headers(false).from_writer(file);
+    if is_new {
+        wtr.write_record(CSV_HEADER)?;
+    }
+    for row in rows {
+        wtr.write_record(row)?;
+    }
+    wtr.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ages_land_in_their_buckets() {
+        let mut hist = OrderAgeHistogram::new();
+        hist.record(0, true);
+        hist.record(250, true); // bounds are inclusive
+        hist.record(251, false);
+        hist.record(4_000, false);
+        hist.record(4_999, true);
+        hist.record(60_001, false);
+        hist.record(u64::MAX, false);
+
+        assert_eq!(hist.counts(100), (2, 0));
+        assert_eq!(hist.counts(500), (0, 1));
+        assert_eq!(hist.counts(5_000), (1, 1));
+        assert_eq!(hist.counts(1_000), (0, 0));
+        assert_eq!(hist.counts(90_000), (0, 2), "past the last bound: overflow bucket");
+    }
+
+    #[test]
+    fn test_rows_cover_every_bucket_and_csv_append() {
+        let mut hist = OrderAgeHistogram::new();
+        hist.record(700, true);
+        hist.record(120_000, false);
+        let rows = hist.rows("2024-01-15T10:30:00Z");
+        assert_eq!(rows.len(), ORDER_AGE_BUCKETS_MS.len() + 1);
+        assert_eq!(rows[0], ["2024-01-15T10:30:00Z", "250", "0", "0"]);
+        assert_eq!(rows[2], ["2024-01-15T10:30:00Z", "1000", "1", "0"]);
+        assert_eq!(rows[rows.len() - 1], ["2024-01-15T10:30:00Z", "inf", "0", "1"]);
+        let (filled, cancelled) = rows.iter().fold((0, 0), |(f, c), row| {
+            (f + row[2].parse::<u64>().unwrap(), c + row[3].parse::<u64>().unwrap())
+        });
+        assert_eq!((filled, cancelled), (1, 1));
+
+        let dir = std::env::temp_dir().join(format!("order_age_hist_test_{}", std::process::id()));
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        append_csv(&dir, date, &rows).unwrap();
+        append_csv(&dir, date, &rows).unwrap();
+        let content = fs::read_to_string(csv_file_path(&dir, date)).unwrap();
+        assert_eq!(content.lines().count(), 1 + 2 * rows.len(), "one header + two full dumps");
+        assert!(content.starts_with("timestamp,le_ms,filled,cancelled"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{Duration, NaiveDate};
+use flate2::{write::GzEncoder, Compression};
+use tracing::{error, info};
+
+const GZ_EXTENSION: &str = "gz";
+
+fn is_gzipped(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == GZ_EXTENSION)
+}
+
+/// Date embedded in a daily log file name, e.g. `trades-2024-01-15.csv` or
+/// `trades-2024-01-15.csv.gz` -> 2024-01-15.
+fn file_date(path: &Path) -> Option<NaiveDate> {
+    let path = if is_gzipped(path) { Path::new(path.file_stem()?) } else { path };
+    let stem = path.file_stem()?.to_str()?;
+    let date = stem.get(stem.len().checked_sub(10)?..)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Uncompressed daily log files from before `today`: no longer written to, so safe to gzip.
+pub fn files_to_compress(existing: &[PathBuf], today: NaiveDate) -> Vec<PathBuf> {
+    existing
+        .iter()
+        .filter(|p| !is_gzipped(p) && file_date(p).is_some_and(|d| d < today))
+        .cloned()
+        .collect()
+}
+
+/// Gzip `path` to `<path>.gz` and remove the original. A partial `.gz` is removed on failure
+/// so the original stays the only copy.
+fn gzip_file(path: &Path) -> io::Result<PathBuf> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".");
+    gz_name.push(GZ_EXTENSION);
+    let gz_path = PathBuf::from(gz_name);
+
+    let result = (|| {
+        let mut input = fs::File::open(path)?;
+        let mut encoder = GzEncoder::new(fs::File::create(&gz_path)?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&gz_path);
+        return Err(e);
+    }
+    fs::remove_file(path)?;
+    Ok(gz_path)
+}
+
+fn list_dir(dir: &Path) -> Option<Vec<PathBuf>> {
+    match fs::read_dir(dir) {
+        Ok(entries) => Some(entries.filter_map(|e| e.ok()).map(|e| e.path()).collect()),
+        Err(e) => {
+            error!("Failed to list log directory {}: {}", dir.display(), e);
+            None
+        }
+    }
+}
+
+/// Gzip every finished daily log file in `dir`. Blocking; call from `spawn_blocking`.
+pub fn compress_dir(dir: &Path, today: NaiveDate) {
+    let Some(existing) = list_dir(dir) else { return };
+    for path in files_to_compress(&existing, today) {
+        match gzip_file(&path) {
+            Ok(gz_path) => info!("[LOG_ROTATION] Compressed {}", gz_path.display()),
+            Err(e) => error!("[LOG_ROTATION] Failed to compress {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Day-rollover housekeeping: gzip finished files, then prune expired ones. Blocking.
+pub fn rotate_dir(dir: &Path, retain_days: u32, today: NaiveDate) {
+    compress_dir(dir, today);
+    prune_dir(dir, retain_days, today);
+}
+
+/// Daily log files older than `retain_days` (relative to `today`).
+/// Files without a date in their name are never pruned. `retain_days == 0` keeps everything.
+pub fn files_to_prune(existing: &[PathBuf], retain_days: u32, today: NaiveDate) -> Vec<PathBuf> {
+    if retain_days == 0 {
+        return Vec::new();
+    }
+    let cutoff = today - Duration::days(retain_days as i64);
+    existing
+        .iter()
+        .filter(|p| file_date(p).is_some_and(|d| d < cutoff))
+        .cloned()
+        .collect()
+}
+
+/// Delete expired daily log files in `dir`. Blocking; call from `spawn_blocking`.
+pub fn prune_dir(dir: &Path, retain_days: u32, today: NaiveDate) {
+    if retain_days == 0 {
+        return;
+    }
+    let Some(existing) = list_dir(dir) else { return };
+    for path in files_to_prune(&existing, retain_days, today) {
+        match fs::remove_file(&path) {
+            Ok(()) => info!("[LOG_RETENTION] Pruned {}", path.display()),
+            Err(e) => error!("[LOG_RETENTION] Failed to prune {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_files_to_prune_keeps_recent_days() {
+        let existing = vec![
+            PathBuf::from("logs/trades/trades-2024-01-01.csv"),
+            PathBuf::from("logs/trades/trades-2024-01-07.csv"),
+            PathBuf::from("logs/trades/trades-2024-01-08.csv"),
+            PathBuf::from("logs/trades/trades-2024-01-15.csv"),
+        ];
+        let pruned = files_to_prune(&existing, 7, date(2024, 1, 15));
+        assert_eq!(pruned, vec![
+            PathBuf::from("logs/trades/trades-2024-01-01.csv"),
+            PathBuf::from("logs/trades/trades-2024-01-07.csv"),
+        ]);
+    }
+
+    #[test]
+    fn test_files_to_prune_metrics_and_unrelated_files() {
+        let existing = vec![
+            PathBuf::from("metrics-2023-12-31.csv"),
+            PathBuf::from("metrics-2024-01-14.csv"),
+            PathBuf::from("README.txt"),
+            PathBuf::from("metrics-latest.csv"),
+        ];
+        let pruned = files_to_prune(&existing, 1, date(2024, 1, 15));
+        assert_eq!(pruned, vec![PathBuf::from("metrics-2023-12-31.csv")]);
+    }
+
+    #[test]
+    fn test_files_to_prune_zero_retention_disabled() {
+        let existing = vec![PathBuf::from("trades-2000-01-01.csv")];
+        assert!(files_to_prune(&existing, 0, date(2024, 1, 15)).is_empty());
+    }
+
+    #[test]
+    fn test_files_to_compress_only_finished_uncompressed() {
+        let existing = vec![
+            PathBuf::from("trades-2024-01-14.csv"),
+            PathBuf::from("trades-2024-01-13.jsonl"),
+            PathBuf::from("trades-2024-01-12.csv.gz"),
+            PathBuf::from("trades-2024-01-15.csv"),
+            PathBuf::from("notes.txt"),
+        ];
+        assert_eq!(files_to_compress(&existing, date(2024, 1, 15)), vec![
+            PathBuf::from("trades-2024-01-14.csv"),
+            PathBuf::from("trades-2024-01-13.jsonl"),
+        ]);
+        // Compressed files still carry their date for retention
+        assert_eq!(file_date(Path::new("trades-2024-01-12.csv.gz")), Some(date(2024, 1, 12)));
+    }
+
+    #[test]
+    fn test_rotate_dir_compresses_yesterday_and_prunes_expired() {
+        use std::io::Read;
+
+        let dir = std::env::temp_dir().join(format!("log_rotation_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let content = "timestamp,event\n2024-01-14T23:59:59Z,ORDER_SENT\n";
+        for name in ["trades-2024-01-14.csv", "trades-2024-01-15.csv", "trades-2024-01-01.csv"] {
+            fs::write(dir.join(name), content).unwrap();
+        }
+        fs::write(dir.join("trades-2024-01-02.csv.gz"), b"old").unwrap();
+
+        // Rollover into 2024-01-15 with 7-day retention
+        rotate_dir(&dir, 7, date(2024, 1, 15));
+
+        let mut names: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["trades-2024-01-14.csv.gz", "trades-2024-01-15.csv"]);
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(dir.join("trades-2024-01-14.csv.gz")).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,14 +1,18 @@
-use std::fs;
-use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use chrono::{NaiveDate, Utc};
+use chrono::NaiveDate;
+use serde::Serialize;
 use tokio::sync::mpsc;
-use tracing::{error, info, warn};
+use tracing::warn;
+
+use crate::logging::batch_writer::{self, LogRecord, LogTarget, WriterHandle};
+use crate::model::LogFormat;
 
 const CHANNEL_BUFFER_SIZE: usize = 1000;
 
-#[derive(Debug, Clone)]
+/// JSONL records carry the same `event` names as the CSV `event` column
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum TradeEvent {
     OrderSent {
         timestamp: String,
@@ -70,6 +74,21 @@ pub enum TradeEvent {
         mid_price: u64,
         open_price: f64,
     },
+    TrailingStopTriggered {
+        timestamp: String,
+        side: String,
+        size: f64,
+        unrealized_pnl: f64,
+        mid_price: u64,
+        open_price: f64,
+    },
+    #[serde(rename = "DAILY_LOSS_LIMIT")]
+    DailyLossLimitTriggered {
+        timestamp: String,
+        day_pnl: f64,
+        limit_jpy: f64,
+        mid_price: u64,
+    },
 }
 
 impl TradeEvent {
@@ -164,10 +183,16 @@ impl TradeEvent {
                     String::new(),
                 ]
             }
-            TradeEvent::StopLossTriggered { timestamp, side, size, unrealized_pnl, mid_price, open_price } => {
+            TradeEvent::StopLossTriggered { timestamp, side, size, unrealized_pnl, mid_price, open_price }
+            | TradeEvent::TrailingStopTriggered { timestamp, side, size, unrealized_pnl, mid_price, open_price } => {
+                let event = if matches!(self, TradeEvent::StopLossTriggered { .. }) {
+                    "STOP_LOSS_TRIGGERED"
+                } else {
+                    "TRAILING_STOP_TRIGGERED"
+                };
                 vec![
                     timestamp.clone(),
-                    "STOP_LOSS_TRIGGERED".to_string(),
+                    event.to_string(),
                     String::new(),
                     side.clone(),
                     format!("{:.0}", open_price),
@@ -185,10 +210,37 @@ impl TradeEvent {
                     String::new(),
                 ]
             }
+            TradeEvent::DailyLossLimitTriggered { timestamp, day_pnl, limit_jpy, mid_price } => {
+                vec![
+                    timestamp.clone(),
+                    "DAILY_LOSS_LIMIT".to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    format!("day_pnl={:.3} limit={}", day_pnl, limit_jpy),
+                    String::new(),
+                    mid_price.to_string(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                ]
+            }
         }
     }
 }
 
+impl LogRecord for TradeEvent {
+    fn csv_row(&self) -> Vec<String> {
+        self.to_csv_row()
+    }
+}
+
 const CSV_HEADER: &[&str] = &[
     "timestamp", "event", "order_id", "side", "price", "size", "is_close", "error", "order_age_ms",
     "mid_price", "t_optimal_ms", "sigma_1s", "spread_pct", "level", "p_fill", "best_ev", "single_leg_ev",
@@ -197,14 +249,23 @@ const CSV_HEADER: &[&str] = &[
 #[derive(Clone)]
 pub struct TradeLogger {
     sender: mpsc::Sender<TradeEvent>,
+    writer: WriterHandle,
 }
 
 impl TradeLogger {
-    pub fn new(log_dir: &str) -> Self {
-        let (sender, receiver) = mpsc::channel(CHANNEL_BUFFER_SIZE);
-        let trades_dir = PathBuf::from(log_dir).join("trades");
-        tokio::spawn(writer_task(trades_dir, receiver));
-        Self { sender }
+    /// `retain_days`: delete daily log files older than this many days (0 = keep forever).
+    pub fn new(log_dir: &str, retain_days: u32, format: LogFormat, dry_run: bool) -> Self {
+        let target = LogTarget {
+            name: "TradeLogger",
+            dir: PathBuf::from(log_dir).join("trades"),
+            path_for: log_file_path,
+            csv_header: CSV_HEADER,
+            format,
+            retain_days,
+            dry_run,
+        };
+        let (sender, writer) = batch_writer::spawn(target, CHANNEL_BUFFER_SIZE);
+        Self { sender, writer }
     }
 
     pub fn log(&self, event: TradeEvent) {
@@ -212,76 +273,32 @@ impl TradeLogger {
             warn!("Trade logger buffer full, dropping event: {}", e);
         }
     }
-}
-
-fn csv_file_path(dir: &PathBuf, date: NaiveDate) -> PathBuf {
-    dir.join(format!("trades-{}.csv", date.format("%Y-%m-%d")))
-}
 
-fn ensure_csv_with_header(path: &PathBuf) -> io::Result<()> {
-    match fs::OpenOptions::new().write(true).create_new(true).open(path) {
-        Ok(file) => {
-            let mut wtr = csv::Writer::from_writer(file);
-            wtr.write_record(CSV_HEADER)?;
-            wtr.flush()?;
-        }
-        Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => {}
-        Err(e) => return Err(e),
+    /// Close the channel (for every clone) and wait for queued events to reach the file
+    pub async fn shutdown(self) {
+        self.writer.shutdown(batch_writer::SHUTDOWN_TIMEOUT).await;
     }
-    Ok(())
 }
 
-fn write_csv_row(trades_dir: &PathBuf, row: &[String]) {
-    let today = Utc::now().date_naive();
-    let file_path = csv_file_path(trades_dir, today);
-
-    if let Err(e) = ensure_csv_with_header(&file_path) {
-        error!("Failed to create CSV header: {}", e);
-        return;
-    }
-
-    let file = match fs::OpenOptions::new().append(true).open(&file_path) {
-        Ok(f) => f,
-        Err(e) => {
-            error!("Failed to open trade log file: {}", e);
-            return;
-        }
-    };
-
-    let mut wtr = csv::WriterBuilder::new()
-        .has_headers(false)
-        .from_writer(file);
-
-    if let Err(e) = wtr.write_record(row) {
-        error!("Failed to write trade event: {}", e);
-    }
-    if let Err(e) = wtr.flush() {
-        error!("Failed to flush trade log: {}", e);
-    }
+fn csv_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("trades-{}.csv", date.format("%Y-%m-%d")))
 }
 
-async fn writer_task(trades_dir: PathBuf, mut receiver: mpsc::Receiver<TradeEvent>) {
-    if let Err(e) = fs::create_dir_all(&trades_dir) {
-        error!("Failed to create trades log directory: {}", e);
-        return;
-    }
-
-    info!("TradeLogger started: {}", trades_dir.display());
+fn jsonl_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("trades-{}.jsonl", date.format("%Y-%m-%d")))
+}
 
-    while let Some(event) = receiver.recv().await {
-        let row = event.to_csv_row();
-        let dir = trades_dir.clone();
-        if let Err(e) = tokio::task::spawn_blocking(move || {
-            write_csv_row(&dir, &row);
-        }).await {
-            error!("Trade log write task panicked: {}", e);
-        }
+fn log_file_path(dir: &Path, date: NaiveDate, format: LogFormat) -> PathBuf {
+    match format {
+        LogFormat::Csv => csv_file_path(dir, date),
+        LogFormat::Jsonl => jsonl_file_path(dir, date),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::logging::jsonl;
 
     #[test]
     fn test_order_sent_csv_row() {
@@ -425,6 +442,40 @@ mod tests {
         assert_eq!(row[13], "");
     }
 
+    #[test]
+    fn test_trailing_stop_is_its_own_event() {
+        let event = TradeEvent::TrailingStopTriggered {
+            timestamp: "2024-01-15T10:30:00Z".to_string(),
+            side: "SELL".to_string(),
+            size: 0.001,
+            unrealized_pnl: 12.5,
+            mid_price: 6505000,
+            open_price: 6490000.0,
+        };
+
+        let row = event.to_csv_row();
+        assert_eq!(row.len(), CSV_HEADER.len());
+        assert_eq!(row[1], "TRAILING_STOP_TRIGGERED");
+        assert_eq!(row[7], "unrealized_pnl=12.500");
+        assert_eq!(serde_json::to_value(&event).unwrap()["event"], "TRAILING_STOP_TRIGGERED");
+    }
+
+    #[test]
+    fn test_daily_loss_limit_csv_row() {
+        let event = TradeEvent::DailyLossLimitTriggered {
+            timestamp: "2024-01-15T10:30:00Z".to_string(),
+            day_pnl: -100.5,
+            limit_jpy: 100.0,
+            mid_price: 6505000,
+        };
+
+        let row = event.to_csv_row();
+        assert_eq!(row.len(), CSV_HEADER.len());
+        assert_eq!(row[1], "DAILY_LOSS_LIMIT");
+        assert_eq!(row[7], "day_pnl=-100.500 limit=100");
+        assert_eq!(row[9], "6505000");
+    }
+
     #[test]
     fn test_csv_header_has_17_columns() {
         assert_eq!(CSV_HEADER.len(), 17);
@@ -445,4 +496,80 @@ mod tests {
         let path = csv_file_path(&dir, date);
         assert_eq!(path, PathBuf::from("logs/trades/trades-2024-01-15.csv"));
     }
+
+    #[test]
+    fn test_order_sent_jsonl_round_trip() {
+        let event = TradeEvent::OrderSent {
+            timestamp: "2024-01-15T10:30:00Z".to_string(),
+            order_id: "123456".to_string(),
+            side: "BUY".to_string(),
+            price: 6500000,
+            size: 0.001,
+            is_close: false,
+            mid_price: 6505000,
+            t_optimal_ms: 3500,
+            sigma_1s: 0.00008,
+            spread_pct: 0.006,
+            level: 5,
+            p_fill: 0.45,
+            best_ev: 1.23,
+            single_leg_ev: 0.67,
+        };
+
+        let line = jsonl::to_line(&event).unwrap();
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["event"], "ORDER_SENT");
+        assert_eq!(json["order_id"], "123456");
+        assert_eq!(json["price"], 6500000);
+        assert_eq!(json["size"], 0.001);
+        assert_eq!(json["is_close"], false);
+        assert_eq!(json["level"], 5);
+        assert_eq!(json["p_fill"], 0.45);
+        // Only the variant's own fields: no empty CSV padding columns
+        assert!(json.get("error").is_none() && json.get("order_age_ms").is_none());
+        assert_eq!(json.as_object().unwrap().len(), 15);
+    }
+
+    #[test]
+    fn test_jsonl_event_names_match_csv() {
+        let events = [
+            TradeEvent::OrderCancelled {
+                timestamp: String::new(), order_id: "1".to_string(), order_age_ms: 0, level: 0,
+                side: "SELL".to_string(), is_close: true,
+            },
+            TradeEvent::DailyLossLimitTriggered { timestamp: String::new(), day_pnl: -1.0, limit_jpy: 1.0, mid_price: 1 },
+        ];
+        for event in events {
+            let json: serde_json::Value = serde_json::from_str(&jsonl::to_line(&event).unwrap()).unwrap();
+            assert_eq!(json["event"], event.to_csv_row()[1].as_str());
+        }
+    }
+
+    #[test]
+    fn test_jsonl_file_path() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert_eq!(jsonl_file_path(Path::new("logs/trades"), date), PathBuf::from("logs/trades/trades-2024-01-15.jsonl"));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_writes_every_queued_event() {
+        let log_dir = std::env::temp_dir().join(format!("trade_logger_shutdown_{}", std::process::id()));
+        let logger = TradeLogger::new(log_dir.to_str().unwrap(), 0, LogFormat::Jsonl, false);
+        // A clone held elsewhere (e.g. by an aborted task) must not keep the writer open
+        let _clone = logger.clone();
+        let count = 300;
+        for i in 0..count {
+            logger.log(TradeEvent::OrderCancelled {
+                timestamp: String::new(), order_id: i.to_string(), order_age_ms: 0, level: 0,
+                side: "BUY".to_string(), is_close: false,
+            });
+        }
+        logger.shutdown().await;
+
+        let path = jsonl_file_path(&log_dir.join("trades"), chrono::Utc::now().date_naive());
+        let content = std::fs::read_to_string(path).unwrap();
+        assert_eq!(content.lines().count(), count);
+        let _ = std::fs::remove_dir_all(&log_dir);
+    }
 }
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use axum::{extract::State, http::header, response::IntoResponse, routing::get, Router};
+use parking_lot::Mutex;
+
+use crate::health::{HealthStates, StatusBody};
+use crate::logging::trade_logger::TradeEvent;
+
+/// Order lifecycle counts since startup, bumped wherever the matching trade-log event is emitted
+#[derive(Debug, Default)]
+pub struct OrderCounters {
+    sent: AtomicU64,
+    filled: AtomicU64,
+    cancelled: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Counters behind `/metrics`, one set per symbol
+static ORDER_COUNTERS: OnceLock<Mutex<HashMap<String, Arc<OrderCounters>>>> = OnceLock::new();
+
+/// `symbol`'s counters, created on first use
+pub fn order_counters(symbol: &str) -> Arc<OrderCounters> {
+    ORDER_COUNTERS
+        .get_or_init(Default::default)
+        .lock()
+        .entry(symbol.to_string())
+        .or_default()
+        .clone()
+}
+
+impl OrderCounters {
+    pub const fn new() -> Self {
+        Self {
+            sent: AtomicU64::new(0),
+            filled: AtomicU64::new(0),
+            cancelled: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    /// Count `event` if it is one of the order lifecycle events; others are ignored
+    pub fn record(&self, event: &TradeEvent) {
+        let counter = match event {
+            TradeEvent::OrderSent { .. } => &self.sent,
+            TradeEvent::OrderFilled { .. } => &self.filled,
+            TradeEvent::OrderCancelled { .. } => &self.cancelled,
+            TradeEvent::OrderFailed { .. } => &self.failed,
+            _ => return,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// One metric family with a `symbol`-labelled sample per row
+fn family<T>(out: &mut String, rows: &[T], name: &str, help: &str, kind: &str, sample: impl Fn(&T) -> (&str, f64)) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    for row in rows {
+        let (symbol, value) = sample(row);
+        let _ = writeln!(out, "{}{{symbol=\"{}\"}} {}", name, symbol, value);
+    }
+}
+
+/// Prometheus text exposition (format 0.0.4) of each symbol's state and order counters
+pub fn render(rows: &[(StatusBody, Arc<OrderCounters>)]) -> String {
+    let mut out = String::new();
+    let gauge = |out: &mut String, name: &str, help: &str, value: fn(&StatusBody) -> f64| {
+        family(out, rows, name, help, "gauge", |(status, _)| (status.symbol.as_str(), value(status)));
+    };
+    gauge(&mut out, "bot_mid_price", "Mid price at the last trade cycle (JPY)", |s| s.mid_price);
+    gauge(&mut out, "bot_long_size", "Open long position size", |s| s.long_size);
+    gauge(&mut out, "bot_short_size", "Open short position size", |s| s.short_size);
+    gauge(&mut out, "bot_collateral_jpy", "Last fetched collateral (JPY)", |s| s.collateral);
+    gauge(&mut out, "bot_pending_orders", "Orders resting on the exchange", |s| s.pending_orders as f64);
+    gauge(&mut out, "bot_best_ev", "Combined single-leg EV of the last chosen levels", |s| s.best_ev);
+    let counter = |out: &mut String, name: &str, help: &str, value: fn(&OrderCounters) -> &AtomicU64| {
+        family(out, rows, name, help, "counter", |(status, counters)| {
+            (status.symbol.as_str(), value(counters).load(Ordering::Relaxed) as f64)
+        });
+    };
+    counter(&mut out, "bot_orders_sent_total", "Orders accepted by the exchange", |c| &c.sent);
+    counter(&mut out, "bot_orders_filled_total", "Orders filled", |c| &c.filled);
+    counter(&mut out, "bot_orders_cancelled_total", "Orders cancelled", |c| &c.cancelled);
+    counter(&mut out, "bot_orders_failed_total", "Orders rejected or failed", |c| &c.failed);
+    out
+}
+
+async fn metrics(State(states): State<HealthStates>) -> impl IntoResponse {
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    let rows: Vec<(StatusBody, Arc<OrderCounters>)> =
+        states.iter().map(|state| (state.status(now_ms), state.counters.clone())).collect();
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render(&rows),
+    )
+}
+
+/// `/metrics`, served next to `/healthz` and `/status`
+pub fn router(states: HealthStates) -> Router {
+    Router::new().route("/metrics", get(metrics)).with_state(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::{HealthState, TradeStatus};
+    use crate::model::{OrderMap, Position};
+    use parking_lot::RwLock;
+
+    fn event_cancelled() -> TradeEvent {
+        TradeEvent::OrderCancelled {
+            timestamp: "2024-01-15T10:30:00Z".to_string(),
+            order_id: "1".to_string(),
+            order_age_ms: 1000,
+            level: 4,
+            side: "BUY".to_string(),
+            is_close: false,
+        }
+    }
+
+    fn state(symbol: &str) -> HealthState {
+        HealthState {
+            symbol: symbol.to_string(),
+            last_ws_message: Arc::new(RwLock::new(0)),
+            ws_stale_threshold_ms: 60_000,
+            position: Arc::new(RwLock::new(Position::new())),
+            orders: Arc::new(Mutex::new(OrderMap::new())),
+            trade_status: Arc::new(RwLock::new(TradeStatus::default())),
+            counters: Arc::new(OrderCounters::new()),
+        }
+    }
+
+    fn rows(states: &[&HealthState]) -> Vec<(StatusBody, Arc<OrderCounters>)> {
+        states.iter().map(|state| (state.status(0), state.counters.clone())).collect()
+    }
+
+    #[test]
+    fn test_render_gauges_and_counters() {
+        let state = state("BTC_JPY");
+        let before = render(&rows(&[&state]));
+        assert!(before.contains("\nbot_mid_price{symbol=\"BTC_JPY\"} 0\n"));
+        assert!(before.contains("# TYPE bot_orders_cancelled_total counter\nbot_orders_cancelled_total{symbol=\"BTC_JPY\"} 0\n"));
+
+        *state.trade_status.write() = TradeStatus { mid_price: 10_000_500.0, collateral: 123_456.0, best_ev: 1.25, ..TradeStatus::default() };
+        state.position.write().long_size = 0.002;
+        state.counters.record(&event_cancelled());
+        state.counters.record(&event_cancelled());
+        let after = render(&rows(&[&state]));
+        assert!(after.contains("# TYPE bot_mid_price gauge\nbot_mid_price{symbol=\"BTC_JPY\"} 10000500\n"), "{}", after);
+        assert!(after.contains("\nbot_long_size{symbol=\"BTC_JPY\"} 0.002\n"));
+        assert!(after.contains("\nbot_best_ev{symbol=\"BTC_JPY\"} 1.25\n"));
+        assert!(after.contains("\nbot_orders_cancelled_total{symbol=\"BTC_JPY\"} 2\n"));
+        assert!(after.contains("\nbot_orders_sent_total{symbol=\"BTC_JPY\"} 0\n"));
+    }
+
+    #[test]
+    fn test_render_labels_each_symbol_separately() {
+        let (btc, eth) = (state("BTC_JPY"), state("ETH_JPY"));
+        *eth.trade_status.write() = TradeStatus { mid_price: 500_000.0, ..TradeStatus::default() };
+        eth.counters.record(&event_cancelled());
+        let out = render(&rows(&[&btc, &eth]));
+        assert_eq!(out.matches("# TYPE bot_mid_price gauge").count(), 1, "one family header for both symbols");
+        assert!(out.contains("bot_mid_price{symbol=\"BTC_JPY\"} 0\nbot_mid_price{symbol=\"ETH_JPY\"} 500000\n"), "{}", out);
+        assert!(out.contains("bot_orders_cancelled_total{symbol=\"BTC_JPY\"} 0\n"));
+        assert!(out.contains("bot_orders_cancelled_total{symbol=\"ETH_JPY\"} 1\n"));
+    }
+
+    #[test]
+    fn test_order_counters_are_kept_per_symbol() {
+        order_counters("TEST_A_JPY").record(&event_cancelled());
+        assert_eq!(order_counters("TEST_A_JPY").cancelled.load(Ordering::Relaxed), 1);
+        assert_eq!(order_counters("TEST_B_JPY").cancelled.load(Ordering::Relaxed), 0);
+    }
+}
@@ -1,8 +1,13 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::fmt;
+use std::net::{IpAddr, Ipv4Addr};
 use std::time::Instant;
+use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
 
+use crate::decimal::{Price, Size};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Position {
     pub long_size: f64,
@@ -30,15 +35,42 @@ impl Position {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Unrealized P&L (JPY) at `mid_price`: (long, short, total). A side below `min_lot` or
+    /// without a known open price contributes 0.
+    pub fn unrealized_pnl(&self, mid_price: f64, min_lot: f64) -> (f64, f64, f64) {
+        let long_pnl = if self.long_size >= min_lot && self.long_open_price > 0.0 {
+            (mid_price - self.long_open_price) * self.long_size
+        } else {
+            0.0
+        };
+        let short_pnl = if self.short_size >= min_lot && self.short_open_price > 0.0 {
+            (self.short_open_price - mid_price) * self.short_size
+        } else {
+            0.0
+        };
+        (long_pnl, short_pnl, long_pnl + short_pnl)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum OrderSide {
     Unknown,
     BUY,
     SELL,
 }
 
+impl OrderSide {
+    /// The other side of the book: the side that closes a position opened on `self`
+    pub fn opposite(&self) -> OrderSide {
+        match self {
+            OrderSide::BUY => OrderSide::SELL,
+            OrderSide::SELL => OrderSide::BUY,
+            OrderSide::Unknown => OrderSide::Unknown,
+        }
+    }
+}
+
 impl fmt::Display for OrderSide {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -68,14 +100,309 @@ pub struct OrderInfo {
     pub side: OrderSide,
     pub timestamp: u64,
     pub is_close: bool,
-    pub mid_price: u64,
+    /// Mid when the order was sent, unrounded: sub-yen symbols need the fraction
+    pub mid_price: f64,
     pub t_optimal_ms: u64,
     pub sigma_1s: f64,
     pub spread_pct: f64,
     pub level: u32,
+    /// Exact level the open was quoted at (None for closes); `level` is its rate for logs,
+    /// this is the key its P(fill) posterior is stored under
+    pub level_key: Option<FloatingExp>,
     pub p_fill: f64,
     pub best_ev: f64,
     pub single_leg_ev: f64,
+    /// Local correlation id (UUID v4) assigned before sending; GMO takes no client order id,
+    /// so it lives in logs and lets a lost-response order be matched later
+    pub client_id: String,
+}
+
+impl OrderInfo {
+    /// Outcome to feed back into the P(fill) posteriors once the order resolves
+    pub fn outcome(&self, filled: bool, age_ms: u64) -> OrderOutcome {
+        OrderOutcome {
+            side: self.side.clone(),
+            filled,
+            is_close: self.is_close,
+            level: self.level_key.clone(),
+            age_ms,
+        }
+    }
+}
+
+/// What the exchange reports about an order the bot holds no id for
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedOrder {
+    pub side: OrderSide,
+    pub price: f64,
+    pub size: f64,
+    /// When the report arrived (ms)
+    pub seen_ms: u64,
+}
+
+/// Count and total size of pending orders in one (side, is_close) bucket
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub size: f64,
+}
+
+/// Pending orders keyed by order id, with a per-(side, is_close) index kept in sync
+/// on insert/remove so "how much is pending on this side" needs no scan.
+/// Entries are only reachable immutably; replace an order by re-inserting it.
+#[derive(Debug, Clone, Default)]
+pub struct OrderMap {
+    orders: HashMap<String, OrderInfo>,
+    pending: HashMap<(OrderSide, bool), PendingSummary>,
+    /// Sent orders whose response was lost: possibly live, exchange id unknown
+    unconfirmed: Vec<OrderInfo>,
+}
+
+impl OrderMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, order_id: String, info: OrderInfo) -> Option<OrderInfo> {
+        self.index_add(&info);
+        let previous = self.orders.insert(order_id, info);
+        if let Some(old) = &previous {
+            self.index_remove(old);
+        }
+        previous
+    }
+
+    pub fn remove(&mut self, order_id: &str) -> Option<OrderInfo> {
+        let removed = self.orders.remove(order_id);
+        if let Some(info) = &removed {
+            self.index_remove(info);
+        }
+        removed
+    }
+
+    pub fn clear(&mut self) {
+        self.orders.clear();
+        self.pending.clear();
+        self.unconfirmed.clear();
+    }
+
+    /// Remember an order whose placement response never arrived, for `adopt_unconfirmed`
+    pub fn add_unconfirmed(&mut self, info: OrderInfo) {
+        self.unconfirmed.push(info);
+    }
+
+    pub fn unconfirmed_len(&self) -> usize {
+        self.unconfirmed.len()
+    }
+
+    /// Drop unconfirmed entries sent more than `max_age_ms` before `now_ms`; returns how many.
+    /// Without order events nothing ever claims them, so this is what bounds the list.
+    pub fn reap_unconfirmed(&mut self, now_ms: u64, max_age_ms: u64) -> usize {
+        let before = self.unconfirmed.len();
+        self.unconfirmed.retain(|o| now_ms.saturating_sub(o.timestamp) <= max_age_ms);
+        before - self.unconfirmed.len()
+    }
+
+    /// Match an order reported under the unknown `order_id` to an unconfirmed one: same side,
+    /// same size, price within `price_tolerance_jpy` and sent at most `max_age_ms` before the
+    /// report. The closest price wins, then the oldest. The match is tracked under `order_id`
+    /// from then on; unconfirmed entries past `max_age_ms` are dropped along the way.
+    pub fn adopt_unconfirmed(
+        &mut self,
+        order_id: &str,
+        reported: &ReportedOrder,
+        max_age_ms: u64,
+        price_tolerance_jpy: f64,
+    ) -> Option<&OrderInfo> {
+        self.reap_unconfirmed(reported.seen_ms, max_age_ms);
+        let price_gap = |o: &OrderInfo| (o.price as f64 - reported.price).abs();
+        let index = self
+            .unconfirmed
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| {
+                o.side == reported.side
+                    && Size::from_f64(o.size) == Size::from_f64(reported.size)
+                    && price_gap(o) <= price_tolerance_jpy
+            })
+            .min_by(|(_, a), (_, b)| price_gap(a).total_cmp(&price_gap(b)).then(a.timestamp.cmp(&b.timestamp)))
+            .map(|(i, _)| i)?;
+        let info = self.unconfirmed.remove(index);
+        self.insert(order_id.to_string(), info);
+        self.orders.get(order_id)
+    }
+
+    pub fn get(&self, order_id: &str) -> Option<&OrderInfo> {
+        self.orders.get(order_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &OrderInfo)> {
+        self.orders.iter()
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &OrderInfo> {
+        self.orders.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn pending(&self, side: &OrderSide, is_close: bool) -> PendingSummary {
+        self.pending.get(&(side.clone(), is_close)).copied().unwrap_or_default()
+    }
+
+    /// Open size resting on `side`, counting unconfirmed opens: a lost response may still
+    /// be a live order, so it takes up position room until it is claimed or reaped
+    pub fn pending_open_size(&self, side: &OrderSide) -> f64 {
+        let unconfirmed: f64 = self.unconfirmed.iter().filter(|o| !o.is_close && o.side == *side).map(|o| o.size).sum();
+        self.pending(side, false).size + unconfirmed
+    }
+
+    pub fn has_pending_close(&self, side: &OrderSide) -> bool {
+        self.pending(side, true).count > 0
+    }
+
+    /// A pending open order on `side` priced within `tolerance_jpy` of `price`, if any
+    pub fn open_order_near(&self, side: &OrderSide, price: u64, tolerance_jpy: f64) -> Option<&OrderInfo> {
+        self.orders
+            .values()
+            .find(|o| !o.is_close && o.side == *side && (o.price as f64 - price as f64).abs() <= tolerance_jpy)
+    }
+
+    fn index_add(&mut self, info: &OrderInfo) {
+        let entry = self.pending.entry((info.side.clone(), info.is_close)).or_default();
+        entry.count += 1;
+        entry.size += info.size;
+    }
+
+    fn index_remove(&mut self, info: &OrderInfo) {
+        let key = (info.side.clone(), info.is_close);
+        if let Some(entry) = self.pending.get_mut(&key) {
+            entry.count = entry.count.saturating_sub(1);
+            entry.size -= info.size;
+            // Drop the bucket when empty so float residue never reads as a pending size
+            if entry.count == 0 {
+                self.pending.remove(&key);
+            }
+        }
+    }
+}
+
+/// Realized PnL from fills, valued against weighted-average open prices per side.
+/// Only sees fills it is fed: a close larger than the tracked side realizes the tracked part.
+#[derive(Debug, Clone, Default)]
+pub struct PnlTracker {
+    long_size: f64,
+    long_avg_price: f64,
+    short_size: f64,
+    short_avg_price: f64,
+    pub realized_pnl: f64,
+    /// Number of times a side went from open back to flat
+    pub round_trips: u64,
+}
+
+impl PnlTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `side` is the fill side; `is_close` marks a settlement (SELL closes a long, BUY a short).
+    pub fn on_fill(&mut self, side: &OrderSide, price: f64, size: f64, is_close: bool) {
+        const FLAT_EPS: f64 = 1e-9;
+        match (is_close, side) {
+            (false, OrderSide::BUY) => {
+                let total = self.long_size + size;
+                self.long_avg_price = (self.long_avg_price * self.long_size + price * size) / total;
+                self.long_size = total;
+            }
+            (false, _) => {
+                let total = self.short_size + size;
+                self.short_avg_price = (self.short_avg_price * self.short_size + price * size) / total;
+                self.short_size = total;
+            }
+            (true, OrderSide::SELL) => {
+                let closed = size.min(self.long_size);
+                if closed <= 0.0 {
+                    return;
+                }
+                self.realized_pnl += (price - self.long_avg_price) * closed;
+                self.long_size -= closed;
+                if self.long_size < FLAT_EPS {
+                    self.long_size = 0.0;
+                    self.long_avg_price = 0.0;
+                    self.round_trips += 1;
+                }
+            }
+            (true, _) => {
+                let closed = size.min(self.short_size);
+                if closed <= 0.0 {
+                    return;
+                }
+                self.realized_pnl += (self.short_avg_price - price) * closed;
+                self.short_size -= closed;
+                if self.short_size < FLAT_EPS {
+                    self.short_size = 0.0;
+                    self.short_avg_price = 0.0;
+                    self.round_trips += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Daily kill switch: P&L (realized + unrealized) since UTC midnight against `limit_jpy`.
+/// Once the day's loss reaches the limit it stays tripped until the next UTC day.
+#[derive(Debug, Clone)]
+pub struct DailyPnlGuard {
+    limit_jpy: f64,
+    day: Option<NaiveDate>,
+    /// Realized + unrealized at the start of `day`
+    baseline: f64,
+    day_pnl: f64,
+    tripped: bool,
+}
+
+impl DailyPnlGuard {
+    /// `limit_jpy <= 0` disables the guard.
+    pub fn new(limit_jpy: f64) -> Self {
+        Self { limit_jpy, day: None, baseline: 0.0, day_pnl: 0.0, tripped: false }
+    }
+
+    /// Change the limit (config reload) without resetting today's baseline
+    pub fn set_limit(&mut self, limit_jpy: f64) {
+        self.limit_jpy = limit_jpy;
+    }
+
+    /// Feed cumulative realized P&L and current unrealized P&L. Returns true only on the
+    /// update that trips the guard.
+    pub fn update(&mut self, today: NaiveDate, realized_total: f64, unrealized: f64) -> bool {
+        let equity = realized_total + unrealized;
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.baseline = equity;
+            self.tripped = false;
+        }
+        self.day_pnl = equity - self.baseline;
+        if self.tripped || self.limit_jpy <= 0.0 || self.day_pnl > -self.limit_jpy {
+            return false;
+        }
+        self.tripped = true;
+        true
+    }
+
+    /// New opens are blocked while tripped; closes are always allowed.
+    pub fn allows_open(&self) -> bool {
+        !self.tripped
+    }
+
+    pub fn day_pnl(&self) -> f64 {
+        self.day_pnl
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -83,11 +410,16 @@ pub struct OrderOutcome {
     pub side: OrderSide,
     pub filled: bool,
     pub is_close: bool,
-    pub level: u32,
+    /// Level the order was quoted at, None for closes
+    pub level: Option<FloatingExp>,
+    /// Time from placement to the fill or cancel
+    pub age_ms: u64,
 }
 
 // ハッシュキーとして登録可能な浮動小数点指数
-#[derive(Debug, Clone, PartialEq)]
+// YAMLでは省略したフィールドにDefault (10^-5 * 1) が入る: `{ rate: 4 }` = 0.004%
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
 pub struct FloatingExp {
     pub base: f64,
     pub exp: f64,
@@ -124,8 +456,138 @@ impl PartialOrd for FloatingExp {
 
 impl Ord for FloatingExp {
     fn cmp(&self, other: &FloatingExp) -> std::cmp::Ordering {
-        // Use total_cmp for consistent NaN handling (available in Rust 1.62+)
+        // Use total_cmp for consistent NaN handling (available in Rust 1.62+). Rate orders the
+        // grid; base/exp only break ties so explicit levels sharing a rate stay distinct keys
         self.rate.total_cmp(&other.rate)
+            .then_with(|| self.base.total_cmp(&other.base))
+            .then_with(|| self.exp.total_cmp(&other.exp))
+    }
+}
+
+/// Exchange trading rules for one symbol (tick, size step, lot bounds)
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SymbolRule {
+    pub symbol: String,
+    pub tick_size: f64,
+    pub size_step: f64,
+    pub min_lot: f64,
+    pub max_lot: f64,
+}
+
+impl SymbolRule {
+    /// Round a price onto the symbol's tick, away from the touch: buys down, sells up.
+    pub fn round_price(&self, price: f64, side: &OrderSide) -> f64 {
+        let price = Price::from_f64(price);
+        match side {
+            OrderSide::SELL => price.ceil_to_tick(self.tick_size),
+            _ => price.floor_to_tick(self.tick_size),
+        }
+        .to_f64()
+    }
+
+    /// Truncate a size down to the symbol's size step. None when that leaves it below the
+    /// symbol's min lot, which the exchange would reject.
+    pub fn round_size(&self, size: f64) -> Option<f64> {
+        let size = Size::from_f64(size).floor_to_step(self.size_step).to_f64();
+        (size >= self.min_lot).then_some(size)
+    }
+
+    pub fn is_valid_price(&self, price: f64) -> bool {
+        Price::from_f64(price).is_multiple_of(self.tick_size)
+    }
+
+    pub fn is_valid_size(&self, size: f64) -> bool {
+        Size::from_f64(size).is_multiple_of(self.size_step)
+    }
+}
+
+/// Per-symbol trading rules, keyed by symbol name (e.g. "BTC_JPY")
+#[derive(Debug, Clone)]
+pub struct SymbolRegistry {
+    rules: HashMap<String, SymbolRule>,
+}
+
+impl Default for SymbolRegistry {
+    /// Built-in rules matching the values the bot previously hardcoded.
+    fn default() -> Self {
+        let mut registry = Self { rules: HashMap::new() };
+        registry.insert(SymbolRule {
+            symbol: "BTC_JPY".to_string(),
+            tick_size: 1.0,
+            size_step: 0.0001,
+            min_lot: 0.0001,
+            max_lot: f64::MAX,
+        });
+        // GMO leverage-trading rules; refreshed from /v1/symbols at startup
+        for (symbol, tick_size, size_step) in [("ETH_JPY", 1.0, 0.1), ("XRP_JPY", 0.001, 10.0), ("BCH_JPY", 1.0, 0.1)] {
+            registry.insert(SymbolRule {
+                symbol: symbol.to_string(),
+                tick_size,
+                size_step,
+                min_lot: size_step,
+                max_lot: f64::MAX,
+            });
+        }
+        registry
+    }
+}
+
+impl SymbolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace the rule for `rule.symbol`.
+    pub fn insert(&mut self, rule: SymbolRule) {
+        self.rules.insert(rule.symbol.clone(), rule);
+    }
+
+    pub fn extend(&mut self, rules: impl IntoIterator<Item = SymbolRule>) {
+        for rule in rules {
+            self.insert(rule);
+        }
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&SymbolRule> {
+        self.rules.get(symbol)
+    }
+}
+
+#[cfg(feature = "gmo")]
+fn default_symbol() -> crate::api::gmo::api::Symbol {
+    crate::api::gmo::api::Symbol::BTC_JPY
+}
+
+/// Parse via `Symbol::from_str` so a typo (or "Unknown") fails config loading.
+#[cfg(feature = "gmo")]
+fn deserialize_symbol<'de, D>(deserializer: D) -> Result<crate::api::gmo::api::Symbol, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    crate::api::gmo::api::Symbol::from_str(&s)
+        .map_err(|_| serde::de::Error::custom(format!("unknown symbol: {}", s)))
+}
+
+/// One instrument of a multi-symbol process. Lots are in base currency, so BTC and XRP cannot
+/// share them: each may override the top-level sizing, and inherits it when unset.
+#[cfg(feature = "gmo")]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SymbolConfig {
+    #[serde(deserialize_with = "deserialize_symbol")]
+    pub symbol: crate::api::gmo::api::Symbol,
+    #[serde(default)]
+    pub min_lot: Option<f64>,
+    #[serde(default)]
+    pub max_lot: Option<f64>,
+    #[serde(default)]
+    pub max_position: Option<f64>,
+}
+
+#[cfg(feature = "gmo")]
+impl SymbolConfig {
+    pub fn new(symbol: crate::api::gmo::api::Symbol) -> Self {
+        Self { symbol, min_lot: None, max_lot: None, max_position: None }
     }
 }
 
@@ -133,6 +595,10 @@ fn default_log_dir() -> String {
     "logs".to_string()
 }
 
+fn default_health_bind_addr() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::LOCALHOST)
+}
+
 fn default_true() -> bool {
     true
 }
@@ -141,6 +607,17 @@ fn default_alpha() -> f64 {
     0.5
 }
 
+fn default_position_penalty() -> f64 { 50.0 }
+
+fn default_imbalance_depth_levels() -> usize { 5 }
+fn default_trade_flow_window_ms() -> i64 { 5000 }
+
+fn default_vol_short_window_ms() -> i64 { 1000 }
+
+fn default_vol_long_window_ms() -> i64 { 5000 }
+
+fn default_vol_expansion_threshold() -> f64 { 1.5 }
+
 fn default_execution_retain_ms() -> u64 {
     5000
 }
@@ -163,39 +640,957 @@ fn default_stop_loss_jpy() -> f64 {
 
 fn default_min_hold_ms() -> u64 { 180000 }
 
+fn default_close_ladder_min_step_jpy() -> f64 { 1.0 }
+
+fn default_close_escalation_step() -> f64 { 1.0 }
+
+fn default_close_spread_factor_floor() -> f64 { 0.1 }
+
+fn default_close_ladder_max_step_jpy() -> f64 { 50.0 }
+
+fn default_cancel_interval_ms() -> u64 { 500 }
+
+fn default_ghost_safe_mode_threshold() -> u32 { 3 }
+
+fn default_ghost_safe_mode_window_secs() -> u64 { 600 }
+
+fn default_ghost_position_cooldown_secs() -> u64 { 60 }
+
+fn default_margin_cooldown_secs() -> u64 { 60 }
+
+fn default_stop_loss_cooldown_secs() -> u64 { 10 }
+
+fn default_position_poll_ms() -> u64 { 5000 }
+
+fn default_preopen_spread_multiplier() -> f64 { 2.0 }
+
+fn default_maintenance_error_threshold() -> u32 { 3 }
+
+fn default_ws_ping_interval_secs() -> u64 { 30 }
+
+fn default_api_max_retries() -> u32 { 2 }
+
+fn default_flip_penalty_decay_ms() -> u64 { 30000 }
+
+fn default_rate_limit_capacity() -> f64 { 10.0 }
+
+fn default_rate_limit_refill_per_sec() -> f64 { 10.0 }
+
+fn default_circuit_breaker_frac() -> f64 { 0.001 }
+
+fn default_price_step_start() -> u32 { 4 }
+
+fn default_price_step_end() -> u32 { 25 }
+
+fn default_price_step_base() -> f64 { 10.0 }
+
+fn default_price_step_exp() -> f64 { -5.0 }
+
+fn default_circuit_breaker_cooldown_secs() -> u64 { 30 }
+
+fn default_circuit_breaker_window_ms() -> i64 { 5000 }
+
+fn default_alert_cooldown_secs() -> u64 { 300 }
+
+/// On-disk format of the trade and metrics logs
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Fixed-column daily CSVs (`trades-<date>.csv`)
+    #[default]
+    Csv,
+    /// One JSON object per event/snapshot (`trades-<date>.jsonl`)
+    Jsonl,
+}
+
+impl LogFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LogFormat::Csv => "csv",
+            LogFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+/// Which exposure `max_position` caps when gating and sizing new opens
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitBasis {
+    /// Each side separately: long_size and short_size must each stay within max_position
+    #[default]
+    Gross,
+    /// Only |long_size - short_size| is capped; offsetting legs free up room
+    Net,
+}
+
+/// How P(fill) is read from each level's Beta posterior when picking quote levels
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplorationMode {
+    /// Posterior mean: deterministic, always exploits
+    #[default]
+    Mean,
+    /// Thompson sampling: one draw per level per cycle, explores wide posteriors
+    Thompson,
+}
+
+/// Where open sizing takes its funding limit from
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SizingSource {
+    /// Leverage account: sizes are bounded by max_position / collateral only
+    #[default]
+    Margin,
+    /// Spot account: buys are also capped by available JPY / price, sells by available base currency
+    Balance,
+}
+
+/// How open order sizes are computed
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SizingMode {
+    /// `max_lot` tapered by `position_ratio` as the side's position grows
+    #[default]
+    PowerLaw,
+    /// Kelly fraction `p - (1 - p) / b` of collateral, converted to BTC at mid
+    Kelly { win_prob: f64, win_loss_ratio: f64 },
+}
+
+impl SizingMode {
+    /// Kelly fraction of collateral to commit; None for the power-law sizer
+    pub fn kelly_fraction(&self) -> Option<f64> {
+        match *self {
+            SizingMode::PowerLaw => None,
+            SizingMode::Kelly { win_prob, win_loss_ratio } => Some(win_prob - (1.0 - win_prob) / win_loss_ratio),
+        }
+    }
+}
+
+fn default_regime_max_spread() -> f64 { f64::MAX }
+
+/// Quoting parameters for one volatility regime
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RegimeParams {
+    pub alpha: f64,
+    /// Only levels whose distance from mid (`FloatingExp::calc`, fraction of mid) lies in
+    /// [min_spread, max_spread] are quoted in this regime
+    #[serde(default)]
+    pub min_spread: f64,
+    #[serde(default = "default_regime_max_spread")]
+    pub max_spread: f64,
+}
+
+/// sigma_1s thresholds splitting calm / normal / volatile markets, with the alpha and level
+/// range to use in each
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct VolRegimeConfig {
+    /// sigma_1s below this is the low regime
+    pub low_sigma_1s: f64,
+    /// sigma_1s at or above this is the high regime
+    pub high_sigma_1s: f64,
+    pub low: RegimeParams,
+    pub mid: RegimeParams,
+    pub high: RegimeParams,
+}
+
+impl VolRegimeConfig {
+    fn validate(&self, errors: &mut Vec<String>) {
+        if !(self.low_sigma_1s.is_finite() && self.low_sigma_1s >= 0.0 && self.low_sigma_1s < self.high_sigma_1s) {
+            errors.push(format!(
+                "vol_regimes.low_sigma_1s ({}) must be >= 0 and < high_sigma_1s ({})",
+                self.low_sigma_1s, self.high_sigma_1s
+            ));
+        }
+        for (name, params) in [("low", &self.low), ("mid", &self.mid), ("high", &self.high)] {
+            if !(params.alpha.is_finite() && params.alpha >= 0.0) {
+                errors.push(format!("vol_regimes.{}.alpha ({}) must be >= 0", name, params.alpha));
+            }
+            if !(params.min_spread >= 0.0 && params.min_spread <= params.max_spread) {
+                errors.push(format!(
+                    "vol_regimes.{}.min_spread ({}) must be in [0, max_spread ({})]",
+                    name, params.min_spread, params.max_spread
+                ));
+            }
+        }
+    }
+}
+
+/// Credential-bearing config string: `Debug` prints a placeholder, so logging the config
+/// (startup, SIGHUP reload) never leaks it
+#[derive(Deserialize, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"<redacted>\"")
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct BotConfig {
     pub order_cancel_ms: u64,
     pub order_interval_ms: u64,
+    /// Trade loop period; falls back to the bot's previous cadence when unset
+    /// (GMO: `order_interval_ms`, bitFlyer: 5000)
+    #[serde(default)]
+    pub trade_interval_ms: Option<u64>,
+    /// GMO: re-quote period for each side on its own, e.g. a faster refresh for the side the
+    /// inventory skew leans on. Unset = every trade loop cycle
+    #[serde(default)]
+    pub buy_interval_ms: Option<u64>,
+    #[serde(default)]
+    pub sell_interval_ms: Option<u64>,
+    /// Cancel loop period (stale-order sweep)
+    #[serde(default = "default_cancel_interval_ms")]
+    pub cancel_interval_ms: u64,
+    /// Position REST poll period
+    #[serde(default = "default_position_poll_ms")]
+    pub position_poll_ms: u64,
     pub position_ratio: f64,
     pub min_lot: f64,
     pub max_lot: f64,
     pub max_position: f64,
+    /// Rescale max_position to `collateral * pct / price` on every collateral refresh, as a
+    /// fraction of collateral (0.5 = half; 0 = fixed max_position). Collateral is account-wide,
+    /// so with several `symbols` each one gets an even share of pct
+    #[serde(default)]
+    pub max_position_pct_of_collateral: f64,
+    /// Clamp on the collateral-scaled max_position (base units); must be >= min_lot while
+    /// scaling is on, or a small collateral leaves no room to open anything
+    #[serde(default)]
+    pub max_position_floor: f64,
+    #[serde(default)]
+    pub max_position_ceiling: f64,
     #[serde(default = "default_log_dir")]
     pub log_dir: String,
+    /// Delete daily trade/metrics logs (compressed or not) older than this many days (0 = keep forever)
+    #[serde(default, alias = "log_retention_days")]
+    pub log_retain_days: u32,
+    /// `csv` (default) or `jsonl` for the trade and metrics logs
+    #[serde(default)]
+    pub log_format: LogFormat,
     #[serde(default = "default_true")]
     pub trade_log_enabled: bool,
     #[serde(default = "default_true")]
     pub metrics_log_enabled: bool,
+    /// Central credible-interval mass for the P(fill) lower bound in metrics (e.g. 0.9; 0 = off)
+    #[serde(default)]
+    pub metrics_credible_level: f64,
+    /// Half-life (ms) weighting P(fill) outcomes by recency within the 1h window, read at
+    /// startup (0 = every outcome in the window counts the same)
+    #[serde(default)]
+    pub p_fill_half_life_ms: u64,
     #[serde(default = "default_alpha")]
     pub alpha: f64,
+    /// Switch alpha and the quoted level range by volatility regime (unset = `alpha`, all levels)
+    #[serde(default)]
+    pub vol_regimes: Option<VolRegimeConfig>,
+    /// Quote skew per min_lot of inventory (JPY): discourages adding to a side, speeds closing it
+    #[serde(default = "default_position_penalty")]
+    pub position_penalty: f64,
+    /// Exchange fees (bps of notional) netted out of the bitFlyer pair EV: maker for resting
+    /// quotes, taker for quotes priced through the touch. 0 = fee-free
+    #[serde(default)]
+    pub maker_fee_bps: f64,
+    #[serde(default)]
+    pub taker_fee_bps: f64,
+    /// Book levels per side summed for the order-book imbalance signal
+    #[serde(default = "default_imbalance_depth_levels")]
+    pub imbalance_depth_levels: usize,
+    /// Spread skew per unit of imbalance: a fully bid-heavy book tightens buys and widens sells
+    /// by this fraction (0 = ignore the book)
+    #[serde(default)]
+    pub imbalance_weight: f64,
+    /// Public executions this recent (ms) are summed for the trade-flow imbalance signal
+    #[serde(default = "default_trade_flow_window_ms")]
+    pub trade_flow_window_ms: i64,
+    /// Spread widening per unit of trade-flow imbalance on the side the flow is hitting:
+    /// all-buy flow widens sells by this fraction, all-sell flow widens buys (0 = ignore flow)
+    #[serde(default)]
+    pub trade_flow_weight: f64,
+    /// Short and long windows (ms) for the realized-volatility ratio logged as `vol_ratio`.
+    /// The long one only sees as far back as `execution_retain_ms` keeps
+    #[serde(default = "default_vol_short_window_ms")]
+    pub vol_short_window_ms: i64,
+    #[serde(default = "default_vol_long_window_ms")]
+    pub vol_long_window_ms: i64,
+    /// `vol_ratio` above which both spreads widen, ahead of the circuit breaker
+    #[serde(default = "default_vol_expansion_threshold")]
+    pub vol_expansion_threshold: f64,
+    /// Spread widening per unit of `vol_ratio` past the threshold (0 = log the ratio only)
+    #[serde(default)]
+    pub vol_expansion_weight: f64,
+    /// Skip an open order when one on the same side already rests within this many JPY of
+    /// its price (0 = exact price only). Unset = no dedup
+    #[serde(default)]
+    pub dedup_price_tolerance_jpy: Option<f64>,
+    /// Minimum expected value (JPY) a side's best level must reach before an open order is
+    /// placed there; closes are never gated. 0 = only non-negative-EV opens
+    #[serde(default)]
+    pub min_ev: f64,
+    /// Net position (BTC, long positive) the inventory skew steers toward instead of flat
+    #[serde(default)]
+    pub target_net_position: f64,
+    /// Percentage grid: level i (start..=end) quotes i * base^exp of mid away.
+    /// Defaults L4..=L25 at 0.001% steps; L1-L3 had the worst adverse selection.
+    #[serde(default = "default_price_step_start")]
+    pub price_step_start: u32,
+    #[serde(default = "default_price_step_end")]
+    pub price_step_end: u32,
+    #[serde(default = "default_price_step_base")]
+    pub price_step_base: f64,
+    #[serde(default = "default_price_step_exp")]
+    pub price_step_exp: f64,
+    /// Quote levels as absolute JPY offsets from mid (e.g. [300, 500, 1000]) instead of the
+    /// default 0.001%-step grid. Empty = percentage grid.
+    #[serde(default)]
+    pub level_offsets_jpy: Vec<u32>,
+    /// Explicit quote levels (e.g. `[{ rate: 4 }, { rate: 6 }, { rate: 10, exp: -5 }]`), replacing
+    /// the generated grid. Must be non-empty and strictly ascending.
+    #[serde(default)]
+    pub levels: Option<Vec<FloatingExp>>,
+    /// Level selection: `mean` (posterior mean) or `thompson` (posterior sampling)
+    #[serde(default)]
+    pub exploration_mode: ExplorationMode,
+    /// Seed for Thompson sampling and reconnect jitter, for reproducible runs. None = entropy
+    #[serde(default)]
+    pub rng_seed: Option<u64>,
+    /// Whether max_position caps per-side (gross) or net exposure
+    #[serde(default)]
+    pub position_limit_basis: LimitBasis,
+    /// Open sizing: `{ mode: power_law }` or `{ mode: kelly, win_prob, win_loss_ratio }`
+    #[serde(default)]
+    pub sizing_mode: SizingMode,
+    /// `margin` (leverage collateral) or `balance` (spot wallet from `get_balance`)
+    #[serde(default)]
+    pub sizing_source: SizingSource,
     #[serde(default = "default_execution_retain_ms")]
     pub execution_retain_ms: u64,
+    /// Skip trading while the average trade-feed delay over the retained executions exceeds
+    /// this (ms, 0 = off)
+    #[serde(default)]
+    pub max_feed_delay_ms: u64,
+    /// Poll `/v1/trades` for executions once the WS trades channel has been silent this long,
+    /// until it delivers again (ms, 0 = WS only)
+    #[serde(default)]
+    pub execution_fallback_after_ms: u64,
     #[serde(default = "default_t_optimal_min_ms")]
     pub t_optimal_min_ms: u64,
     #[serde(default = "default_t_optimal_max_ms")]
     pub t_optimal_max_ms: u64,
     #[serde(default = "default_close_spread_factor")]
     pub close_spread_factor: f64,
+    /// Each close order cancelled for age multiplies that side's close_spread_factor by this
+    /// (1 = no escalation), down to close_spread_factor_floor; a close fill resets it
+    #[serde(default = "default_close_escalation_step")]
+    pub close_escalation_step: f64,
+    #[serde(default = "default_close_spread_factor_floor")]
+    pub close_spread_factor_floor: f64,
+    /// Each unfilled close cycle moves the close price this fraction of the remaining
+    /// distance toward mid (0 = no laddering), with every step bounded to [min, max] JPY
+    #[serde(default)]
+    pub close_ladder_factor: f64,
+    #[serde(default = "default_close_ladder_min_step_jpy")]
+    pub close_ladder_min_step_jpy: f64,
+    #[serde(default = "default_close_ladder_max_step_jpy")]
+    pub close_ladder_max_step_jpy: f64,
     #[serde(default = "default_stop_loss_jpy")]
     pub stop_loss_jpy: f64,
+    /// Stop-loss / trailing-stop closes go out as MARKET only while walking the book puts the
+    /// average fill within this many JPY of mid; otherwise as a LIMIT (FAK) at that bound (0 = always MARKET)
+    #[serde(default)]
+    pub max_close_slippage_jpy: f64,
+    /// Trailing stop: MARKET-close a side once its unrealized P&L falls this far (JPY) below its
+    /// peak since opening. Only arms after the side has been in profit (0 = off)
+    #[serde(default)]
+    pub trailing_stop_jpy: f64,
+    /// Daily kill switch: once the P&L since UTC midnight (realized + unrealized) loses this much
+    /// (JPY), new opens stop until the next UTC day; closes continue (0 = off). Needs
+    /// `private_ws_enabled`, which is where realized P&L comes from
+    #[serde(default)]
+    pub daily_loss_limit_jpy: f64,
+    /// Take-profit: LIMIT-close a side at the touch once its unrealized P&L exceeds this (JPY, 0 = off)
+    #[serde(default)]
+    pub take_profit_jpy: f64,
     #[serde(default = "default_min_hold_ms")]
     pub min_hold_ms: u64,
+    /// After startup the trade loop runs this long (book, volatility, EV, metrics) before it
+    /// places any quote; stop-loss / trailing-stop exits are not held back. Startup-only (0 = off)
+    #[serde(default)]
+    pub warmup_ms: u64,
+    /// Circuit breaker: pause when the recent trade price range exceeds this fraction of mid
+    /// (0.001 = 0.1% = 10bps). Also read as `circuit_breaker_bps`, its old misnamed key
+    #[serde(default = "default_circuit_breaker_frac", alias = "circuit_breaker_bps")]
+    pub circuit_breaker_frac: f64,
+    /// How long the circuit breaker pauses trading once tripped
+    #[serde(default = "default_circuit_breaker_cooldown_secs")]
+    pub circuit_breaker_cooldown_secs: u64,
+    /// Lookback for the circuit breaker's price range (independent of execution_retain_ms)
+    #[serde(default = "default_circuit_breaker_window_ms")]
+    pub circuit_breaker_window_ms: i64,
+    /// Reset every level's P(fill) posterior to the prior once a circuit-breaker cooldown ends
+    #[serde(default)]
+    pub reset_probs_on_circuit_breaker: bool,
+    #[serde(default)]
+    pub quote_improve_only: bool,
+    /// GMO timeInForce for orders ("SOK" / "FAK" / "FAS" / "FOK"). None = exchange default.
+    #[serde(default)]
+    pub time_in_force: Option<String>,
+    /// Per-symbol tick/lot overrides; take precedence over rules fetched from the exchange.
+    #[serde(default)]
+    pub symbol_rules: Vec<SymbolRule>,
+    /// Instrument to trade (BTC_JPY, ETH_JPY, BCH_JPY). XRP_JPY parses but is rejected by
+    /// `validate` while prices are tracked in whole yen: its tick is 0.001.
+    #[cfg(feature = "gmo")]
+    #[serde(default = "default_symbol", deserialize_with = "deserialize_symbol")]
+    pub symbol: crate::api::gmo::api::Symbol,
+    /// Trade several instruments in one process over shared HTTP / WebSocket connections.
+    /// Empty = `symbol` alone
+    #[cfg(feature = "gmo")]
+    #[serde(default)]
+    pub symbols: Vec<SymbolConfig>,
+    /// Private API token bucket: burst size and sustained requests/sec
+    #[serde(default = "default_rate_limit_capacity")]
+    pub rate_limit_capacity: f64,
+    #[serde(default = "default_rate_limit_refill_per_sec")]
+    pub rate_limit_refill_per_sec: f64,
+    /// Max order requests (send / cancel / close) in flight at once across all tasks (0 = unlimited)
+    #[serde(default)]
+    pub max_in_flight_orders: usize,
+    /// New opens stop while this many orders are tracked, e.g. when cancels lag; closes are
+    /// never held back (0 = unlimited)
+    #[serde(default)]
+    pub max_pending_orders: usize,
+    /// Hard cap on order + cancel requests per rolling second; excess requests are deferred to
+    /// the next cycle instead of queued (0 = off)
+    #[serde(default)]
+    pub max_orders_per_sec: usize,
+    /// Retries for transient API failures (timeout / 502 / 503 / 504); order and close POSTs
+    /// only retry connection failures, since those can't have reached GMO. 0 disables
+    #[serde(default = "default_api_max_retries")]
+    pub api_max_retries: u32,
+    /// Extra JPY distance on re-opening the side just closed by a long↔short flip (0 = off)
+    #[serde(default)]
+    pub flip_penalty_jpy: f64,
+    /// Time constant of the flip penalty's exponential decay
+    #[serde(default = "default_flip_penalty_decay_ms")]
+    pub flip_penalty_decay_ms: u64,
+    /// Open quotes rest at least this many JPY behind the touch: buy <= best_bid - offset,
+    /// sell >= best_ask + offset, after every adjustment (0 = may join the touch)
+    #[serde(default)]
+    pub min_touch_offset_jpy: f64,
+    /// Clamp close order size to the position on that side (prevents flipping)
+    #[serde(default = "default_true")]
+    pub clamp_close_to_position: bool,
+    /// Skip the cycle when our own rounded buy price is not strictly below our sell price
+    #[serde(default = "default_true")]
+    pub skip_self_crossed_quotes: bool,
+    /// Max per-leg size difference between local and exchange position before logging a
+    /// discrepancy (0 = disabled). Only checked with the private WS enabled.
+    #[serde(default)]
+    pub position_drift_tolerance: f64,
+    /// On drift above tolerance, cancel all open orders so quoting restarts from the exchange state
+    #[serde(default)]
+    pub position_drift_cancel_orders: bool,
+    /// Enter safe mode after this many ghost-position detections within the window (0 = never)
+    #[serde(default = "default_ghost_safe_mode_threshold")]
+    pub ghost_safe_mode_threshold: u32,
+    #[serde(default = "default_ghost_safe_mode_window_secs")]
+    pub ghost_safe_mode_window_secs: u64,
+    /// After a ghost position (MARKET close answered ERR-422): closes and stop-loss stay
+    /// suppressed and position polls are ignored this long. Must exceed `stop_loss_cooldown_secs`
+    #[serde(default = "default_ghost_position_cooldown_secs")]
+    pub ghost_position_cooldown_secs: u64,
+    /// New orders are suppressed this long after an ERR-201 (insufficient margin)
+    #[serde(default = "default_margin_cooldown_secs")]
+    pub margin_cooldown_secs: u64,
+    /// No further stop-loss / trailing-stop MARKET close this long after one is sent,
+    /// so a position poll can catch up before the next
+    #[serde(default = "default_stop_loss_cooldown_secs")]
+    pub stop_loss_cooldown_secs: u64,
+    /// Spread multiplier for open quotes while GMO reports PREOPEN or an unrecognised status
+    /// (1.0 = quote as normal). MAINTENANCE always suspends quoting.
+    #[serde(default = "default_preopen_spread_multiplier")]
+    pub preopen_spread_multiplier: f64,
+    /// Consecutive maintenance errors from get_collateral / get_position that cancel orders and
+    /// pause trading until a backed-off probe gets through (0 = never pause)
+    #[serde(default = "default_maintenance_error_threshold")]
+    pub maintenance_error_threshold: u32,
+    /// Halt new opens when a fill lands further than this from the order's recorded mid (bps, 0 = off).
+    /// Needs the private WS, which is where fill prices come from.
+    #[serde(default)]
+    pub implausible_fill_band_bps: f64,
+    /// Paper trading: orders are simulated against public executions and never sent to the API
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Slack/Discord-compatible webhook for critical events (stop-loss, ghost, margin, WS stale).
+    /// Unset = no alerts
+    #[serde(default)]
+    pub alert_webhook_url: Option<Secret>,
+    /// Repeats of the same alert kind within this window are dropped
+    #[serde(default = "default_alert_cooldown_secs")]
+    pub alert_cooldown_secs: u64,
+    /// Port for the `/healthz` and `/status` HTTP endpoints (0 = disabled)
+    #[serde(default)]
+    pub health_port: u16,
+    /// Address the health port listens on. `/status`, `/metrics` and `/flatten` are served
+    /// without TLS, so it stays on loopback unless a proxy or firewall fronts it
+    #[serde(default = "default_health_bind_addr")]
+    pub health_bind_addr: IpAddr,
+    /// Shared secret for `POST /flatten` on the health port (`Authorization: Bearer <secret>`).
+    /// Unset = no admin endpoint
+    #[serde(default)]
+    pub admin_secret: Option<Secret>,
+    /// Subscribe to GMO's private WS (executionEvents / orderEvents) for exact fill detection
+    #[serde(default = "default_true")]
+    pub private_ws_enabled: bool,
+    /// bitFlyer's counterpart: subscribe to child_order_events. Off unless opted into, since
+    /// the bitFlyer bot has always run on get_position polling alone
+    #[serde(default)]
+    pub bitflyer_private_ws_enabled: bool,
+    /// Public WebSocket keepalive ping interval (min 1s)
+    #[serde(default = "default_ws_ping_interval_secs")]
+    pub ws_ping_interval_secs: u64,
+    /// Soft collateral floor (JPY): below it new opens are suppressed (0 = off)
+    #[serde(default)]
+    pub min_collateral_jpy: f64,
+    /// Hard collateral floor (JPY): below it cancel all, MARKET-close everything and halt (0 = off)
+    #[serde(default)]
+    pub emergency_flatten_collateral_jpy: f64,
+}
+
+/// Parse one override value into `field`; invalid values are logged and leave the YAML value.
+fn override_field<T: FromStr + fmt::Debug>(key: &str, raw: Option<String>, field: &mut T) {
+    let Some(raw) = raw else {
+        return;
+    };
+    match raw.trim().parse::<T>() {
+        Ok(value) => {
+            tracing::info!("[CONFIG] {}={:?} overrides {:?}", key, value, field);
+            *field = value;
+        }
+        Err(_) => tracing::warn!("[CONFIG] Ignoring {}={:?}: not a valid value", key, raw),
+    }
+}
+
+impl BotConfig {
+    /// Read `path`, apply env overrides and validate: the same steps as at startup.
+    pub fn load(path: &str) -> Result<BotConfig, Vec<String>> {
+        let yaml = std::fs::read_to_string(path).map_err(|e| vec![format!("failed to read {}: {}", path, e)])?;
+        let mut config: BotConfig =
+            serde_yaml::from_str(&yaml).map_err(|e| vec![format!("failed to parse {}: {}", path, e)])?;
+        config.apply_env_overrides();
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Apply `BOT_<FIELD>` environment variables (e.g. `BOT_MIN_LOT`) on top of the YAML,
+    /// so several instances can share one config file. Call before `validate`.
+    pub fn apply_env_overrides(&mut self) {
+        self.apply_overrides(|key| std::env::var(key).ok());
+    }
+
+    fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) {
+        macro_rules! overrides {
+            ($($key:literal => $field:expr),* $(,)?) => {
+                $(override_field($key, lookup($key), &mut $field);)*
+            };
+        }
+        overrides! {
+            "BOT_ORDER_CANCEL_MS" => self.order_cancel_ms,
+            "BOT_ORDER_INTERVAL_MS" => self.order_interval_ms,
+            "BOT_CANCEL_INTERVAL_MS" => self.cancel_interval_ms,
+            "BOT_POSITION_POLL_MS" => self.position_poll_ms,
+            "BOT_POSITION_RATIO" => self.position_ratio,
+            "BOT_MIN_LOT" => self.min_lot,
+            "BOT_MAX_LOT" => self.max_lot,
+            "BOT_MAX_POSITION" => self.max_position,
+            "BOT_MAX_POSITION_PCT_OF_COLLATERAL" => self.max_position_pct_of_collateral,
+            "BOT_MAX_POSITION_FLOOR" => self.max_position_floor,
+            "BOT_MAX_POSITION_CEILING" => self.max_position_ceiling,
+            "BOT_LOG_DIR" => self.log_dir,
+            "BOT_LOG_RETAIN_DAYS" => self.log_retain_days,
+            "BOT_TRADE_LOG_ENABLED" => self.trade_log_enabled,
+            "BOT_METRICS_LOG_ENABLED" => self.metrics_log_enabled,
+            "BOT_P_FILL_HALF_LIFE_MS" => self.p_fill_half_life_ms,
+            "BOT_ALPHA" => self.alpha,
+            "BOT_T_OPTIMAL_MIN_MS" => self.t_optimal_min_ms,
+            "BOT_T_OPTIMAL_MAX_MS" => self.t_optimal_max_ms,
+            "BOT_CLOSE_SPREAD_FACTOR" => self.close_spread_factor,
+            "BOT_STOP_LOSS_JPY" => self.stop_loss_jpy,
+            "BOT_STOP_LOSS_COOLDOWN_SECS" => self.stop_loss_cooldown_secs,
+            "BOT_GHOST_POSITION_COOLDOWN_SECS" => self.ghost_position_cooldown_secs,
+            "BOT_MARGIN_COOLDOWN_SECS" => self.margin_cooldown_secs,
+            "BOT_RESET_PROBS_ON_CIRCUIT_BREAKER" => self.reset_probs_on_circuit_breaker,
+            "BOT_EXECUTION_FALLBACK_AFTER_MS" => self.execution_fallback_after_ms,
+            "BOT_MAINTENANCE_ERROR_THRESHOLD" => self.maintenance_error_threshold,
+            "BOT_MIN_HOLD_MS" => self.min_hold_ms,
+            "BOT_WARMUP_MS" => self.warmup_ms,
+            "BOT_RATE_LIMIT_CAPACITY" => self.rate_limit_capacity,
+            "BOT_RATE_LIMIT_REFILL_PER_SEC" => self.rate_limit_refill_per_sec,
+            "BOT_MAX_IN_FLIGHT_ORDERS" => self.max_in_flight_orders,
+            "BOT_MAX_PENDING_ORDERS" => self.max_pending_orders,
+            "BOT_MAX_ORDERS_PER_SEC" => self.max_orders_per_sec,
+            "BOT_API_MAX_RETRIES" => self.api_max_retries,
+            "BOT_DRY_RUN" => self.dry_run,
+            "BOT_HEALTH_PORT" => self.health_port,
+            "BOT_HEALTH_BIND_ADDR" => self.health_bind_addr,
+            "BOT_PRIVATE_WS_ENABLED" => self.private_ws_enabled,
+            "BOT_BITFLYER_PRIVATE_WS_ENABLED" => self.bitflyer_private_ws_enabled,
+            "BOT_MIN_COLLATERAL_JPY" => self.min_collateral_jpy,
+            "BOT_EMERGENCY_FLATTEN_COLLATERAL_JPY" => self.emergency_flatten_collateral_jpy,
+        }
+        if let Some(raw) = lookup("BOT_TRADE_INTERVAL_MS") {
+            let mut interval = self.trade_interval_ms.unwrap_or(self.order_interval_ms);
+            override_field("BOT_TRADE_INTERVAL_MS", Some(raw), &mut interval);
+            self.trade_interval_ms = Some(interval);
+        }
+        if let Some(secret) = lookup("BOT_ADMIN_SECRET") {
+            // Not echoed like the other overrides: it is a credential
+            tracing::info!("[CONFIG] BOT_ADMIN_SECRET overrides admin_secret");
+            self.admin_secret = Some(Secret::new(secret));
+        }
+        #[cfg(feature = "gmo")]
+        if let Some(raw) = lookup("BOT_SYMBOL") {
+            match raw.trim().parse::<crate::api::gmo::api::Symbol>() {
+                Ok(symbol) => {
+                    tracing::info!("[CONFIG] BOT_SYMBOL={} overrides {}", symbol, self.symbol);
+                    self.symbol = symbol;
+                }
+                Err(_) => tracing::warn!("[CONFIG] Ignoring BOT_SYMBOL={:?}: unknown symbol", raw),
+            }
+        }
+    }
+
+    /// The instruments to run: `symbols`, or `symbol` alone when that is empty
+    #[cfg(feature = "gmo")]
+    pub fn symbol_configs(&self) -> Vec<SymbolConfig> {
+        if self.symbols.is_empty() {
+            vec![SymbolConfig::new(self.symbol.clone())]
+        } else {
+            self.symbols.clone()
+        }
+    }
+
+    /// The config one symbol's tasks run with: its symbol and sizing applied, `symbols` cleared
+    #[cfg(feature = "gmo")]
+    pub fn for_symbol(&self, symbol: &SymbolConfig) -> BotConfig {
+        BotConfig {
+            symbol: symbol.symbol.clone(),
+            symbols: Vec::new(),
+            min_lot: symbol.min_lot.unwrap_or(self.min_lot),
+            max_lot: symbol.max_lot.unwrap_or(self.max_lot),
+            max_position: symbol.max_position.unwrap_or(self.max_position),
+            // One collateral backs every symbol: without the split N symbols would size to N x it
+            max_position_pct_of_collateral: self.max_position_pct_of_collateral / self.symbol_configs().len() as f64,
+            ..self.clone()
+        }
+    }
+
+    /// Cross-field invariants serde cannot express. Returns every violation, not just the first.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.min_lot > self.max_lot {
+            errors.push(format!("min_lot ({}) must be <= max_lot ({})", self.min_lot, self.max_lot));
+        }
+        if self.max_lot > self.max_position {
+            errors.push(format!("max_lot ({}) must be <= max_position ({})", self.max_lot, self.max_position));
+        }
+        if !(self.max_position_pct_of_collateral.is_finite() && self.max_position_pct_of_collateral >= 0.0) {
+            errors.push(format!(
+                "max_position_pct_of_collateral ({}) must be >= 0",
+                self.max_position_pct_of_collateral
+            ));
+        }
+        if !(self.max_position_floor.is_finite() && self.max_position_floor >= 0.0) {
+            errors.push(format!("max_position_floor ({}) must be >= 0", self.max_position_floor));
+        }
+        if self.max_position_pct_of_collateral > 0.0
+            && !(self.max_position_ceiling.is_finite() && self.max_position_ceiling > 0.0 && self.max_position_ceiling >= self.max_position_floor)
+        {
+            errors.push(format!(
+                "max_position_ceiling ({}) must be > 0 and >= max_position_floor ({}) when max_position_pct_of_collateral is set",
+                self.max_position_ceiling, self.max_position_floor
+            ));
+        }
+        if self.max_position_pct_of_collateral > 0.0 && self.max_position_floor < self.min_lot {
+            errors.push(format!(
+                "max_position_floor ({}) must be >= min_lot ({}) when max_position_pct_of_collateral is set",
+                self.max_position_floor, self.min_lot
+            ));
+        }
+        // RateLimiter::new asserts both; caught here so a bad value is a config error, not a panic
+        if !(self.rate_limit_capacity.is_finite() && self.rate_limit_capacity >= 1.0) {
+            errors.push(format!("rate_limit_capacity ({}) must be >= 1", self.rate_limit_capacity));
+        }
+        if !(self.rate_limit_refill_per_sec.is_finite() && self.rate_limit_refill_per_sec > 0.0) {
+            errors.push(format!("rate_limit_refill_per_sec ({}) must be > 0", self.rate_limit_refill_per_sec));
+        }
+        if !(self.position_ratio > 0.0 && self.position_ratio <= 1.0) {
+            errors.push(format!("position_ratio ({}) must be in (0, 1]", self.position_ratio));
+        }
+        #[cfg(feature = "gmo")]
+        if let Err(e) = parse_time_in_force(self.time_in_force.as_deref()) {
+            errors.push(e);
+        }
+        for (name, bps) in [("maker_fee_bps", self.maker_fee_bps), ("taker_fee_bps", self.taker_fee_bps)] {
+            if !bps.is_finite() || bps.abs() > 100.0 {
+                errors.push(format!("{} ({}) must be within [-100, 100]", name, bps));
+            }
+        }
+        if !(0.0..=0.5).contains(&self.imbalance_weight) {
+            errors.push(format!("imbalance_weight ({}) must be in [0, 0.5]", self.imbalance_weight));
+        }
+        for (name, interval) in [("buy_interval_ms", self.buy_interval_ms), ("sell_interval_ms", self.sell_interval_ms)] {
+            if interval == Some(0) {
+                errors.push(format!("{} must be > 0 when set", name));
+            }
+        }
+        if let Some(tolerance) = self.dedup_price_tolerance_jpy {
+            if !tolerance.is_finite() || tolerance < 0.0 {
+                errors.push(format!("dedup_price_tolerance_jpy ({}) must be >= 0", tolerance));
+            }
+        }
+        if self.admin_secret.as_ref().is_some_and(|secret| secret.expose().trim().is_empty()) {
+            errors.push("admin_secret must not be empty when set".to_string());
+        }
+        if !self.min_ev.is_finite() {
+            errors.push(format!("min_ev ({}) must be finite", self.min_ev));
+        }
+        if let Some(regimes) = &self.vol_regimes {
+            regimes.validate(&mut errors);
+        }
+        if self.trade_flow_window_ms <= 0 {
+            errors.push(format!("trade_flow_window_ms ({}) must be > 0", self.trade_flow_window_ms));
+        }
+        if !(0.0..=0.5).contains(&self.trade_flow_weight) {
+            errors.push(format!("trade_flow_weight ({}) must be in [0, 0.5]", self.trade_flow_weight));
+        }
+        if !(self.vol_short_window_ms > 0 && self.vol_short_window_ms < self.vol_long_window_ms) {
+            errors.push(format!(
+                "vol_short_window_ms ({}) must be > 0 and < vol_long_window_ms ({})",
+                self.vol_short_window_ms, self.vol_long_window_ms
+            ));
+        }
+        if !(self.vol_expansion_threshold.is_finite() && self.vol_expansion_threshold >= 1.0) {
+            errors.push(format!("vol_expansion_threshold ({}) must be >= 1", self.vol_expansion_threshold));
+        }
+        if !(self.vol_expansion_weight.is_finite() && self.vol_expansion_weight >= 0.0) {
+            errors.push(format!("vol_expansion_weight ({}) must be >= 0", self.vol_expansion_weight));
+        }
+        if !self.target_net_position.is_finite() || self.target_net_position.abs() > self.max_position {
+            errors.push(format!(
+                "target_net_position ({}) must be within +/- max_position ({})",
+                self.target_net_position, self.max_position
+            ));
+        }
+        if let SizingMode::Kelly { win_prob, win_loss_ratio } = self.sizing_mode {
+            if !(win_prob > 0.0 && win_prob < 1.0 && win_loss_ratio > 0.0) {
+                errors.push(format!(
+                    "sizing_mode kelly needs win_prob in (0, 1) and win_loss_ratio > 0 (got {}, {})",
+                    win_prob, win_loss_ratio
+                ));
+            } else if self.sizing_mode.kelly_fraction().is_some_and(|f| f <= 0.0) {
+                errors.push(format!(
+                    "sizing_mode kelly fraction must be > 0 (win_prob {}, win_loss_ratio {} has no edge)",
+                    win_prob, win_loss_ratio
+                ));
+            }
+        }
+        if self.t_optimal_min_ms > self.t_optimal_max_ms {
+            errors.push(format!(
+                "t_optimal_min_ms ({}) must be <= t_optimal_max_ms ({})",
+                self.t_optimal_min_ms, self.t_optimal_max_ms
+            ));
+        }
+        if !(self.close_spread_factor > 0.0 && self.close_spread_factor < 1.0) {
+            errors.push(format!("close_spread_factor ({}) must be in (0, 1)", self.close_spread_factor));
+        }
+        if !(self.close_escalation_step > 0.0 && self.close_escalation_step <= 1.0) {
+            errors.push(format!("close_escalation_step ({}) must be in (0, 1]", self.close_escalation_step));
+        }
+        if self.close_escalation_step < 1.0
+            && !(self.close_spread_factor_floor > 0.0 && self.close_spread_factor_floor <= self.close_spread_factor)
+        {
+            errors.push(format!(
+                "close_spread_factor_floor ({}) must be in (0, close_spread_factor ({})]",
+                self.close_spread_factor_floor, self.close_spread_factor
+            ));
+        }
+        if self.close_ladder_factor > 0.0 && self.close_ladder_min_step_jpy > self.close_ladder_max_step_jpy {
+            errors.push(format!(
+                "close_ladder_min_step_jpy ({}) must be <= close_ladder_max_step_jpy ({})",
+                self.close_ladder_min_step_jpy, self.close_ladder_max_step_jpy
+            ));
+        }
+        if self.price_step_start == 0 || self.price_step_start > self.price_step_end {
+            errors.push(format!(
+                "price_step_start ({}) must be >= 1 and <= price_step_end ({})",
+                self.price_step_start, self.price_step_end
+            ));
+        }
+        if let Some(levels) = &self.levels {
+            if levels.is_empty() {
+                errors.push("levels must not be empty (omit it to use the generated grid)".to_string());
+            } else if levels.iter().any(|l| !(l.calc().is_finite() && l.calc() > 0.0)) {
+                errors.push(format!("levels must all be finite and > 0 (got {:?})", levels));
+            } else if levels.windows(2).any(|w| w[0].rate >= w[1].rate || w[0].calc() >= w[1].calc()) {
+                errors.push(format!("levels must be strictly ascending by rate and distance (got {:?})", levels));
+            }
+            if !self.level_offsets_jpy.is_empty() {
+                errors.push("levels and level_offsets_jpy are mutually exclusive".to_string());
+            }
+        }
+        if self.price_step_base <= 0.0 {
+            errors.push(format!("price_step_base ({}) must be > 0", self.price_step_base));
+        }
+        if self.stop_loss_jpy < 0.0 {
+            errors.push(format!("stop_loss_jpy ({}) must be >= 0", self.stop_loss_jpy));
+        }
+        if self.ghost_position_cooldown_secs <= self.stop_loss_cooldown_secs {
+            errors.push(format!(
+                "ghost_position_cooldown_secs ({}) must be > stop_loss_cooldown_secs ({})",
+                self.ghost_position_cooldown_secs, self.stop_loss_cooldown_secs
+            ));
+        }
+        if !(self.max_close_slippage_jpy.is_finite() && self.max_close_slippage_jpy >= 0.0) {
+            errors.push(format!("max_close_slippage_jpy ({}) must be >= 0", self.max_close_slippage_jpy));
+        }
+        if !(self.min_touch_offset_jpy.is_finite() && self.min_touch_offset_jpy >= 0.0) {
+            errors.push(format!("min_touch_offset_jpy ({}) must be >= 0", self.min_touch_offset_jpy));
+        }
+        if self.trailing_stop_jpy < 0.0 {
+            errors.push(format!("trailing_stop_jpy ({}) must be >= 0", self.trailing_stop_jpy));
+        }
+        if self.daily_loss_limit_jpy < 0.0 {
+            errors.push(format!("daily_loss_limit_jpy ({}) must be >= 0", self.daily_loss_limit_jpy));
+        }
+        // Realized P&L only moves on private WS fills; without them the limit never trips on closed losses
+        if self.daily_loss_limit_jpy > 0.0 && !self.private_ws_enabled {
+            errors.push(format!(
+                "daily_loss_limit_jpy ({}) requires private_ws_enabled", self.daily_loss_limit_jpy
+            ));
+        }
+        if self.take_profit_jpy < 0.0 {
+            errors.push(format!("take_profit_jpy ({}) must be >= 0", self.take_profit_jpy));
+        }
+        for rule in &self.symbol_rules {
+            if !(rule.tick_size > 0.0 && rule.size_step > 0.0) {
+                errors.push(format!(
+                    "symbol_rules.{} tick_size ({}) and size_step ({}) must be > 0",
+                    rule.symbol, rule.tick_size, rule.size_step,
+                ));
+            }
+        }
+        // The public book, trade buffer and order prices are kept in whole yen, so a sub-yen tick
+        // would collapse neighbouring levels into one. `symbols` entries are checked per symbol below.
+        #[cfg(feature = "gmo")]
+        if self.symbols.is_empty() {
+            let symbol = self.symbol.to_string();
+            let tick_size = self.symbol_rules.iter().rev().find(|rule| rule.symbol == symbol).map(|rule| rule.tick_size)
+                .or_else(|| SymbolRegistry::default().get(&symbol).map(|rule| rule.tick_size));
+            if let Some(tick_size) = tick_size.filter(|tick| *tick > 0.0 && *tick < 1.0) {
+                errors.push(format!("symbol ({}) has a tick_size of {} JPY; sub-yen ticks are not supported", symbol, tick_size));
+            }
+        }
+        // Per-symbol configs share everything but sizing: only report what the overrides broke
+        #[cfg(feature = "gmo")]
+        let top_level = errors.clone();
+        #[cfg(feature = "gmo")]
+        for (i, symbol) in self.symbols.iter().enumerate() {
+            if self.symbols[..i].iter().any(|s| s.symbol == symbol.symbol) {
+                errors.push(format!("symbols lists {} more than once", symbol.symbol));
+                continue;
+            }
+            if let Err(symbol_errors) = self.for_symbol(symbol).validate() {
+                errors.extend(
+                    symbol_errors.into_iter()
+                        .filter(|e| !top_level.contains(e))
+                        .map(|e| format!("symbols.{} {}", symbol.symbol, e)),
+                );
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+}
+
+/// Parse the configured time_in_force string. None/empty = exchange default.
+#[cfg(feature = "gmo")]
+pub fn parse_time_in_force(value: Option<&str>) -> Result<Option<crate::api::gmo::api::TimeInForce>, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(v) => v.to_uppercase().parse()
+            .map(Some)
+            .map_err(|_| format!("time_in_force ('{}') must be one of SOK, FAK, FAS or FOK", v)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::model::FloatingExp;
+    use crate::model::{DailyPnlGuard, FloatingExp, OrderInfo, OrderMap, OrderSide, PnlTracker, Position, RegimeParams, ReportedOrder, Secret, SizingMode, SizingSource, SymbolRegistry, SymbolRule, VolRegimeConfig};
+
+    /// The required BotConfig keys; tests append the fields they exercise
+    const BASE_YAML: &str = "order_cancel_ms: 10000\norder_interval_ms: 3000\nposition_ratio: 0.9\nmin_lot: 0.001\nmax_lot: 0.001\nmax_position: 0.002\n";
+
+    #[test]
+    fn order_side_opposite() {
+        assert_eq!(OrderSide::BUY.opposite(), OrderSide::SELL);
+        assert_eq!(OrderSide::SELL.opposite(), OrderSide::BUY);
+        assert_eq!(OrderSide::Unknown.opposite(), OrderSide::Unknown);
+        assert_eq!(OrderSide::BUY.opposite().opposite(), OrderSide::BUY);
+    }
+
+    #[test]
+    fn position_unrealized_pnl_long() {
+        let pos = Position { long_size: 0.001, long_open_price: 14_000_000.0, ..Default::default() };
+        // -5000 * 0.001 = -5.0 JPY
+        let (long_pnl, short_pnl, total) = pos.unrealized_pnl(13_995_000.0, 0.001);
+        assert!((long_pnl + 5.0).abs() < 1e-9, "expected -5.0 JPY, got {}", long_pnl);
+        assert_eq!((short_pnl, total), (0.0, long_pnl));
+    }
+
+    #[test]
+    fn position_unrealized_pnl_short() {
+        let pos = Position { short_size: 0.001, short_open_price: 14_000_000.0, ..Default::default() };
+        let (long_pnl, short_pnl, total) = pos.unrealized_pnl(14_005_000.0, 0.001);
+        assert!((short_pnl + 5.0).abs() < 1e-9, "expected -5.0 JPY, got {}", short_pnl);
+        assert_eq!((long_pnl, total), (0.0, short_pnl));
+    }
+
+    #[test]
+    fn position_unrealized_pnl_both_sides_total() {
+        let pos = Position {
+            long_size: 0.002, short_size: 0.001,
+            long_open_price: 14_002_000.0, short_open_price: 13_998_000.0,
+            ..Default::default()
+        };
+        // long: -2000 * 0.002 = -4.0, short: -2000 * 0.001 = -2.0
+        let (long_pnl, short_pnl, total) = pos.unrealized_pnl(14_000_000.0, 0.001);
+        assert!((long_pnl + 4.0).abs() < 1e-9 && (short_pnl + 2.0).abs() < 1e-9);
+        assert!((total + 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn position_unrealized_pnl_guards() {
+        // open_price = 0: position not yet tracked
+        let untracked = Position { long_size: 0.001, ..Default::default() };
+        assert_eq!(untracked.unrealized_pnl(13_000_000.0, 0.001), (0.0, 0.0, 0.0));
+        // Dust below min_lot is ignored
+        let dust = Position { short_size: 0.0005, short_open_price: 14_000_000.0, ..Default::default() };
+        assert_eq!(dust.unrealized_pnl(15_000_000.0, 0.001), (0.0, 0.0, 0.0));
+        assert_eq!(Position::new().unrealized_pnl(14_000_000.0, 0.001), (0.0, 0.0, 0.0));
+    }
 
     #[test]
     fn floating_exp1() {
@@ -214,4 +1609,640 @@ mod tests {
         let t = FloatingExp::new(10.0, 2.0, 3.0);
         assert_eq!(t.calc(), 300.0);
     }
+
+    fn eth_rule() -> SymbolRule {
+        SymbolRule {
+            symbol: "ETH_JPY".to_string(),
+            tick_size: 1.0,
+            size_step: 0.01,
+            min_lot: 0.01,
+            max_lot: 100.0,
+        }
+    }
+
+    #[test]
+    fn symbol_registry_default_btc() {
+        let registry = SymbolRegistry::new();
+        let btc = registry.get("BTC_JPY").unwrap();
+        assert_eq!(btc.tick_size, 1.0);
+        assert_eq!(btc.size_step, 0.0001);
+        assert!(registry.get("DOGE_JPY").is_none());
+
+        assert_eq!(btc.round_price(10_000_123.9, &OrderSide::BUY), 10_000_123.0);
+        assert_eq!(btc.round_price(10_000_123.1, &OrderSide::SELL), 10_000_124.0);
+        assert_eq!(btc.round_size(0.00129), Some(0.0012));
+        assert_eq!(btc.round_size(0.001), Some(0.001));
+        // Below min_lot once rounded: rejected rather than sent as 0
+        assert_eq!(btc.round_size(0.00009), None);
+        assert!(btc.is_valid_size(0.001));
+        assert!(btc.is_valid_size(0.0037));
+        assert!(!btc.is_valid_size(0.00015));
+        assert!(btc.is_valid_price(10_000_123.0));
+        assert!(!btc.is_valid_price(10_000_123.5));
+        // A price a hair under a tick is that tick, not the one below
+        assert_eq!(btc.round_price(10_000_000.0 - 2e-9, &OrderSide::BUY), 10_000_000.0);
+        assert_eq!(btc.round_price(10_000_000.0 + 2e-9, &OrderSide::SELL), 10_000_000.0);
+        assert!(btc.is_valid_price(10_000_000.0 - 2e-9));
+    }
+
+    #[test]
+    fn symbol_registry_second_instrument() {
+        let mut registry = SymbolRegistry::new();
+        registry.extend(vec![eth_rule()]);
+        let eth = registry.get("ETH_JPY").unwrap();
+
+        assert_eq!(eth.round_size(0.129), Some(0.12));
+        assert_eq!(eth.round_size(0.3), Some(0.3));
+        assert_eq!(eth.round_size(0.009), None);
+        assert!(eth.is_valid_size(0.05));
+        assert!(!eth.is_valid_size(0.005));
+        assert_eq!(eth.min_lot, 0.01);
+        assert_eq!(eth.max_lot, 100.0);
+        // BTC rule untouched
+        assert_eq!(registry.get("BTC_JPY").unwrap().size_step, 0.0001);
+    }
+
+    #[test]
+    fn symbol_registry_insert_replaces() {
+        let mut registry = SymbolRegistry::new();
+        registry.insert(SymbolRule {
+            symbol: "BTC_JPY".to_string(),
+            tick_size: 5.0,
+            size_step: 0.01,
+            min_lot: 0.01,
+            max_lot: 5.0,
+        });
+        let btc = registry.get("BTC_JPY").unwrap();
+        assert_eq!(btc.round_price(10_000_123.0, &OrderSide::BUY), 10_000_120.0);
+        assert_eq!(btc.round_price(10_000_123.0, &OrderSide::SELL), 10_000_125.0);
+        assert!(!btc.is_valid_size(0.001));
+    }
+
+    #[test]
+    fn symbol_registry_default_alt_symbols() {
+        let registry = SymbolRegistry::new();
+
+        let eth = registry.get("ETH_JPY").unwrap();
+        assert!(eth.is_valid_size(0.3));
+        assert!(!eth.is_valid_size(0.05));
+        assert_eq!(eth.round_size(0.37), Some(0.3));
+
+        let xrp = registry.get("XRP_JPY").unwrap();
+        assert!(xrp.is_valid_size(30.0));
+        assert!(!xrp.is_valid_size(15.0));
+        assert!(xrp.is_valid_price(85.123));
+        assert_eq!(xrp.min_lot, 10.0);
+
+        let bch = registry.get("BCH_JPY").unwrap();
+        assert!(bch.is_valid_size(0.1));
+        assert!(!bch.is_valid_size(0.0001));
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_symbol_parsing() {
+        use crate::api::gmo::api::Symbol;
+        use crate::model::BotConfig;
+
+        let config: BotConfig = serde_yaml::from_str(BASE_YAML).unwrap();
+        assert_eq!(config.symbol, Symbol::BTC_JPY);
+
+        for (name, expected) in [("ETH_JPY", Symbol::ETH_JPY), ("XRP_JPY", Symbol::XRP_JPY), ("BCH_JPY", Symbol::BCH_JPY)] {
+            let config: BotConfig = serde_yaml::from_str(&format!("{}symbol: {}\n", BASE_YAML, name)).unwrap();
+            assert_eq!(config.symbol, expected);
+        }
+
+        assert!(serde_yaml::from_str::<BotConfig>(&format!("{}symbol: DOGE_JPY\n", BASE_YAML)).is_err());
+        assert!(serde_yaml::from_str::<BotConfig>(&format!("{}symbol: Unknown\n", BASE_YAML)).is_err());
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_symbols_inherit_and_override_sizing() {
+        use crate::api::gmo::api::Symbol;
+        use crate::model::{BotConfig, SymbolConfig};
+
+        let single: BotConfig = serde_yaml::from_str(BASE_YAML).unwrap();
+        assert_eq!(single.symbol_configs(), vec![SymbolConfig::new(Symbol::BTC_JPY)]);
+
+        let multi = "symbols:\n  - symbol: BTC_JPY\n  - symbol: ETH_JPY\n    min_lot: 0.1\n    max_lot: 0.1\n    max_position: 0.3\n";
+        let config: BotConfig = serde_yaml::from_str(&format!("{}{}", BASE_YAML, multi)).unwrap();
+        assert_eq!(config.validate(), Ok(()));
+        let symbols = config.symbol_configs();
+        assert_eq!(symbols.len(), 2);
+
+        let btc = config.for_symbol(&symbols[0]);
+        assert_eq!((btc.symbol.clone(), btc.min_lot, btc.max_position), (Symbol::BTC_JPY, 0.001, 0.002));
+        let eth = config.for_symbol(&symbols[1]);
+        assert_eq!((eth.symbol.clone(), eth.min_lot, eth.max_lot, eth.max_position), (Symbol::ETH_JPY, 0.1, 0.1, 0.3));
+        assert!(eth.symbols.is_empty());
+        assert_eq!(eth.alpha, config.alpha, "everything else is shared");
+
+        // The collateral-scaled cap splits one collateral between the symbols
+        let scaled = BotConfig { max_position_pct_of_collateral: 0.5, ..config.clone() };
+        assert_eq!(scaled.for_symbol(&symbols[0]).max_position_pct_of_collateral, 0.25);
+        assert_eq!(scaled.for_symbol(&symbols[1]).max_position_pct_of_collateral, 0.25);
+        assert_eq!(single.for_symbol(&single.symbol_configs()[0]).max_position_pct_of_collateral, single.max_position_pct_of_collateral);
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_validate_rejects_bad_symbols() {
+        use crate::api::gmo::api::Symbol;
+        use crate::model::SymbolConfig;
+
+        let mut config = valid_config();
+        config.symbols = vec![SymbolConfig { min_lot: Some(1.0), ..SymbolConfig::new(Symbol::ETH_JPY) }];
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("symbols.ETH_JPY min_lot"), "{:?}", errors);
+
+        config.symbols = vec![SymbolConfig::new(Symbol::BTC_JPY), SymbolConfig::new(Symbol::BTC_JPY)];
+        assert!(config.validate().unwrap_err()[0].starts_with("symbols lists BTC_JPY"));
+
+        // A top-level violation is reported once, not again per symbol
+        config.symbols = vec![SymbolConfig::new(Symbol::BTC_JPY), SymbolConfig::new(Symbol::ETH_JPY)];
+        config.stop_loss_jpy = -1.0;
+        assert_eq!(config.validate().unwrap_err().len(), 1);
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_validate_rejects_sub_yen_ticks() {
+        use crate::api::gmo::api::Symbol;
+        use crate::model::SymbolConfig;
+
+        let mut config = valid_config();
+        config.symbol = Symbol::XRP_JPY;
+        config.min_lot = 10.0;
+        config.max_lot = 10.0;
+        config.max_position = 30.0;
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("symbol (XRP_JPY)"), "{:?}", errors);
+
+        // A whole-yen override makes it acceptable; a sub-yen override of BTC does not
+        config.symbol_rules = vec![SymbolRule { symbol: "XRP_JPY".to_string(), tick_size: 1.0, size_step: 10.0, min_lot: 10.0, max_lot: f64::MAX }];
+        assert_eq!(config.validate(), Ok(()));
+        let mut btc = valid_config();
+        btc.symbol_rules = vec![SymbolRule { symbol: "BTC_JPY".to_string(), tick_size: 0.5, ..eth_rule() }];
+        assert!(btc.validate().unwrap_err()[0].starts_with("symbol (BTC_JPY)"));
+
+        let mut multi = valid_config();
+        multi.symbols = vec![SymbolConfig::new(Symbol::BTC_JPY), SymbolConfig { min_lot: Some(10.0), max_lot: Some(10.0), max_position: Some(30.0), ..SymbolConfig::new(Symbol::XRP_JPY) }];
+        let errors = multi.validate().unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("symbols.XRP_JPY symbol (XRP_JPY)"), "{:?}", errors);
+    }
+
+    #[test]
+    fn bot_config_sizing_mode_parsing() {
+        use crate::model::BotConfig;
+
+        let config: BotConfig = serde_yaml::from_str(BASE_YAML).unwrap();
+        assert_eq!(config.sizing_mode, SizingMode::PowerLaw);
+
+        let yaml = format!("{}sizing_mode:\n  mode: kelly\n  win_prob: 0.55\n  win_loss_ratio: 1.5\n", BASE_YAML);
+        let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.sizing_mode, SizingMode::Kelly { win_prob: 0.55, win_loss_ratio: 1.5 });
+        assert_eq!(config.sizing_source, SizingSource::Margin);
+
+        let yaml = format!("{}sizing_source: balance\n", BASE_YAML);
+        let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.sizing_source, SizingSource::Balance);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn bot_config_levels_from_yaml() {
+        use crate::model::BotConfig;
+
+        let config: BotConfig = serde_yaml::from_str(BASE_YAML).unwrap();
+        assert!(config.levels.is_none());
+
+        let yaml = format!("{}levels:\n  - rate: 4\n  - {{ rate: 6 }}\n  - {{ base: 10, exp: -4, rate: 8 }}\n", BASE_YAML);
+        let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+        let levels = config.levels.clone().unwrap();
+        assert_eq!(levels[0], FloatingExp::new(10.0, -5.0, 4.0), "omitted fields take the default grid");
+        let calcs: Vec<f64> = levels.iter().map(FloatingExp::calc).collect();
+        for (calc, expected) in calcs.iter().zip([4e-5, 6e-5, 8e-4]) {
+            assert!((calc - expected).abs() < 1e-15, "{} vs {}", calc, expected);
+        }
+        assert!(levels.windows(2).all(|w| w[0] < w[1]));
+        assert!(config.validate().is_ok());
+
+        // Round-trips through serde
+        let json = serde_json::to_string(&levels).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<FloatingExp>>(&json).unwrap(), levels);
+    }
+
+    #[test]
+    fn bot_config_log_format_parsing() {
+        use crate::model::{BotConfig, LogFormat};
+
+        let config: BotConfig = serde_yaml::from_str(BASE_YAML).unwrap();
+        assert_eq!(config.log_format, LogFormat::Csv);
+        let config: BotConfig = serde_yaml::from_str(&format!("{}log_format: jsonl\n", BASE_YAML)).unwrap();
+        assert_eq!((config.log_format, config.log_format.extension()), (LogFormat::Jsonl, "jsonl"));
+        assert!(serde_yaml::from_str::<BotConfig>(&format!("{}log_format: parquet\n", BASE_YAML)).is_err());
+
+        let config: BotConfig = serde_yaml::from_str(&format!("{}log_retention_days: 14\n", BASE_YAML)).unwrap();
+        assert_eq!(config.log_retain_days, 14);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn bot_config_circuit_breaker_frac_reads_old_key() {
+        use crate::model::BotConfig;
+
+        let config: BotConfig = serde_yaml::from_str(&format!("{}circuit_breaker_frac: 0.002\n", BASE_YAML)).unwrap();
+        assert_eq!(config.circuit_breaker_frac, 0.002);
+        let config: BotConfig = serde_yaml::from_str(&format!("{}circuit_breaker_bps: 0.002\n", BASE_YAML)).unwrap();
+        assert_eq!(config.circuit_breaker_frac, 0.002, "old key keeps its fraction meaning");
+    }
+
+    #[test]
+    fn bot_config_debug_redacts_secrets() {
+        use crate::model::BotConfig;
+
+        let yaml = format!("{}alert_webhook_url: https://hooks.example.com/T000/B000/tok3n\nadmin_secret: hunter2\n", BASE_YAML);
+        let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(config.admin_secret.as_ref().map(Secret::expose), Some("hunter2"));
+
+        let logged = format!("{:?}", config);
+        assert!(!logged.contains("hunter2") && !logged.contains("tok3n"), "{}", logged);
+        assert!(logged.contains("admin_secret: Some(\"<redacted>\")"));
+        assert!(logged.contains("min_lot: 0.001"), "everything else is still logged");
+    }
+
+    fn order_map_info(side: OrderSide, size: f64, is_close: bool) -> OrderInfo {
+        OrderInfo {
+            price: 10_000_000, size, side, timestamp: 0, is_close,
+            mid_price: 0.0, t_optimal_ms: 0, sigma_1s: 0.0, spread_pct: 0.0,
+            level: 0, level_key: None, p_fill: 0.0, best_ev: 0.0, single_leg_ev: 0.0, client_id: String::new(),
+        }
+    }
+
+    /// Recompute the index by scanning, for comparison with the incremental one
+    fn scanned(orders: &OrderMap, side: &OrderSide, is_close: bool) -> (usize, f64) {
+        let matching: Vec<_> = orders.values().filter(|o| o.side == *side && o.is_close == is_close).collect();
+        (matching.len(), matching.iter().map(|o| o.size).sum())
+    }
+
+    fn assert_index_consistent(orders: &OrderMap) {
+        for side in [OrderSide::BUY, OrderSide::SELL] {
+            for is_close in [false, true] {
+                let summary = orders.pending(&side, is_close);
+                let (count, size) = scanned(orders, &side, is_close);
+                assert_eq!(summary.count, count, "{:?} close={}", side, is_close);
+                assert!((summary.size - size).abs() < 1e-12, "{:?} close={}: {} vs {}", side, is_close, summary.size, size);
+            }
+        }
+    }
+
+    #[test]
+    fn order_map_index_tracks_inserts_and_removals() {
+        let mut orders = OrderMap::new();
+        orders.insert("b1".to_string(), order_map_info(OrderSide::BUY, 0.001, false));
+        orders.insert("b2".to_string(), order_map_info(OrderSide::BUY, 0.002, false));
+        orders.insert("s1".to_string(), order_map_info(OrderSide::SELL, 0.001, true));
+        assert_index_consistent(&orders);
+        assert!((orders.pending_open_size(&OrderSide::BUY) - 0.003).abs() < 1e-12);
+        assert_eq!(orders.pending_open_size(&OrderSide::SELL), 0.0);
+        assert!(orders.has_pending_close(&OrderSide::SELL));
+        assert!(!orders.has_pending_close(&OrderSide::BUY));
+
+        assert!(orders.remove("b1").is_some());
+        assert!(orders.remove("b1").is_none(), "double remove must not double-count");
+        assert_index_consistent(&orders);
+        assert!((orders.pending_open_size(&OrderSide::BUY) - 0.002).abs() < 1e-12);
+
+        orders.remove("s1");
+        assert!(!orders.has_pending_close(&OrderSide::SELL));
+        orders.remove("b2");
+        assert_eq!(orders.pending_open_size(&OrderSide::BUY), 0.0, "empty bucket must read exactly 0");
+        assert!(orders.is_empty());
+    }
+
+    #[test]
+    fn order_map_reinsert_same_id_replaces_index_entry() {
+        let mut orders = OrderMap::new();
+        orders.insert("x".to_string(), order_map_info(OrderSide::BUY, 0.001, false));
+        // Same id re-inserted as a SELL close: the old BUY open entry must leave the index
+        let previous = orders.insert("x".to_string(), order_map_info(OrderSide::SELL, 0.002, true));
+        assert!(previous.is_some());
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders.pending(&OrderSide::BUY, false).count, 0);
+        assert_eq!(orders.pending(&OrderSide::SELL, true).count, 1);
+        assert_index_consistent(&orders);
+
+        orders.clear();
+        assert_index_consistent(&orders);
+        assert!(!orders.has_pending_close(&OrderSide::SELL));
+    }
+
+    #[test]
+    fn order_map_adopts_unconfirmed_by_side_size_price_and_age() {
+        let unconfirmed = |client_id: &str, side: OrderSide, price: u64, size: f64, timestamp: u64| OrderInfo {
+            price, timestamp, client_id: client_id.to_string(), ..order_map_info(side, size, false)
+        };
+        let mut orders = OrderMap::new();
+        orders.add_unconfirmed(unconfirmed("stale", OrderSide::BUY, 10_000_000, 0.001, 1_000));
+        orders.add_unconfirmed(unconfirmed("far", OrderSide::BUY, 10_000_005, 0.001, 50_000));
+        orders.add_unconfirmed(unconfirmed("near", OrderSide::BUY, 10_000_000, 0.001, 55_000));
+        orders.add_unconfirmed(unconfirmed("bigger", OrderSide::BUY, 10_000_000, 0.002, 55_000));
+        orders.add_unconfirmed(unconfirmed("sell", OrderSide::SELL, 10_000_000, 0.001, 55_000));
+
+        let reported = ReportedOrder { side: OrderSide::BUY, price: 10_000_000.0, size: 0.001, seen_ms: 70_000 };
+        let adopted = orders.adopt_unconfirmed("9001", &reported, 30_000, 10.0).unwrap();
+        assert_eq!(adopted.client_id, "near", "closest price wins");
+        assert_eq!(orders.get("9001").unwrap().client_id, "near");
+        assert_eq!(orders.pending(&OrderSide::BUY, false).size, 0.001);
+        // The still-unconfirmed BUYs ("far", "bigger") may be live too
+        assert_eq!(orders.pending_open_size(&OrderSide::BUY), 0.004);
+        // "stale" aged out; the rest wait for their own reports
+        assert_eq!(orders.unconfirmed_len(), 3);
+
+        // Nothing fits: wrong size, price too far off
+        let odd = ReportedOrder { size: 0.003, ..reported.clone() };
+        assert!(orders.adopt_unconfirmed("9002", &odd, 30_000, 10.0).is_none());
+        let off = ReportedOrder { price: 10_000_050.0, ..reported.clone() };
+        assert!(orders.adopt_unconfirmed("9002", &off, 30_000, 10.0).is_none());
+        assert!(orders.get("9002").is_none());
+
+        // The remaining BUY 0.001 is still claimable, within the price tolerance
+        let adopted = orders.adopt_unconfirmed("9003", &reported, 30_000, 10.0).unwrap();
+        assert_eq!(adopted.client_id, "far");
+        assert_index_consistent(&orders);
+
+        orders.clear();
+        assert_eq!(orders.unconfirmed_len(), 0);
+
+        // Nothing claims them without order events: reaped on age alone, counted as pending till then
+        orders.add_unconfirmed(unconfirmed("lost", OrderSide::SELL, 10_000_000, 0.002, 100_000));
+        orders.add_unconfirmed(OrderInfo { is_close: true, ..unconfirmed("lost close", OrderSide::SELL, 10_000_000, 0.001, 120_000) });
+        let open_sell = |orders: &OrderMap| orders.pending_open_size(&OrderSide::SELL);
+        assert_eq!(open_sell(&orders), 0.002, "unconfirmed closes take no open room");
+        assert_eq!(orders.reap_unconfirmed(130_000, 30_000), 0);
+        assert_eq!(orders.reap_unconfirmed(140_000, 30_000), 1);
+        assert_eq!(open_sell(&orders), 0.0);
+        assert_eq!(orders.unconfirmed_len(), 1);
+    }
+
+    fn valid_config() -> crate::model::BotConfig {
+        serde_yaml::from_str(BASE_YAML).unwrap()
+    }
+
+    fn vol_regimes() -> VolRegimeConfig {
+        let params = |alpha| RegimeParams { alpha, min_spread: 0.0, max_spread: f64::MAX };
+        VolRegimeConfig { low_sigma_1s: 0.00003, high_sigma_1s: 0.0001, low: params(0.3), mid: params(0.5), high: params(0.8) }
+    }
+
+    #[test]
+    fn bot_config_validate_accepts_valid_config() {
+        assert_eq!(valid_config().validate(), Ok(()));
+        let mut edge = valid_config();
+        edge.position_ratio = 1.0;
+        edge.stop_loss_jpy = 0.0;
+        assert_eq!(edge.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bot_config_validate_rejects_each_invariant() {
+        type Mutation = fn(&mut crate::model::BotConfig);
+
+        let cases: [(Mutation, &str); 51] = [
+            (|c| { c.min_lot = 0.002; c.max_position = 0.01; }, "min_lot"),
+            (|c| c.max_lot = 0.005, "max_lot"),
+            (|c| c.rate_limit_capacity = 0.5, "rate_limit_capacity"),
+            (|c| c.rate_limit_refill_per_sec = 0.0, "rate_limit_refill_per_sec"),
+            (|c| c.rate_limit_refill_per_sec = f64::NAN, "rate_limit_refill_per_sec"),
+            (|c| c.position_ratio = 0.0, "position_ratio"),
+            (|c| c.position_ratio = 1.5, "position_ratio"),
+            (|c| { c.t_optimal_min_ms = 5000; c.t_optimal_max_ms = 1000; }, "t_optimal_min_ms"),
+            (|c| c.close_spread_factor = 0.0, "close_spread_factor"),
+            (|c| c.close_spread_factor = 1.0, "close_spread_factor"),
+            (|c| c.close_escalation_step = 1.2, "close_escalation_step"),
+            (|c| { c.close_escalation_step = 0.8; c.close_spread_factor_floor = 0.9; }, "close_spread_factor_floor"),
+            (|c| c.stop_loss_jpy = -1.0, "stop_loss_jpy"),
+            (|c| c.ghost_position_cooldown_secs = 5, "ghost_position_cooldown_secs"),
+            (|c| c.stop_loss_cooldown_secs = 60, "ghost_position_cooldown_secs"),
+            (|c| c.take_profit_jpy = -1.0, "take_profit_jpy"),
+            (|c| c.trailing_stop_jpy = -1.0, "trailing_stop_jpy"),
+            (|c| c.max_close_slippage_jpy = -1.0, "max_close_slippage_jpy"),
+            (|c| c.min_touch_offset_jpy = f64::NAN, "min_touch_offset_jpy"),
+            (|c| c.max_position_pct_of_collateral = -0.1, "max_position_pct_of_collateral"),
+            (|c| c.max_position_floor = f64::INFINITY, "max_position_floor"),
+            (|c| { c.max_position_pct_of_collateral = 0.5; c.max_position_floor = 0.01; c.max_position_ceiling = 0.005; }, "max_position_ceiling"),
+            (|c| { c.max_position_pct_of_collateral = 0.5; c.max_position_floor = 0.0005; c.max_position_ceiling = 0.01; }, "max_position_floor"),
+            (|c| c.daily_loss_limit_jpy = -1.0, "daily_loss_limit_jpy"),
+            (|c| { c.daily_loss_limit_jpy = 5_000.0; c.private_ws_enabled = false; }, "daily_loss_limit_jpy"),
+            (|c| { c.close_ladder_factor = 0.5; c.close_ladder_min_step_jpy = 30.0; c.close_ladder_max_step_jpy = 10.0; }, "close_ladder_min_step_jpy"),
+            (|c| c.price_step_start = 0, "price_step_start"),
+            (|c| { c.price_step_start = 10; c.price_step_end = 5; }, "price_step_start"),
+            (|c| c.price_step_base = 0.0, "price_step_base"),
+            (|c| c.sizing_mode = SizingMode::Kelly { win_prob: 1.0, win_loss_ratio: 1.0 }, "sizing_mode"),
+            (|c| c.sizing_mode = SizingMode::Kelly { win_prob: 0.4, win_loss_ratio: 1.0 }, "sizing_mode"),
+            (|c| c.imbalance_weight = 0.6, "imbalance_weight"),
+            (|c| c.min_ev = f64::NAN, "min_ev"),
+            (|c| c.admin_secret = Some(Secret::new(" ")), "admin_secret"),
+            (|c| c.dedup_price_tolerance_jpy = Some(-1.0), "dedup_price_tolerance_jpy"),
+            (|c| c.vol_regimes = Some(VolRegimeConfig { low_sigma_1s: 0.0001, ..vol_regimes() }), "vol_regimes.low_sigma_1s"),
+            (|c| c.vol_regimes = Some(VolRegimeConfig { high: RegimeParams { alpha: -0.1, ..vol_regimes().high }, ..vol_regimes() }), "vol_regimes.high.alpha"),
+            (|c| c.vol_regimes = Some(VolRegimeConfig { low: RegimeParams { min_spread: 0.0002, max_spread: 0.0001, ..vol_regimes().low }, ..vol_regimes() }), "vol_regimes.low.min_spread"),
+            (|c| c.sell_interval_ms = Some(0), "sell_interval_ms"),
+            (|c| c.trade_flow_window_ms = 0, "trade_flow_window_ms"),
+            (|c| c.trade_flow_weight = -0.1, "trade_flow_weight"),
+            (|c| c.vol_short_window_ms = 5000, "vol_short_window_ms"),
+            (|c| c.vol_expansion_threshold = 0.8, "vol_expansion_threshold"),
+            (|c| c.vol_expansion_weight = -0.1, "vol_expansion_weight"),
+            (|c| c.target_net_position = 0.003, "target_net_position"),
+            (|c| c.target_net_position = -0.003, "target_net_position"),
+            (|c| c.levels = Some(Vec::new()), "levels"),
+            (|c| c.levels = Some(vec![FloatingExp::new(10.0, -5.0, 6.0), FloatingExp::new(10.0, -5.0, 4.0)]), "levels"),
+            (|c| c.levels = Some(vec![FloatingExp::new(10.0, -5.0, 0.0)]), "levels"),
+            (|c| { c.levels = Some(vec![FloatingExp::default()]); c.level_offsets_jpy = vec![500]; }, "levels"),
+            (|c| c.symbol_rules = vec![SymbolRule { tick_size: 0.0, ..eth_rule() }], "symbol_rules.ETH_JPY"),
+        ];
+        for (i, (mutate, field)) in cases.iter().enumerate() {
+            let mut config = valid_config();
+            mutate(&mut config);
+            let errors = config.validate().unwrap_err();
+            assert_eq!(errors.len(), 1, "case {}: {:?}", i, errors);
+            assert!(errors[0].starts_with(field), "case {}: {:?}", i, errors);
+        }
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn parse_time_in_force_accepts_known_values_in_any_case() {
+        use crate::api::gmo::api::TimeInForce;
+        use crate::model::parse_time_in_force;
+
+        assert_eq!(parse_time_in_force(None), Ok(None));
+        assert_eq!(parse_time_in_force(Some("")), Ok(None));
+        assert_eq!(parse_time_in_force(Some("FAK")), Ok(Some(TimeInForce::FAK)));
+        assert_eq!(parse_time_in_force(Some("fok")), Ok(Some(TimeInForce::FOK)));
+        assert_eq!(parse_time_in_force(Some("SOK")), Ok(Some(TimeInForce::SOK)));
+        assert!(parse_time_in_force(Some("GTC")).is_err());
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_validate_rejects_unknown_time_in_force() {
+        let mut config = valid_config();
+        config.time_in_force = Some("GTC".to_string());
+        let errors = config.validate().unwrap_err();
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with("time_in_force"), "{:?}", errors);
+    }
+
+    #[test]
+    fn bot_config_validate_reports_every_violation() {
+        let mut config = valid_config();
+        config.max_lot = 0.0005;
+        config.position_ratio = 2.0;
+        config.stop_loss_jpy = -5.0;
+        assert_eq!(config.validate().unwrap_err().len(), 3);
+    }
+
+    #[test]
+    fn bot_config_overrides_only_present_and_valid_keys() {
+        use std::collections::HashMap;
+
+        let vars: HashMap<&str, &str> = HashMap::from([
+            ("BOT_MIN_LOT", "0.002"),
+            ("BOT_MAX_LOT", " 0.003 "),
+            ("BOT_MAX_POSITION", "not-a-number"),
+            ("BOT_PRIVATE_WS_ENABLED", "false"),
+            ("BOT_LOG_DIR", "logs/instance-2"),
+            ("BOT_TRADE_INTERVAL_MS", "1500"),
+            ("BOT_HEALTH_BIND_ADDR", "0.0.0.0"),
+        ]);
+        let mut config = valid_config();
+        assert!(config.health_bind_addr.is_loopback(), "health port stays local by default");
+        config.apply_overrides(|key| vars.get(key).map(|v| v.to_string()));
+
+        assert_eq!(config.min_lot, 0.002);
+        assert_eq!(config.max_lot, 0.003);
+        assert_eq!(config.max_position, 0.002, "invalid value keeps the YAML value");
+        assert!(!config.private_ws_enabled);
+        assert_eq!(config.log_dir, "logs/instance-2");
+        assert_eq!(config.trade_interval_ms, Some(1500));
+        assert!(config.health_bind_addr.is_unspecified());
+        assert_eq!(config.order_interval_ms, 3000, "absent keys are untouched");
+        assert_eq!(config.stop_loss_jpy, valid_config().stop_loss_jpy);
+    }
+
+    #[test]
+    fn bot_config_env_overrides_read_process_env() {
+        // Only this test touches these variables
+        std::env::set_var("BOT_STOP_LOSS_JPY", "25.5");
+        std::env::set_var("BOT_API_MAX_RETRIES", "-1");
+        let mut config = valid_config();
+        config.apply_env_overrides();
+        std::env::remove_var("BOT_STOP_LOSS_JPY");
+        std::env::remove_var("BOT_API_MAX_RETRIES");
+
+        assert_eq!(config.stop_loss_jpy, 25.5);
+        assert_eq!(config.api_max_retries, valid_config().api_max_retries, "u32 rejects -1");
+
+        let mut untouched = valid_config();
+        untouched.apply_env_overrides();
+        assert_eq!(untouched.stop_loss_jpy, valid_config().stop_loss_jpy);
+    }
+
+    #[cfg(feature = "gmo")]
+    #[test]
+    fn bot_config_symbol_override() {
+        use crate::api::gmo::api::Symbol;
+
+        let mut config = valid_config();
+        config.apply_overrides(|key| (key == "BOT_SYMBOL").then(|| "ETH_JPY".to_string()));
+        assert_eq!(config.symbol, Symbol::ETH_JPY);
+
+        config.apply_overrides(|key| (key == "BOT_SYMBOL").then(|| "DOGE_JPY".to_string()));
+        assert_eq!(config.symbol, Symbol::ETH_JPY, "unknown symbol is ignored");
+    }
+
+    #[test]
+    fn daily_pnl_guard_trips_at_limit_and_blocks_opens_only() {
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut guard = DailyPnlGuard::new(100.0);
+        assert!(!guard.update(day, 50.0, 0.0), "first update sets the baseline");
+        assert!(!guard.update(day, 0.0, -49.0));
+        assert!(guard.allows_open());
+
+        // 50 -> -50: exactly the limit
+        assert!(guard.update(day, -20.0, -30.0));
+        assert!(!guard.allows_open());
+        assert_eq!(guard.day_pnl(), -100.0);
+        // Stays tripped (and reports the trip once) even if the day recovers
+        assert!(!guard.update(day, 60.0, 0.0));
+        assert!(!guard.allows_open());
+    }
+
+    #[test]
+    fn daily_pnl_guard_resets_at_day_rollover() {
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut guard = DailyPnlGuard::new(10.0);
+        guard.update(day, 0.0, 0.0);
+        assert!(guard.update(day, -15.0, 0.0));
+
+        // New UTC day: yesterday's loss becomes the baseline
+        assert!(!guard.update(day.succ_opt().unwrap(), -15.0, -5.0));
+        assert!(guard.allows_open());
+        assert_eq!(guard.day_pnl(), 0.0);
+        assert!(guard.update(day.succ_opt().unwrap(), -30.0, 0.0));
+    }
+
+    #[test]
+    fn daily_pnl_guard_disabled_with_zero_limit() {
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        let mut guard = DailyPnlGuard::new(0.0);
+        guard.update(day, 0.0, 0.0);
+        assert!(!guard.update(day, -1e9, 0.0));
+        assert!(guard.allows_open());
+    }
+
+    #[test]
+    fn pnl_tracker_long_round_trip_realizes_delta_times_size() {
+        let mut pnl = PnlTracker::new();
+        pnl.on_fill(&OrderSide::BUY, 10_000_000.0, 0.001, false);
+        assert_eq!(pnl.realized_pnl, 0.0);
+        assert_eq!(pnl.round_trips, 0);
+
+        pnl.on_fill(&OrderSide::SELL, 10_000_500.0, 0.001, true);
+        assert!((pnl.realized_pnl - 500.0 * 0.001).abs() < 1e-9, "pnl={}", pnl.realized_pnl);
+        assert_eq!(pnl.round_trips, 1);
+    }
+
+    #[test]
+    fn pnl_tracker_short_and_weighted_average() {
+        let mut pnl = PnlTracker::new();
+        // Short 0.001 @ 10,000,000 and 0.003 @ 10,000,400 -> avg 10,000,300
+        pnl.on_fill(&OrderSide::SELL, 10_000_000.0, 0.001, false);
+        pnl.on_fill(&OrderSide::SELL, 10_000_400.0, 0.003, false);
+        // Partial close doesn't complete a round trip
+        pnl.on_fill(&OrderSide::BUY, 10_000_100.0, 0.002, true);
+        assert!((pnl.realized_pnl - 200.0 * 0.002).abs() < 1e-9, "pnl={}", pnl.realized_pnl);
+        assert_eq!(pnl.round_trips, 0);
+
+        pnl.on_fill(&OrderSide::BUY, 10_000_500.0, 0.002, true);
+        assert!((pnl.realized_pnl - (0.4 - 0.4)).abs() < 1e-9, "pnl={}", pnl.realized_pnl);
+        assert_eq!(pnl.round_trips, 1);
+    }
+
+    #[test]
+    fn pnl_tracker_ignores_untracked_close_excess() {
+        let mut pnl = PnlTracker::new();
+        pnl.on_fill(&OrderSide::SELL, 10_000_000.0, 0.001, true);
+        assert_eq!(pnl.realized_pnl, 0.0);
+        assert_eq!(pnl.round_trips, 0);
+
+        pnl.on_fill(&OrderSide::BUY, 10_000_000.0, 0.001, false);
+        pnl.on_fill(&OrderSide::SELL, 10_001_000.0, 0.002, true);
+        assert!((pnl.realized_pnl - 1.0).abs() < 1e-9, "only the tracked 0.001 is realized");
+        assert_eq!(pnl.round_trips, 1);
+    }
 }
@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
+/// No reconnect is attempted sooner than this, jitter included
+pub const MIN_DELAY: Duration = Duration::from_secs(1);
+/// Exponential backoff stops growing here
+pub const MAX_DELAY: Duration = Duration::from_secs(60);
+/// Each delay is spread by up to ±20% so both bots (and both feeds) don't reconnect in lockstep
+pub const JITTER_RATIO: f64 = 0.2;
+/// More than STORM_MAX_RECONNECTS reconnects within STORM_WINDOW...
+pub const STORM_WINDOW: Duration = Duration::from_secs(60);
+pub const STORM_MAX_RECONNECTS: usize = 5;
+/// ...and the next one waits this long instead
+pub const STORM_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// Delay before the next WebSocket reconnect.
+///
+/// Errors back off exponentially from MIN_DELAY to MAX_DELAY; a clean close starts over at
+/// MIN_DELAY. A server that keeps closing cleanly would otherwise be redialled every second,
+/// so reconnects are also counted over STORM_WINDOW and a burst gets STORM_COOLDOWN.
+#[derive(Debug)]
+pub struct ReconnectBackoff {
+    delay: Duration,
+    recent: VecDeque<Instant>,
+}
+
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReconnectBackoff {
+    pub fn new() -> Self {
+        Self { delay: MIN_DELAY, recent: VecDeque::new() }
+    }
+
+    /// Record a connection that just ended at `now` (`clean` = closed normally) and return
+    /// how long to wait before dialling again.
+    pub fn next_delay<R: Rng>(&mut self, clean: bool, now: Instant, rng: &mut R) -> Duration {
+        if clean {
+            self.delay = MIN_DELAY;
+        }
+        self.recent.push_back(now);
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) > STORM_WINDOW) {
+            self.recent.pop_front();
+        }
+
+        let base = if self.in_storm() { STORM_COOLDOWN } else { self.delay };
+        self.delay = (self.delay * 2).min(MAX_DELAY);
+        jittered(base, rng.gen_range(-1.0..=1.0))
+    }
+
+    /// Too many reconnects within STORM_WINDOW
+    pub fn in_storm(&self) -> bool {
+        self.recent.len() > STORM_MAX_RECONNECTS
+    }
+}
+
+/// `base` scaled by `1 + JITTER_RATIO * unit` (`unit` in [-1, 1]), never below MIN_DELAY
+fn jittered(base: Duration, unit: f64) -> Duration {
+    base.mul_f64(1.0 + JITTER_RATIO * unit.clamp(-1.0, 1.0)).max(MIN_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let mut backoff = ReconnectBackoff::new();
+            // Third error in a row: 4s base
+            let t0 = Instant::now();
+            backoff.next_delay(false, t0, &mut rng);
+            backoff.next_delay(false, t0, &mut rng);
+            let d = backoff.next_delay(false, t0, &mut rng);
+            assert!((Duration::from_millis(3_200)..=Duration::from_millis(4_800)).contains(&d), "{:?}", d);
+        }
+        assert_eq!(jittered(Duration::from_secs(10), 1.0), Duration::from_secs(12));
+        assert_eq!(jittered(Duration::from_secs(10), -1.0), Duration::from_secs(8));
+        // The floor holds even when the jitter would undercut it
+        assert_eq!(jittered(MIN_DELAY, -1.0), MIN_DELAY);
+    }
+
+    #[test]
+    fn test_reconnect_storm_extends_delay() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut backoff = ReconnectBackoff::new();
+        let t0 = Instant::now();
+        // Clean closes every few seconds stay at the floor...
+        for i in 0..STORM_MAX_RECONNECTS as u64 {
+            let d = backoff.next_delay(true, t0 + Duration::from_secs(i * 5), &mut rng);
+            assert!(d <= MIN_DELAY.mul_f64(1.0 + JITTER_RATIO), "reconnect {}: {:?}", i, d);
+        }
+        // ...until one too many lands in the window
+        let d = backoff.next_delay(true, t0 + Duration::from_secs(30), &mut rng);
+        assert!(backoff.in_storm());
+        assert!(d >= STORM_COOLDOWN.mul_f64(1.0 - JITTER_RATIO), "{:?}", d);
+
+        // Once the burst has aged out of the window, the floor applies again
+        let d = backoff.next_delay(true, t0 + Duration::from_secs(30) + STORM_WINDOW + Duration::from_secs(1), &mut rng);
+        assert!(!backoff.in_storm());
+        assert!(d <= MIN_DELAY.mul_f64(1.0 + JITTER_RATIO), "{:?}", d);
+    }
+}
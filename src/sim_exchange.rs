@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+
+use crate::model::OrderSide;
+
+/// Simulated fill produced by `SimExchange::match_executions`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimFill {
+    pub order_id: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub size: f64,
+    pub is_close: bool,
+}
+
+#[derive(Debug, Clone)]
+struct SimOrder {
+    side: OrderSide,
+    /// None = MARKET: fills at the next execution price
+    price: Option<u64>,
+    size: f64,
+    is_close: bool,
+}
+
+#[derive(Debug, Default)]
+struct SimState {
+    next_id: u64,
+    resting: HashMap<String, SimOrder>,
+    /// Timestamp (ms) of the newest execution already matched
+    last_execution_ms: i64,
+}
+
+/// Paper-trading exchange for dry-run mode. Orders never leave the process; they are filled
+/// against the public executions stream instead.
+///
+/// A resting LIMIT fills in full at its own price once a public trade prints strictly through
+/// it (buy: trade < price, sell: trade > price). Touching the price is not enough since our
+/// place in the queue is unknown. MARKET orders fill at the next trade price.
+#[derive(Debug, Default)]
+pub struct SimExchange {
+    state: Mutex<SimState>,
+}
+
+impl SimExchange {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn place(&self, order: SimOrder) -> String {
+        let mut state = self.state.lock();
+        state.next_id += 1;
+        let order_id = format!("dry-{}", state.next_id);
+        state.resting.insert(order_id.clone(), order);
+        order_id
+    }
+
+    pub fn place_limit(&self, side: OrderSide, price: u64, size: f64, is_close: bool) -> String {
+        self.place(SimOrder { side, price: Some(price), size, is_close })
+    }
+
+    pub fn place_market(&self, side: OrderSide, size: f64, is_close: bool) -> String {
+        self.place(SimOrder { side, price: None, size, is_close })
+    }
+
+    /// Returns false when the order is unknown, i.e. already (simulated) filled.
+    pub fn cancel(&self, order_id: &str) -> bool {
+        self.state.lock().resting.remove(order_id).is_some()
+    }
+
+    /// Cancel every resting LIMIT order; pending MARKET orders still fill.
+    pub fn cancel_all(&self) -> usize {
+        let mut state = self.state.lock();
+        let before = state.resting.len();
+        state.resting.retain(|_, order| order.price.is_none());
+        before - state.resting.len()
+    }
+
+    pub fn open_order_count(&self) -> usize {
+        self.state.lock().resting.len()
+    }
+
+    /// Match resting orders against `(price, size, timestamp_ms)` executions newer than the last
+    /// call. Filled orders are removed and returned.
+    pub fn match_executions(&self, executions: &[(u64, f64, i64)]) -> Vec<SimFill> {
+        let mut state = self.state.lock();
+        let mut fills = Vec::new();
+        let mut newest = state.last_execution_ms;
+
+        for &(trade_price, _, timestamp) in executions {
+            if timestamp <= state.last_execution_ms {
+                continue;
+            }
+            newest = newest.max(timestamp);
+
+            let crossed: Vec<String> = state
+                .resting
+                .iter()
+                .filter(|(_, order)| match (order.price, &order.side) {
+                    (None, _) => true,
+                    (Some(price), OrderSide::BUY) => trade_price < price,
+                    (Some(price), OrderSide::SELL) => trade_price > price,
+                    (Some(_), OrderSide::Unknown) => false,
+                })
+                .map(|(id, _)| id.clone())
+                .collect();
+
+            for order_id in crossed {
+                if let Some(order) = state.resting.remove(&order_id) {
+                    fills.push(SimFill {
+                        order_id,
+                        side: order.side,
+                        price: order.price.unwrap_or(trade_price) as f64,
+                        size: order.size,
+                        is_close: order.is_close,
+                    });
+                }
+            }
+        }
+
+        state.last_execution_ms = newest;
+        fills
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_fills_only_when_trade_prints_through() {
+        let sim = SimExchange::new();
+        let buy = sim.place_limit(OrderSide::BUY, 10_000_000, 0.001, false);
+        let sell = sim.place_limit(OrderSide::SELL, 10_001_000, 0.001, false);
+
+        // Touching the price is not a fill
+        assert!(sim.match_executions(&[(10_000_000, 0.01, 1), (10_001_000, 0.01, 2)]).is_empty());
+
+        let fills = sim.match_executions(&[(9_999_999, 0.01, 3)]);
+        assert_eq!(fills, vec![SimFill {
+            order_id: buy, side: OrderSide::BUY, price: 10_000_000.0, size: 0.001, is_close: false,
+        }]);
+        assert_eq!(sim.open_order_count(), 1);
+
+        let fills = sim.match_executions(&[(10_001_001, 0.01, 4)]);
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].order_id, sell);
+        assert_eq!(sim.open_order_count(), 0);
+    }
+
+    #[test]
+    fn test_executions_are_matched_once() {
+        let sim = SimExchange::new();
+        let executions = [(9_000_000, 0.01, 10)];
+        sim.match_executions(&executions);
+
+        // Order placed after the trade must not fill on the same (already seen) trade
+        sim.place_limit(OrderSide::BUY, 10_000_000, 0.001, false);
+        assert!(sim.match_executions(&executions).is_empty());
+        assert_eq!(sim.match_executions(&[(9_000_000, 0.01, 11)]).len(), 1);
+    }
+
+    #[test]
+    fn test_market_fills_at_next_trade_and_survives_cancel_all() {
+        let sim = SimExchange::new();
+        sim.place_limit(OrderSide::SELL, 10_500_000, 0.001, false);
+        let market = sim.place_market(OrderSide::SELL, 0.002, true);
+
+        assert_eq!(sim.cancel_all(), 1);
+        let fills = sim.match_executions(&[(10_000_123, 0.01, 1)]);
+        assert_eq!(fills, vec![SimFill {
+            order_id: market, side: OrderSide::SELL, price: 10_000_123.0, size: 0.002, is_close: true,
+        }]);
+    }
+
+    #[test]
+    fn test_cancel_reports_already_filled() {
+        let sim = SimExchange::new();
+        let id = sim.place_limit(OrderSide::BUY, 10_000_000, 0.001, false);
+        sim.match_executions(&[(9_000_000, 0.01, 1)]);
+        assert!(!sim.cancel(&id));
+
+        let id = sim.place_limit(OrderSide::BUY, 10_000_000, 0.001, false);
+        assert!(sim.cancel(&id));
+        assert!(!sim.cancel(&id));
+    }
+}
@@ -0,0 +1,393 @@
+//! Pure pricing pipeline: volatility estimate, per-side EV level selection and
+//! quote price calculation. Kept free of I/O so it can be replayed in tests.
+
+use std::collections::BTreeMap;
+
+use tracing::debug;
+
+use crate::bayes_prob::BayesProb;
+use crate::decimal::Price;
+use crate::model::{FloatingExp, OrderSide, Position, RegimeParams, SizingMode, VolRegimeConfig};
+use crate::util;
+
+/// Single-leg EV: P(fill) * (spread_capture - expected_adverse)
+pub fn single_leg_ev(
+    mid_price: f64,
+    volatility: f64,
+    alpha: f64,
+    level: &FloatingExp,
+    p_fill: f64,
+) -> f64 {
+    let spread_capture = mid_price * level.calc();
+    let expected_adverse = volatility * alpha;
+    p_fill * (spread_capture - expected_adverse)
+}
+
+/// Each side independently selects optimal level (old: 22x22 pair -> new: 22+22 independent)
+/// Returns (best_buy_key, buy_p_fill, best_sell_key, sell_p_fill, combined_ev)
+pub fn maximize_single_leg_ev(
+    mid_price: f64,
+    volatility: f64,
+    alpha: f64,
+    buy: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+    sell: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+) -> Option<(FloatingExp, f64, FloatingExp, f64, f64)> {
+    maximize_single_leg_ev_with(mid_price, volatility, alpha, buy, sell, BayesProb::calc_average)
+}
+
+/// Same as `maximize_single_leg_ev`, with P(fill) taken from `p_fill` instead of the posterior
+/// mean (e.g. a Thompson sample).
+pub fn maximize_single_leg_ev_with(
+    mid_price: f64,
+    volatility: f64,
+    alpha: f64,
+    buy: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+    sell: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+    p_fill: impl FnMut(&BayesProb) -> f64,
+) -> Option<(FloatingExp, f64, FloatingExp, f64, f64)> {
+    maximize_single_leg_ev_within(mid_price, volatility, alpha, buy, sell, (f64::NEG_INFINITY, f64::INFINITY), p_fill)
+}
+
+/// Same as `maximize_single_leg_ev_with`, over only the levels whose distance from mid
+/// (`calc()`) lies in `spread_range` (inclusive). None when either side has no such level.
+pub fn maximize_single_leg_ev_within(
+    mid_price: f64,
+    volatility: f64,
+    alpha: f64,
+    buy: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+    sell: &BTreeMap<FloatingExp, (f64, BayesProb)>,
+    spread_range: (f64, f64),
+    mut p_fill: impl FnMut(&BayesProb) -> f64,
+) -> Option<(FloatingExp, f64, FloatingExp, f64, f64)> {
+    let in_range = |k: &FloatingExp| (spread_range.0..=spread_range.1).contains(&k.calc());
+    let best_buy = buy.iter()
+        .filter(|(k, _)| in_range(k))
+        .map(|(k, (_, b))| {
+            let p = p_fill(b);
+            (k.clone(), p, single_leg_ev(mid_price, volatility, alpha, k, p))
+        })
+        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+
+    let best_sell = sell.iter()
+        .filter(|(k, _)| in_range(k))
+        .map(|(k, (_, b))| {
+            let p = p_fill(b);
+            (k.clone(), p, single_leg_ev(mid_price, volatility, alpha, k, p))
+        })
+        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+
+    match (best_buy, best_sell) {
+        (Some((bk, bp, bev)), Some((sk, sp, sev))) => {
+            debug!("Best single-leg EV: buy={:.6} sell={:.6} combined={:.6}", bev, sev, bev + sev);
+            Some((bk, bp, sk, sp, bev + sev))
+        }
+        _ => None,
+    }
+}
+
+/// Volatility regime of a sigma_1s reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolRegime {
+    Low,
+    Mid,
+    High,
+}
+
+/// Low below `low_sigma_1s`, High at or above `high_sigma_1s`, Mid in between
+pub fn classify_regime(sigma_1s: f64, regimes: &VolRegimeConfig) -> VolRegime {
+    if sigma_1s < regimes.low_sigma_1s {
+        VolRegime::Low
+    } else if sigma_1s >= regimes.high_sigma_1s {
+        VolRegime::High
+    } else {
+        VolRegime::Mid
+    }
+}
+
+pub fn regime_params(regimes: &VolRegimeConfig, regime: VolRegime) -> &RegimeParams {
+    match regime {
+        VolRegime::Low => &regimes.low,
+        VolRegime::Mid => &regimes.mid,
+        VolRegime::High => &regimes.high,
+    }
+}
+
+/// Minimum volatility as a fraction of mean price (0.1 bps = 0.001%)
+pub const MIN_VOLATILITY_BPS: f64 = 0.00001;
+
+pub fn calculate_volatility(executions: &[(u64, f64, i64)]) -> f64 {
+    // Need at least 2 data points for log-returns
+    if executions.len() < 2 {
+        let mean_price = executions.first().map(|e| e.0 as f64).unwrap_or(6_500_000.0);
+        return mean_price * MIN_VOLATILITY_BPS;
+    }
+
+    let prices: Vec<f64> = executions.iter().map(|e| e.0 as f64).collect();
+    let mean_price = prices.iter().sum::<f64>() / prices.len() as f64;
+
+    // Calculate log-returns: ln(p[i] / p[i-1])
+    let log_returns: Vec<f64> = prices
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln())
+        .collect();
+
+    if log_returns.is_empty() {
+        return mean_price * MIN_VOLATILITY_BPS;
+    }
+
+    // EWMA variance: σ²_t = λ * σ²_{t-1} + (1-λ) * r²_t
+    // RiskMetrics standard lambda = 0.94
+    // Seed with initial window variance, then EWMA from remaining data only (no double-counting)
+    // Mean-zero assumption: r² instead of (r-μ)², appropriate for HFT tick data
+    // When data <= seed_n points, falls back to simple variance (no EWMA weighting).
+    // With execution_retain_ms=30000 and typical 2-5 ticks/sec, we have 60-150 returns;
+    // seed_n=10 edge case only triggers during startup or very low activity.
+    const LAMBDA: f64 = 0.94;
+    let seed_n = log_returns.len().min(10);
+    let mut ewma_var = log_returns[..seed_n].iter().map(|r| r.powi(2)).sum::<f64>()
+        / seed_n as f64;
+    for r in &log_returns[seed_n..] {
+        ewma_var = LAMBDA * ewma_var + (1.0 - LAMBDA) * r.powi(2);
+    }
+    let stddev = ewma_var.sqrt();
+
+    // Convert log-return stddev to absolute price units
+    let volatility = mean_price * stddev;
+
+    // Apply minimum floor
+    volatility.max(mean_price * MIN_VOLATILITY_BPS)
+}
+
+/// Realized volatility (JPY) over each of `windows_ms`, counted back from the latest execution:
+/// RMS of tick log-returns (mean-zero, like `calculate_volatility`) scaled by the window's mean
+/// price, floored at `MIN_VOLATILITY_BPS`. A short window running well above a long one shows
+/// volatility expanding before the price range is wide enough to trip the circuit breaker.
+/// A window with no executions is floored at `fallback_price` (the current mid).
+pub fn calculate_volatility_multi(executions: &[(u64, f64, i64)], windows_ms: &[i64], fallback_price: f64) -> Vec<f64> {
+    let latest = executions.iter().map(|e| e.2).max().unwrap_or(0);
+    windows_ms
+        .iter()
+        .map(|window| {
+            let prices: Vec<f64> = executions.iter().filter(|e| e.2 >= latest - window).map(|e| e.0 as f64).collect();
+            let mean_price = if prices.is_empty() { fallback_price } else { prices.iter().sum::<f64>() / prices.len() as f64 };
+            let squared_returns: Vec<f64> = prices
+                .windows(2)
+                .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+                .map(|w| (w[1] / w[0]).ln().powi(2))
+                .collect();
+            if squared_returns.is_empty() {
+                return mean_price * MIN_VOLATILITY_BPS;
+            }
+            let stddev = (squared_returns.iter().sum::<f64>() / squared_returns.len() as f64).sqrt();
+            (mean_price * stddev).max(mean_price * MIN_VOLATILITY_BPS)
+        })
+        .collect()
+}
+
+/// Spread multiplier increment for both sides once the short/long volatility ratio passes
+/// `threshold`: `(ratio - threshold) * weight`, capped at 1 (spreads at most double).
+pub fn vol_expansion_widening(ratio: f64, threshold: f64, weight: f64) -> f64 {
+    if !ratio.is_finite() {
+        return 0.0;
+    }
+    ((ratio - threshold).max(0.0) * weight).min(1.0)
+}
+
+pub fn calculate_order_prices(
+    mid_price: f64,
+    best_pair: &(FloatingExp, FloatingExp),
+    position: &Position,
+    position_penalty: f64,
+    min_lot: f64,
+) -> (f64, f64) {
+    let bid = mid_price - best_pair.0.calc() * mid_price;
+    let ask = mid_price + best_pair.1.calc() * mid_price;
+
+    // Penalty discourages adding to existing positions AND accelerates closing:
+    // Long-heavy: lower buy price (harder to buy more) + lower sell price (easier to close long)
+    // Short-heavy: raise sell price (harder to sell more) + raise buy price (easier to close short)
+    let buy_order_price = bid - position_penalty * position.long_size / min_lot
+                             + position_penalty * position.short_size / min_lot;
+    let sell_order_price = ask + position_penalty * position.short_size / min_lot
+                              - position_penalty * position.long_size / min_lot;
+
+    // Extreme penalties must still yield a sane price: never below 0 nor above 2x mid.
+    // Snapped to the fixed-point grid so float noise can't push a price under its tick later
+    (
+        Price::from_f64(buy_order_price.clamp(0.0, 2.0 * mid_price)).to_f64(),
+        Price::from_f64(sell_order_price.clamp(0.0, 2.0 * mid_price)).to_f64(),
+    )
+}
+
+/// `price` on a valid `tick`, rounded away from the touch so the rounding never makes a quote
+/// more aggressive: buys round down, sells round up.
+pub fn round_to_tick(price: f64, tick: f64, side: &OrderSide) -> f64 {
+    let price = Price::from_f64(price);
+    match side {
+        OrderSide::SELL => price.ceil_to_tick(tick),
+        _ => price.floor_to_tick(tick),
+    }
+    .to_f64()
+}
+
+/// Last guard before the tick rounding: (buy, sell) open prices forced onto the passive side,
+/// at least `min_offset_jpy` behind the touch, whatever the penalty math produced. A NaN price
+/// falls back to the bound itself.
+pub fn passive_quotes(buy: f64, sell: f64, best_bid: f64, best_ask: f64, min_offset_jpy: f64) -> (f64, f64) {
+    (buy.min(best_bid - min_offset_jpy), sell.max(best_ask + min_offset_jpy))
+}
+
+/// (buy, sell) open sizes: `max_lot` shrinks as that side's position grows, never below
+/// `min_lot` and never past `max_position_size` (0 once less than `min_lot` is left).
+pub fn calculate_order_sizes(
+    position: &Position,
+    max_position_size: f64,
+    min_lot: f64,
+    max_lot: f64,
+    position_ratio: f64,
+) -> (f64, f64) {
+    let remaining_long = (max_position_size - position.long_size).max(0.0);
+    let remaining_short = (max_position_size - position.short_size).max(0.0);
+
+    let buy_size = if remaining_long < min_lot {
+        0.0
+    } else {
+        util::round_size(
+            max_lot * (1.0 - position.long_size.powf(position_ratio) / max_position_size),
+        )
+        .max(min_lot)
+        .min(remaining_long)
+    };
+
+    let sell_size = if remaining_short < min_lot {
+        0.0
+    } else {
+        util::round_size(
+            max_lot * (1.0 - position.short_size.powf(position_ratio) / max_position_size),
+        )
+        .max(min_lot)
+        .min(remaining_short)
+    };
+
+    (buy_size, sell_size)
+}
+
+/// (buy, sell) open sizes for a Kelly `fraction` of `collateral` (JPY) at `mid_price`:
+/// clamped to [`min_lot`, `max_lot`] and to the room left under `max_position_size`
+/// (0 once less than `min_lot` is left, or when there is no edge).
+pub fn kelly_order_sizes(
+    position: &Position,
+    max_position_size: f64,
+    min_lot: f64,
+    max_lot: f64,
+    fraction: f64,
+    collateral: f64,
+    mid_price: f64,
+) -> (f64, f64) {
+    let kelly_size = if fraction > 0.0 && mid_price > 0.0 {
+        util::round_size(fraction * collateral.max(0.0) / mid_price).clamp(min_lot, max_lot)
+    } else {
+        0.0
+    };
+    let size_for = |held: f64| {
+        let remaining = (max_position_size - held).max(0.0);
+        if remaining < min_lot { 0.0 } else { kelly_size.min(remaining) }
+    };
+    (size_for(position.long_size), size_for(position.short_size))
+}
+
+/// max_position scaled to collateral: `collateral * pct / price` base units, clamped to
+/// [floor, ceiling]. None while scaling is off (pct <= 0) or the price is unknown.
+pub fn collateral_max_position(collateral: f64, pct: f64, price: f64, floor: f64, ceiling: f64) -> Option<f64> {
+    if pct <= 0.0 || price <= 0.0 {
+        return None;
+    }
+    Some((collateral.max(0.0) * pct / price).min(ceiling).max(floor))
+}
+
+/// (buy, sell) open sizes for the configured `SizingMode`
+#[allow(clippy::too_many_arguments)]
+pub fn order_sizes(
+    mode: &SizingMode,
+    position: &Position,
+    max_position_size: f64,
+    min_lot: f64,
+    max_lot: f64,
+    position_ratio: f64,
+    collateral: f64,
+    mid_price: f64,
+) -> (f64, f64) {
+    match mode.kelly_fraction() {
+        None => calculate_order_sizes(position, max_position_size, min_lot, max_lot, position_ratio),
+        Some(fraction) => kelly_order_sizes(
+            position, max_position_size, min_lot, max_lot, fraction, collateral, mid_price,
+        ),
+    }
+}
+
+/// Cap (buy, sell) open sizes by spot wallet funds: a buy needs `available_jpy` at
+/// `price`, a sell needs `available_base` of the base currency. Never raises a size.
+pub fn balance_capped_sizes(
+    buy_size: f64,
+    sell_size: f64,
+    available_jpy: f64,
+    available_base: f64,
+    price: f64,
+) -> (f64, f64) {
+    let affordable = if price > 0.0 { available_jpy.max(0.0) / price } else { 0.0 };
+    (buy_size.min(affordable), sell_size.min(available_base.max(0.0)))
+}
+
+/// Top-of-book pressure over the best `depth_levels` on each side:
+/// (bid_vol - ask_vol) / (bid_vol + ask_vol), in [-1, 1]. 0 for an empty book.
+pub fn order_book_imbalance(bids: &BTreeMap<u64, f64>, asks: &BTreeMap<u64, f64>, depth_levels: usize) -> f64 {
+    let bid_vol: f64 = bids.values().rev().take(depth_levels).sum();
+    let ask_vol: f64 = asks.values().take(depth_levels).sum();
+    let total = bid_vol + ask_vol;
+    if total > 0.0 { (bid_vol - ask_vol) / total } else { 0.0 }
+}
+
+/// Aggressor pressure over public executions in the last `window_ms` before `now`:
+/// (buy_vol - sell_vol) / (buy_vol + sell_vol) from the signed sizes, in [-1, 1].
+/// 0 when nothing traded in the window.
+pub fn trade_flow_imbalance(executions: &[(u64, f64, i64)], now: i64, window_ms: i64) -> f64 {
+    let (buy_vol, sell_vol) = executions
+        .iter()
+        .filter(|e| e.2 >= now - window_ms)
+        .fold((0.0, 0.0), |(buy, sell), e| if e.1 > 0.0 { (buy + e.1, sell) } else { (buy, sell - e.1) });
+    let total = buy_vol + sell_vol;
+    if total > 0.0 { (buy_vol - sell_vol) / total } else { 0.0 }
+}
+
+/// (buy, sell) spread multiplier increments that back off the side flow is hitting.
+/// Aggressive buys (`flow` > 0) lift our asks, so only the sell side widens, by
+/// `flow * weight`; aggressive sells widen the buy side. The other side is left alone.
+pub fn trade_flow_widening(flow: f64, weight: f64) -> (f64, f64) {
+    let flow = flow.clamp(-1.0, 1.0);
+    ((-flow).max(0.0) * weight, flow.max(0.0) * weight)
+}
+
+/// Percentage levels L`start`..=L`end`: level i quotes i * 0.001% (1e-5) away from mid.
+pub fn percent_levels(start: u32, end: u32) -> Vec<FloatingExp> {
+    step_levels(start, end, 10.0, -5.0)
+}
+
+/// Levels L`start`..=L`end` on a `base^exp` grid: level i quotes i * base^exp of mid away.
+pub fn step_levels(start: u32, end: u32, base: f64, exp: f64) -> Vec<FloatingExp> {
+    (start..=end)
+        .map(|i| FloatingExp { base, exp, rate: i as f64 })
+        .collect()
+}
+
+/// Fixed-JPY levels, expressed as `FloatingExp` so they plug into the same pipeline.
+/// `base = reference_mid, exp = -1` makes `calc() = offset / reference_mid`, i.e. exactly
+/// `offset` JPY at the reference mid (and proportionally scaled as mid moves away from it).
+/// `rate` carries the JPY offset, so the logged level is the offset itself.
+pub fn jpy_offset_levels(offsets_jpy: &[u32], reference_mid: f64) -> Vec<FloatingExp> {
+    offsets_jpy
+        .iter()
+        .map(|&offset| FloatingExp { base: reference_mid, exp: -1.0, rate: offset as f64 })
+        .collect()
+}
+
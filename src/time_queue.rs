@@ -1,9 +1,11 @@
 use std::time::{Duration, Instant};
 
 // 直近duration間のdataを保持する
+// max_lenを指定した場合は、時間に関係なく古いものから捨てて件数を上限に抑える
 #[derive(Debug, Clone)]
 pub struct TimeQueue<T: Clone> {
     duration: Duration,
+    max_len: Option<usize>,
     data: Vec<(Instant, T)>,
 }
 
@@ -11,10 +13,33 @@ impl<T: Clone> TimeQueue<T> {
     pub fn new(duration: Duration) -> Self {
         Self {
             duration,
+            max_len: None,
             data: Vec::new(),
         }
     }
 
+    pub fn new_bounded(duration: Duration, max_len: usize) -> Self {
+        Self {
+            duration,
+            max_len: Some(max_len),
+            data: Vec::new(),
+        }
+    }
+
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    // 上限を超えた分を先頭(最古)から捨てる
+    fn enforce_max_len(&mut self) {
+        if let Some(max_len) = self.max_len {
+            if self.data.len() > max_len {
+                let excess = self.data.len() - max_len;
+                self.data.drain(..excess);
+            }
+        }
+    }
+
     pub fn duration(&self) -> Duration {
         self.duration
     }
@@ -26,11 +51,13 @@ impl<T: Clone> TimeQueue<T> {
     pub fn push(&mut self, item: T) {
         let now = Instant::now();
         self.data.push((now, item));
+        self.enforce_max_len();
     }
 
     pub fn extend(&mut self, items: Vec<T>) {
         let now = Instant::now();
         self.data.extend(items.into_iter().map(|item| (now, item)));
+        self.enforce_max_len();
     }
 
     pub fn first(&self) -> Option<T> {
@@ -41,6 +68,16 @@ impl<T: Clone> TimeQueue<T> {
         self.data.last().map(|(_, item)| item.clone())
     }
 
+    // 挿入順(古い順)に借用で走査する。get_dataと違いcloneしない
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.data.iter().map(|(_, item)| item)
+    }
+
+    // 全要素を借用したまま畳み込む
+    pub fn fold_values<B>(&self, init: B, f: impl FnMut(B, &T) -> B) -> B {
+        self.iter().fold(init, f)
+    }
+
     pub fn get_data(&self) -> Vec<T> {
         self.data.iter().map(|(_, item)| item.clone()).collect()
     }
@@ -51,6 +88,10 @@ impl<T: Clone> TimeQueue<T> {
             .retain(|(instant, _)| now.duration_since(*instant) <= self.duration);
     }
 
+    pub fn clear(&mut self) {
+        self.data.clear();
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
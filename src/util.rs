@@ -1,3 +1,5 @@
+use rand::{rngs::StdRng, SeedableRng};
+
 // 少数点8桁までで丸める
 pub fn round_size(size: f64) -> f64 {
     let base: f64 = 10.0;
@@ -6,6 +8,24 @@ pub fn round_size(size: f64) -> f64 {
     (size * pow).round() / pow
 }
 
+/// RNG for sampling and jitter: reproducible from `seed` (config `rng_seed`), else from entropy
+pub fn seeded_rng(seed: Option<u64>) -> StdRng {
+    seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64)
+}
+
+/// `seeded_rng` for one task (e.g. "public_ws", "thompson/BTC_JPY"): the seed is mixed with a
+/// hash of `task`, so tasks sharing one `rng_seed` still draw independent sequences
+pub fn task_rng(seed: Option<u64>, task: &str) -> StdRng {
+    // FNV-1a: stable across Rust versions, unlike DefaultHasher
+    let hash = task.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    seeded_rng(seed.map(|seed| seed ^ hash))
+}
+
+/// Fresh UUID v4 string for `OrderInfo::client_id`
+pub fn new_client_order_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -30,4 +50,39 @@ mod test {
         let rounded = round_size(size);
         assert_eq!(rounded, 0.12345679);
     }
+
+    #[test]
+    fn test_new_client_order_id_is_unique_uuid_v4() {
+        let ids: std::collections::HashSet<String> = (0..1000).map(|_| new_client_order_id()).collect();
+        assert_eq!(ids.len(), 1000);
+        let id = ids.iter().next().unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(id.as_bytes()[14], b'4', "version nibble: {}", id);
+    }
+
+    #[test]
+    fn test_seeded_rng_repeats_its_sequence() {
+        use rand::Rng;
+
+        let draws = |seed| -> Vec<u64> {
+            let mut rng = seeded_rng(seed);
+            (0..8).map(|_| rng.gen()).collect()
+        };
+        assert_eq!(draws(Some(42)), draws(Some(42)));
+        assert_ne!(draws(Some(42)), draws(Some(43)));
+        assert_ne!(draws(None), draws(None), "unseeded runs differ");
+    }
+
+    #[test]
+    fn test_task_rng_differs_per_task() {
+        use rand::Rng;
+
+        let draws = |seed, task| -> Vec<u64> {
+            let mut rng = task_rng(seed, task);
+            (0..8).map(|_| rng.gen()).collect()
+        };
+        assert_eq!(draws(Some(42), "public_ws"), draws(Some(42), "public_ws"));
+        assert_ne!(draws(Some(42), "public_ws"), draws(Some(42), "private_ws"));
+        assert_ne!(draws(Some(42), "thompson/BTC_JPY"), draws(Some(42), "thompson/ETH_JPY"));
+    }
 }
@@ -0,0 +1,126 @@
+//! 価格決定パイプラインのゴールデンテスト
+//! 固定の約定データと設定から volatility -> EV最大化 -> 注文価格 までを再現し、
+//! 数値が変わっていないことを確認する
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use trading_bot::bayes_prob::{BayesProb, BetaDistribution};
+use trading_bot::model::{BotConfig, FloatingExp, Position};
+use trading_bot::strategy::{calculate_order_prices, calculate_volatility, maximize_single_leg_ev};
+
+// ============================================================
+// Fixture
+// ============================================================
+
+const CONFIG_YAML: &str = r#"
+order_cancel_ms: 10000
+order_interval_ms: 5000
+position_ratio: 0.9
+min_lot: 0.001
+max_lot: 0.001
+max_position: 0.002
+alpha: 0.7
+"#;
+
+/// (price, size, timestamp_ms) — 約30秒分のBTC_JPY約定
+const EXECUTIONS: &[(u64, f64, i64)] = &[
+    (14_000_000, 0.010, 1_700_000_000_000),
+    (14_000_500, 0.002, 1_700_000_001_200),
+    (13_999_800, 0.005, 1_700_000_002_900),
+    (14_001_200, 0.001, 1_700_000_004_100),
+    (14_001_000, 0.020, 1_700_000_005_600),
+    (14_002_300, 0.003, 1_700_000_007_000),
+    (14_001_700, 0.004, 1_700_000_008_800),
+    (14_000_900, 0.010, 1_700_000_010_300),
+    (13_999_500, 0.001, 1_700_000_011_900),
+    (13_998_800, 0.002, 1_700_000_013_400),
+    (13_999_900, 0.006, 1_700_000_015_000),
+    (14_000_400, 0.001, 1_700_000_016_700),
+    (14_001_600, 0.008, 1_700_000_018_200),
+    (14_003_000, 0.002, 1_700_000_019_900),
+    (14_002_100, 0.005, 1_700_000_021_300),
+    (14_002_600, 0.001, 1_700_000_023_000),
+    (14_001_900, 0.003, 1_700_000_024_600),
+    (14_000_700, 0.010, 1_700_000_026_100),
+    (14_001_300, 0.002, 1_700_000_027_800),
+    (14_001_800, 0.004, 1_700_000_029_500),
+];
+
+const MID_PRICE: f64 = 14_001_500.0;
+const POSITION_PENALTY: f64 = 50.0;
+
+/// Per-level (trials, fills) observed for each side, for levels L4..=L25
+fn level_history(side_bias: u64) -> Vec<(u32, u64, u64)> {
+    (4..=25u32)
+        .map(|i| {
+            let trials = 20;
+            let fills = (30u64.saturating_sub(i as u64 + side_bias)) / 3;
+            (i, trials, fills.min(trials))
+        })
+        .collect()
+}
+
+fn build_probabilities(history: &[(u32, u64, u64)]) -> BTreeMap<FloatingExp, (f64, BayesProb)> {
+    let prior = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(3600));
+    history
+        .iter()
+        .map(|&(i, n, r)| {
+            let mut prob = prior.clone();
+            prob.update(n, r);
+            (FloatingExp { base: 10.0, exp: -5.0, rate: i as f64 }, (0.0, prob))
+        })
+        .collect()
+}
+
+// ============================================================
+// Golden Values
+// ============================================================
+
+const EXPECTED_VOLATILITY: f64 = 922.2837546891415;
+const EXPECTED_BUY_RATE: f64 = 18.0;
+const EXPECTED_SELL_RATE: f64 = 19.0;
+/// Be(1 + 5, 10 + 15)
+const EXPECTED_BUY_P_FILL: f64 = 5.0 / 31.0;
+/// Be(1 + 4, 10 + 16)
+const EXPECTED_SELL_P_FILL: f64 = 4.0 / 31.0;
+const EXPECTED_BEST_EV: f64 = 562.3258821115617;
+const EXPECTED_FLAT_PRICES: (f64, f64) = (13_998_979.73, 14_004_160.285);
+/// Long 0.002 = 2 lots -> both quotes shifted down by 2 * 50 JPY
+const EXPECTED_LONG_PRICES: (f64, f64) = (13_998_879.73, 14_004_060.285);
+
+fn assert_close(actual: f64, expected: f64, what: &str) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "{} drifted: actual={:?} expected={:?}",
+        what, actual, expected
+    );
+}
+
+#[test]
+fn test_golden_pricing_pipeline() {
+    let config: BotConfig = serde_yaml::from_str(CONFIG_YAML).unwrap();
+
+    let volatility = calculate_volatility(EXECUTIONS);
+
+    let buy = build_probabilities(&level_history(0));
+    let sell = build_probabilities(&level_history(2));
+    let (buy_key, buy_p_fill, sell_key, sell_p_fill, best_ev) =
+        maximize_single_leg_ev(MID_PRICE, volatility, config.alpha, &buy, &sell).unwrap();
+
+    let best_pair = (buy_key, sell_key);
+    let flat = calculate_order_prices(MID_PRICE, &best_pair, &Position::new(), POSITION_PENALTY, config.min_lot);
+    let long = Position { long_size: 0.002, ..Position::new() };
+    let long_prices = calculate_order_prices(MID_PRICE, &best_pair, &long, POSITION_PENALTY, config.min_lot);
+
+    assert_close(volatility, EXPECTED_VOLATILITY, "volatility");
+    assert_close(best_pair.0.rate, EXPECTED_BUY_RATE, "buy level");
+    assert_close(best_pair.1.rate, EXPECTED_SELL_RATE, "sell level");
+    assert_close(buy_p_fill, EXPECTED_BUY_P_FILL, "buy p_fill");
+    assert_close(sell_p_fill, EXPECTED_SELL_P_FILL, "sell p_fill");
+    assert_close(best_ev, EXPECTED_BEST_EV, "best_ev");
+    assert_close(flat.0, EXPECTED_FLAT_PRICES.0, "flat buy price");
+    assert_close(flat.1, EXPECTED_FLAT_PRICES.1, "flat sell price");
+    assert_close(long_prices.0, EXPECTED_LONG_PRICES.0, "long buy price");
+    assert_close(long_prices.1, EXPECTED_LONG_PRICES.1, "long sell price");
+}
@@ -21,6 +21,7 @@ fn test_position_new() {
 }
 
 #[test]
+#[allow(clippy::clone_on_copy)]
 fn test_position_clone() {
     let mut pos = Position::new();
     pos.long_size = 0.05;
@@ -61,20 +62,22 @@ fn test_order_info_creation() {
         side: OrderSide::BUY,
         timestamp: 1234567890,
         is_close: false,
-        mid_price: 10_000_050,
+        mid_price: 10_000_050.0,
         t_optimal_ms: 3000,
         sigma_1s: 0.00008,
         spread_pct: 0.006,
         level: 5,
+        level_key: None,
         p_fill: 0.45,
         best_ev: 1.23,
         single_leg_ev: 0.67,
+        client_id: String::new(),
     };
     assert_eq!(info.price, 10_000_000);
     assert_eq!(info.size, 0.01);
     assert_eq!(info.side, OrderSide::BUY);
     assert_eq!(info.timestamp, 1234567890);
-    assert_eq!(info.mid_price, 10_000_050);
+    assert_eq!(info.mid_price, 10_000_050.0);
     assert_eq!(info.t_optimal_ms, 3000);
     assert!((info.sigma_1s - 0.00008).abs() < 1e-10);
     assert!((info.spread_pct - 0.006).abs() < 1e-10);
@@ -153,6 +156,28 @@ t_optimal_max_ms: 20000
     assert_eq!(config.order_interval_ms, 5000);
 }
 
+#[test]
+fn test_bot_config_loop_intervals() {
+    let base = r#"
+order_cancel_ms: 10000
+order_interval_ms: 3000
+position_ratio: 0.9
+min_lot: 0.001
+max_lot: 0.001
+max_position: 0.002
+"#;
+    let config: BotConfig = serde_yaml::from_str(base).unwrap();
+    assert_eq!(config.trade_interval_ms, None);
+    assert_eq!(config.cancel_interval_ms, 500);
+    assert_eq!(config.position_poll_ms, 5000);
+
+    let yaml = format!("{}trade_interval_ms: 1500\ncancel_interval_ms: 250\nposition_poll_ms: 2000\n", base);
+    let config: BotConfig = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(config.trade_interval_ms, Some(1500));
+    assert_eq!(config.cancel_interval_ms, 250);
+    assert_eq!(config.position_poll_ms, 2000);
+}
+
 // ============================================================
 // BetaDistribution Tests
 // ============================================================
@@ -183,7 +208,77 @@ fn test_bayes_prob_update() {
 
     prob.update(1, 1);
     let avg = prob.calc_average();
-    assert!(avg >= 0.0 && avg <= 1.0);
+    assert!((0.0..=1.0).contains(&avg));
+}
+
+#[test]
+fn test_bayes_prob_update_matches_cloned_sum() {
+    let prior = BetaDistribution::new(1, 10);
+    let mut prob = BayesProb::new(prior.clone(), Duration::from_secs(300));
+    // Reference: the previous get_data() + sum path
+    let mut reference: TimeQueue<(u64, u64)> = TimeQueue::new(Duration::from_secs(300));
+
+    for (n, r) in [(1, 0), (1, 1), (20, 5), (1, 0), (3, 3)] {
+        prob.update(n, r);
+        reference.push((n, r));
+        let (total_n, total_r) = reference.get_data().iter()
+            .fold((0u64, 0u64), |(acc_n, acc_r), &(n, r)| (acc_n + n, acc_r + r));
+        assert_eq!(prob.posterior(), ((prior.a + total_r) as f64, (prior.b + (total_n - total_r)) as f64));
+    }
+}
+
+#[test]
+fn test_bayes_prob_variance_beta_2_2() {
+    let prob = BayesProb::new(BetaDistribution::new(2, 2), Duration::from_secs(300));
+    // ab / ((a+b)^2 (a+b+1)) = 4 / (16 * 5)
+    assert!((prob.variance() - 0.05).abs() < 1e-12);
+
+    let narrow = BayesProb::new(BetaDistribution::new(200, 200), Duration::from_secs(300));
+    assert!(narrow.variance() < prob.variance());
+}
+
+#[test]
+fn test_bayes_prob_credible_interval_beta_2_2_symmetric() {
+    let prob = BayesProb::new(BetaDistribution::new(2, 2), Duration::from_secs(300));
+    let (lo, hi) = prob.credible_interval(0.95);
+    // CDF of Be(2,2) is 3x^2 - 2x^3; 2.5% point is ~0.0942993
+    assert!((lo - 0.094_299_3).abs() < 1e-6, "lo={}", lo);
+    assert!((lo + hi - 1.0).abs() < 1e-9, "interval not symmetric: ({}, {})", lo, hi);
+
+    let (lo50, hi50) = prob.credible_interval(0.5);
+    assert!(lo < lo50 && lo50 < 0.5 && 0.5 < hi50 && hi50 < hi);
+}
+
+#[test]
+fn test_bayes_prob_credible_interval_degenerate() {
+    let never = BayesProb::new(BetaDistribution::new(0, 1), Duration::from_secs(300));
+    assert_eq!(never.credible_interval(0.9), (0.0, 0.0));
+    let skewed = BayesProb::new(BetaDistribution::new(1, 10), Duration::from_secs(300));
+    let (lo, hi) = skewed.credible_interval(0.9);
+    assert!(lo < skewed.calc_average() && skewed.calc_average() < hi);
+}
+
+#[test]
+fn test_bayes_prob_sample_in_unit_interval() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    for (a, b) in [(1, 10), (1, 1), (50, 3), (0, 1), (1, 0), (0, 0)] {
+        let prob = BayesProb::new(BetaDistribution::new(a, b), Duration::from_secs(300));
+        for _ in 0..1000 {
+            let p = prob.sample(&mut rng);
+            assert!((0.0..=1.0).contains(&p), "Be({}, {}) sampled {}", a, b, p);
+        }
+    }
+}
+
+#[test]
+fn test_bayes_prob_sample_mean_approaches_analytic_mean() {
+    use rand::SeedableRng;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    let prob = BayesProb::new(BetaDistribution::new(90, 10), Duration::from_secs(300));
+    let n = 20_000;
+    let mean = (0..n).map(|_| prob.sample(&mut rng)).sum::<f64>() / n as f64;
+    assert!((mean - prob.calc_average()).abs() < 0.005, "sample mean {} vs {}", mean, prob.calc_average());
 }
 
 // ============================================================
@@ -224,6 +319,48 @@ fn test_time_queue_extend() {
     assert_eq!(queue.len(), 5);
 }
 
+#[test]
+fn test_time_queue_bounded_push_keeps_newest() {
+    let max_len = 5;
+    let mut queue: TimeQueue<i32> = TimeQueue::new_bounded(Duration::from_secs(60), max_len);
+    for i in 0..(max_len as i32 + 3) {
+        queue.push(i);
+    }
+    assert_eq!(queue.len(), max_len);
+    assert_eq!(queue.get_data(), vec![3, 4, 5, 6, 7]);
+}
+
+#[test]
+fn test_time_queue_bounded_extend_keeps_newest() {
+    let mut queue: TimeQueue<i32> = TimeQueue::new_bounded(Duration::from_secs(60), 4);
+    queue.extend(vec![1, 2]);
+    queue.extend(vec![3, 4, 5, 6, 7]);
+    assert_eq!(queue.len(), 4);
+    assert_eq!(queue.first(), Some(4));
+    assert_eq!(queue.last(), Some(7));
+}
+
+#[test]
+fn test_time_queue_new_is_unbounded() {
+    let mut queue: TimeQueue<i32> = TimeQueue::new(Duration::from_secs(60));
+    queue.extend((0..10_000).collect());
+    assert_eq!(queue.len(), 10_000);
+    assert_eq!(queue.max_len(), None);
+}
+
+#[test]
+fn test_time_queue_iter_in_insertion_order() {
+    let mut queue: TimeQueue<i32> = TimeQueue::new(Duration::from_secs(60));
+    queue.push(3);
+    queue.extend(vec![1, 4]);
+    queue.push(1);
+
+    let items: Vec<i32> = queue.iter().copied().collect();
+    assert_eq!(items, vec![3, 1, 4, 1]);
+    assert_eq!(items, queue.get_data());
+    assert_eq!(queue.fold_values(0, |acc, &x| acc * 10 + x), 3141);
+}
+
 // ============================================================
 // Util Tests
 // ============================================================